@@ -0,0 +1,415 @@
+#![allow(dead_code)]
+use crate::avx2::utf8check::*;
+use crate::portability::*;
+use crate::stage1::{finalize_structurals, find_odd_backslash_sequences, BlockState};
+use crate::*;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+use std::mem;
+
+#[derive(Debug)]
+struct SimdInput {
+    lo: __m256i,
+    hi: __m256i,
+}
+
+#[target_feature(enable = "avx2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn fill_input(ptr: &[u8]) -> SimdInput {
+    #[allow(clippy::cast_ptr_alignment)]
+    SimdInput {
+        lo: _mm256_loadu_si256(ptr.as_ptr() as *const __m256i),
+        hi: _mm256_loadu_si256(ptr.as_ptr().add(32) as *const __m256i),
+    }
+}
+
+#[target_feature(enable = "avx2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn check_utf8(
+    input: &SimdInput,
+    has_error: &mut __m256i,
+    previous: &mut AvxProcessedUtfBytes,
+) {
+    let highbit: __m256i = _mm256_set1_epi8(static_cast_i8!(0x80u8));
+    if (_mm256_testz_si256(_mm256_or_si256(input.lo, input.hi), highbit)) == 1 {
+        // it is ascii, we just check continuation
+        *has_error = _mm256_or_si256(
+            _mm256_cmpgt_epi8(
+                previous.carried_continuations,
+                _mm256_setr_epi8(
+                    9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9,
+                    9, 9, 9, 9, 9, 1,
+                ),
+            ),
+            *has_error,
+        );
+    } else {
+        // it is not ascii so we have to do heavy work
+        *previous = avxcheck_utf8_bytes(input.lo, &previous, has_error);
+        *previous = avxcheck_utf8_bytes(input.hi, &previous, has_error);
+    }
+}
+
+/// a straightforward comparison of a mask against input. 5 uops; would be
+/// cheaper in AVX512.
+#[target_feature(enable = "avx2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn cmp_mask_against_input(input: &SimdInput, m: u8) -> u64 {
+    let mask: __m256i = _mm256_set1_epi8(m as i8);
+    let cmp_res_0: __m256i = _mm256_cmpeq_epi8(input.lo, mask);
+    let res_0: u64 = u64::from(static_cast_u32!(_mm256_movemask_epi8(cmp_res_0)));
+    let cmp_res_1: __m256i = _mm256_cmpeq_epi8(input.hi, mask);
+    let res_1: u64 = _mm256_movemask_epi8(cmp_res_1) as u64;
+    res_0 | (res_1 << 32)
+}
+
+// find all values less than or equal than the content of maxval (using unsigned arithmetic)
+#[target_feature(enable = "avx2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn unsigned_lteq_against_input(input: &SimdInput, maxval: __m256i) -> u64 {
+    let cmp_res_0: __m256i = _mm256_cmpeq_epi8(_mm256_max_epu8(maxval, input.lo), maxval);
+    // TODO: c++ uses static cast, here what are the implications?
+    let res_0: u64 = u64::from(static_cast_u32!(_mm256_movemask_epi8(cmp_res_0)));
+    let cmp_res_1: __m256i = _mm256_cmpeq_epi8(_mm256_max_epu8(maxval, input.hi), maxval);
+    let res_1: u64 = _mm256_movemask_epi8(cmp_res_1) as u64;
+    res_0 | (res_1 << 32)
+}
+
+// return both the quote mask (which is a half-open mask that covers the first
+// quote in an unescaped quote pair and everything in the quote pair) and the
+// quote bits, which are the simple unescaped quoted bits.
+//
+// We also update the prev_iter_inside_quote value to tell the next iteration
+// whether we finished the final iteration inside a quote pair; if so, this
+// inverts our behavior of whether we're inside quotes for the next iteration.
+//
+// Note that we don't do any error checking to see if we have backslash
+// sequences outside quotes; these
+// backslash sequences (of any length) will be detected elsewhere.
+#[target_feature(enable = "avx2,pclmulqdq")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn find_quote_mask_and_bits(
+    input: &SimdInput,
+    odd_ends: u64,
+    prev_iter_inside_quote: &mut u64,
+    quote_bits: &mut u64,
+    error_mask: &mut u64,
+) -> u64 {
+    *quote_bits = cmp_mask_against_input(&input, b'"');
+    *quote_bits &= !odd_ends;
+    // remove from the valid quoted region the unescapted characters.
+    #[allow(overflowing_literals)]
+    let mut quote_mask: u64 = _mm_cvtsi128_si64(_mm_clmulepi64_si128(
+        _mm_set_epi64x(0, static_cast_i64!(*quote_bits)),
+        _mm_set1_epi8(0xFF),
+        0,
+    )) as u64;
+    quote_mask ^= *prev_iter_inside_quote;
+    // All Unicode characters may be placed within the
+    // quotation marks, except for the characters that MUST be escaped:
+    // quotation mark, reverse solidus, and the control characters (U+0000
+    //through U+001F).
+    // https://tools.ietf.org/html/rfc8259
+    let unescaped: u64 = unsigned_lteq_against_input(&input, _mm256_set1_epi8(0x1F));
+    *error_mask |= quote_mask & unescaped;
+    // right shift of a signed value expected to be well-defined and standard
+    // compliant as of C++20,
+    // John Regher from Utah U. says this is fine code
+    *prev_iter_inside_quote = static_cast_u64!(static_cast_i64!(quote_mask) >> 63);
+    quote_mask
+}
+
+#[target_feature(enable = "avx2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn find_whitespace_and_structurals(
+    input: &SimdInput,
+    whitespace: &mut u64,
+    structurals: &mut u64,
+) {
+    // do a 'shufti' to detect structural JSON characters
+    // they are
+    // * `{` 0x7b
+    // * `}` 0x7d
+    // * `:` 0x3a
+    // * `[` 0x5b
+    // * `]` 0x5d
+    // * `,` 0x2c
+    // these go into the first 3 buckets of the comparison (1/2/4)
+
+    // we are also interested in the four whitespace characters:
+    // * space 0x20
+    // * linefeed 0x0a
+    // * horizontal tab 0x09
+    // * carriage return 0x0d
+    // these go into the next 2 buckets of the comparison (8/16)
+
+    // TODO: const?
+    let low_nibble_mask: __m256i = _mm256_setr_epi8(
+        16, 0, 0, 0, 0, 0, 0, 0, 0, 8, 12, 1, 2, 9, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 8, 12, 1, 2,
+        9, 0, 0,
+    );
+    // TODO: const?
+    let high_nibble_mask: __m256i = _mm256_setr_epi8(
+        8, 0, 18, 4, 0, 1, 0, 1, 0, 0, 0, 3, 2, 1, 0, 0, 8, 0, 18, 4, 0, 1, 0, 1, 0, 0, 0, 3, 2, 1,
+        0, 0,
+    );
+
+    let structural_shufti_mask: __m256i = _mm256_set1_epi8(0x7);
+    let whitespace_shufti_mask: __m256i = _mm256_set1_epi8(0x18);
+
+    let v_lo: __m256i = _mm256_and_si256(
+        _mm256_shuffle_epi8(low_nibble_mask, input.lo),
+        _mm256_shuffle_epi8(
+            high_nibble_mask,
+            _mm256_and_si256(_mm256_srli_epi32(input.lo, 4), _mm256_set1_epi8(0x7f)),
+        ),
+    );
+
+    let v_hi: __m256i = _mm256_and_si256(
+        _mm256_shuffle_epi8(low_nibble_mask, input.hi),
+        _mm256_shuffle_epi8(
+            high_nibble_mask,
+            _mm256_and_si256(_mm256_srli_epi32(input.hi, 4), _mm256_set1_epi8(0x7f)),
+        ),
+    );
+    let tmp_lo: __m256i = _mm256_cmpeq_epi8(
+        _mm256_and_si256(v_lo, structural_shufti_mask),
+        _mm256_set1_epi8(0),
+    );
+    let tmp_hi: __m256i = _mm256_cmpeq_epi8(
+        _mm256_and_si256(v_hi, structural_shufti_mask),
+        _mm256_set1_epi8(0),
+    );
+
+    let structural_res_0: u64 = u64::from(static_cast_u32!(_mm256_movemask_epi8(tmp_lo)));
+    let structural_res_1: u64 = _mm256_movemask_epi8(tmp_hi) as u64;
+    *structurals = !(structural_res_0 | (structural_res_1 << 32));
+
+    let tmp_ws_lo: __m256i = _mm256_cmpeq_epi8(
+        _mm256_and_si256(v_lo, whitespace_shufti_mask),
+        _mm256_set1_epi8(0),
+    );
+    let tmp_ws_hi: __m256i = _mm256_cmpeq_epi8(
+        _mm256_and_si256(v_hi, whitespace_shufti_mask),
+        _mm256_set1_epi8(0),
+    );
+
+    let ws_res_0: u64 = u64::from(static_cast_u32!(_mm256_movemask_epi8(tmp_ws_lo)));
+    let ws_res_1: u64 = _mm256_movemask_epi8(tmp_ws_hi) as u64;
+    *whitespace = !(ws_res_0 | (ws_res_1 << 32));
+}
+
+// flatten out values in 'bits' assuming that they are are to have values of idx
+// plus their position in the bitvector, and store these indexes at
+// base_ptr[base] incrementing base as we go
+// will potentially store extra values beyond end of valid bits, so base_ptr
+// needs to be large enough to handle this
+//TODO: usize was u32 here does this matter?
+#[target_feature(enable = "avx2,popcnt,bmi1")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn flatten_bits(base: &mut Vec<u32>, idx: u32, mut bits: u64) {
+    let cnt: usize = hamming(bits) as usize;
+    let mut l = base.len();
+    let idx_minus_64 = idx.wrapping_sub(64);
+    let idx_64_v = _mm256_set_epi32(
+        static_cast_i32!(idx_minus_64),
+        static_cast_i32!(idx_minus_64),
+        static_cast_i32!(idx_minus_64),
+        static_cast_i32!(idx_minus_64),
+        static_cast_i32!(idx_minus_64),
+        static_cast_i32!(idx_minus_64),
+        static_cast_i32!(idx_minus_64),
+        static_cast_i32!(idx_minus_64),
+    );
+
+    // We're doing some trickery here.
+    // We reserve 64 extra entries, because we've at most 64 bit to set
+    // then we trunctate the base to the next base (that we calcuate above)
+    // We later indiscriminatory writre over the len we set but that's OK
+    // since we ensure we reserve the needed space
+    base.reserve(64);
+    base.set_len(l + cnt);
+
+    while bits != 0 {
+        let v0 = static_cast_i32!(trailingzeroes(bits));
+        bits &= bits.wrapping_sub(1);
+        let v1 = static_cast_i32!(trailingzeroes(bits));
+        bits &= bits.wrapping_sub(1);
+        let v2 = static_cast_i32!(trailingzeroes(bits));
+        bits &= bits.wrapping_sub(1);
+        let v3 = static_cast_i32!(trailingzeroes(bits));
+        bits &= bits.wrapping_sub(1);
+        let v4 = static_cast_i32!(trailingzeroes(bits));
+        bits &= bits.wrapping_sub(1);
+        let v5 = static_cast_i32!(trailingzeroes(bits));
+        bits &= bits.wrapping_sub(1);
+        let v6 = static_cast_i32!(trailingzeroes(bits));
+        bits &= bits.wrapping_sub(1);
+        let v7 = static_cast_i32!(trailingzeroes(bits));
+        bits &= bits.wrapping_sub(1);
+
+        let v: __m256i = _mm256_set_epi32(v7, v6, v5, v4, v3, v2, v1, v0);
+        let v: __m256i = _mm256_add_epi32(idx_64_v, v);
+        #[allow(clippy::cast_ptr_alignment)]
+        _mm256_storeu_si256(base.as_mut_ptr().add(l) as *mut __m256i, v);
+        l += 8;
+    }
+}
+
+// Stage 1 for one document, fed 64 byte block by 64 byte block.
+pub(crate) struct Stage1 {
+    state: BlockState,
+    has_error: __m256i,
+    previous: AvxProcessedUtfBytes,
+}
+
+impl Stage1 {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: BlockState::default(),
+            // An all zero vector, see `AvxProcessedUtfBytes::default`
+            has_error: unsafe { mem::zeroed() },
+            previous: AvxProcessedUtfBytes::default(),
+        }
+    }
+
+    // Indexes the 64 bytes of `block` that start at `idx` in the input. The
+    // structurals of the previous block get flattened into
+    // `structural_indexes`, so the blocks have to follow each other.
+    #[target_feature(enable = "avx2,pclmulqdq,popcnt,bmi1")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn block(
+        &mut self,
+        block: &[u8],
+        idx: usize,
+        structural_indexes: &mut Vec<u32>,
+    ) {
+        /*
+        #ifndef _MSC_VER
+          __builtin_prefetch(buf + idx + 128);
+        #endif
+         */
+        let state = &mut self.state;
+        let input: SimdInput = fill_input(block);
+        check_utf8(&input, &mut self.has_error, &mut self.previous);
+        // detect odd sequences of backslashes
+        let odd_ends: u64 = find_odd_backslash_sequences(
+            cmp_mask_against_input(&input, b'\\'),
+            &mut state.prev_iter_ends_odd_backslash,
+        );
+
+        // detect insides of quote pairs ("quote_mask") and also our quote_bits
+        // themselves
+        let mut quote_bits: u64 = 0;
+        let quote_mask: u64 = find_quote_mask_and_bits(
+            &input,
+            odd_ends,
+            &mut state.prev_iter_inside_quote,
+            &mut quote_bits,
+            &mut state.error_mask,
+        );
+
+        // take the previous iterations structural bits, not our current iteration,
+        // and flatten
+        flatten_bits(structural_indexes, idx as u32, state.structurals);
+
+        let mut whitespace: u64 = 0;
+        let mut structurals: u64 = 0;
+        find_whitespace_and_structurals(&input, &mut whitespace, &mut structurals);
+
+        // fixup structurals to reflect quotes and add pseudo-structural characters
+        state.structurals = finalize_structurals(
+            structurals,
+            whitespace,
+            quote_mask,
+            quote_bits,
+            &mut state.prev_iter_ends_pseudo_pred,
+        );
+    }
+
+    // Flattens the structurals of the last block, `idx` is the end of the
+    // last block.
+    #[target_feature(enable = "avx2,pclmulqdq,popcnt,bmi1")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn finish(
+        &mut self,
+        idx: usize,
+        structural_indexes: &mut Vec<u32>,
+    ) -> std::result::Result<(), ErrorType> {
+        // This test isn't in upstream, for some reason the error mask is et for then.
+        if self.state.prev_iter_inside_quote != 0 {
+            return Err(ErrorType::Syntax);
+        }
+        // finally, flatten out the remaining structurals from the last iteration
+        flatten_bits(structural_indexes, idx as u32, self.state.structurals);
+        self.state.structurals = 0;
+        Ok(())
+    }
+
+    // Checks for errors in the blocks seen so far.
+    #[target_feature(enable = "avx2")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn check(&self) -> std::result::Result<(), ErrorType> {
+        if self.state.error_mask != 0 {
+            return Err(ErrorType::Syntax);
+        }
+
+        if _mm256_testz_si256(self.has_error, self.has_error) != 0 {
+            Ok(())
+        } else {
+            Err(ErrorType::InvalidUTF8)
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    #[target_feature(enable = "avx2,pclmulqdq,popcnt,bmi1")]
+    pub(crate) unsafe fn find_structural_bits_avx2(
+        input: &[u8],
+        structural_indexes: &mut Vec<u32>,
+    ) -> std::result::Result<(), ErrorType> {
+        let len = input.len();
+        // 6 is a heuristic number to estimate it turns out a rate of 1/6 structural caracters lears
+        // almost never to relocations.
+        structural_indexes.clear();
+        structural_indexes.reserve(len / 6);
+        structural_indexes.push(0); // push extra root element
+
+        let mut stage1 = Stage1::new();
+
+        let lenminus64: usize = if len < 64 { 0 } else { len as usize - 64 };
+        let mut idx: usize = 0;
+
+        while idx < lenminus64 {
+            stage1.block(input.get_unchecked(idx..), idx, structural_indexes);
+            idx += 64;
+        }
+
+        // The last (partial) block needs to be properly padded or else we
+        // risk invalidating the UTF-8 checks.
+        if idx < len {
+            let mut tmpbuf: [u8; 64] = [0x20; 64];
+            tmpbuf
+                .as_mut_ptr()
+                .copy_from(input.as_ptr().add(idx), len as usize - idx);
+            stage1.block(&tmpbuf, idx, structural_indexes);
+            idx += 64;
+        }
+        stage1.finish(idx, structural_indexes)?;
+
+        // a valid JSON file cannot have zero structural indexes - we should have
+        // found something (note that we compare to 1 as we always add the root!)
+        if structural_indexes.len() == 1 {
+            return Err(ErrorType::EOF);
+        }
+
+        if structural_indexes.last() > Some(&(len as u32)) {
+            return Err(ErrorType::InternalError);
+        }
+
+        stage1.check()
+    }
+}
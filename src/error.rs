@@ -1,3 +1,4 @@
+use crate::stringparse::read_str;
 use std::fmt;
 
 #[derive(Debug, PartialEq)]
@@ -40,6 +41,22 @@ pub enum ErrorType {
     ExpectedObjectContent,
     ExpectedObjectKey,
     Overflow,
+    UnsupportedImplementation,
+    Io(String),
+    KeyNotFound,
+    IndexOutOfBounds,
+    DepthLimit,
+    DocumentSizeLimit,
+    StringLengthLimit,
+    ElementCountLimit,
+    KeyCountLimit,
+    DuplicateKey,
+    UnterminatedComment,
+    InvalidPointer,
+    InvalidJsonPath,
+    InvalidPatch,
+    TestFailed,
+    InvalidSchema(String),
 }
 
 #[derive(Debug, PartialEq)]
@@ -47,7 +64,69 @@ pub struct Error {
     structural: usize,
     index: usize,
     character: char,
-    error: ErrorType,
+    pub(crate) error: ErrorType,
+    // where in the input the error happened, only known for errors raised
+    // while parsing
+    location: Option<Box<Location>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Location {
+    offset: usize,
+    line: usize,
+    column: usize,
+    excerpt: String,
+    // RFC 6901 pointer to the value that failed, when the structure of the
+    // input is known
+    pointer: Option<String>,
+}
+
+// The number of bytes shown on either side of an error.
+const EXCERPT_LEN: usize = 32;
+
+// The character of errors that aren't at a character of the input, this is
+// the poop emoji.
+const UNKNOWN_CHARACTER: char = '💩';
+
+impl Location {
+    // Line and column are only needed once something went wrong, so they
+    // are computed from the input when the error is created instead of
+    // being tracked while parsing.
+    fn new(input: &[u8], offset: usize) -> Self {
+        let offset = offset.min(input.len());
+        let before = &input[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|c| *c == b'\n')
+            .map_or(0, |i| i + 1);
+        let line = before.iter().filter(|c| **c == b'\n').count() + 1;
+        // count characters, not bytes, UTF-8 continuation bytes are skipped
+        let column = before[line_start..]
+            .iter()
+            .filter(|c| (**c & 0xC0) != 0x80)
+            .count()
+            + 1;
+
+        let mut start = line_start.max(offset.saturating_sub(EXCERPT_LEN));
+        while start < offset && (input[start] & 0xC0) == 0x80 {
+            start += 1;
+        }
+        let mut end = input[offset..]
+            .iter()
+            .position(|c| *c == b'\n' || *c == b'\r')
+            .map_or(input.len(), |i| offset + i)
+            .min(offset + EXCERPT_LEN);
+        while end > offset && end < input.len() && (input[end] & 0xC0) == 0x80 {
+            end -= 1;
+        }
+        Self {
+            offset,
+            line,
+            column,
+            excerpt: String::from_utf8_lossy(&input[start..end]).into_owned(),
+            pointer: None,
+        }
+    }
 }
 
 impl Error {
@@ -57,23 +136,318 @@ impl Error {
             index,
             character,
             error,
+            location: None,
+        }
+    }
+
+    /// An error at byte `offset` of `input`, the line and column are
+    /// computed from the input.
+    pub(crate) fn at(input: &[u8], structural: usize, offset: usize, error: ErrorType) -> Self {
+        Error {
+            structural,
+            index: offset,
+            character: input.get(offset).map_or(UNKNOWN_CHARACTER, |c| *c as char),
+            error,
+            location: Some(Box::new(Location::new(input, offset))),
         }
     }
+
+    /// Adds the JSON Pointer of the value the error was found in, taken
+    /// from the structural indexes the error was raised at.
+    pub(crate) fn with_pointer(mut self, input: &[u8], structural_indexes: &[u32]) -> Self {
+        let structural = self.structural;
+        if let Some(l) = self.location.as_mut() {
+            l.pointer = Some(pointer(input, structural_indexes, structural));
+        }
+        self
+    }
+
+    /// Adds a JSON Pointer made up of the given path segments, for parsers
+    /// that know where they are without the structural indexes.
+    pub(crate) fn with_path<S: AsRef<str>>(mut self, path: &[S]) -> Self {
+        if let Some(l) = self.location.as_mut() {
+            let mut res = String::new();
+            for s in path {
+                res.push('/');
+                res.push_str(&escape(s.as_ref()));
+            }
+            l.pointer = Some(res);
+        }
+        self
+    }
+
     pub fn generic(t: ErrorType) -> Self {
         Self {
             structural: 0,
             index: 0,
-            character: '💩', //this is the poop emoji
+            character: UNKNOWN_CHARACTER,
             error: t,
+            location: None,
+        }
+    }
+
+    // Stage 1 only tells us that a document is broken, not where. This is
+    // only called once it failed so we can afford to go over the input
+    // again to find the first invalid byte or the string that causes the
+    // error.
+    pub(crate) fn stage1(input: &[u8], error: ErrorType) -> Self {
+        let found = match error {
+            ErrorType::InvalidUTF8 => std::str::from_utf8(input)
+                .err()
+                .map(|e| (e.valid_up_to(), ErrorType::InvalidUTF8)),
+            ErrorType::Syntax => find_string_error(input),
+            _ => None,
+        };
+        match found {
+            Some((offset, error)) => Error::at(input, 0, offset, error),
+            None => Error::generic(error),
         }
     }
+
+    /// The kind of error.
+    pub fn error_type(&self) -> &ErrorType {
+        &self.error
+    }
+
+    /// The byte offset in the input the error was found at, for errors not
+    /// caused by the input this is `None`. For streams of documents the
+    /// offset is relative to the start of the document.
+    pub fn offset(&self) -> Option<usize> {
+        self.location.as_ref().map(|l| l.offset)
+    }
+
+    /// The line (starting at 1) the error was found at, or 0 if the error
+    /// was not caused by the input.
+    pub fn line(&self) -> usize {
+        self.location.as_ref().map_or(0, |l| l.line)
+    }
+
+    /// The column (starting at 1, counted in characters) the error was
+    /// found at, or 0 if the error was not caused by the input.
+    pub fn column(&self) -> usize {
+        self.location.as_ref().map_or(0, |l| l.column)
+    }
+
+    /// A short part of the line around the error.
+    pub fn excerpt(&self) -> Option<&str> {
+        self.location.as_ref().map(|l| l.excerpt.as_str())
+    }
+
+    /// The RFC 6901 JSON Pointer of the value that was being parsed, such
+    /// as `/events/12/prices/0`, the empty string stands for the whole
+    /// document. Errors found before the structure of the input is known
+    /// have no pointer.
+    ///
+    /// The parsers don't keep track of the path while parsing, that would
+    /// slow down every document that parses fine. Instead the path is
+    /// rebuilt when the error is raised by going over the structural
+    /// characters in front of it once, which takes time linear in how far
+    /// into the document the error is.
+    ///
+    /// When parsing a mutable buffer keys are unescaped in place, so keys
+    /// containing escape sequences might not be reproduced exactly.
+    pub fn pointer(&self) -> Option<&str> {
+        self.location
+            .as_ref()
+            .and_then(|l| l.pointer.as_ref())
+            .map(String::as_str)
+    }
+}
+
+// Rebuilds the path to the value at `structural` by going over the
+// structural characters before it, so nothing has to be tracked while
+// parsing. This is O(n) in `structural` but only runs once per failed
+// parse, documents without errors never pay for it.
+fn pointer(input: &[u8], structural_indexes: &[u32], structural: usize) -> String {
+    enum Segment {
+        Index(usize),
+        // offset of the current key, `None` until it is read
+        Key(Option<usize>),
+    }
+    let char_at = |i: usize| {
+        structural_indexes
+            .get(i)
+            .and_then(|o| input.get(*o as usize).map(|c| (*o as usize, *c)))
+    };
+    let mut stack = Vec::new();
+    for i in 1..structural {
+        match char_at(i) {
+            Some((_, b'{')) => stack.push(Segment::Key(None)),
+            Some((_, b'[')) => stack.push(Segment::Index(0)),
+            Some((_, b'}')) | Some((_, b']')) => {
+                stack.pop();
+            }
+            Some((_, b',')) => match stack.last_mut() {
+                Some(Segment::Index(n)) => *n += 1,
+                Some(Segment::Key(k)) => *k = None,
+                None => (),
+            },
+            Some((o, b'"')) => {
+                if let Some(Segment::Key(k @ None)) = stack.last_mut() {
+                    *k = Some(o);
+                }
+            }
+            Some(_) => (),
+            None => break,
+        }
+    }
+    // an error at the end of an array or object belongs to it, not to its
+    // last element
+    if let Some((_, b'}')) | Some((_, b']')) = char_at(structural) {
+        stack.pop();
+    }
+    let mut res = String::new();
+    for s in stack {
+        match s {
+            Segment::Index(n) => {
+                res.push('/');
+                res.push_str(&n.to_string());
+            }
+            Segment::Key(Some(o)) => {
+                res.push('/');
+                res.push_str(&escape(&read_str(input, o)));
+            }
+            Segment::Key(None) => break,
+        }
+    }
+    res
 }
+
+// Escapes a reference token of a JSON Pointer.
+pub(crate) fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+// Finds the first unescaped control character inside a string, or the
+// start of a string that is never closed.
+fn find_string_error(input: &[u8]) -> Option<(usize, ErrorType)> {
+    let mut start = None;
+    let mut escaped = false;
+    for (i, c) in input.iter().enumerate() {
+        match (start, *c) {
+            (None, b'"') => start = Some(i),
+            (None, _) => (),
+            (Some(_), c) if c < 0x20 => return Some((i, ErrorType::Syntax)),
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), b'\\') => escaped = true,
+            (Some(_), b'"') => start = None,
+            (Some(_), _) => (),
+        }
+    }
+    start.map(|i| (i, ErrorType::UnterminatedString))
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{:?} at chracter {} ('{}')",
-            self.error, self.index, self.character
-        )
+        match self.location {
+            Some(ref l) => match l.pointer {
+                Some(ref p) if !p.is_empty() => write!(
+                    f,
+                    "{:?} in {} at line {} column {}: {}",
+                    self.error, p, l.line, l.column, l.excerpt
+                ),
+                _ => write!(
+                    f,
+                    "{:?} at line {} column {}: {}",
+                    self.error, l.line, l.column, l.excerpt
+                ),
+            },
+            // errors created with `Error::new` know where they are, the
+            // others only what went wrong
+            None if self.character == UNKNOWN_CHARACTER => write!(f, "{:?}", self.error),
+            None => write!(
+                f,
+                "{:?} at offset {} ('{}')",
+                self.error, self.index, self.character
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Error, ErrorType};
+    use crate::{to_owned_value, to_owned_value_ref, Parser};
+
+    fn error(input: &str) -> Error {
+        let mut d = input.as_bytes().to_vec();
+        to_owned_value(&mut d).unwrap_err()
+    }
+
+    #[test]
+    fn without_location() {
+        let e = Error::generic(ErrorType::DocumentSizeLimit);
+        assert_eq!(e.offset(), None);
+        assert_eq!(e.to_string(), "DocumentSizeLimit");
+        let e = Error::new(0, 3, 'x', ErrorType::Syntax);
+        assert_eq!(e.to_string(), "Syntax at offset 3 ('x')");
+    }
+
+    #[test]
+    fn line_and_column() {
+        let e = error("{\n  \"a\": 1,\n  \"b\": tru\n}");
+        assert_eq!(e.error_type(), &ErrorType::ExpectedBoolean);
+        assert_eq!(e.line(), 3);
+        assert_eq!(e.column(), 8);
+        assert_eq!(e.offset(), Some(19));
+        assert_eq!(e.excerpt(), Some("  \"b\": tru"));
+        assert_eq!(
+            e.to_string(),
+            "ExpectedBoolean in /b at line 3 column 8:   \"b\": tru"
+        );
+        // columns count characters
+        let e = error("[\"ünï\", -]");
+        assert_eq!((e.line(), e.column()), (1, 9));
+    }
+
+    #[test]
+    fn stage1_offsets() {
+        let e = error("[\n\"a\",\n\"b\u{1}\"]");
+        assert_eq!(e.error_type(), &ErrorType::Syntax);
+        assert_eq!((e.line(), e.column(), e.offset()), (3, 3, Some(9)));
+        let e = error("[1,\n \"abc]");
+        assert_eq!(e.error_type(), &ErrorType::UnterminatedString);
+        assert_eq!((e.line(), e.column()), (2, 2));
+        let mut d = b"[\"a\", \"\xc3\x28\"]".to_vec();
+        let e = to_owned_value(&mut d).unwrap_err();
+        assert_eq!(e.error_type(), &ErrorType::InvalidUTF8);
+        assert_eq!(e.offset(), Some(7));
+        let mut d = b"{\"a\":1}\n{\"b\":\"\x01\"}\n".to_vec();
+        let mut parser = Parser::new();
+        let e = parser.parse_many_owned(&mut d).nth(1).unwrap().unwrap_err();
+        assert_eq!((e.line(), e.offset()), (1, Some(6)));
+    }
+
+    #[test]
+    fn pointer() {
+        let e = error(r#"{"events": [{}, {"prices": [{"amount": -}]}]}"#);
+        assert_eq!(e.pointer(), Some("/events/1/prices/0/amount"));
+        let e = error(r#"[1, 2, {"a/b": [], "c~d": [1, 2, x]}]"#);
+        assert_eq!(e.pointer(), Some("/2/c~0d/2"));
+        // escaped keys are only kept as they are in immutable input
+        let e =
+            to_owned_value_ref(br#"{"a": {"b": 1, "a/b\n\u00e9\ud83d\ude00": tru}}"#).unwrap_err();
+        assert_eq!(e.pointer(), Some("/a/a~1b\né😀"));
+        assert_eq!(e.to_string().split(' ').nth(2), Some("/a/a~1b\né😀"));
+        // errors in the structure itself
+        let e = error(r#"{"a": [1, 2 3]}"#);
+        assert_eq!(e.pointer(), Some("/a/1"));
+        let e = error(r#"{"a": {"b": 1,}}"#);
+        assert_eq!(e.pointer(), Some("/a"));
+        let e = error("-");
+        assert_eq!(e.pointer(), Some(""));
+        assert_eq!(Error::generic(ErrorType::EOF).pointer(), None);
+    }
+
+    #[test]
+    fn excerpt() {
+        let long = format!("[{}x, {}2]", "1, ".repeat(50), "2, ".repeat(50));
+        let e = error(&long);
+        let excerpt = e.excerpt().unwrap();
+        assert!(excerpt.len() <= 64);
+        assert!(excerpt.contains("1, 1, x, 2, 2"));
+        let e = Error::generic(ErrorType::EOF);
+        assert_eq!((e.line(), e.column(), e.offset()), (0, 0, None));
+        assert_eq!(e.excerpt(), None);
     }
 }
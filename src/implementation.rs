@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The SIMD flavour used for stage 1 (structural indexing and UTF-8
+/// validation) and for unescaping strings.
+///
+/// By default the best implementation supported by the CPU we are
+/// running on is picked at runtime, so a binary does not need to be
+/// compiled with `target-cpu=native` to be safe to run on an older
+/// host. The explicit variants are mostly useful for testing and
+/// benchmarking.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Implementation {
+    /// 256 bit wide AVX2 implementation, requires `avx2`, `pclmulqdq`,
+    /// `popcnt` and `bmi1`.
+    Avx2,
+    /// 128 bit wide SSE implementation, requires `sse4.2`, `pclmulqdq`
+    /// and `popcnt`.
+    Sse42,
+    /// Pure rust scalar implementation that works everywhere.
+    Scalar,
+}
+
+// 0 means we have not probed the CPU yet, otherwise this holds the
+// implementation index + 1.
+static DETECTED: AtomicUsize = AtomicUsize::new(0);
+
+impl Implementation {
+    /// All implementations, from the fastest to the most portable one.
+    pub const ALL: [Implementation; 3] = [
+        Implementation::Avx2,
+        Implementation::Sse42,
+        Implementation::Scalar,
+    ];
+
+    /// Returns the fastest implementation the current CPU supports. The
+    /// CPU is only probed the first time this is called.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub fn detect() -> Self {
+        match DETECTED.load(Ordering::Relaxed) {
+            0 => {
+                let best = Self::ALL
+                    .iter()
+                    .cloned()
+                    .find(|i| i.is_supported())
+                    .unwrap_or(Implementation::Scalar);
+                DETECTED.store(best as usize + 1, Ordering::Relaxed);
+                best
+            }
+            1 => Implementation::Avx2,
+            2 => Implementation::Sse42,
+            _ => Implementation::Scalar,
+        }
+    }
+
+    /// Tests if this implementation can be used on the current CPU.
+    pub fn is_supported(self) -> bool {
+        match self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Implementation::Avx2 => {
+                is_x86_feature_detected!("avx2")
+                    && is_x86_feature_detected!("pclmulqdq")
+                    && is_x86_feature_detected!("popcnt")
+                    && is_x86_feature_detected!("bmi1")
+            }
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Implementation::Sse42 => {
+                is_x86_feature_detected!("sse4.2")
+                    && is_x86_feature_detected!("pclmulqdq")
+                    && is_x86_feature_detected!("popcnt")
+            }
+            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+            Implementation::Avx2 | Implementation::Sse42 => false,
+            Implementation::Scalar => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Implementation;
+
+    #[test]
+    fn detect_is_stable() {
+        let first = Implementation::detect();
+        assert!(first.is_supported());
+        assert_eq!(first, Implementation::detect());
+    }
+
+    #[test]
+    fn scalar_is_always_supported() {
+        assert!(Implementation::Scalar.is_supported());
+    }
+}
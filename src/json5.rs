@@ -0,0 +1,707 @@
+//! Parsing of [JSON5](https://spec.json5.org) documents, enabled by
+//! `Parser::json5`.
+//!
+//! JSON5 can't go through stage 1: strings can be single quoted, keys
+//! don't need quotes at all and whitespace, escapes and numbers come in a
+//! lot more forms, so there is no set of structural characters a SIMD pass
+//! could find. Documents are parsed by a plain recursive descent parser
+//! instead that builds a `BorrowedValue`, owned values and serde are built
+//! on top of that. This is a lot slower than parsing JSON, but JSON5 is
+//! meant for config files and the like where that doesn't matter.
+//!
+//! The values are the same the JSON parser produces for the same numbers
+//! and strings, integers that don't fit into 64 bits end up as floats
+//! since JSON5 numbers are doubles. Limits and the duplicate key policy
+//! apply the same way, raw numbers are only kept for numbers that are
+//! valid JSON.
+use crate::value::borrowed::{Map, Value};
+use crate::value::insert_new;
+use crate::{DuplicateKeys, Error, ErrorType, Parser, Result};
+use std::borrow::Cow;
+use std::str;
+
+// The path to the value we're in, keys are kept as the offset they start
+// at and only read again for the pointer of an error.
+#[derive(Clone, Copy)]
+enum Segment {
+    Index(usize),
+    Key(usize),
+}
+
+struct Json5<'p, 'de> {
+    parser: &'p Parser,
+    input: &'de str,
+    pos: usize,
+    depth: usize,
+    path: Vec<Segment>,
+    // set once we ran out of input, for streams this means the document
+    // might continue in input we haven't read yet
+    at_end: bool,
+}
+
+// What the start of a stream holds, see `next`.
+pub(crate) enum Next<'de> {
+    // a document and the offset right after it
+    Document(Value<'de>, usize),
+    // the document isn't complete yet
+    Incomplete,
+    // there is nothing but whitespace and comments left
+    End,
+}
+
+// Parses the input as a single JSON5 document.
+pub(crate) fn parse<'de>(parser: &Parser, input: &'de [u8]) -> Result<Value<'de>> {
+    match stry!(parse_document(parser, input)) {
+        Some(v) => Ok(v),
+        None => Err(Error::generic(ErrorType::EOF)),
+    }
+}
+
+// Parses the input as a single JSON5 document that can be empty.
+pub(crate) fn parse_document<'de>(parser: &Parser, input: &'de [u8]) -> Result<Option<Value<'de>>> {
+    if input.len() > parser.limits.max_document_size {
+        return Err(Error::generic(ErrorType::DocumentSizeLimit));
+    }
+    let input = match str::from_utf8(input) {
+        Ok(input) => input,
+        Err(e) => return Err(Error::at(input, 0, e.valid_up_to(), ErrorType::InvalidUTF8)),
+    };
+    let mut p = Json5::new(parser, input);
+    stry!(p.skip_whitespace());
+    if p.pos == input.len() {
+        return Ok(None);
+    }
+    let v = stry!(p.parse_value());
+    stry!(p.skip_whitespace());
+    if p.pos < input.len() {
+        return Err(p.error(p.pos, ErrorType::TrailingCharacters));
+    }
+    Ok(Some(v))
+}
+
+// Parses the first document of a stream of JSON5 documents, `eof` tells
+// if `input` is all there is. Until then a document is only complete once
+// something follows it, a number or a word could still go on otherwise.
+pub(crate) fn next<'de>(parser: &Parser, input: &'de [u8], eof: bool) -> Result<Next<'de>> {
+    let (input, complete) = match str::from_utf8(input) {
+        Ok(input) => (input, eof),
+        // a character that was cut in half by a read
+        Err(e) if e.error_len().is_none() && !eof => (
+            unsafe { str::from_utf8_unchecked(&input[..e.valid_up_to()]) },
+            false,
+        ),
+        Err(e) => return Err(Error::at(input, 0, e.valid_up_to(), ErrorType::InvalidUTF8)),
+    };
+    let mut p = Json5::new(parser, input);
+    let r = p.skip_whitespace().and_then(|_| {
+        if p.pos == input.len() {
+            Ok(None)
+        } else {
+            p.parse_value().map(Some)
+        }
+    });
+    if !complete && (p.at_end || p.pos == input.len()) {
+        return Ok(Next::Incomplete);
+    }
+    match stry!(r) {
+        Some(_) if p.pos > parser.limits.max_document_size => {
+            Err(Error::generic(ErrorType::DocumentSizeLimit))
+        }
+        Some(v) => Ok(Next::Document(v, p.pos)),
+        None => Ok(Next::End),
+    }
+}
+
+impl<'p, 'de> Json5<'p, 'de> {
+    fn new(parser: &'p Parser, input: &'de str) -> Self {
+        Self {
+            parser,
+            input,
+            pos: 0,
+            depth: 0,
+            path: Vec::new(),
+            at_end: false,
+        }
+    }
+
+    fn error(&self, offset: usize, error: ErrorType) -> Error {
+        let path: Vec<String> = self
+            .path
+            .iter()
+            .map(|s| match *s {
+                Segment::Index(i) => i.to_string(),
+                Segment::Key(offset) => {
+                    let mut p = Json5::new(self.parser, self.input);
+                    p.pos = offset;
+                    p.parse_key().map(Cow::into_owned).unwrap_or_default()
+                }
+            })
+            .collect();
+        Error::at(self.input.as_bytes(), 0, offset, error).with_path(&path)
+    }
+
+    // An error caused by the input ending early.
+    fn end(&mut self, offset: usize, error: ErrorType) -> Error {
+        self.at_end = true;
+        self.error(offset, error)
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).cloned()
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    // Skips whitespace and comments.
+    fn skip_whitespace(&mut self) -> Result<()> {
+        loop {
+            match self.peek() {
+                Some(b'\t') | Some(b'\n') | Some(0x0b) | Some(0x0c) | Some(b'\r') | Some(b' ') => {
+                    self.pos += 1
+                }
+                Some(b'/') => match self.input.as_bytes().get(self.pos + 1) {
+                    Some(b'/') => {
+                        self.pos = self.input[self.pos..]
+                            .find(is_line_terminator)
+                            .map_or(self.input.len(), |i| self.pos + i);
+                    }
+                    Some(b'*') => match self.input[self.pos + 2..].find("*/") {
+                        Some(i) => self.pos += i + 4,
+                        None => return Err(self.end(self.pos, ErrorType::UnterminatedComment)),
+                    },
+                    Some(_) => return Ok(()),
+                    None => {
+                        self.at_end = true;
+                        return Ok(());
+                    }
+                },
+                Some(c) if c >= 0x80 => match self.peek_char() {
+                    Some(c) if is_whitespace(c) => self.pos += c.len_utf8(),
+                    _ => return Ok(()),
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value<'de>> {
+        match self.peek() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') | Some(b'\'') => self.parse_string().map(Value::String),
+            Some(b't') => self.parse_literal("true", Value::Bool(true), ErrorType::ExpectedBoolean),
+            Some(b'f') => {
+                self.parse_literal("false", Value::Bool(false), ErrorType::ExpectedBoolean)
+            }
+            Some(b'n') => self.parse_literal("null", Value::Null, ErrorType::ExpectedNull),
+            Some(b'-') | Some(b'+') | Some(b'.') | Some(b'0'..=b'9') | Some(b'I') | Some(b'N') => {
+                self.parse_number()
+            }
+            Some(_) => Err(self.error(self.pos, ErrorType::UnexpectedCharacter)),
+            None => Err(self.end(self.pos, ErrorType::UnexpectedEnd)),
+        }
+    }
+
+    // `true`, `false` or `null`, not followed by anything that would make
+    // it a longer word.
+    fn parse_literal(
+        &mut self,
+        word: &str,
+        value: Value<'de>,
+        error: ErrorType,
+    ) -> Result<Value<'de>> {
+        let rest = &self.input[self.pos..];
+        if rest.starts_with(word) && !rest[word.len()..].starts_with(is_identifier_part) {
+            self.pos += word.len();
+            Ok(value)
+        } else if word.starts_with(rest) {
+            Err(self.end(self.pos, error))
+        } else {
+            Err(self.error(self.pos, error))
+        }
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.parser.limits.max_depth {
+            return Err(self.error(self.pos, ErrorType::DepthLimit));
+        }
+        self.depth += 1;
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_array(&mut self) -> Result<Value<'de>> {
+        stry!(self.enter());
+        let mut res = Vec::new();
+        loop {
+            stry!(self.skip_whitespace());
+            match self.peek() {
+                Some(b']') => break,
+                None => return Err(self.end(self.pos, ErrorType::ExpectedArrayContent)),
+                _ => (),
+            }
+            if res.len() >= self.parser.limits.max_elements {
+                return Err(self.error(self.pos, ErrorType::ElementCountLimit));
+            }
+            self.path.push(Segment::Index(res.len()));
+            res.push(stry!(self.parse_value()));
+            self.path.pop();
+            stry!(self.skip_whitespace());
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => break,
+                None => return Err(self.end(self.pos, ErrorType::ExpectedArrayContent)),
+                _ => return Err(self.error(self.pos, ErrorType::ExpectedArrayContent)),
+            }
+        }
+        self.pos += 1;
+        self.depth -= 1;
+        Ok(Value::Array(res))
+    }
+
+    fn parse_object(&mut self) -> Result<Value<'de>> {
+        stry!(self.enter());
+        let mut res = Map::new();
+        let mut keys = 0;
+        loop {
+            stry!(self.skip_whitespace());
+            match self.peek() {
+                Some(b'}') => break,
+                None => return Err(self.end(self.pos, ErrorType::ExpectedObjectContent)),
+                _ => (),
+            }
+            keys += 1;
+            if keys > self.parser.limits.max_keys {
+                return Err(self.error(self.pos, ErrorType::KeyCountLimit));
+            }
+            let key_start = self.pos;
+            let key = stry!(self.parse_key());
+            stry!(self.skip_whitespace());
+            match self.peek() {
+                Some(b':') => self.pos += 1,
+                None => return Err(self.end(self.pos, ErrorType::ExpectedObjectColon)),
+                _ => return Err(self.error(self.pos, ErrorType::ExpectedObjectColon)),
+            }
+            stry!(self.skip_whitespace());
+            self.path.push(Segment::Key(key_start));
+            let value = stry!(self.parse_value());
+            self.path.pop();
+            match self.parser.duplicate_keys {
+                DuplicateKeys::KeepLast => {
+                    res.insert(key, value);
+                }
+                DuplicateKeys::KeepFirst => {
+                    if !res.contains_key(&key) {
+                        insert_new(&mut res, key, value);
+                    }
+                }
+                DuplicateKeys::Error => {
+                    if res.contains_key(&key) {
+                        return Err(self.error(key_start, ErrorType::DuplicateKey));
+                    }
+                    insert_new(&mut res, key, value);
+                }
+            }
+            stry!(self.skip_whitespace());
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => break,
+                None => return Err(self.end(self.pos, ErrorType::ExpectedObjectContent)),
+                _ => return Err(self.error(self.pos, ErrorType::ExpectedObjectContent)),
+            }
+        }
+        self.pos += 1;
+        self.depth -= 1;
+        Ok(Value::Object(res))
+    }
+
+    fn parse_key(&mut self) -> Result<Cow<'de, str>> {
+        match self.peek() {
+            Some(b'"') | Some(b'\'') => self.parse_string(),
+            _ => self.parse_identifier(),
+        }
+    }
+
+    // An ECMAScript IdentifierName used as a key, `\u` escapes included.
+    fn parse_identifier(&mut self) -> Result<Cow<'de, str>> {
+        let start = self.pos;
+        let mut owned: Option<String> = None;
+        let mut chunk = start;
+        while let Some(c) = self.peek_char() {
+            let first = self.pos == start;
+            if c == '\\' {
+                let escape = self.pos;
+                let s = owned.get_or_insert_with(String::new);
+                s.push_str(&self.input[chunk..self.pos]);
+                match self.input.as_bytes().get(self.pos + 1) {
+                    Some(b'u') => (),
+                    Some(_) => return Err(self.error(escape, ErrorType::InvalidEscape)),
+                    None => return Err(self.end(escape, ErrorType::InvalidEscape)),
+                }
+                self.pos += 2;
+                let c = stry!(self.parse_unicode_escape(escape));
+                if !is_identifier_start(c) && (first || !is_identifier_part(c)) {
+                    return Err(self.error(escape, ErrorType::InvalidEscape));
+                }
+                s.push(c);
+                chunk = self.pos;
+            } else if is_identifier_start(c) || (!first && is_identifier_part(c)) {
+                self.pos += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+        if self.pos == start {
+            return Err(match self.peek() {
+                Some(_) => self.error(self.pos, ErrorType::ExpectedObjectKey),
+                None => self.end(self.pos, ErrorType::ExpectedObjectKey),
+            });
+        }
+        stry!(self.check_string_length(start, self.pos - start));
+        Ok(match owned {
+            Some(mut s) => {
+                s.push_str(&self.input[chunk..self.pos]);
+                Cow::Owned(s)
+            }
+            None => Cow::Borrowed(&self.input[start..self.pos]),
+        })
+    }
+
+    fn check_string_length(&self, start: usize, len: usize) -> Result<()> {
+        if len > self.parser.limits.max_string_length {
+            Err(self.error(start, ErrorType::StringLengthLimit))
+        } else {
+            Ok(())
+        }
+    }
+
+    // A single or double quoted string, strings without escapes are
+    // borrowed from the input.
+    fn parse_string(&mut self) -> Result<Cow<'de, str>> {
+        let start = self.pos;
+        let bytes = self.input.as_bytes();
+        let quote = bytes[start];
+        self.pos += 1;
+        let mut owned: Option<String> = None;
+        let mut chunk = self.pos;
+        loop {
+            match bytes.get(self.pos) {
+                Some(c) if *c == quote => break,
+                Some(b'\\') => {
+                    let s = owned.get_or_insert_with(String::new);
+                    s.push_str(&self.input[chunk..self.pos]);
+                    stry!(self.parse_escape(s));
+                    chunk = self.pos;
+                }
+                // line separators and paragraph separators are fine, only
+                // line feeds and carriage returns have to be escaped
+                Some(b'\n') | Some(b'\r') => return Err(self.error(self.pos, ErrorType::Syntax)),
+                Some(_) => self.pos += 1,
+                None => return Err(self.end(start, ErrorType::UnterminatedString)),
+            }
+        }
+        let end = self.pos;
+        self.pos += 1;
+        stry!(self.check_string_length(start, end - start - 1));
+        Ok(match owned {
+            Some(mut s) => {
+                s.push_str(&self.input[chunk..end]);
+                Cow::Owned(s)
+            }
+            None => Cow::Borrowed(&self.input[chunk..end]),
+        })
+    }
+
+    // Appends the character the escape sequence at the current position
+    // stands for, escaped line terminators stand for nothing.
+    fn parse_escape(&mut self, s: &mut String) -> Result<()> {
+        let escape = self.pos;
+        self.pos += 1;
+        let c = match self.peek_char() {
+            Some(c) => c,
+            None => return Err(self.end(escape, ErrorType::InvalidEscape)),
+        };
+        self.pos += c.len_utf8();
+        match c {
+            'b' => s.push('\u{8}'),
+            'f' => s.push('\u{c}'),
+            'n' => s.push('\n'),
+            'r' => s.push('\r'),
+            't' => s.push('\t'),
+            'v' => s.push('\u{b}'),
+            '0' => {
+                if let Some(b'0'..=b'9') = self.peek() {
+                    return Err(self.error(escape, ErrorType::InvalidEscape));
+                }
+                s.push('\0')
+            }
+            '1'..='9' => return Err(self.error(escape, ErrorType::InvalidEscape)),
+            'x' => {
+                let c = stry!(self.parse_hex(escape, 2));
+                s.push(std::char::from_u32(c).unwrap_or_default())
+            }
+            'u' => s.push(stry!(self.parse_unicode_escape(escape))),
+            '\r' => {
+                if self.peek() == Some(b'\n') {
+                    self.pos += 1;
+                }
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => (),
+            c => s.push(c),
+        }
+        Ok(())
+    }
+
+    // The `len` hex digits at the current position.
+    fn parse_hex(&mut self, escape: usize, len: usize) -> Result<u32> {
+        match self.input.get(self.pos..self.pos + len) {
+            Some(digits) if digits.bytes().all(|c| c.is_ascii_hexdigit()) => {
+                self.pos += len;
+                Ok(u32::from_str_radix(digits, 16).unwrap_or_default())
+            }
+            Some(_) => Err(self.error(escape, ErrorType::InvalidUnicodeEscape)),
+            None if self.input.len() - self.pos < len => {
+                Err(self.end(escape, ErrorType::InvalidUnicodeEscape))
+            }
+            None => Err(self.error(escape, ErrorType::InvalidUnicodeEscape)),
+        }
+    }
+
+    // The code point after a `\u`, surrogate pairs are made up of two
+    // escapes.
+    fn parse_unicode_escape(&mut self, escape: usize) -> Result<char> {
+        let cp = stry!(self.parse_hex(escape, 4));
+        let cp = if (0xD800..0xDC00).contains(&cp) {
+            let rest = &self.input[self.pos..];
+            if !rest.starts_with("\\u") {
+                return Err(if "\\u".starts_with(rest) {
+                    self.end(escape, ErrorType::InvlaidUnicodeCodepoint)
+                } else {
+                    self.error(escape, ErrorType::InvlaidUnicodeCodepoint)
+                });
+            }
+            self.pos += 2;
+            let low = stry!(self.parse_hex(escape, 4));
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error(escape, ErrorType::InvlaidUnicodeCodepoint));
+            }
+            0x10000 + ((cp - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            cp
+        };
+        std::char::from_u32(cp)
+            .ok_or_else(|| self.error(escape, ErrorType::InvlaidUnicodeCodepoint))
+    }
+
+    fn parse_number(&mut self) -> Result<Value<'de>> {
+        let start = self.pos;
+        let bytes = self.input.as_bytes();
+        let negative = bytes[start] == b'-';
+        let mut i = if let b'-' | b'+' = bytes[start] {
+            start + 1
+        } else {
+            start
+        };
+        let digits = |mut i: usize, hex: bool| {
+            while let Some(c) = bytes.get(i) {
+                if c.is_ascii_digit() || (hex && c.is_ascii_hexdigit()) {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            i
+        };
+
+        let rest = &self.input[i..];
+        let value = if let Some(v) = non_finite(rest) {
+            i += if v.is_nan() { 3 } else { 8 };
+            Value::F64(if negative { -v } else { v })
+        } else if "Infinity".starts_with(rest) || "NaN".starts_with(rest) {
+            return Err(self.invalid_number(self.input.len()));
+        } else if rest.starts_with("0x") || rest.starts_with("0X") {
+            let end = digits(i + 2, true);
+            if end == i + 2 {
+                return Err(self.invalid_number(end));
+            }
+            let n = integer(&self.input[i + 2..end], 16, negative);
+            i = end;
+            n
+        } else {
+            let int_start = i;
+            i = digits(i, false);
+            let int_end = i;
+            let mut json = int_end > int_start;
+            if bytes.get(int_start) == Some(&b'0') && int_end - int_start > 1 {
+                return Err(self.error(start, ErrorType::InvalidNumber));
+            }
+            let point = bytes.get(i) == Some(&b'.');
+            let mut float = point;
+            if point {
+                let frac_start = i + 1;
+                i = digits(frac_start, false);
+                json &= i > frac_start;
+            }
+            if i == int_end + 1 && int_end == int_start {
+                // neither digits before nor after the point
+                return Err(self.invalid_number(i));
+            }
+            if let Some(b'e') | Some(b'E') = bytes.get(i) {
+                float = true;
+                i += 1;
+                if let Some(b'-') | Some(b'+') = bytes.get(i) {
+                    i += 1;
+                }
+                let exp_start = i;
+                i = digits(i, false);
+                if i == exp_start {
+                    return Err(self.invalid_number(i));
+                }
+            }
+            if int_end == int_start && !float {
+                return Err(self.invalid_number(i));
+            }
+            json &= bytes[start] != b'+';
+            let text = &self.input[start..i];
+            if json && self.parser.raw_numbers {
+                Value::RawNumber(Cow::Borrowed(text))
+            } else if float {
+                Value::F64(text.parse().unwrap_or_default())
+            } else {
+                integer(&self.input[int_start..int_end], 10, negative)
+            }
+        };
+        self.pos = i;
+        if self.input[i..].starts_with(|c| c == '.' || is_identifier_part(c)) {
+            return Err(self.error(start, ErrorType::InvalidNumber));
+        }
+        Ok(value)
+    }
+
+    // A number that ends before it's complete, at `i`.
+    fn invalid_number(&mut self, i: usize) -> Error {
+        let start = self.pos;
+        if i == self.input.len() {
+            self.end(start, ErrorType::InvalidNumber)
+        } else {
+            self.error(start, ErrorType::InvalidNumber)
+        }
+    }
+}
+
+// An integer the way the JSON parser has it: an `i64` if it fits, an
+// `u64` for positive ones that don't, a float for anything larger.
+fn integer<'de>(digits: &str, radix: u32, negative: bool) -> Value<'de> {
+    match u64::from_str_radix(digits, radix) {
+        Ok(n) if !negative && n <= i64::max_value() as u64 => Value::I64(n as i64),
+        Ok(n) if !negative => Value::U64(n),
+        Ok(n) if n <= i64::max_value() as u64 + 1 => Value::I64((n as i64).wrapping_neg()),
+        _ => {
+            let n = if radix == 10 {
+                digits.parse().unwrap_or_default()
+            } else {
+                match u128::from_str_radix(digits, radix) {
+                    Ok(n) => n as f64,
+                    Err(_) => digits.chars().fold(0.0, |n, c| {
+                        n * f64::from(radix) + f64::from(c.to_digit(radix).unwrap_or_default())
+                    }),
+                }
+            };
+            Value::F64(if negative { -n } else { n })
+        }
+    }
+}
+
+fn non_finite(input: &str) -> Option<f64> {
+    if input.starts_with("Infinity") {
+        Some(std::f64::INFINITY)
+    } else if input.starts_with("NaN") {
+        Some(std::f64::NAN)
+    } else {
+        None
+    }
+}
+
+fn is_line_terminator(c: char) -> bool {
+    c == '\n' || c == '\r' || c == '\u{2028}' || c == '\u{2029}'
+}
+
+fn is_whitespace(c: char) -> bool {
+    match c {
+        '\t' | '\n' | '\u{b}' | '\u{c}' | '\r' | ' ' | '\u{a0}' | '\u{feff}' => true,
+        // line and paragraph separators
+        '\u{2028}' | '\u{2029}' => true,
+        // the rest of the space separator category
+        '\u{1680}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' | '\u{3000}' => true,
+        _ => false,
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '$' || c == '_' || c.is_alphabetic()
+}
+
+fn is_identifier_part(c: char) -> bool {
+    // zero width non-joiner and joiner
+    is_identifier_start(c) || c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        assert_eq!(integer("ff", 16, false), Value::I64(255));
+        assert_eq!(integer("ff", 16, true), Value::I64(-255));
+        assert_eq!(
+            integer("9223372036854775808", 10, true),
+            Value::I64(i64::min_value())
+        );
+        assert_eq!(
+            integer("9223372036854775808", 10, false),
+            Value::U64(9_223_372_036_854_775_808)
+        );
+        assert_eq!(
+            integer("9223372036854775809", 10, true),
+            Value::F64(-9_223_372_036_854_775_809.0)
+        );
+        assert_eq!(
+            integer("10000000000000000", 16, false),
+            Value::F64(18_446_744_073_709_551_616.0)
+        );
+    }
+
+    fn next_end(input: &str, eof: bool) -> Option<usize> {
+        match next(&Parser::new(), input.as_bytes(), eof).unwrap() {
+            Next::Document(_, end) => Some(end),
+            Next::Incomplete => None,
+            Next::End => Some(0),
+        }
+    }
+
+    #[test]
+    fn streams() {
+        // anything that could still go on with more input
+        for input in &[
+            "12", "tru", "'ab", "[1", "[1]", "{a", "{\\", "/", "/* x", "-Inf", "'\\u12", "'\\",
+        ] {
+            assert_eq!(next_end(input, false), None, "{}", input);
+        }
+        // a character cut in half
+        assert!(matches!(
+            next(&Parser::new(), &"'ä'".as_bytes()[..2], false),
+            Ok(Next::Incomplete)
+        ));
+        assert_eq!(next_end("12 ", false), Some(2));
+        assert_eq!(next_end("[1] ", false), Some(3));
+        assert_eq!(next_end(" 'a'b", false), Some(4));
+        assert_eq!(next_end("12", true), Some(2));
+        assert_eq!(next_end(" // x\n", true), Some(0));
+        assert!(next(&Parser::new(), b"[1 2", false).is_err());
+        assert!(next(&Parser::new(), b"/* x", true).is_err());
+    }
+}
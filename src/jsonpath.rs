@@ -0,0 +1,859 @@
+//! JSONPath queries over `OwnedValue` and `BorrowedValue`.
+//!
+//! An expression is compiled once into a `JsonPath` and can then be run
+//! against any value implementing `ValueTrait`. The syntax follows
+//! [RFC 9535](https://www.rfc-editor.org/rfc/rfc9535) and the consensus of
+//! the [JSONPath comparison](https://cburgmer.github.io/json-path-comparison/)
+//! where the two agree:
+//!
+//! - `$` is the root, `@` the current node in a filter
+//! - `.name`, `['name']` or `["name"]` select a member of an object
+//! - `.*` or `[*]` select all elements of an array or values of an object
+//! - `[1]` and `[-1]` select an element of an array counting from the start
+//!   or the end
+//! - `[start:end:step]` selects a slice of an array, every part is optional
+//! - `[?(@.price > 10)]` selects the elements or values a filter holds for
+//! - `..` selects from a node and all of its descendants
+//! - `[0, 'a', 1:3]` selects the union of its selectors in the given order
+//!
+//! Filters compare with `==`, `!=`, `<`, `<=`, `>` and `>=`, combine with
+//! `&&`, `||`, `!` and parentheses and take paths, strings, numbers,
+//! `true`, `false` and `null` as operands. A path on its own tests if it
+//! selects anything. A path that doesn't select exactly one node compares
+//! as nothing: it only equals another such path and is neither smaller nor
+//! larger than anything. Numbers compare by value, strings by their code
+//! points, anything else only for equality.
+//!
+//! ```
+//! use simd_json::{json, JsonPath, ValueTrait};
+//!
+//! let path = JsonPath::compile("$.events[?(@.price > 10)].name").unwrap();
+//! let v = json!({"events": [
+//!     {"name": "cheap", "price": 5},
+//!     {"name": "pricey", "price": 20}
+//! ]});
+//! let names: Vec<_> = path.query(&v).iter().filter_map(|v| v.as_str()).collect();
+//! assert_eq!(names, vec!["pricey"]);
+//! ```
+use crate::value::{compare_numbers, equal_nodes, number_of, Number};
+use crate::value::{ArrayTrait, ObjectTrait, ValueTrait};
+use crate::{Error, ErrorType, Result};
+use std::cmp::Ordering;
+
+// How deep filters and parentheses can be nested, compiling recurses once
+// per level.
+const MAX_DEPTH: usize = 128;
+
+/// A compiled JSONPath expression, see the module documentation for the
+/// syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// Compiles an expression, fails with `ErrorType::InvalidJsonPath` at
+    /// the offset in the expression where it stops making sense.
+    pub fn compile(expr: &str) -> Result<Self> {
+        let mut c = Compiler {
+            input: expr.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        stry!(c.expect(b'$'));
+        let segments = stry!(c.segments());
+        if c.pos != c.input.len() {
+            return Err(c.error());
+        }
+        Ok(Self { segments })
+    }
+
+    /// The nodes of `root` the expression selects, in the order it
+    /// selects them. Values of objects are visited in the order the
+    /// object iterates them in.
+    pub fn query<'v, V: ValueTrait>(&self, root: &'v V) -> Vec<&'v V> {
+        select(&self.segments, root, root)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Segment {
+    // `..`, the selectors apply to the node and all its descendants
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(Expr),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(Query),
+    Compare(Operand, Op, Operand),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Query(Query),
+    Literal(Literal),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Query {
+    // starts at `$` rather than `@`
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+}
+
+struct Compiler<'e> {
+    input: &'e [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'e> Compiler<'e> {
+    fn error(&self) -> Error {
+        Error::at(self.input, 0, self.pos, ErrorType::InvalidJsonPath)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).cloned()
+    }
+
+    fn ws(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, s: &[u8]) -> bool {
+        if self.input[self.pos..].starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn nest(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            Err(self.error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>> {
+        let mut segments = Vec::new();
+        loop {
+            // blanks are allowed before a segment, but not after the last
+            let start = self.pos;
+            self.ws();
+            let descendant = self.eat(b"..");
+            let selectors = if descendant {
+                match self.peek() {
+                    Some(b'[') => stry!(self.bracket()),
+                    Some(b'*') => {
+                        self.pos += 1;
+                        vec![Selector::Wildcard]
+                    }
+                    _ => vec![Selector::Name(stry!(self.name()))],
+                }
+            } else if self.eat(b".") {
+                if self.eat(b"*") {
+                    vec![Selector::Wildcard]
+                } else {
+                    vec![Selector::Name(stry!(self.name()))]
+                }
+            } else if self.peek() == Some(b'[') {
+                stry!(self.bracket())
+            } else {
+                self.pos = start;
+                return Ok(segments);
+            };
+            segments.push(Segment {
+                descendant,
+                selectors,
+            });
+        }
+    }
+
+    // A member name after a dot, anything but ASCII punctuation and
+    // blanks.
+    fn name(&mut self) -> Result<String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == b'_' || c == b'-' || c >= 0x80 {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if start == self.pos {
+            return Err(self.error());
+        }
+        // only stops at ASCII so this is still valid UTF-8
+        Ok(String::from_utf8_lossy(&self.input[start..self.pos]).into_owned())
+    }
+
+    fn bracket(&mut self) -> Result<Vec<Selector>> {
+        stry!(self.expect(b'['));
+        let mut selectors = Vec::new();
+        loop {
+            self.ws();
+            selectors.push(stry!(self.selector()));
+            self.ws();
+            if !self.eat(b",") {
+                break;
+            }
+        }
+        stry!(self.expect(b']'));
+        Ok(selectors)
+    }
+
+    fn selector(&mut self) -> Result<Selector> {
+        match self.peek() {
+            Some(q @ b'\'') | Some(q @ b'"') => Ok(Selector::Name(stry!(self.string(q)))),
+            Some(b'*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some(b'?') => {
+                self.pos += 1;
+                stry!(self.nest());
+                let e = stry!(self.or());
+                self.depth -= 1;
+                Ok(Selector::Filter(e))
+            }
+            _ => {
+                let start = stry!(self.optional_int());
+                self.ws();
+                if !self.eat(b":") {
+                    return start.map(Selector::Index).ok_or_else(|| self.error());
+                }
+                self.ws();
+                let end = stry!(self.optional_int());
+                self.ws();
+                let step = if self.eat(b":") {
+                    self.ws();
+                    stry!(self.optional_int())
+                } else {
+                    None
+                };
+                Ok(Selector::Slice(start, end, step))
+            }
+        }
+    }
+
+    // An integer without leading zeros or `-0`, if there is one.
+    fn optional_int(&mut self) -> Result<Option<i64>> {
+        let start = self.pos;
+        self.eat(b"-");
+        let digits = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        let n = &self.input[digits..self.pos];
+        if n.is_empty() {
+            if digits == start {
+                return Ok(None);
+            }
+            return Err(self.error());
+        }
+        if n[0] == b'0' && (n.len() > 1 || digits != start) {
+            self.pos = digits;
+            return Err(self.error());
+        }
+        match std::str::from_utf8(&self.input[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            Some(i) => Ok(Some(i)),
+            None => {
+                self.pos = start;
+                Err(self.error())
+            }
+        }
+    }
+
+    // A string in single or double quotes with JSON escapes, `\'` is
+    // allowed as well.
+    fn string(&mut self, quote: u8) -> Result<String> {
+        self.pos += 1;
+        let mut res = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error()),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let c = match self.peek() {
+                        Some(b'u') => {
+                            self.pos += 1;
+                            stry!(self.unicode_escape())
+                        }
+                        Some(c) => {
+                            let c = match c {
+                                b'b' => '\u{8}',
+                                b'f' => '\u{c}',
+                                b'n' => '\n',
+                                b'r' => '\r',
+                                b't' => '\t',
+                                b'/' => '/',
+                                b'\\' => '\\',
+                                b'\'' => '\'',
+                                b'"' => '"',
+                                _ => return Err(self.error()),
+                            };
+                            self.pos += 1;
+                            c
+                        }
+                        None => return Err(self.error()),
+                    };
+                    let mut buf = [0; 4];
+                    res.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                Some(c) if c < 0x20 => return Err(self.error()),
+                Some(c) => {
+                    res.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        String::from_utf8(res).map_err(|_| self.error())
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let hex = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .filter(|h| h.bytes().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|h| u32::from_str_radix(h, 16).ok());
+        match hex {
+            Some(h) => {
+                self.pos += 4;
+                Ok(h)
+            }
+            None => Err(self.error()),
+        }
+    }
+
+    // The character of a `\u` escape starting after the `u`, a surrogate
+    // pair takes two escapes.
+    fn unicode_escape(&mut self) -> Result<char> {
+        let start = self.pos;
+        let high = stry!(self.hex4());
+        let c = if (0xD800..0xDC00).contains(&high) {
+            if !self.eat(b"\\u") {
+                return Err(self.error());
+            }
+            let low = stry!(self.hex4());
+            if !(0xDC00..0xE000).contains(&low) {
+                self.pos -= 4;
+                return Err(self.error());
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        std::char::from_u32(c).ok_or_else(|| {
+            self.pos = start;
+            self.error()
+        })
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut e = stry!(self.and());
+        loop {
+            self.ws();
+            if !self.eat(b"||") {
+                return Ok(e);
+            }
+            e = Expr::Or(Box::new(e), Box::new(stry!(self.and())));
+        }
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut e = stry!(self.unary());
+        loop {
+            self.ws();
+            if !self.eat(b"&&") {
+                return Ok(e);
+            }
+            e = Expr::And(Box::new(e), Box::new(stry!(self.unary())));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        self.ws();
+        if self.peek() == Some(b'!') && self.input.get(self.pos + 1) != Some(&b'=') {
+            self.pos += 1;
+            stry!(self.nest());
+            let e = stry!(self.unary());
+            self.depth -= 1;
+            return Ok(Expr::Not(Box::new(e)));
+        }
+        if self.eat(b"(") {
+            stry!(self.nest());
+            let e = stry!(self.or());
+            self.depth -= 1;
+            self.ws();
+            stry!(self.expect(b')'));
+            return Ok(e);
+        }
+        let start = self.pos;
+        let lhs = stry!(self.operand());
+        self.ws();
+        let op = if self.eat(b"==") {
+            Op::Eq
+        } else if self.eat(b"!=") {
+            Op::Ne
+        } else if self.eat(b"<=") {
+            Op::Le
+        } else if self.eat(b">=") {
+            Op::Ge
+        } else if self.eat(b"<") {
+            Op::Lt
+        } else if self.eat(b">") {
+            Op::Gt
+        } else {
+            return match lhs {
+                Operand::Query(q) => Ok(Expr::Exists(q)),
+                Operand::Literal(_) => {
+                    self.pos = start;
+                    Err(self.error())
+                }
+            };
+        };
+        self.ws();
+        let rhs = stry!(self.operand());
+        Ok(Expr::Compare(lhs, op, rhs))
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        let literal = match self.peek() {
+            Some(b'@') | Some(b'$') => {
+                let absolute = self.peek() == Some(b'$');
+                self.pos += 1;
+                let segments = stry!(self.segments());
+                return Ok(Operand::Query(Query { absolute, segments }));
+            }
+            Some(q @ b'\'') | Some(q @ b'"') => Literal::String(stry!(self.string(q))),
+            Some(b'-') | Some(b'0'..=b'9') => Literal::Number(stry!(self.number())),
+            _ => {
+                let start = self.pos;
+                let literal = if self.eat(b"true") {
+                    Literal::Bool(true)
+                } else if self.eat(b"false") {
+                    Literal::Bool(false)
+                } else if self.eat(b"null") {
+                    Literal::Null
+                } else {
+                    return Err(self.error());
+                };
+                if let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == b'_' {
+                        self.pos = start;
+                        return Err(self.error());
+                    }
+                }
+                literal
+            }
+        };
+        Ok(Operand::Literal(literal))
+    }
+
+    // A number as JSON writes them.
+    fn number(&mut self) -> Result<Number> {
+        let start = self.pos;
+        self.eat(b"-");
+        let digits = |c: &mut Self| {
+            let start = c.pos;
+            while let Some(b'0'..=b'9') = c.peek() {
+                c.pos += 1;
+            }
+            c.pos - start
+        };
+        let int = self.pos;
+        let n = digits(self);
+        if n == 0 || (n > 1 && self.input[int] == b'0') {
+            self.pos = int;
+            return Err(self.error());
+        }
+        let mut float = false;
+        if self.eat(b".") {
+            float = true;
+            if digits(self) == 0 {
+                return Err(self.error());
+            }
+        }
+        if self.eat(b"e") || self.eat(b"E") {
+            float = true;
+            if !self.eat(b"+") {
+                self.eat(b"-");
+            }
+            if digits(self) == 0 {
+                return Err(self.error());
+            }
+        }
+        let s = String::from_utf8_lossy(&self.input[start..self.pos]);
+        let n = if float {
+            s.parse().ok().map(Number::Float)
+        } else {
+            s.parse()
+                .ok()
+                .map(Number::Int)
+                .or_else(|| s.parse().ok().map(Number::Float))
+        };
+        n.ok_or_else(|| {
+            self.pos = start;
+            self.error()
+        })
+    }
+}
+
+fn select<'v, V: ValueTrait>(segments: &[Segment], root: &'v V, current: &'v V) -> Vec<&'v V> {
+    let mut nodes = vec![current];
+    for s in segments {
+        let mut next = Vec::new();
+        for n in nodes {
+            if s.descendant {
+                let mut descendants = Vec::new();
+                descend(n, &mut descendants);
+                for d in descendants {
+                    apply(&s.selectors, root, d, &mut next);
+                }
+            } else {
+                apply(&s.selectors, root, n, &mut next);
+            }
+        }
+        nodes = next;
+    }
+    nodes
+}
+
+// The node followed by all of its descendants, parents come before their
+// children.
+fn descend<'v, V: ValueTrait>(v: &'v V, out: &mut Vec<&'v V>) {
+    out.push(v);
+    if let Some(a) = v.as_array() {
+        for e in a.iter() {
+            descend(e, out);
+        }
+    } else if let Some(o) = v.as_object() {
+        for e in o.values() {
+            descend(e, out);
+        }
+    }
+}
+
+fn children<'v, V: ValueTrait>(v: &'v V) -> impl Iterator<Item = &'v V> {
+    let elements = v.as_array().into_iter().flat_map(|a| a.iter());
+    let values = v.as_object().into_iter().flat_map(|o| o.values());
+    elements.chain(values)
+}
+
+fn apply<'v, V: ValueTrait>(selectors: &[Selector], root: &'v V, v: &'v V, out: &mut Vec<&'v V>) {
+    for s in selectors {
+        match s {
+            Selector::Name(n) => {
+                if let Some(e) = v.as_object().and_then(|o| o.get(n)) {
+                    out.push(e);
+                }
+            }
+            Selector::Wildcard => out.extend(children(v)),
+            Selector::Index(i) => {
+                if let Some(a) = v.as_array() {
+                    let len = a.len() as i64;
+                    let i = if *i < 0 { len + i } else { *i };
+                    if i >= 0 && i < len {
+                        out.extend(a.get(i as usize));
+                    }
+                }
+            }
+            Selector::Slice(start, end, step) => {
+                if let Some(a) = v.as_array() {
+                    slice(a, *start, *end, step.unwrap_or(1), out);
+                }
+            }
+            Selector::Filter(e) => out.extend(children(v).filter(|c| e.test(root, *c))),
+        }
+    }
+}
+
+// Slices as RFC 9535 section 2.3.4.2.2 describes it.
+fn slice<'v, A: ArrayTrait>(
+    a: &'v A,
+    start: Option<i64>,
+    end: Option<i64>,
+    step: i64,
+    out: &mut Vec<&'v A::Element>,
+) {
+    let len = a.len() as i64;
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let mut push = |i: i64| out.extend(a.get(i as usize));
+    if step > 0 {
+        let lower = start.map_or(0, normalize).max(0).min(len);
+        let upper = end.map_or(len, normalize).max(0).min(len);
+        let mut i = lower;
+        while i < upper {
+            push(i);
+            i = match i.checked_add(step) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).max(-1).min(len - 1);
+        let lower = end.map_or(-1, normalize).max(-1).min(len - 1);
+        let mut i = upper;
+        while lower < i {
+            push(i);
+            i = match i.checked_add(step) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+    }
+}
+
+// What an operand of a comparison evaluates to.
+enum Value<'v, V> {
+    Nothing,
+    Node(&'v V),
+    Literal(&'v Literal),
+}
+
+impl Expr {
+    fn test<V: ValueTrait>(&self, root: &V, current: &V) -> bool {
+        match self {
+            Expr::Or(l, r) => l.test(root, current) || r.test(root, current),
+            Expr::And(l, r) => l.test(root, current) && r.test(root, current),
+            Expr::Not(e) => !e.test(root, current),
+            Expr::Exists(q) => !q.select(root, current).is_empty(),
+            Expr::Compare(l, op, r) => {
+                let l = l.eval(root, current);
+                let r = r.eval(root, current);
+                match op {
+                    Op::Eq => equal(&l, &r),
+                    Op::Ne => !equal(&l, &r),
+                    Op::Lt => less(&l, &r),
+                    Op::Le => less(&l, &r) || equal(&l, &r),
+                    Op::Gt => less(&r, &l),
+                    Op::Ge => less(&r, &l) || equal(&l, &r),
+                }
+            }
+        }
+    }
+}
+
+impl Query {
+    fn select<'v, V: ValueTrait>(&self, root: &'v V, current: &'v V) -> Vec<&'v V> {
+        select(
+            &self.segments,
+            root,
+            if self.absolute { root } else { current },
+        )
+    }
+}
+
+impl Operand {
+    fn eval<'v, V: ValueTrait>(&'v self, root: &'v V, current: &'v V) -> Value<'v, V> {
+        match self {
+            Operand::Literal(l) => Value::Literal(l),
+            Operand::Query(q) => match q.select(root, current).as_slice() {
+                [v] => Value::Node(*v),
+                _ => Value::Nothing,
+            },
+        }
+    }
+}
+
+impl<'v, V: ValueTrait> Value<'v, V> {
+    fn number(&self) -> Option<Number> {
+        match self {
+            Value::Node(v) => number_of(*v),
+            Value::Literal(Literal::Number(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn str(&self) -> Option<&str> {
+        match self {
+            Value::Node(v) => v.as_str(),
+            Value::Literal(Literal::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+fn equal<V: ValueTrait>(a: &Value<V>, b: &Value<V>) -> bool {
+    if let (Some(a), Some(b)) = (a.number(), b.number()) {
+        return compare_numbers(a, b) == Some(Ordering::Equal);
+    }
+    match (a, b) {
+        (Value::Nothing, Value::Nothing) => true,
+        (Value::Node(a), Value::Node(b)) => equal_nodes(*a, *b),
+        (Value::Node(v), Value::Literal(l)) | (Value::Literal(l), Value::Node(v)) => match l {
+            Literal::Null => v.is_null(),
+            Literal::Bool(b) => v.as_bool() == Some(*b),
+            Literal::String(s) => v.as_str() == Some(s),
+            Literal::Number(_) => false,
+        },
+        (Value::Literal(a), Value::Literal(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn less<V: ValueTrait>(a: &Value<V>, b: &Value<V>) -> bool {
+    if let (Some(a), Some(b)) = (a.number(), b.number()) {
+        return compare_numbers(a, b) == Some(Ordering::Less);
+    }
+    match (a.str(), b.str()) {
+        // UTF-8 sorts by code point
+        (Some(a), Some(b)) => a < b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error_at(expr: &str) -> Option<usize> {
+        let e = JsonPath::compile(expr).unwrap_err();
+        assert_eq!(e.error_type(), &ErrorType::InvalidJsonPath);
+        e.offset()
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error_at(""), Some(0));
+        assert_eq!(error_at("key"), Some(0));
+        assert_eq!(error_at("$."), Some(2));
+        assert_eq!(error_at("$.key "), Some(5));
+        assert_eq!(error_at("$[01]"), Some(2));
+        assert_eq!(error_at("$[-0]"), Some(3));
+        assert_eq!(error_at("$[0"), Some(3));
+        assert_eq!(error_at("$['a"), Some(4));
+        assert_eq!(error_at("$['\\x']"), Some(4));
+        assert_eq!(error_at("$[99999999999999999999]"), Some(2));
+        assert_eq!(error_at("$[?(@.a = 1)]"), Some(8));
+        assert_eq!(error_at("$[?(1)]"), Some(4));
+        assert_eq!(error_at("$[?(@.a == nul)]"), Some(11));
+        let deep = format!("$[?{}@{}]", "(".repeat(200), ")".repeat(200));
+        assert_eq!(error_at(&deep), Some(3 + MAX_DEPTH));
+    }
+
+    #[test]
+    fn strings() {
+        let path = JsonPath::compile(r#"$["a\"b", 'c\'d', 'é😀\n']"#).unwrap();
+        assert_eq!(
+            path.segments[0].selectors,
+            vec![
+                Selector::Name("a\"b".to_string()),
+                Selector::Name("c'd".to_string()),
+                Selector::Name("é😀\n".to_string()),
+            ]
+        );
+        assert_eq!(error_at(r#"$['\ud83d']"#), Some(9));
+        assert_eq!(error_at(r#"$['\ud83dA']"#), Some(9));
+        assert_eq!(error_at(r#"$['\ud83d\u0041']"#), Some(11));
+    }
+
+    #[test]
+    fn slices() {
+        let mut d = b"[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]".to_vec();
+        let v = crate::to_owned_value(&mut d).unwrap();
+        let ints = |expr: &str| -> Vec<i64> {
+            let path = JsonPath::compile(expr).unwrap();
+            path.query(&v).iter().filter_map(|v| v.as_i64()).collect()
+        };
+        assert_eq!(ints("$[1:4]"), vec![1, 2, 3]);
+        assert_eq!(ints("$[::3]"), vec![0, 3, 6, 9]);
+        assert_eq!(ints("$[-2:]"), vec![8, 9]);
+        assert_eq!(ints("$[::-3]"), vec![9, 6, 3, 0]);
+        assert_eq!(ints("$[5:1:-2]"), vec![5, 3]);
+        assert_eq!(ints("$[1:5:0]"), Vec::<i64>::new());
+        assert_eq!(ints("$[-100:100:9223372036854775807]"), vec![0]);
+        assert_eq!(ints("$[100:-100:-9223372036854775808]"), vec![9]);
+    }
+
+    #[test]
+    fn numbers() {
+        let mut d = br#"[1, 1.0, 18446744073709551615, 1e300, "1"]"#.to_vec();
+        let v = crate::to_owned_value(&mut d).unwrap();
+        let count = |expr: &str| JsonPath::compile(expr).unwrap().query(&v).len();
+        assert_eq!(count("$[?(@ == 1)]"), 2);
+        assert_eq!(count("$[?(@ == 1.0)]"), 2);
+        assert_eq!(count("$[?(@ == 1e0)]"), 2);
+        assert_eq!(count("$[?(@ == 18446744073709551615)]"), 1);
+        assert_eq!(count("$[?(@ > 18446744073709551614)]"), 2);
+        assert_eq!(count("$[?(@ < -1e400)]"), 0);
+        assert_eq!(count("$[?(@ >= '1')]"), 1);
+
+        // integers are compared with floats without rounding them
+        let mut d = b"[9007199254740993, 9007199254740992.0, 18446744073709551615, -1]".to_vec();
+        let v = crate::to_owned_value(&mut d).unwrap();
+        let count = |expr: &str| JsonPath::compile(expr).unwrap().query(&v).len();
+        assert_eq!(count("$[?(@ == 9007199254740992.0)]"), 1);
+        assert_eq!(count("$[?(@ > 9007199254740992.0)]"), 2);
+        assert_eq!(count("$[?(@ == 9007199254740993)]"), 1);
+        assert_eq!(count("$[?(@ < 18446744073709551616.0)]"), 4);
+        assert_eq!(count("$[?(@ > -0.5)]"), 3);
+        assert_eq!(count("$[?(@ < 1e40)]"), 4);
+        assert_eq!(count("$[?(@ > -1e40)]"), 4);
+        let limit = 2f64.powi(127);
+        let cmp = |a: i128, b: f64| compare_numbers(Number::Int(a), Number::Float(b));
+        assert_eq!(cmp(std::i128::MAX, limit), Some(Ordering::Less));
+        assert_eq!(cmp(std::i128::MIN, -limit), Some(Ordering::Equal));
+        assert_eq!(cmp(std::i128::MIN, -limit * 2.0), Some(Ordering::Greater));
+        assert_eq!(cmp(-3, -2.5), Some(Ordering::Less));
+        assert_eq!(cmp(0, std::f64::NAN), None);
+        assert_eq!(
+            compare_numbers(Number::Float(2.5), Number::Int(2)),
+            Some(Ordering::Greater)
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! The relaxed dialect enabled by `Parser::lenient`.
+//!
+//! Comments are overwritten with whitespace before stage 1 ever sees the
+//! input, so the SIMD code doesn't have to know about them and offsets
+//! into the input stay the same. Trailing commas are dropped from the
+//! structural indexes between stage 1 and stage 2, this way neither
+//! stage 2 nor anything parsing the document afterwards has to deal with
+//! them. `NaN` and `Infinity` are the only part stage 2 and the number
+//! parser know about.
+use crate::{Error, ErrorType, Result};
+
+// Where in the input the blanking left off, only strings have to be
+// carried over as comments are blanked in one go.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Code,
+    String,
+    Escape,
+}
+
+pub(crate) struct Comments {
+    state: State,
+}
+
+impl Default for Comments {
+    fn default() -> Self {
+        Self { state: State::Code }
+    }
+}
+
+impl Comments {
+    // Overwrites the `//` and `/* */` comments in `input[from..]` with
+    // spaces, newlines are kept so lines and columns still match the
+    // original. Returns how far it got: a comment that could still be
+    // closed by input we don't have yet is left alone unless this is the
+    // end of the input, in which case a block comment that is never closed
+    // fails with its offset.
+    pub(crate) fn blank(
+        &mut self,
+        input: &mut [u8],
+        from: usize,
+        eof: bool,
+    ) -> std::result::Result<usize, usize> {
+        let mut i = from;
+        while i < input.len() {
+            match self.state {
+                State::String => match input[i] {
+                    b'"' => self.state = State::Code,
+                    b'\\' => self.state = State::Escape,
+                    _ => (),
+                },
+                State::Escape => self.state = State::String,
+                State::Code => match (input[i], input.get(i + 1)) {
+                    (b'"', _) => self.state = State::String,
+                    (b'/', None) if !eof => return Ok(i),
+                    (b'/', Some(b'/')) => {
+                        let end = match input[i..].iter().position(|c| *c == b'\n') {
+                            Some(end) => i + end,
+                            None if eof => input.len(),
+                            None => return Ok(i),
+                        };
+                        blank(&mut input[i..end]);
+                        i = end;
+                        continue;
+                    }
+                    (b'/', Some(b'*')) => {
+                        let end = match input[i + 2..].windows(2).position(|w| w == b"*/") {
+                            Some(end) => i + 2 + end + 2,
+                            None if eof => return Err(i),
+                            None => return Ok(i),
+                        };
+                        blank(&mut input[i..end]);
+                        i = end;
+                        continue;
+                    }
+                    _ => (),
+                },
+            }
+            i += 1;
+        }
+        Ok(i)
+    }
+}
+
+fn blank(comment: &mut [u8]) {
+    for c in comment.iter_mut().filter(|c| **c != b'\n') {
+        *c = b' ';
+    }
+}
+
+// Blanks all comments in a document we have as a whole.
+pub(crate) fn strip_comments(input: &mut [u8]) -> Result<()> {
+    match Comments::default().blank(input, 0, true) {
+        Ok(_) => Ok(()),
+        Err(offset) => Err(Error::at(input, 0, offset, ErrorType::UnterminatedComment)),
+    }
+}
+
+// Drops every comma that follows a value and is directly followed by the
+// `]` or `}` closing the array or object. Anything else, like `[,]` or
+// `[1,,]`, is left for stage 2 to reject.
+pub(crate) fn drop_trailing_commas(input: &[u8], structural_indexes: &mut Vec<u32>) {
+    let at = |i: Option<&u32>| i.and_then(|i| input.get(*i as usize)).cloned();
+    // the first structural index is the extra root element
+    let mut prev = None;
+    let mut len = 1;
+    for i in 1..structural_indexes.len() {
+        let c = at(structural_indexes.get(i));
+        if c == Some(b',') {
+            let next = at(structural_indexes.get(i + 1));
+            let after_value = match prev {
+                None | Some(b'[') | Some(b'{') | Some(b',') | Some(b':') => false,
+                _ => true,
+            };
+            if after_value && (next == Some(b']') || next == Some(b'}')) {
+                continue;
+            }
+        }
+        structural_indexes[len] = structural_indexes[i];
+        len += 1;
+        prev = c;
+    }
+    structural_indexes.truncate(len);
+}
+
+// `NaN`, `Infinity` or `-Infinity` at the start of `input` if it is
+// followed by a structural character, whitespace or the end of the input.
+pub(crate) fn non_finite(input: &[u8]) -> Option<f64> {
+    let (value, len) = if input.starts_with(b"NaN") {
+        (std::f64::NAN, 3)
+    } else if input.starts_with(b"Infinity") {
+        (std::f64::INFINITY, 8)
+    } else if input.starts_with(b"-Infinity") {
+        (std::f64::NEG_INFINITY, 9)
+    } else {
+        return None;
+    };
+    match input.get(len) {
+        Some(c) if crate::charutils::is_not_structural_or_whitespace(*c) != 0 => None,
+        _ => Some(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stripped(input: &str) -> String {
+        let mut input = input.as_bytes().to_vec();
+        strip_comments(&mut input).unwrap();
+        String::from_utf8(input).unwrap()
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(stripped("[1, // one\n2]"), "[1,       \n2]");
+        assert_eq!(stripped("[1, /* one\n*/ 2]"), "[1,       \n   2]");
+        assert_eq!(stripped("/**/1/*/ */"), "    1      ");
+        assert_eq!(stripped("1 // the end"), "1           ");
+        assert_eq!(
+            stripped(r#"["//", "/*", "\"//"]"#),
+            r#"["//", "/*", "\"//"]"#
+        );
+        assert_eq!(stripped(r#"["\\"//""#), r#"["\\"   "#);
+        // not comments, left for stage 2 to reject
+        assert_eq!(stripped("[1 / 2]/"), "[1 / 2]/");
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let mut input = b"[1, /* two */ 3 /* four ]".to_vec();
+        let e = strip_comments(&mut input).unwrap_err();
+        assert_eq!(e.error_type(), &ErrorType::UnterminatedComment);
+        assert_eq!(e.offset(), Some(16));
+    }
+
+    #[test]
+    fn chunks() {
+        let input = b"[1, /* a */ 2, // b\n\"/*\"]";
+        let mut expected = input.to_vec();
+        strip_comments(&mut expected).unwrap();
+        // fed byte by byte comments are only blanked once complete
+        let mut buf = Vec::new();
+        let mut comments = Comments::default();
+        let mut done = 0;
+        for c in input.iter() {
+            buf.push(*c);
+            done = comments.blank(&mut buf, done, false).unwrap();
+            assert!(buf[..done] == expected[..done]);
+        }
+        assert_eq!(comments.blank(&mut buf, done, true), Ok(buf.len()));
+        assert_eq!(buf, expected);
+    }
+
+    fn trailing_commas_dropped(input: &str) -> String {
+        let mut structural_indexes = vec![0];
+        for (i, c) in input.bytes().enumerate() {
+            if c != b' ' {
+                structural_indexes.push(i as u32);
+            }
+        }
+        drop_trailing_commas(input.as_bytes(), &mut structural_indexes);
+        structural_indexes[1..]
+            .iter()
+            .map(|i| input.as_bytes()[*i as usize] as char)
+            .collect()
+    }
+
+    #[test]
+    fn trailing_commas() {
+        assert_eq!(trailing_commas_dropped("[1,2,]"), "[1,2]");
+        assert_eq!(trailing_commas_dropped("[[],{},]"), "[[],{}]");
+        assert_eq!(trailing_commas_dropped("{\"a\":1,}"), "{\"a\":1}");
+        assert_eq!(trailing_commas_dropped("[,]"), "[,]");
+        assert_eq!(trailing_commas_dropped("[1,,]"), "[1,,]");
+        assert_eq!(trailing_commas_dropped("{\"a\":,}"), "{\"a\":,}");
+        assert_eq!(trailing_commas_dropped(",]"), ",]");
+    }
+
+    #[test]
+    fn non_finite_numbers() {
+        assert!(non_finite(b"NaN").unwrap().is_nan());
+        assert_eq!(non_finite(b"Infinity]"), Some(std::f64::INFINITY));
+        assert_eq!(non_finite(b"-Infinity ,"), Some(std::f64::NEG_INFINITY));
+        assert_eq!(non_finite(b"Nan"), None);
+        assert_eq!(non_finite(b"NaNa"), None);
+        assert_eq!(non_finite(b"Infinite"), None);
+        assert_eq!(non_finite(b"-Inf"), None);
+        assert_eq!(non_finite(b"1"), None);
+    }
+}
@@ -4,9 +4,9 @@
 //! most of the design closely with a few exceptions to make it better
 //! fit into the rust ecosystem.
 //!
-//! Note: by default rustc will compile for compatibility, not
-//! performance, to take advantage of the simd part of simd json. You
-//! have to use a native cpu target on a avx2 capable host system. An
+//! Note: the SIMD implementation (AVX2, SSE4.2 or a scalar fallback)
+//! is selected at runtime based on the CPU, see `Implementation`.
+//! Compiling for a native cpu target still helps performance, an
 //! example how to do this can be found in the `.cargo` directory on
 //! [github](https://github.com/Licenser/simdjson-rs).
 //!
@@ -77,37 +77,146 @@ pub mod serde;
 mod charutils;
 #[macro_use]
 mod macros;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2;
 mod error;
+mod implementation;
+mod json5;
+pub mod jsonpath;
+mod lenient;
+mod limits;
+pub mod merge_patch;
+mod ndjson;
 mod numberparse;
+pub mod ondemand;
 mod parsedjson;
+pub mod patch;
 mod portability;
+mod reader;
+mod scalar;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod sse42;
 mod stage1;
 mod stage2;
 mod stringparse;
-mod utf8check;
+pub mod tape;
 pub mod value;
 
 use crate::numberparse::Number;
-use crate::portability::*;
-use crate::stringparse::*;
-#[cfg(target_arch = "x86")]
-use std::arch::x86::*;
-#[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
+use crate::parsedjson::Buffers;
+use std::borrow::Cow;
+use std::marker::PhantomData;
 use std::mem;
+use std::ptr;
+use std::slice;
 use std::str;
 
 pub use crate::error::{Error, ErrorType};
+pub use crate::implementation::Implementation;
+pub use crate::jsonpath::JsonPath;
+pub use crate::limits::Limits;
+#[cfg(feature = "serde_impl")]
+pub use crate::ndjson::DeserializeMany;
+pub use crate::ndjson::ParseMany;
+pub use crate::parsedjson::{DuplicateKeys, Parser};
+#[cfg(feature = "serde_impl")]
+pub use crate::reader::DeserializeManyReader;
+pub use crate::reader::ParseManyReader;
+#[cfg(feature = "schema")]
+pub use crate::schema::Schema;
+pub use crate::tape::{to_tape, Tape};
 pub use crate::value::*;
 
-const SIMDJSON_PADDING: usize = mem::size_of::<__m256i>();
+// Enough padding for the widest (avx2) loads.
+const SIMDJSON_PADDING: usize = 32;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+// The input of a deserializer. If we were handed a mutable buffer
+// unescaped strings are written back into it so we can borrow them, for
+// immutable input strings containing escapes are copied out of `strings`.
+//
+// We keep a pointer and length instead of a slice: the strings handed out
+// borrow parts of the input while we write into others, a slice over all
+// of it would alias both. Slices are only created for as long as they are
+// needed, or for the parts of the input we hand out, which are never
+// written to again.
+#[derive(Clone, Copy)]
+struct Input<'de> {
+    ptr: *mut u8,
+    len: usize,
+    mutable: bool,
+    _marker: PhantomData<&'de mut [u8]>,
+}
+
+// Safe since `Input` behaves like the `&'de mut [u8]` or `&'de [u8]` it was
+// created from, both of which are `Send` and `Sync`.
+unsafe impl Send for Input<'_> {}
+unsafe impl Sync for Input<'_> {}
+
+impl<'de> Input<'de> {
+    fn from_mut(input: &'de mut [u8]) -> Self {
+        Input {
+            ptr: input.as_mut_ptr(),
+            len: input.len(),
+            mutable: true,
+            _marker: PhantomData,
+        }
+    }
+
+    fn from_ref(input: &'de [u8]) -> Self {
+        Input {
+            ptr: input.as_ptr() as *mut u8,
+            len: input.len(),
+            mutable: false,
+            _marker: PhantomData,
+        }
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    // The whole input, only valid until the next write to it.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    // The part of the input from `start` to `end` for handing out, the
+    // caller has to make sure it is in bounds and never written to again.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    unsafe fn part(&self, start: usize, end: usize) -> &'de [u8] {
+        slice::from_raw_parts(self.ptr.add(start), end - start)
+    }
+
+    // The part of the input from `start` to `end` as an input of its own.
+    fn sub(&self, start: usize, end: usize) -> Self {
+        assert!(start <= end && end <= self.len);
+        Input {
+            ptr: unsafe { self.ptr.add(start) },
+            len: end - start,
+            mutable: self.mutable,
+            _marker: PhantomData,
+        }
+    }
+
+    // The whole input for modifying it, `None` for immutable input. It
+    // must not be used once a part of it was handed out.
+    fn bytes_mut(&mut self) -> Option<&mut [u8]> {
+        if self.mutable {
+            Some(unsafe { slice::from_raw_parts_mut(self.ptr, self.len) })
+        } else {
+            None
+        }
+    }
+}
+
 pub struct Deserializer<'de> {
-    // This string starts with the input data and characters are truncated off
-    // the beginning as data is parsed.
-    input: &'de mut [u8],
+    input: Input<'de>,
     //data: Vec<u8>,
     strings: Vec<u8>,
     structural_indexes: Vec<u32>,
@@ -115,18 +224,101 @@ pub struct Deserializer<'de> {
     counts: Vec<usize>,
     str_offset: usize,
     iidx: usize,
+    implementation: Implementation,
+    // Keep numbers as their source text instead of converting them.
+    raw_numbers: bool,
+    duplicate_keys: DuplicateKeys,
+    // Accept `NaN`, `Infinity` and `-Infinity` as numbers.
+    lenient: bool,
+    // The index of the structural closing each array or object, by the
+    // index of the one opening it. Only filled once members with
+    // duplicate keys have to be skipped, see `Deserializer::container_end`.
+    container_ends: Vec<u32>,
 }
 
 impl<'de> Deserializer<'de> {
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
     fn error(&self, error: ErrorType) -> Error {
-        Error::new(self.idx, self.iidx, self.c() as char, error)
+        Error::at(self.input.bytes(), self.idx, self.iidx, error)
+            .with_pointer(self.input.bytes(), &self.structural_indexes)
+    }
+
+    // An error at the structural `idx` instead of the current one.
+    fn error_at(&self, idx: usize, error: ErrorType) -> Error {
+        let iidx = self.structural_indexes[idx] as usize;
+        let input = self.input.bytes();
+        Error::at(input, idx, iidx, error).with_pointer(input, &self.structural_indexes)
     }
     // By convention, `Deserializer` constructors are named like `from_xyz`.
     // That way basic use cases are satisfied by something like
     // `serde_json::from_str(...)` while advanced use cases that require a
     // deserializer can make one with `serde_json::Deserializer::from_str(...)`.
     pub fn from_slice(input: &'de mut [u8]) -> Result<Self> {
+        Deserializer::from_slice_with_implementation(input, Implementation::detect())
+    }
+
+    /// Creates a deserializer that uses the given implementation instead of
+    /// the best one supported by the CPU. Passing an implementation the CPU
+    /// does not support results in an `ErrorType::UnsupportedImplementation`.
+    pub fn from_slice_with_implementation(
+        input: &'de mut [u8],
+        implementation: Implementation,
+    ) -> Result<Self> {
+        Deserializer::new(Input::from_mut(input), implementation)
+    }
+
+    /// Creates a deserializer for an immutable slice, the input is left
+    /// untouched and strings containing escapes get copied.
+    pub fn from_slice_ref(input: &'de [u8]) -> Result<Self> {
+        Deserializer::from_slice_ref_with_implementation(input, Implementation::detect())
+    }
+
+    /// Same as `from_slice_ref` but with a given implementation, see
+    /// `from_slice_with_implementation`.
+    pub fn from_slice_ref_with_implementation(
+        input: &'de [u8],
+        implementation: Implementation,
+    ) -> Result<Self> {
+        Deserializer::new(Input::from_ref(input), implementation)
+    }
+
+    fn new(input: Input<'de>, implementation: Implementation) -> Result<Self> {
+        let mut buffers = Buffers::default();
+        stry!(Deserializer::index(
+            input.bytes(),
+            implementation,
+            &Limits::default(),
+            false,
+            &mut buffers
+        ));
+        Ok(Deserializer::from_buffers(
+            input,
+            implementation,
+            false,
+            DuplicateKeys::default(),
+            false,
+            &mut buffers,
+        ))
+    }
+
+    // Runs stage 1 and stage 2 over the input, the structural indexes and
+    // counts end up in the buffers. Comments have to be stripped before
+    // for lenient parsing.
+    fn index(
+        input: &[u8],
+        implementation: Implementation,
+        limits: &Limits,
+        lenient: bool,
+        buffers: &mut Buffers,
+    ) -> Result<()> {
+        if !implementation.is_supported() {
+            return Err(Error::generic(ErrorType::UnsupportedImplementation));
+        }
+        // checked up front so we don't even index documents that are too
+        // large
+        if input.len() > limits.max_document_size {
+            return Err(Error::generic(ErrorType::DocumentSizeLimit));
+        }
         // We have to pick an initial size of the structural indexes.
         // 6 is a heuristic that seems to work well for the benchmark
         // data and limit re-allocation frequency.
@@ -136,7 +328,7 @@ impl<'de> Deserializer<'de> {
         let buf_start: usize = input.as_ptr() as *const () as usize;
         let needs_relocation = (buf_start + input.len()) % page_size::get() < SIMDJSON_PADDING;
 
-        let s1_result: std::result::Result<Vec<u32>, ErrorType> = if needs_relocation {
+        let s1_result: std::result::Result<(), ErrorType> = if needs_relocation {
             let mut data: Vec<u8> = Vec::with_capacity(len + SIMDJSON_PADDING);
             unsafe {
                 data.set_len(len + 1);
@@ -145,31 +337,73 @@ impl<'de> Deserializer<'de> {
                     .clone_from_slice(input);
                 *(data.get_unchecked_mut(len)) = 0;
                 data.set_len(len);
-                Deserializer::find_structural_bits(&data)
+                Deserializer::find_structural_bits_into(
+                    &data,
+                    implementation,
+                    &mut buffers.structural_indexes,
+                )
             }
         } else {
-            unsafe { Deserializer::find_structural_bits(input) }
-        };
-        let structural_indexes = match s1_result {
-            Ok(i) => i,
-            Err(t) => {
-                return Err(Error::generic(t));
+            unsafe {
+                Deserializer::find_structural_bits_into(
+                    input,
+                    implementation,
+                    &mut buffers.structural_indexes,
+                )
             }
         };
+        if let Err(t) = s1_result {
+            return Err(Error::stage1(input, t));
+        }
+        if lenient {
+            lenient::drop_trailing_commas(input, &mut buffers.structural_indexes);
+        }
 
-        let counts = Deserializer::validate(input, &structural_indexes)?;
-
-        let strings = Vec::with_capacity(len + SIMDJSON_PADDING);
-
-        Ok(Deserializer {
-            counts,
-            structural_indexes,
+        stry!(Deserializer::validate_into(
+            input,
+            &buffers.structural_indexes,
+            limits,
+            lenient,
+            &mut buffers.counts,
+            &mut buffers.stack,
+        ));
+
+        buffers.strings.clear();
+        buffers.strings.reserve(len + SIMDJSON_PADDING);
+        Ok(())
+    }
+
+    // Creates a deserializer for an input that was indexed into the given
+    // buffers, they are taken out of it until `return_buffers` is called.
+    fn from_buffers(
+        input: Input<'de>,
+        implementation: Implementation,
+        raw_numbers: bool,
+        duplicate_keys: DuplicateKeys,
+        lenient: bool,
+        buffers: &mut Buffers,
+    ) -> Self {
+        Deserializer {
+            counts: mem::replace(&mut buffers.counts, Vec::new()),
+            structural_indexes: mem::replace(&mut buffers.structural_indexes, Vec::new()),
             input,
             idx: 0,
-            strings,
+            strings: mem::replace(&mut buffers.strings, Vec::new()),
             str_offset: 0,
             iidx: 0,
-        })
+            implementation,
+            raw_numbers,
+            duplicate_keys,
+            lenient,
+            container_ends: Vec::new(),
+        }
+    }
+
+    // Hands the buffers taken by `from_buffers` back so they can be reused.
+    fn return_buffers(self, buffers: &mut Buffers) {
+        buffers.counts = self.counts;
+        buffers.structural_indexes = self.structural_indexes;
+        buffers.strings = self.strings;
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
@@ -178,15 +412,6 @@ impl<'de> Deserializer<'de> {
         self.iidx = unsafe { *self.structural_indexes.get_unchecked(self.idx) as usize };
     }
 
-    #[cfg_attr(not(feature = "no-inline"), inline(always))]
-    fn c(&self) -> u8 {
-        unsafe {
-            *self
-                .input
-                .get_unchecked(*self.structural_indexes.get_unchecked(self.idx) as usize)
-        }
-    }
-
     // pull out the check so we don't need to
     // stry every time
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
@@ -194,7 +419,7 @@ impl<'de> Deserializer<'de> {
         unsafe {
             self.idx += 1;
             self.iidx = *self.structural_indexes.get_unchecked(self.idx) as usize;
-            *self.input.get_unchecked(self.iidx)
+            *self.input.bytes().get_unchecked(self.iidx)
         }
     }
 
@@ -204,198 +429,53 @@ impl<'de> Deserializer<'de> {
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
-    fn parse_str_(&mut self) -> Result<&'de str> {
-        // Add 1 to skip the initial "
-        let idx = self.iidx + 1;
-        let mut padding = [0u8; 32];
-        //let mut read: usize = 0;
-
-        // we include the terminal '"' so we know where to end
-        // This is safe since we check sub's lenght in the range access above and only
-        // create sub sliced form sub to `sub.len()`.
-
-        let src: &[u8] = unsafe { &self.input.get_unchecked(idx..) };
-        let mut src_i: usize = 0;
-        let mut len = src_i;
-        loop {
-            let v: __m256i = if src.len() >= src_i + 32 {
-                // This is safe since we ensure src is at least 32 wide
-                #[allow(clippy::cast_ptr_alignment)]
-                unsafe {
-                    _mm256_loadu_si256(src.as_ptr().add(src_i) as *const __m256i)
-                }
-            } else {
-                unsafe {
-                    padding
-                        .get_unchecked_mut(..src.len() - src_i)
-                        .clone_from_slice(src.get_unchecked(src_i..));
-                    // This is safe since we ensure src is at least 32 wide
-                    #[allow(clippy::cast_ptr_alignment)]
-                    _mm256_loadu_si256(padding.as_ptr() as *const __m256i)
-                }
-            };
-
-            // store to dest unconditionally - we can overwrite the bits we don't like
-            // later
-            let bs_bits: u32 = unsafe {
-                static_cast_u32!(_mm256_movemask_epi8(_mm256_cmpeq_epi8(
-                    v,
-                    _mm256_set1_epi8(b'\\' as i8)
-                )))
-            };
-            let quote_mask = unsafe { _mm256_cmpeq_epi8(v, _mm256_set1_epi8(b'"' as i8)) };
-            let quote_bits = unsafe { static_cast_u32!(_mm256_movemask_epi8(quote_mask)) };
-            if (bs_bits.wrapping_sub(1) & quote_bits) != 0 {
-                // we encountered quotes first. Move dst to point to quotes and exit
-                // find out where the quote is...
-                let quote_dist: u32 = trailingzeroes(u64::from(quote_bits)) as u32;
-
-                ///////////////////////
-                // Above, check for overflow in case someone has a crazy string (>=4GB?)
-                // But only add the overflow check when the document itself exceeds 4GB
-                // Currently unneeded because we refuse to parse docs larger or equal to 4GB.
-                ////////////////////////
-
-                // we advance the point, accounting for the fact that we have a NULl termination
-
-                len += quote_dist as usize;
-                unsafe {
-                    let v = self.input.get_unchecked(idx..idx + len) as *const [u8] as *const str;
-                    return Ok(&*v);
-                }
-
-                // we compare the pointers since we care if they are 'at the same spot'
-                // not if they are the same value
-            }
-            if (quote_bits.wrapping_sub(1) & bs_bits) != 0 {
-                // Move to the 'bad' character
-                let bs_dist: u32 = trailingzeroes(u64::from(bs_bits));
-                len += bs_dist as usize;
-                src_i += bs_dist as usize;
-                break;
-            } else {
-                // they are the same. Since they can't co-occur, it means we encountered
-                // neither.
-                src_i += 32;
-                len += 32;
-            }
+    fn parse_str_(&mut self) -> Result<Cow<'de, str>> {
+        match self.implementation {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Implementation::Avx2 => unsafe { self.parse_str_avx2() },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Implementation::Sse42 => unsafe { self.parse_str_sse42() },
+            _ => unsafe { self.parse_str_scalar() },
         }
+    }
 
-        let mut dst_i: usize = 0;
-        let dst: &mut [u8] = &mut self.strings;
-
-        loop {
-            let v: __m256i = if src.len() >= src_i + 32 {
-                // This is safe since we ensure src is at least 32 wide
-                #[allow(clippy::cast_ptr_alignment)]
-                unsafe {
-                    _mm256_loadu_si256(src.as_ptr().add(src_i) as *const __m256i)
-                }
-            } else {
-                unsafe {
-                    padding
-                        .get_unchecked_mut(..src.len() - src_i)
-                        .clone_from_slice(src.get_unchecked(src_i..));
-                    // This is safe since we ensure src is at least 32 wide
-                    #[allow(clippy::cast_ptr_alignment)]
-                    _mm256_loadu_si256(padding.as_ptr() as *const __m256i)
-                }
-            };
-
-            #[allow(clippy::cast_ptr_alignment)]
-            unsafe {
-                _mm256_storeu_si256(dst.as_mut_ptr().add(dst_i) as *mut __m256i, v)
-            };
-
-            // store to dest unconditionally - we can overwrite the bits we don't like
-            // later
-            let bs_bits: u32 = unsafe {
-                static_cast_u32!(_mm256_movemask_epi8(_mm256_cmpeq_epi8(
-                    v,
-                    _mm256_set1_epi8(b'\\' as i8)
-                )))
-            };
-            let quote_mask = unsafe { _mm256_cmpeq_epi8(v, _mm256_set1_epi8(b'"' as i8)) };
-            let quote_bits = unsafe { static_cast_u32!(_mm256_movemask_epi8(quote_mask)) };
-            if (bs_bits.wrapping_sub(1) & quote_bits) != 0 {
-                // we encountered quotes first. Move dst to point to quotes and exit
-                // find out where the quote is...
-                let quote_dist: u32 = trailingzeroes(u64::from(quote_bits)) as u32;
-
-                ///////////////////////
-                // Above, check for overflow in case someone has a crazy string (>=4GB?)
-                // But only add the overflow check when the document itself exceeds 4GB
-                // Currently unneeded because we refuse to parse docs larger or equal to 4GB.
-                ////////////////////////
-
-                // we advance the point, accounting for the fact that we have a NULl termination
-
-                dst_i += quote_dist as usize;
-                unsafe {
-                    self.input
-                        .get_unchecked_mut(idx + len..idx + len + dst_i)
-                        .clone_from_slice(&self.strings.get_unchecked(..dst_i));
-                    let v = self.input.get_unchecked(idx..idx + len + dst_i) as *const [u8]
-                        as *const str;
-                    self.str_offset += dst_i as usize;
-                    return Ok(&*v);
-                }
-
-                // we compare the pointers since we care if they are 'at the same spot'
-                // not if they are the same value
-            }
-            if (quote_bits.wrapping_sub(1) & bs_bits) != 0 {
-                // find out where the backspace is
-                let bs_dist: u32 = trailingzeroes(u64::from(bs_bits));
-                let escape_char: u8 = unsafe { *src.get_unchecked(src_i + bs_dist as usize + 1) };
-                // we encountered backslash first. Handle backslash
-                if escape_char == b'u' {
-                    // move src/dst up to the start; they will be further adjusted
-                    // within the unicode codepoint handling code.
-                    src_i += bs_dist as usize;
-                    dst_i += bs_dist as usize;
-                    let (o, s) = if let Ok(r) =
-                        handle_unicode_codepoint(unsafe { src.get_unchecked(src_i..) }, unsafe {
-                            dst.get_unchecked_mut(dst_i..)
-                        }) {
-                        r
-                    } else {
-                        return Err(self.error(ErrorType::InvlaidUnicodeCodepoint));
-                    };
-                    if o == 0 {
-                        return Err(self.error(ErrorType::InvlaidUnicodeCodepoint));
-                    };
-                    // We moved o steps forword at the destiation and 6 on the source
-                    src_i += s;
-                    dst_i += o;
-                } else {
-                    // simple 1:1 conversion. Will eat bs_dist+2 characters in input and
-                    // write bs_dist+1 characters to output
-                    // note this may reach beyond the part of the buffer we've actually
-                    // seen. I think this is ok
-                    let escape_result: u8 =
-                        unsafe { *ESCAPE_MAP.get_unchecked(escape_char as usize) };
-                    if escape_result == 0 {
-                        return Err(self.error(ErrorType::InvalidEscape));
-                    }
-                    unsafe {
-                        *dst.get_unchecked_mut(dst_i + bs_dist as usize) = escape_result;
-                    }
-                    src_i += bs_dist as usize + 2;
-                    dst_i += bs_dist as usize + 1;
-                }
-            } else {
-                // they are the same. Since they can't co-occur, it means we encountered
-                // neither.
-                src_i += 32;
-                dst_i += 32;
-            }
+    // Called by the string parsers once a string with escapes is
+    // unescaped: the first `len` bytes after `idx` are taken as is from the
+    // input, the `unescaped_len` bytes following them are in `strings`.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    unsafe fn unescaped_str(
+        &mut self,
+        idx: usize,
+        len: usize,
+        unescaped_len: usize,
+    ) -> Cow<'de, str> {
+        let unescaped = slice::from_raw_parts(self.strings.as_ptr(), unescaped_len);
+        if !self.input.mutable {
+            let mut s = String::with_capacity(len + unescaped_len);
+            s.push_str(str::from_utf8_unchecked(self.input.part(idx, idx + len)));
+            s.push_str(str::from_utf8_unchecked(unescaped));
+            Cow::Owned(s)
+        } else {
+            ptr::copy_nonoverlapping(
+                unescaped.as_ptr(),
+                self.input.ptr.add(idx + len),
+                unescaped_len,
+            );
+            self.str_offset += unescaped_len;
+            let v = self.input.part(idx, idx + len + unescaped_len);
+            Cow::Borrowed(str::from_utf8_unchecked(v))
         }
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
-    fn parse_number_root(&mut self, minus: bool) -> Result<Number> {
-        let input = unsafe { &self.input.get_unchecked(self.iidx..) };
+    fn parse_number_root(&mut self, minus: bool) -> Result<Number<'de>> {
+        if let Some(n) = self.parse_non_finite() {
+            return Ok(Number::F64(n));
+        }
+        if self.raw_numbers {
+            return self.parse_number_raw().map(Number::Raw);
+        }
+        let input = unsafe { self.input.bytes().get_unchecked(self.iidx..) };
         let len = input.len();
         let mut copy = vec![0u8; len + SIMDJSON_PADDING];
         copy[len] = 0;
@@ -406,10 +486,18 @@ impl<'de> Deserializer<'de> {
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
-    fn parse_number(&mut self, minus: bool) -> Result<Number> {
-        let input = unsafe { &self.input.get_unchecked(self.iidx..) };
+    fn parse_number(&mut self, minus: bool) -> Result<Number<'de>> {
+        if let Some(n) = self.parse_non_finite() {
+            return Ok(Number::F64(n));
+        }
+        if self.raw_numbers {
+            return self.parse_number_raw().map(Number::Raw);
+        }
+        let input = unsafe { self.input.bytes().get_unchecked(self.iidx..) };
         let len = input.len();
-        if len < SIMDJSON_PADDING {
+        // A number that is the last structural is a number at the root, it
+        // can run up to the end of the input so we need the padding.
+        if len < SIMDJSON_PADDING || self.idx + 1 == self.structural_indexes.len() {
             let mut copy = vec![0u8; len + SIMDJSON_PADDING];
             unsafe {
                 copy.as_mut_ptr().copy_from(input.as_ptr(), len);
@@ -421,22 +509,54 @@ impl<'de> Deserializer<'de> {
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
-    fn parse_number_(&mut self, minus: bool) -> Result<Number> {
-        let input = unsafe { &self.input.get_unchecked(self.iidx..) };
+    fn parse_number_(&mut self, minus: bool) -> Result<Number<'de>> {
+        if let Some(n) = self.parse_non_finite() {
+            return Ok(Number::F64(n));
+        }
+        if self.raw_numbers {
+            return self.parse_number_raw().map(Number::Raw);
+        }
+        let input = unsafe { self.input.bytes().get_unchecked(self.iidx..) };
         self.parse_number_int(input, minus)
     }
+
+    // `NaN`, `Infinity` and `-Infinity` are numbers when parsing leniently.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn parse_non_finite(&self) -> Option<f64> {
+        if unlikely!(self.lenient) {
+            lenient::non_finite(unsafe { self.input.bytes().get_unchecked(self.iidx..) })
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::serde::from_slice;
+    #[cfg(feature = "serde_impl")]
+    use super::owned::to_value;
+    #[cfg(feature = "serde_impl")]
+    use super::serde::{from_slice, from_slice_ref, from_str_ref, to_string, to_string_pretty};
+    #[cfg(feature = "serde_impl")]
+    use super::{owned::Map, ErrorType};
     use super::{
-        owned::to_value, owned::Map, owned::Value, to_borrowed_value, to_owned_value, Deserializer,
+        owned::Value, to_borrowed_value, to_borrowed_value_ref, to_owned_value, to_owned_value_ref,
+        BorrowedValue, Deserializer,
     };
+    #[cfg(feature = "serde_impl")]
     use halfbrown::HashMap;
     use proptest::prelude::*;
-    use serde::Deserialize;
+    #[cfg(feature = "serde_impl")]
+    use serde::{Deserialize, Serialize};
+    #[cfg(feature = "serde_impl")]
     use serde_json;
+    use std::borrow::Cow;
+
+    #[test]
+    fn send_sync() {
+        fn is_send_sync<T: Send + Sync>() {}
+        is_send_sync::<Deserializer<'static>>();
+    }
 
     #[test]
     fn count1() {
@@ -480,6 +600,7 @@ mod tests {
         assert_eq!(simd.counts[2], 0);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn empty() {
         let mut d = String::from("");
@@ -490,6 +611,7 @@ mod tests {
         assert!(v_serde.is_err());
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn bool_true() {
         let mut d = String::from("true");
@@ -503,6 +625,7 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::from(true)));
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn bool_false() {
         let mut d = String::from("false");
@@ -516,6 +639,7 @@ mod tests {
         //assert!(false)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn union() {
         let mut d = String::from("null");
@@ -528,6 +652,7 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::Null));
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn int() {
         let mut d = String::from("42");
@@ -540,6 +665,7 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::from(42)));
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn zero() {
         let mut d = String::from("0");
@@ -552,6 +678,7 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::from(0)));
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn one() {
         let mut d = String::from("1");
@@ -564,6 +691,7 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::from(1)));
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn minus_one() {
         let mut d = String::from("-1");
@@ -576,6 +704,83 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::from(-1)));
     }
 
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn u64_max() {
+        let mut d = String::from("18446744073709551615");
+        let mut d1 = d.clone();
+        let mut d1 = unsafe { d1.as_bytes_mut() };
+        let mut d = unsafe { d.as_bytes_mut() };
+        let v_serde: serde_json::Value = serde_json::from_slice(d).expect("");
+        let v_simd: serde_json::Value = from_slice(&mut d).expect("");
+        assert_eq!(v_simd, v_serde);
+        assert_eq!(to_value(&mut d1), Ok(Value::U64(u64::max_value())));
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn i64_bounds() {
+        use crate::ValueTrait;
+        let mut d =
+            String::from("[9223372036854775807, 9223372036854775808, -9223372036854775808]");
+        let mut d1 = d.clone();
+        let mut d1 = unsafe { d1.as_bytes_mut() };
+        let mut d = unsafe { d.as_bytes_mut() };
+        let v_serde: serde_json::Value = serde_json::from_slice(d).expect("");
+        let v_simd: serde_json::Value = from_slice(&mut d).expect("");
+        assert_eq!(v_simd, v_serde);
+        let v = to_value(&mut d1).expect("");
+        assert_eq!(
+            v,
+            Value::Array(vec![
+                Value::I64(i64::max_value()),
+                Value::U64(1 << 63),
+                Value::I64(i64::min_value())
+            ])
+        );
+        let a = v.as_array().expect("");
+        assert_eq!(a[1].as_u64(), Some(1 << 63));
+        assert_eq!(a[1].as_i64(), None);
+        assert_eq!(a[2].as_u64(), None);
+        assert_eq!(a[2].as_i64(), Some(i64::min_value()));
+        assert!(a[1] == 1u64 << 63);
+        assert!(to_value(&mut String::from("18446744073709551616").into_bytes()).is_err());
+        assert!(to_value(&mut String::from("-9223372036854775809").into_bytes()).is_err());
+    }
+
+    #[test]
+    fn u64_from() {
+        use crate::ValueTrait;
+        assert_eq!(Value::from(42u64), Value::I64(42));
+        assert_eq!(Value::from(u64::max_value()), Value::U64(u64::max_value()));
+        assert_eq!(
+            BorrowedValue::from(u64::max_value()),
+            BorrowedValue::U64(u64::max_value())
+        );
+        assert_eq!(Value::from(-1).as_u64(), None);
+        assert_eq!(BorrowedValue::from(-1).as_u64(), None);
+        assert_eq!(
+            Value::from(BorrowedValue::U64(u64::max_value())),
+            Value::U64(u64::max_value())
+        );
+        #[cfg(feature = "serde_impl")]
+        {
+            let v: u64 =
+                from_slice(&mut String::from("18446744073709551615").into_bytes()).expect("");
+            assert_eq!(v, u64::max_value());
+            assert!(
+                from_slice::<i64>(&mut String::from("18446744073709551615").into_bytes()).is_err()
+            );
+            let v: u64 = crate::serde::from_owned_value(Value::U64(u64::max_value())).expect("");
+            assert_eq!(v, u64::max_value());
+            assert_eq!(
+                crate::serde::to_owned_value(u64::max_value()),
+                Ok(Value::U64(u64::max_value()))
+            );
+        }
+    }
+
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn float() {
         let mut d = String::from("23.0");
@@ -588,6 +793,7 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::from(23.0)));
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn string() {
         let mut d = String::from(r#""snot""#);
@@ -600,6 +806,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn lonely_quote() {
         let mut d = String::from(r#"""#);
@@ -610,6 +817,7 @@ mod tests {
         assert!(v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn lonely_quote1() {
         let mut d = String::from(r#"["]"#);
@@ -619,6 +827,7 @@ mod tests {
         assert!(v_simd);
         assert!(v_serde);
     }
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn lonely_quote2() {
         let mut d = String::from(r#"[1, "]"#);
@@ -629,6 +838,7 @@ mod tests {
         assert!(v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn lonely_quote3() {
         let mut d = String::from(r#"{": 1}"#);
@@ -639,6 +849,7 @@ mod tests {
         assert!(v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn empty_string() {
         let mut d = String::from(r#""""#);
@@ -651,6 +862,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn empty_array() {
         let mut d = String::from(r#"[]"#);
@@ -663,6 +875,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn malformed_array() {
         let mut d = String::from(r#"[["#);
@@ -681,6 +894,7 @@ mod tests {
         assert!(v_serde.is_err());
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn double_array() {
         let mut d = String::from(r#"[[]]"#);
@@ -696,6 +910,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn null_null_array() {
         let mut d = String::from(r#"[[],null,null]"#);
@@ -715,6 +930,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn one_element_array() {
         let mut d = String::from(r#"["snot"]"#);
@@ -730,6 +946,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn two_element_array() {
         let mut d = String::from(r#"["snot", "badger"]"#);
@@ -748,6 +965,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn list() {
         let mut d = String::from(r#"[42, 23.0, "snot badger"]"#);
@@ -767,6 +985,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn nested_list1() {
         let mut d = String::from(r#"[42, [23.0, "snot"], "bad", "ger"]"#);
@@ -788,6 +1007,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn nested_list2() {
         let mut d = String::from(r#"[42, [23.0, "snot"], {"bad": "ger"}]"#);
@@ -797,6 +1017,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn utf8() {
         let mut d = String::from(r#""\u000e""#);
@@ -808,6 +1029,7 @@ mod tests {
         //assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn unicode() {
         let mut d = String::from(r#""¡\"""#);
@@ -817,6 +1039,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn odd_array() {
         let mut d = String::from("[{},null]");
@@ -832,6 +1055,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn map2() {
         let mut d = String::from(r#"[{"\u0000":null}]"#);
@@ -841,6 +1065,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn null() {
         let mut d = String::from(r#"null"#);
@@ -852,6 +1077,7 @@ mod tests {
         let v_simd: serde_json::Value = from_slice(&mut d).expect("");
         assert_eq!(v_simd, v_serde);
     }
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn null_null() {
         let mut d = String::from(r#"[null, null]"#);
@@ -867,6 +1093,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn nested_null() {
         let mut d = String::from(r#"[[null, null]]"#);
@@ -886,6 +1113,7 @@ mod tests {
         assert_eq!(v_simd, v_serde);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn nestednested_null() {
         let mut d = String::from(r#"[[[null, null]]]"#);
@@ -904,6 +1132,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn odd_array2() {
         let mut d = String::from("[[\"\\u0000\\\"\"]]");
@@ -913,6 +1142,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn odd_array3() {
         let mut d = String::from("[{\"\\u0000\\u0000\":null}]");
@@ -922,6 +1152,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn odd_array4() {
         let mut d = String::from("[{\"\\u0000𐀀a\":null}]");
@@ -931,6 +1162,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn float1() {
         let mut d = String::from("2.3250706903316115e307");
@@ -941,6 +1173,7 @@ mod tests {
     }
 
     // We ignore this since serde is less percise on this test
+    #[cfg(feature = "serde_impl")]
     #[ignore]
     #[test]
     fn float2() {
@@ -951,6 +1184,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn map0() {
         let mut d = String::from(r#"{"snot": "badger"}"#);
@@ -965,6 +1199,7 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::Object(h)));
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn map1() {
         let mut d = String::from(r#"{"snot": "badger", "badger": "snot"}"#);
@@ -980,6 +1215,7 @@ mod tests {
         assert_eq!(to_value(&mut d1), Ok(Value::Object(h)));
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn tpl1() {
         let mut d = String::from("[-65.613616999999977, 43.420273000000009]");
@@ -989,6 +1225,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn tpl2() {
         let mut d = String::from("[[-65.613616999999977, 43.420273000000009]]");
@@ -998,6 +1235,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn tpl3() {
         let mut d = String::from(
@@ -1008,6 +1246,7 @@ mod tests {
         let v_simd: Vec<(f32, f32)> = from_slice(&mut d).expect("simd_json");
         assert_eq!(v_simd, v_serde)
     }
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn tpl4() {
         let mut d = String::from("[[[-65.613616999999977,43.420273000000009]]]");
@@ -1016,6 +1255,7 @@ mod tests {
         let v_simd: Vec<Vec<(f32, f32)>> = from_slice(&mut d).expect("simd_json");
         assert_eq!(v_simd, v_serde)
     }
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn tpl5() {
         let mut d = String::from("[[[-65.613616999999977,43.420273000000009], [-65.613616999999977,43.420273000000009]]]");
@@ -1025,6 +1265,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn tpl6() {
         let mut d = String::from("[[[[-65.613616999999977,43.420273000000009], [-65.613616999999977,43.420273000000009]]]]");
@@ -1034,6 +1275,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn tpl7() {
         let mut d = String::from("[[[[-65.613616999999977,43.420273000000009], [-65.613616999999977,43.420273000000009]]]]");
@@ -1043,17 +1285,20 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
-    #[derive(Deserialize, PartialEq, Debug)]
+    #[cfg(feature = "serde_impl")]
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
     struct Obj {
         a: u64,
         b: u64,
     }
 
-    #[derive(Deserialize, PartialEq, Debug)]
+    #[cfg(feature = "serde_impl")]
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
     struct Obj1 {
         a: Obj,
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn obj() {
         let mut d = String::from(r#"{"a": 1, "b":1}"#);
@@ -1063,6 +1308,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn obj2() {
         let mut d =
@@ -1070,9 +1316,39 @@ mod tests {
         let mut d = unsafe { d.as_bytes_mut() };
         let v_serde: HashMap<String, Obj> = serde_json::from_slice(d).expect("serde_json");
         let v_simd: HashMap<String, Obj> = from_slice(&mut d).expect("simd_json");
-        assert_eq!(v_simd, v_serde)
+        assert_eq!(v_simd, v_serde);
+        assert_eq!(
+            to_string(&v_simd).expect("simd_json"),
+            serde_json::to_string(&v_simd).expect("serde_json")
+        );
+        assert_eq!(
+            to_string_pretty(&v_simd).expect("simd_json"),
+            serde_json::to_string_pretty(&v_simd).expect("serde_json")
+        );
     }
 
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn obj_error_pointer() {
+        let mut d = br#"{"a": {"a": 1, "b": 1}, "b": {"a": 1, "b": -1}}"#.to_vec();
+        let e = from_slice::<HashMap<String, Obj>>(&mut d).unwrap_err();
+        assert_eq!(e.error_type(), &ErrorType::ExpectedUnsigned);
+        assert_eq!(e.pointer(), Some("/b/b"));
+        let mut d = br#"[{"a": 1, "b": 1}, {"a": "1", "b": 1}]"#.to_vec();
+        let e = from_slice::<Vec<Obj>>(&mut d).unwrap_err();
+        assert_eq!(e.pointer(), Some("/1/a"));
+        // errors raised by serde get placed at the value they are about
+        let mut d = br#"[{"a": 1, "b": 1}, {"a": 1}]"#.to_vec();
+        let e = from_slice::<Vec<Obj>>(&mut d).unwrap_err();
+        assert_eq!(
+            e.error_type(),
+            &ErrorType::Serde("missing field `b`".to_string())
+        );
+        assert_eq!(e.pointer(), Some("/1"));
+        assert_eq!(e.offset(), Some(26));
+    }
+
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn obj3() {
         let mut d = String::from(
@@ -1085,6 +1361,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn obj4() {
         let mut d = String::from(r#"{"c": {"a": {"a": 1, "b":1}}}"#);
@@ -1094,6 +1371,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn vecvec() {
         let mut d = String::from("[[[-65.613616999999977,43.420273000000009], [-65.613616999999977,43.420273000000009]], [[-65.613616999999977,43.420273000000009], [-65.613616999999977,43.420273000000009]]]");
@@ -1103,6 +1381,7 @@ mod tests {
         assert_eq!(v_simd, v_serde)
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn crazy_string() {
         // there is unicode in here!
@@ -1115,8 +1394,57 @@ mod tests {
     }
 
     #[test]
-    fn event() {
+    fn immutable_input() {
+        let d = br#"{"plain": "abc", "escaped": "a\"b\u00e9c", "n": [1, 2.5]}"#;
+        let orig = d.to_vec();
+        let mut d1 = d.to_vec();
+
+        let v = to_borrowed_value_ref(d).expect("");
+        assert_eq!(&d[..], &orig[..]);
+        if let BorrowedValue::Object(o) = &v {
+            assert!(match &o["plain"] {
+                BorrowedValue::String(Cow::Borrowed("abc")) => true,
+                _ => false,
+            });
+            assert!(match &o["escaped"] {
+                BorrowedValue::String(Cow::Owned(s)) => s == "a\"béc",
+                _ => false,
+            });
+        } else {
+            panic!("not an object")
+        }
+        assert_eq!(v, to_borrowed_value(&mut d1).expect(""));
+        assert_eq!(to_owned_value_ref(d).expect(""), Value::from(v));
+        assert_eq!(&d[..], &orig[..]);
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn immutable_input_serde() {
         #[derive(Deserialize, Debug, PartialEq)]
+        struct Test<'a> {
+            plain: &'a str,
+            escaped: String,
+        }
+        let d = r#"{"plain": "abc", "escaped": "a\nb"}"#;
+        let v: Test = from_str_ref(d).expect("");
+        assert_eq!(
+            v,
+            Test {
+                plain: "abc",
+                escaped: String::from("a\nb")
+            }
+        );
+        let v_serde: serde_json::Value = serde_json::from_str(d).expect("");
+        let v_simd: serde_json::Value = from_slice_ref(d.as_bytes()).expect("");
+        assert_eq!(v_simd, v_serde);
+        assert_eq!(d, r#"{"plain": "abc", "escaped": "a\nb"}"#);
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn event() {
+        #[derive(Deserialize, Serialize, Debug, PartialEq)]
         #[serde(deny_unknown_fields, rename_all = "camelCase")]
         pub struct CitmCatalog {
             pub area_names: HashMap<String, String>,
@@ -1125,7 +1453,7 @@ mod tests {
             pub events: HashMap<String, Event>,
         }
         pub type Id = u32;
-        #[derive(Deserialize, Debug, PartialEq)]
+        #[derive(Deserialize, Serialize, Debug, PartialEq)]
         #[serde(deny_unknown_fields, rename_all = "camelCase")]
         pub struct Event {
             pub description: (),
@@ -1206,7 +1534,15 @@ mod tests {
         let mut d = unsafe { d.as_bytes_mut() };
         let v_serde: CitmCatalog = serde_json::from_slice(d).expect("serde_json");
         let v_simd: CitmCatalog = from_slice(&mut d).expect("simd_json");
-        assert_eq!(v_simd, v_serde)
+        assert_eq!(v_simd, v_serde);
+        assert_eq!(
+            to_string(&v_simd).expect("simd_json"),
+            serde_json::to_string(&v_simd).expect("serde_json")
+        );
+        assert_eq!(
+            to_string_pretty(&v_simd).expect("simd_json"),
+            serde_json::to_string_pretty(&v_simd).expect("serde_json")
+        );
     }
 
     // How much do we care about this, it's within the same range and
@@ -1223,7 +1559,6 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn silly_float2() {
         let v = Value::from(-6.990585694841803e305);
         let s = v.to_string();
@@ -1234,6 +1569,7 @@ mod tests {
     }
 
     //6.576692109929364e305
+    #[cfg(feature = "serde_impl")]
     fn arb_json() -> BoxedStrategy<String> {
         let leaf = prop_oneof![
             Just(Value::Null),
@@ -1258,6 +1594,7 @@ mod tests {
         .boxed()
     }
 
+    #[cfg(feature = "serde_impl")]
     fn arb_json_value() -> BoxedStrategy<Value> {
         let leaf = prop_oneof![
             Just(Value::Null),
@@ -1281,6 +1618,7 @@ mod tests {
         .boxed()
     }
 
+    #[cfg(feature = "serde_impl")]
     proptest! {
         #![proptest_config(ProptestConfig {
             // Setting both fork and timeout is redundant since timeout implies
@@ -1299,6 +1637,7 @@ mod tests {
         }
 
     }
+    #[cfg(feature = "serde_impl")]
     proptest! {
         #![proptest_config(ProptestConfig {
             // Setting both fork and timeout is redundant since timeout implies
@@ -1319,12 +1658,21 @@ mod tests {
                 let mut d3 = d.clone();
                 let d3 = unsafe{ d3.as_bytes_mut()};
                 assert_eq!(v_simd_serde, v_serde);
+                assert_eq!(to_string(&v_serde).expect(""), serde_json::to_string(&v_serde).expect(""));
+                assert_eq!(
+                    to_string_pretty(&v_serde).expect(""),
+                    serde_json::to_string_pretty(&v_serde).expect("")
+                );
                 let v_simd_owned = to_owned_value(d2);
                 assert!(v_simd_owned.is_ok());
                 let v_simd_borrowed = to_borrowed_value(d3);
                 dbg!(&v_simd_borrowed);
                 assert!(v_simd_borrowed.is_ok());
-                assert_eq!(v_simd_owned.unwrap(), super::OwnedValue::from(v_simd_borrowed.unwrap()));
+                let v_simd_borrowed = v_simd_borrowed.unwrap();
+                assert_eq!(v_simd_owned.unwrap(), super::OwnedValue::from(v_simd_borrowed.clone()));
+                let v_simd_ref: serde_json::Value = from_slice_ref(d.as_bytes()).expect("");
+                assert_eq!(v_simd_ref, v_serde);
+                assert_eq!(to_borrowed_value_ref(d.as_bytes()).expect(""), v_simd_borrowed);
             }
 
         }
@@ -1343,10 +1691,12 @@ mod tests {
         })]
         #[test]
         fn prop_junk(d in arb_junk()) {
+            #[cfg(feature = "serde_impl")]
             let mut d1 = d.clone();
             let mut d2 = d.clone();
             let mut d3 = d.clone();
 
+            #[cfg(feature = "serde_impl")]
             let _ = from_slice::<serde_json::Value>(&mut d1);
             let _ = to_borrowed_value(&mut d2);
             let _ = to_owned_value(&mut d3);
@@ -1364,12 +1714,15 @@ mod tests {
 
         #[test]
         fn prop_string(d in "\\PC*") {
+            #[cfg(feature = "serde_impl")]
             let mut d1 = d.clone();
+            #[cfg(feature = "serde_impl")]
             let mut d1 = unsafe{ d1.as_bytes_mut()};
             let mut d2 = d.clone();
             let mut d2 = unsafe{ d2.as_bytes_mut()};
             let mut d3 = d.clone();
             let mut d3 = unsafe{ d3.as_bytes_mut()};
+            #[cfg(feature = "serde_impl")]
             let _ = from_slice::<serde_json::Value>(&mut d1);
             let _ = to_borrowed_value(&mut d2);
             let _ = to_owned_value(&mut d3);
@@ -0,0 +1,53 @@
+/// Upper bounds for documents from untrusted sources, see
+/// `Parser::limits`.
+///
+/// Everything but the nesting depth is unlimited by default, the depth
+/// is limited to 1024 since parsing a value recurses once per level.
+///
+/// ```
+/// use simd_json::{ErrorType, Limits, Parser};
+/// let mut parser = Parser::new().limits(Limits {
+///     max_string_length: 8,
+///     max_elements: 3,
+///     ..Limits::default()
+/// });
+/// let mut d = br#"["short", "strings"]"#.to_vec();
+/// assert!(parser.parse_owned(&mut d).is_ok());
+/// let mut d = br#"["a very long string"]"#.to_vec();
+/// let e = parser.parse_owned(&mut d).unwrap_err();
+/// assert_eq!(e.error_type(), &ErrorType::StringLengthLimit);
+/// let mut d = br#"[1, 2, 3, 4]"#.to_vec();
+/// let e = parser.parse_owned(&mut d).unwrap_err();
+/// assert_eq!(e.error_type(), &ErrorType::ElementCountLimit);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// How deep arrays and objects can be nested, fails with
+    /// `ErrorType::DepthLimit`.
+    pub max_depth: usize,
+    /// The size of a document in bytes, fails with
+    /// `ErrorType::DocumentSizeLimit`. For streams of documents this
+    /// applies to every document on its own.
+    pub max_document_size: usize,
+    /// The length of a string or key in bytes as written in the input,
+    /// escape sequences included, fails with `ErrorType::StringLengthLimit`.
+    pub max_string_length: usize,
+    /// The number of elements in an array, fails with
+    /// `ErrorType::ElementCountLimit`.
+    pub max_elements: usize,
+    /// The number of keys in an object, fails with
+    /// `ErrorType::KeyCountLimit`.
+    pub max_keys: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 1024,
+            max_document_size: usize::max_value(),
+            max_string_length: usize::max_value(),
+            max_elements: usize::max_value(),
+            max_keys: usize::max_value(),
+        }
+    }
+}
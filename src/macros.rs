@@ -401,17 +401,22 @@ macro_rules! stry {
 
 #[cfg(test)]
 mod test {
+    #[cfg(feature = "serde_impl")]
     use crate::*;
+    #[cfg(feature = "serde_impl")]
     use halfbrown::hashmap;
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn array() {
         let v: OwnedValue = json!(vec![1]);
         assert_eq!(OwnedValue::Array(vec![OwnedValue::I64(1)]), v);
     }
 
+    #[cfg(feature = "serde_impl")]
     #[test]
     fn obj() {
         let v: OwnedValue = json!(hashmap! {"test" => 1});
-        assert_eq!(OwnedValue::Object(hashmap! {"test".into() => 1.into()}), v);
+        let expected = hashmap! {"test".into() => 1.into()};
+        assert_eq!(OwnedValue::Object(expected.into_iter().collect()), v);
     }
 }
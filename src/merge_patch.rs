@@ -0,0 +1,172 @@
+//! JSON Merge Patch ([RFC 7396](https://tools.ietf.org/html/rfc7396))
+//! for `OwnedValue`.
+//!
+//! A merge patch looks like the document it changes: members of objects
+//! in it are merged into the document, `null` removes a member and
+//! anything else replaces what is there.
+//!
+//! ```
+//! use simd_json::{json, merge_patch};
+//!
+//! let mut doc = json!({"a": {"b": 1, "c": 2}, "d": [1, 2]});
+//! let p = json!({"a": {"b": null, "e": 3}, "d": [3]});
+//! merge_patch::apply(&mut doc, &p);
+//! assert_eq!(doc, json!({"a": {"c": 2, "e": 3}, "d": [3]}));
+//!
+//! let to = json!({"a": {"c": 2}, "f": true});
+//! let p = merge_patch::diff(&doc, &to);
+//! assert_eq!(p, json!({"a": {"e": null}, "d": null, "f": true}));
+//! merge_patch::apply(&mut doc, &p);
+//! assert_eq!(doc, to);
+//! ```
+
+use crate::value::borrowed::Value as BorrowedValue;
+use crate::value::owned::{Map, Value};
+use crate::value::{equal_nodes, ObjectTrait, ValueTrait};
+
+// What a patch needs to be able to do besides being a value: turn the
+// parts of it that end up in the document into owned values.
+trait Patch: ValueTrait {
+    fn to_owned_value(&self) -> Value;
+}
+
+impl Patch for Value {
+    fn to_owned_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl Patch for BorrowedValue<'_> {
+    fn to_owned_value(&self) -> Value {
+        match self {
+            BorrowedValue::Null => Value::Null,
+            BorrowedValue::Bool(b) => Value::Bool(*b),
+            BorrowedValue::F64(f) => Value::F64(*f),
+            BorrowedValue::I64(i) => Value::I64(*i),
+            BorrowedValue::U64(i) => Value::U64(*i),
+            BorrowedValue::RawNumber(n) => Value::RawNumber(n.to_string()),
+            BorrowedValue::String(s) => Value::from(s.to_string()),
+            BorrowedValue::Array(a) => Value::Array(a.iter().map(Patch::to_owned_value).collect()),
+            BorrowedValue::Object(m) => Value::Object(
+                m.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_owned_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Merges a patch into a document. If the patch is an object its
+/// members are merged into the members of the document with the same
+/// key, where `null` removes the member, otherwise it replaces the
+/// document. A document that isn't an object becomes an empty one
+/// before an object is merged into it.
+pub fn apply(target: &mut Value, patch: &Value) {
+    merge(target, patch)
+}
+
+/// Merges a patch that borrows from its input into a document, like
+/// `apply`. Only the parts of the patch that end up in the document are
+/// copied.
+pub fn apply_borrowed(target: &mut Value, patch: &BorrowedValue<'_>) {
+    merge(target, patch)
+}
+
+fn merge<P: Patch>(target: &mut Value, patch: &P) {
+    let members = match patch.as_object() {
+        Some(members) => members,
+        None => {
+            *target = patch.to_owned_value();
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = Value::object();
+    }
+    if let Value::Object(m) = target {
+        for (k, v) in members.iter() {
+            if v.is_null() {
+                ObjectTrait::remove(m, k);
+            } else if let Some(e) = m.get_mut(k) {
+                merge(e, v);
+            } else {
+                let mut e = Value::Null;
+                merge(&mut e, v);
+                m.insert(k.to_string(), e);
+            }
+        }
+    }
+}
+
+/// A merge patch that turns `from` into `to`. Objects are compared key by
+/// key, values that are objects in both are compared in turn, everything
+/// else that differs is replaced as a whole. Numbers are the same if
+/// their values are.
+///
+/// Merge patches can't set a member to `null`, so members that are
+/// `null` in `to` but not in `from` get removed when the patch is
+/// applied, as do `null`s in new objects.
+pub fn diff(from: &Value, to: &Value) -> Value {
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut m = Map::new();
+            for k in a.keys() {
+                if !b.contains_key(k) {
+                    m.insert(k.clone(), Value::Null);
+                }
+            }
+            for (k, w) in b.iter() {
+                match a.get(k) {
+                    Some(v) if equal_nodes(v, w) => (),
+                    Some(v) => {
+                        m.insert(k.clone(), diff(v, w));
+                    }
+                    None => {
+                        m.insert(k.clone(), w.clone());
+                    }
+                }
+            }
+            Value::Object(m)
+        }
+        _ => to.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "serde_impl")]
+    use super::*;
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn borrowed() {
+        let mut d = br#"{"a": {"b": null, "c": [1, {"d": null}]}, "e": 1.5, "f": "g"}"#.to_vec();
+        let p = crate::to_borrowed_value(&mut d).unwrap();
+        let mut doc = json!({"a": {"b": 1}, "e": null});
+        apply_borrowed(&mut doc, &p);
+        assert_eq!(
+            doc,
+            json!({"a": {"c": [1, {"d": null}]}, "e": 1.5, "f": "g"})
+        );
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn nulls() {
+        // a null in `to` can't be told apart from a removal
+        let from = json!({"a": 1, "b": {"c": 2}});
+        let to = json!({"a": null, "b": {"c": 2, "d": null}, "e": {"f": null}});
+        let p = diff(&from, &to);
+        assert_eq!(p, json!({"a": null, "b": {"d": null}, "e": {"f": null}}));
+        let mut doc = from.clone();
+        apply(&mut doc, &p);
+        assert_eq!(doc, json!({"b": {"c": 2}, "e": {}}));
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn numbers() {
+        assert_eq!(diff(&json!({"a": 1}), &json!({"a": 1.0})), json!({}));
+        assert_eq!(diff(&json!([1, 2]), &json!([1, 2])), json!([1, 2]));
+    }
+}
@@ -0,0 +1,507 @@
+/// Parsing of newline delimited JSON (NDJSON / JSON Lines) where every
+/// line of the input holds one document.
+///
+/// Stage 1 runs once over the whole input, the structural indexes are
+/// then split where the nesting depth gets back to zero and stage 2 runs
+/// per document. This way an invalid document results in an error for
+/// this document only and the remaining ones are still parsed. Documents
+/// may span lines or share one, but a document spanning lines that turns
+/// out to be invalid only fails its first line: an object that is never
+/// closed would swallow every document after it otherwise. If stage 1
+/// fails for the input as a whole, say due to an unterminated string, we
+/// fall back to running it line by line. The same goes for lenient
+/// parsing, comments are stripped from every line before stage 1 runs
+/// over it so they can't span lines. JSON5 records are parsed line by
+/// line as well, strings continued on the next line are not supported
+/// here.
+use crate::value::borrowed::BorrowDeserializer;
+use crate::value::owned::OwnedDeserializer;
+use crate::{
+    json5, lenient, BorrowedValue, Deserializer, Error, Input, OwnedValue, Parser, Result,
+};
+#[cfg(feature = "serde_impl")]
+use serde_ext::Deserialize;
+use std::marker::PhantomData;
+
+struct Records<'p, 'de> {
+    parser: &'p mut Parser,
+    input: Input<'de>,
+    // Whether stage 1 succeeded for the whole input, its structural indexes
+    // are in the parser's `documents` buffer then. Otherwise we index line
+    // by line.
+    indexed: bool,
+    // start of the next record
+    pos: usize,
+    // first structural index in `documents` belonging to the next record
+    sidx: usize,
+}
+
+// Looks for the end of the document starting at the first of the given
+// structural indexes, returns the number of structural indexes belonging
+// to it and the offset right after it. A document that is an object or
+// array ends with the structural closing it, any other ends where the
+// next structural starts. One that isn't closed takes up the rest of the
+// input.
+fn document_end(input: &[u8], structural_indexes: &[u32]) -> (usize, usize) {
+    let mut depth = 0;
+    for (i, idx) in structural_indexes.iter().enumerate() {
+        let idx = *idx as usize;
+        match input[idx] {
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth == 1 => return (i + 1, idx + 1),
+            b'}' | b']' if depth > 1 => depth -= 1,
+            _ if depth == 0 => {
+                let end = structural_indexes
+                    .get(1)
+                    .map_or(input.len(), |next| *next as usize);
+                return (1, end);
+            }
+            _ => (),
+        }
+    }
+    (structural_indexes.len(), input.len())
+}
+
+impl<'p, 'de> Records<'p, 'de> {
+    fn new(parser: &'p mut Parser, input: &'de mut [u8]) -> Self {
+        let input = Input::from_mut(input);
+        let indexed = !parser.lenient
+            && !parser.json5
+            && unsafe {
+                Deserializer::find_structural_bits_into(
+                    input.bytes(),
+                    parser.implementation,
+                    &mut parser.buffers.documents,
+                )
+            }
+            .is_ok();
+        Self {
+            parser,
+            input,
+            indexed,
+            pos: 0,
+            // skip the extra root element
+            sidx: 1,
+        }
+    }
+
+    // Indexes the next record and creates a deserializer for it, the
+    // caller has to hand the buffers back to the parser when done.
+    fn next_record(&mut self) -> Option<Result<Deserializer<'de>>> {
+        if self.indexed {
+            self.next_document()
+        } else {
+            self.next_line()
+        }
+    }
+
+    // Splits the next document off the structural indexes of the whole
+    // input.
+    fn next_document(&mut self) -> Option<Result<Deserializer<'de>>> {
+        let first = self.sidx;
+        let start = self.pos;
+        let documents = &self.parser.buffers.documents;
+        if first >= documents.len() {
+            return None;
+        }
+        let (count, end) = document_end(self.input.bytes(), &documents[first..]);
+        self.pos = end;
+        self.sidx = first + count;
+        let r = self.document(start, first, count, end);
+        if r.is_ok() {
+            return Some(r);
+        }
+
+        // Where an invalid document ends is a guess, if it spans lines
+        // only the first one fails and we go on after it.
+        let documents = &self.parser.buffers.documents;
+        let from = documents[first] as usize;
+        let line_end = match self.input.bytes()[from..end]
+            .iter()
+            .position(|c| *c == b'\n')
+        {
+            Some(i) => from + i,
+            None => return Some(r),
+        };
+        let count = documents[first..first + count]
+            .iter()
+            .take_while(|i| (**i as usize) < line_end)
+            .count();
+        self.pos = line_end;
+        self.sidx = first + count;
+        Some(self.document(start, first, count, line_end))
+    }
+
+    // Runs stage 2 over the input from `start` to `end` using `count`
+    // structural indexes from `first` on.
+    fn document(
+        &mut self,
+        start: usize,
+        first: usize,
+        count: usize,
+        end: usize,
+    ) -> Result<Deserializer<'de>> {
+        let buffers = &mut self.parser.buffers;
+        buffers.structural_indexes.clear();
+        buffers.structural_indexes.push(0); // push extra root element
+        buffers.structural_indexes.extend(
+            buffers.documents[first..first + count]
+                .iter()
+                .map(|i| *i - start as u32),
+        );
+        let record = self.input.sub(start, end);
+        self.validate(record)
+    }
+
+    // Indexes the next non empty line on its own.
+    fn next_line(&mut self) -> Option<Result<Deserializer<'de>>> {
+        loop {
+            if self.pos >= self.input.len() {
+                return None;
+            }
+            let start = self.pos;
+            let end = self.input.bytes()[start..]
+                .iter()
+                .position(|c| *c == b'\n')
+                .map_or(self.input.len(), |i| start + i);
+            self.pos = end + 1;
+            let mut record = self.input.sub(start, end);
+            if self.parser.lenient {
+                if let Some(Err(e)) = record.bytes_mut().map(lenient::strip_comments) {
+                    return Some(Err(e));
+                }
+            }
+            let line = record.bytes();
+            if line
+                .iter()
+                .all(|c| *c == b' ' || *c == b'\t' || *c == b'\r')
+            {
+                continue;
+            }
+            let buffers = &mut self.parser.buffers;
+            if let Err(e) = unsafe {
+                Deserializer::find_structural_bits_into(
+                    line,
+                    self.parser.implementation,
+                    &mut buffers.structural_indexes,
+                )
+            } {
+                return Some(Err(Error::stage1(line, e)));
+            }
+            if self.parser.lenient {
+                lenient::drop_trailing_commas(line, &mut buffers.structural_indexes);
+            }
+            return Some(self.validate(record));
+        }
+    }
+
+    // Runs stage 2 over a record using the structural indexes in the
+    // parser's buffers.
+    fn validate(&mut self, record: Input<'de>) -> Result<Deserializer<'de>> {
+        let buffers = &mut self.parser.buffers;
+        stry!(Deserializer::validate_into(
+            record.bytes(),
+            &buffers.structural_indexes,
+            &self.parser.limits,
+            self.parser.lenient,
+            &mut buffers.counts,
+            &mut buffers.stack,
+        ));
+        buffers.strings.clear();
+        buffers
+            .strings
+            .reserve(record.len() + crate::SIMDJSON_PADDING);
+
+        Ok(Deserializer::from_buffers(
+            record,
+            self.parser.implementation,
+            self.parser.raw_numbers,
+            self.parser.duplicate_keys,
+            self.parser.lenient,
+            buffers,
+        ))
+    }
+
+    // Parses the next non empty line as JSON5, lines holding nothing but
+    // comments count as empty.
+    fn next_json5(&mut self) -> Option<Result<BorrowedValue<'de>>> {
+        while self.pos < self.input.len() {
+            let start = self.pos;
+            let end = self.input.bytes()[start..]
+                .iter()
+                .position(|c| *c == b'\n')
+                .map_or(self.input.len(), |i| start + i);
+            self.pos = end + 1;
+            let record = unsafe { self.input.part(start, end) };
+            match json5::parse_document(self.parser, record) {
+                Ok(Some(v)) => return Some(Ok(v)),
+                Ok(None) => (),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+/// Iterator over the records of a NDJSON input, created by
+/// `Parser::parse_many_borrowed` and `Parser::parse_many_owned`.
+pub struct ParseMany<'p, 'de, V> {
+    records: Records<'p, 'de>,
+    _value: PhantomData<V>,
+}
+
+impl<'p, 'de> Iterator for ParseMany<'p, 'de, BorrowedValue<'de>> {
+    type Item = Result<BorrowedValue<'de>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.records.parser.json5 {
+            return self.records.next_json5();
+        }
+        let de = match self.records.next_record()? {
+            Ok(de) => de,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut de = BorrowDeserializer::from_deserializer(de);
+        let r = de.parse();
+        de.into_deserializer()
+            .return_buffers(&mut self.records.parser.buffers);
+        Some(r)
+    }
+}
+
+impl<'p, 'de> Iterator for ParseMany<'p, 'de, OwnedValue> {
+    type Item = Result<OwnedValue>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.records.parser.json5 {
+            let r = self.records.next_json5()?;
+            return Some(r.map(OwnedValue::from));
+        }
+        let de = match self.records.next_record()? {
+            Ok(de) => de,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut de = OwnedDeserializer::from_deserializer(de);
+        let r = de.parse();
+        de.into_deserializer()
+            .return_buffers(&mut self.records.parser.buffers);
+        Some(r)
+    }
+}
+
+/// Iterator deserializing the records of a NDJSON input using serde,
+/// created by `Parser::deserialize_many`.
+#[cfg(feature = "serde_impl")]
+pub struct DeserializeMany<'p, 'de, T> {
+    records: Records<'p, 'de>,
+    _value: PhantomData<T>,
+}
+
+#[cfg(feature = "serde_impl")]
+impl<'p, 'de, T> Iterator for DeserializeMany<'p, 'de, T>
+where
+    T: Deserialize<'de>,
+{
+    type Item = Result<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.records.parser.json5 {
+            let r = self.records.next_json5()?;
+            return Some(r.and_then(T::deserialize));
+        }
+        let mut de = match self.records.next_record()? {
+            Ok(de) => de,
+            Err(e) => return Some(Err(e)),
+        };
+        let r = T::deserialize(&mut de).map_err(|e| de.locate(e));
+        de.return_buffers(&mut self.records.parser.buffers);
+        Some(r)
+    }
+}
+
+impl Parser {
+    /// Parses newline delimited JSON into borrowed Value doms, one per
+    /// document. Documents are usually on lines of their own but may span
+    /// lines or share one. An invalid document yields an error and parsing
+    /// continues with the next one.
+    ///
+    /// ```
+    /// let mut parser = simd_json::Parser::new();
+    /// let mut d = b"{\"id\": 1}\n{\"id\": }\n[2]\n".to_vec();
+    /// let r: Vec<_> = parser.parse_many_borrowed(&mut d).collect();
+    /// assert_eq!(r.len(), 3);
+    /// assert!(r[1].is_err());
+    /// ```
+    pub fn parse_many_borrowed<'p, 'de>(
+        &'p mut self,
+        input: &'de mut [u8],
+    ) -> ParseMany<'p, 'de, BorrowedValue<'de>> {
+        ParseMany {
+            records: Records::new(self, input),
+            _value: PhantomData,
+        }
+    }
+
+    /// Parses newline delimited JSON into owned Value doms, see
+    /// `parse_many_borrowed`.
+    pub fn parse_many_owned<'p, 'de>(
+        &'p mut self,
+        input: &'de mut [u8],
+    ) -> ParseMany<'p, 'de, OwnedValue> {
+        ParseMany {
+            records: Records::new(self, input),
+            _value: PhantomData,
+        }
+    }
+
+    /// Deserializes newline delimited JSON using serde, see
+    /// `parse_many_borrowed`.
+    #[cfg(feature = "serde_impl")]
+    pub fn deserialize_many<'p, 'de, T>(
+        &'p mut self,
+        input: &'de mut [u8],
+    ) -> DeserializeMany<'p, 'de, T>
+    where
+        T: Deserialize<'de>,
+    {
+        DeserializeMany {
+            records: Records::new(self, input),
+            _value: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{to_owned_value, Implementation, OwnedValue, Parser};
+
+    fn expected(input: &str) -> Vec<Option<OwnedValue>> {
+        input
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| to_owned_value(&mut l.as_bytes().to_vec()).ok())
+            .collect()
+    }
+
+    fn check(input: &str) {
+        let expected = expected(input);
+        for i in Implementation::ALL.iter().filter(|i| i.is_supported()) {
+            let mut parser = Parser::with_implementation(*i).unwrap();
+            let mut d = input.as_bytes().to_vec();
+            let owned: Vec<_> = parser.parse_many_owned(&mut d).map(|r| r.ok()).collect();
+            assert_eq!(expected, owned, "{:?}", i);
+            let mut d = input.as_bytes().to_vec();
+            let borrowed: Vec<_> = parser
+                .parse_many_borrowed(&mut d)
+                .map(|r| r.ok().map(OwnedValue::from))
+                .collect();
+            assert_eq!(expected, borrowed, "{:?}", i);
+        }
+    }
+
+    #[test]
+    fn records() {
+        check("{\"a\": 1}\n[1, 2, \"x\\ny\"]\n\"str\"\n42\ntrue\nnull\n-1.5\n");
+    }
+
+    #[test]
+    fn blank_lines_and_crlf() {
+        check("\n  \r\n{\"a\": [1]}\r\n\n\n  [] \n");
+        check("");
+        check("1");
+    }
+
+    #[test]
+    fn bad_records_dont_abort() {
+        check("{\"a\": 1}\n{\"a\": }\n[1, 2]\n");
+        check("{\"a\": \"unterminated}\n{\"b\": 2}\n");
+        check("{\"a\": \"\\x\"}\n{\"b\": 2}\n");
+    }
+
+    fn parse(input: &str) -> Vec<Option<OwnedValue>> {
+        let mut res = Vec::new();
+        for i in Implementation::ALL.iter().filter(|i| i.is_supported()) {
+            let mut parser = Parser::with_implementation(*i).unwrap();
+            let mut d = input.as_bytes().to_vec();
+            let owned: Vec<_> = parser.parse_many_owned(&mut d).map(|r| r.ok()).collect();
+            if !res.is_empty() {
+                assert_eq!(res, owned, "{:?}", i);
+            }
+            res = owned;
+        }
+        res
+    }
+
+    // The records we expect, `None` for the invalid ones.
+    fn values(docs: &[Option<&str>]) -> Vec<Option<OwnedValue>> {
+        docs.iter()
+            .map(|d| d.map(|d| to_owned_value(&mut d.as_bytes().to_vec()).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn documents_split_on_depth() {
+        assert_eq!(
+            parse("[1,\n2]\n3\n{\"a\":\n{}}"),
+            values(&[Some("[1, 2]"), Some("3"), Some(r#"{"a": {}}"#)])
+        );
+        assert_eq!(
+            parse("[1] [2]\n\"a\"\"b\"4 5"),
+            values(&[
+                Some("[1]"),
+                Some("[2]"),
+                Some(r#""a""#),
+                Some(r#""b""#),
+                Some("4"),
+                Some("5")
+            ])
+        );
+    }
+
+    #[test]
+    fn unbalanced_documents_fail_their_line() {
+        assert_eq!(
+            parse("{\"a\": 1\n{\"b\": 2}\n[3,\n4]\n"),
+            values(&[None, Some(r#"{"b": 2}"#), Some("[3, 4]")])
+        );
+        assert_eq!(
+            parse("{\"a\": [1}\n[2]]\n[3]"),
+            values(&[None, Some("[2]"), None, Some("[3]")])
+        );
+        assert_eq!(parse("{\"a\": }\n[1]"), values(&[None, Some("[1]")]));
+    }
+
+    #[test]
+    fn invalid_utf8_only_fails_its_line() {
+        let mut d = b"[1]\n[\"\xff\"]\n[3]\n".to_vec();
+        let mut parser = Parser::new();
+        let r: Vec<_> = parser.parse_many_owned(&mut d).map(|r| r.is_ok()).collect();
+        assert_eq!(r, vec![true, false, true]);
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn deserialize_many() {
+        use serde_derive::Deserialize;
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Entry<'a> {
+            level: &'a str,
+            code: u64,
+        }
+        let mut d = b"{\"level\": \"info\", \"code\": 1}\n{\"level\": 1}\n{\"level\": \"warn\", \"code\": 2}\n".to_vec();
+        let mut parser = Parser::new();
+        let r: Vec<Option<Entry>> = parser.deserialize_many(&mut d).map(|r| r.ok()).collect();
+        assert_eq!(
+            r,
+            vec![
+                Some(Entry {
+                    level: "info",
+                    code: 1
+                }),
+                None,
+                Some(Entry {
+                    level: "warn",
+                    code: 2
+                })
+            ]
+        );
+    }
+}
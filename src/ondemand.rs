@@ -0,0 +1,507 @@
+//! On-demand access to a document, only the values that are looked at get
+//! parsed.
+//!
+//! Stage 1 and stage 2 run as usual, so the structure of the document is
+//! validated up front, but strings and numbers are only parsed (and with
+//! that validated) when they are accessed. Where every array and object
+//! ends is recorded once when the document is indexed, so subtrees that
+//! aren't looked at are skipped in a single step without touching their
+//! content. Keys that appear more than once in an object are resolved at
+//! the same time, the last member with the key is the one that is kept.
+//! `Document::validate_all` parses every value for when the whole
+//! document has to be known to be valid.
+//!
+//! ```
+//! let d = br#"{"user": {"id": 42, "name": "ann"}, "tags": ["a", "b"]}"#;
+//! let doc = simd_json::ondemand::Document::from_slice(d)?;
+//! assert_eq!(doc.get("user")?.get("id")?.as_u64()?, 42);
+//! let tags: Vec<_> = doc.get("tags")?.as_array()?.map(|t| t.as_str()).collect();
+//! assert_eq!(tags.len(), 2);
+//! # Ok::<(), simd_json::Error>(())
+//! ```
+use crate::numberparse::Number;
+use crate::value::owned::{Map, Value as OwnedValue};
+use crate::value::ValueType;
+use crate::{Deserializer, Error, ErrorType, Implementation, Result};
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+/// An indexed document whose values are parsed on access.
+///
+/// The input isn't modified, strings without escapes are borrowed from it.
+pub struct Document<'de> {
+    // Only borrowed mutably for the duration of parsing a single string or
+    // number.
+    de: RefCell<Deserializer<'de>>,
+    // The members left out of objects as their key appears again later,
+    // as pairs of the index of the object and the member, in order.
+    dropped: Vec<(usize, usize)>,
+}
+
+impl<'de> Document<'de> {
+    /// Indexes the input using the best implementation supported by the
+    /// CPU.
+    pub fn from_slice(input: &'de [u8]) -> Result<Self> {
+        Document::from_slice_with_implementation(input, Implementation::detect())
+    }
+
+    /// Indexes the input using the given implementation.
+    pub fn from_slice_with_implementation(
+        input: &'de [u8],
+        implementation: Implementation,
+    ) -> Result<Self> {
+        let mut de = stry!(Deserializer::from_slice_ref_with_implementation(
+            input,
+            implementation
+        ));
+        let dropped = stry!(de.index_duplicate_members());
+        Ok(Self {
+            de: RefCell::new(de),
+            dropped,
+        })
+    }
+
+    /// The root value of the document.
+    pub fn root(&self) -> Element<'_, 'de> {
+        // skip the extra root element
+        Element { doc: self, idx: 1 }
+    }
+
+    /// Looks up a key of the root object, see `Element::get`.
+    pub fn get(&self, key: &str) -> Result<Element<'_, 'de>> {
+        self.root().get(key)
+    }
+
+    /// Looks up an index of the root array, see `Element::get_idx`.
+    pub fn get_idx(&self, i: usize) -> Result<Element<'_, 'de>> {
+        self.root().get_idx(i)
+    }
+
+    /// Parses every string and number of the document, returning the first
+    /// error found.
+    pub fn validate_all(&self) -> Result<()> {
+        self.root().validate()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn c(&self, idx: usize) -> u8 {
+        let de = self.de.borrow();
+        de.input.bytes()[de.structural_indexes[idx] as usize]
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn count(&self, idx: usize) -> usize {
+        self.de.borrow().counts[idx]
+    }
+
+    // The number of members left out of the object at `idx`.
+    fn dropped(&self, idx: usize) -> usize {
+        if self.dropped.is_empty() {
+            return 0;
+        }
+        let start = match self.dropped.binary_search(&(idx, 0)) {
+            Ok(i) | Err(i) => i,
+        };
+        self.dropped[start..]
+            .iter()
+            .take_while(|(object, _)| *object == idx)
+            .count()
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn is_dropped(&self, idx: usize, member: usize) -> bool {
+        !self.dropped.is_empty() && self.dropped.binary_search(&(idx, member)).is_ok()
+    }
+
+    // Returns the structural index following the value at `idx` without
+    // looking at anything in it.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn skip(&self, idx: usize) -> usize {
+        match self.c(idx) {
+            b'[' | b'{' => self.de.borrow().container_ends[idx] as usize + 1,
+            _ => idx + 1,
+        }
+    }
+
+    fn error(&self, idx: usize, error: ErrorType) -> Error {
+        let de = self.de.borrow();
+        let iidx = de.structural_indexes[idx] as usize;
+        let input = de.input.bytes();
+        Error::at(input, idx, iidx, error).with_pointer(input, &de.structural_indexes)
+    }
+
+    fn parse_str(&self, idx: usize) -> Result<Cow<'de, str>> {
+        let mut de = self.de.borrow_mut();
+        de.idx = idx;
+        de.iidx = de.structural_indexes[idx] as usize;
+        de.parse_str_()
+    }
+
+    fn parse_number(&self, idx: usize) -> Result<Number<'de>> {
+        let mut de = self.de.borrow_mut();
+        de.idx = idx;
+        de.iidx = de.structural_indexes[idx] as usize;
+        match de.input.bytes()[de.iidx] {
+            b'-' => de.parse_number(true),
+            b'0'..=b'9' => de.parse_number(false),
+            _ => Err(de.error(ErrorType::ExpectedNumber)),
+        }
+    }
+}
+
+/// A value of a `Document`, it is only parsed when one of the accessors
+/// is called.
+#[derive(Clone, Copy)]
+pub struct Element<'a, 'de> {
+    doc: &'a Document<'de>,
+    idx: usize,
+}
+
+impl<'a, 'de> Element<'a, 'de> {
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn c(&self) -> u8 {
+        self.doc.c(self.idx)
+    }
+
+    fn error(&self, error: ErrorType) -> Error {
+        self.doc.error(self.idx, error)
+    }
+
+    /// The type of the value, for numbers this requires parsing them.
+    pub fn kind(&self) -> Result<ValueType> {
+        Ok(match self.c() {
+            b'"' => ValueType::String,
+            b'n' => ValueType::Null,
+            b't' | b'f' => ValueType::Bool,
+            b'[' => ValueType::Array,
+            b'{' => ValueType::Object,
+            _ => match stry!(self.doc.parse_number(self.idx)) {
+                Number::I64(_) => ValueType::I64,
+                Number::U64(_) => ValueType::U64,
+                Number::F64(_) => ValueType::F64,
+                Number::Raw(_) => ValueType::RawNumber,
+            },
+        })
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.c() == b'n'
+    }
+
+    pub fn as_bool(&self) -> Result<bool> {
+        match self.c() {
+            b't' => Ok(true),
+            b'f' => Ok(false),
+            _ => Err(self.error(ErrorType::ExpectedBoolean)),
+        }
+    }
+
+    pub fn as_i64(&self) -> Result<i64> {
+        match self.c() {
+            b'-' | b'0'..=b'9' => match stry!(self.doc.parse_number(self.idx)) {
+                Number::I64(n) => Ok(n),
+                Number::Raw(n) => n.parse().map_err(|_| self.error(ErrorType::ExpectedSigned)),
+                _ => Err(self.error(ErrorType::ExpectedSigned)),
+            },
+            _ => Err(self.error(ErrorType::ExpectedSigned)),
+        }
+    }
+
+    pub fn as_u64(&self) -> Result<u64> {
+        match self.c() {
+            b'0'..=b'9' => match stry!(self.doc.parse_number(self.idx)) {
+                Number::I64(n) if n >= 0 => Ok(n as u64),
+                Number::U64(n) => Ok(n),
+                Number::Raw(n) => n
+                    .parse()
+                    .map_err(|_| self.error(ErrorType::ExpectedUnsigned)),
+                _ => Err(self.error(ErrorType::ExpectedUnsigned)),
+            },
+            _ => Err(self.error(ErrorType::ExpectedUnsigned)),
+        }
+    }
+
+    /// The value as a float, integers are converted.
+    pub fn as_f64(&self) -> Result<f64> {
+        match self.c() {
+            b'-' | b'0'..=b'9' => match stry!(self.doc.parse_number(self.idx)) {
+                Number::F64(n) => Ok(n),
+                Number::I64(n) => Ok(n as f64),
+                Number::U64(n) => Ok(n as f64),
+                Number::Raw(n) => n.parse().map_err(|_| self.error(ErrorType::ExpectedFloat)),
+            },
+            _ => Err(self.error(ErrorType::ExpectedFloat)),
+        }
+    }
+
+    /// The string, borrowed from the input unless it contains escapes.
+    pub fn as_str(&self) -> Result<Cow<'de, str>> {
+        match self.c() {
+            b'"' => self.doc.parse_str(self.idx),
+            _ => Err(self.error(ErrorType::ExpectedString)),
+        }
+    }
+
+    /// Iterates over the elements of an array.
+    pub fn as_array(&self) -> Result<ArrayIter<'a, 'de>> {
+        match self.c() {
+            b'[' => Ok(ArrayIter {
+                doc: self.doc,
+                idx: self.idx + 1,
+                remaining: self.doc.count(self.idx),
+            }),
+            _ => Err(self.error(ErrorType::ExpectedArray)),
+        }
+    }
+
+    /// Iterates over the members of an object.
+    pub fn as_object(&self) -> Result<ObjectIter<'a, 'de>> {
+        match self.c() {
+            b'{' => Ok(ObjectIter {
+                doc: self.doc,
+                object: self.idx,
+                idx: self.idx + 1,
+                member: 0,
+                len: self.doc.count(self.idx),
+                remaining: self.doc.count(self.idx) - self.doc.dropped(self.idx),
+            }),
+            _ => Err(self.error(ErrorType::ExpectedMap)),
+        }
+    }
+
+    /// The number of elements of an array or members of an object.
+    pub fn len(&self) -> Result<usize> {
+        match self.c() {
+            b'[' => Ok(self.doc.count(self.idx)),
+            b'{' => Ok(self.doc.count(self.idx) - self.doc.dropped(self.idx)),
+            _ => Err(self.error(ErrorType::ExpectedArray)),
+        }
+    }
+
+    /// Looks up a key, the keys are parsed and the values of the other
+    /// members skipped. If the key appears more than once the last value
+    /// is returned, as in the Value doms by default.
+    pub fn get(&self, key: &str) -> Result<Element<'a, 'de>> {
+        for r in stry!(self.as_object()) {
+            let (k, v) = stry!(r);
+            if k == key {
+                return Ok(v);
+            }
+        }
+        Err(self.error(ErrorType::KeyNotFound))
+    }
+
+    /// Looks up an index, the elements before it are skipped.
+    pub fn get_idx(&self, i: usize) -> Result<Element<'a, 'de>> {
+        stry!(self.as_array())
+            .nth(i)
+            .ok_or_else(|| self.error(ErrorType::IndexOutOfBounds))
+    }
+
+    /// Parses the value and everything in it into an owned Value dom.
+    pub fn to_owned_value(&self) -> Result<OwnedValue> {
+        Ok(match self.c() {
+            b'"' => OwnedValue::from(stry!(self.as_str())),
+            b'n' => OwnedValue::Null,
+            b't' => OwnedValue::Bool(true),
+            b'f' => OwnedValue::Bool(false),
+            b'[' => {
+                let mut res = Vec::with_capacity(self.doc.count(self.idx));
+                for e in stry!(self.as_array()) {
+                    res.push(stry!(e.to_owned_value()));
+                }
+                OwnedValue::Array(res)
+            }
+            b'{' => {
+                let mut res = Map::with_capacity(self.doc.count(self.idx));
+                for r in stry!(self.as_object()) {
+                    let (k, v) = stry!(r);
+                    res.insert(k.into_owned(), stry!(v.to_owned_value()));
+                }
+                OwnedValue::Object(res)
+            }
+            _ => OwnedValue::from(stry!(self.doc.parse_number(self.idx))),
+        })
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self.c() {
+            b'"' => self.as_str().map(|_| ()),
+            b'n' | b't' | b'f' => Ok(()),
+            b'[' => {
+                for e in stry!(self.as_array()) {
+                    stry!(e.validate());
+                }
+                Ok(())
+            }
+            b'{' => {
+                for r in stry!(self.as_object()) {
+                    let (_, v) = stry!(r);
+                    stry!(v.validate());
+                }
+                Ok(())
+            }
+            _ => self.doc.parse_number(self.idx).map(|_| ()),
+        }
+    }
+}
+
+/// Iterator over the elements of an array, created by
+/// `Element::as_array`.
+pub struct ArrayIter<'a, 'de> {
+    doc: &'a Document<'de>,
+    idx: usize,
+    remaining: usize,
+}
+
+impl<'a, 'de> Iterator for ArrayIter<'a, 'de> {
+    type Item = Element<'a, 'de>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let e = Element {
+            doc: self.doc,
+            idx: self.idx,
+        };
+        // skip the value and the following `,` or `]`
+        self.idx = self.doc.skip(self.idx) + 1;
+        Some(e)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Iterator over the members of an object, created by
+/// `Element::as_object`. Parsing a key can fail so the members come as
+/// results.
+pub struct ObjectIter<'a, 'de> {
+    doc: &'a Document<'de>,
+    object: usize,
+    idx: usize,
+    member: usize,
+    len: usize,
+    remaining: usize,
+}
+
+impl<'a, 'de> Iterator for ObjectIter<'a, 'de> {
+    type Item = Result<(Cow<'de, str>, Element<'a, 'de>)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.member < self.len {
+            let member = self.member;
+            self.member += 1;
+            let key_idx = self.idx;
+            let value = Element {
+                doc: self.doc,
+                // skip the key and `:`
+                idx: key_idx + 2,
+            };
+            // skip the value and the following `,` or `}`
+            self.idx = self.doc.skip(value.idx) + 1;
+            if self.doc.is_dropped(self.object, member) {
+                continue;
+            }
+            self.remaining -= 1;
+            return Some(self.doc.parse_str(key_idx).map(|k| (k, value)));
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::to_owned_value;
+
+    fn check(input: &str) {
+        let expected = to_owned_value(&mut input.as_bytes().to_vec()).unwrap();
+        for i in Implementation::ALL.iter().filter(|i| i.is_supported()) {
+            let doc = Document::from_slice_with_implementation(input.as_bytes(), *i).unwrap();
+            assert_eq!(Ok(()), doc.validate_all());
+            assert_eq!(
+                Ok(expected.clone()),
+                doc.root().to_owned_value(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn same_as_value() {
+        check("null");
+        check("-12");
+        check("1.5");
+        check("\"a\\\"b\\u00e4\"");
+        check("[]");
+        check("{}");
+        check("[1, [2, [3, []]], {\"a\": {\"b\": [true, false, null]}}, \"x\"]");
+        check("{\"a\": 1, \"b\": [1.5, -2], \"c\\n\": {\"d\": {}}, \"e\": \"f\"}");
+    }
+
+    #[test]
+    fn navigate() -> Result<()> {
+        let d = br#"{"skip": [[1, 2, [3]], {"x": "y"}, []], "users": [{"id": 1, "name": "a\nb"}, {"id": 2}], "n": -1.5}"#;
+        let doc = Document::from_slice(d)?;
+        assert_eq!(doc.root().len()?, 3);
+        assert_eq!(doc.get("users")?.get_idx(1)?.get("id")?.as_u64()?, 2);
+        assert_eq!(doc.get("users")?.get_idx(0)?.get("name")?.as_str()?, "a\nb");
+        assert_eq!(doc.get("n")?.as_f64()?, -1.5);
+        assert_eq!(
+            doc.get("skip")?
+                .get_idx(0)?
+                .get_idx(2)?
+                .get_idx(0)?
+                .as_i64()?,
+            3
+        );
+        assert_eq!(doc.get("skip")?.get_idx(1)?.get("x")?.as_str()?, "y");
+        let keys: Result<Vec<_>> = doc.root().as_object()?.map(|r| r.map(|(k, _)| k)).collect();
+        assert_eq!(keys?, vec!["skip", "users", "n"]);
+        let ids: Result<Vec<_>> = doc
+            .get("users")?
+            .as_array()?
+            .map(|u| u.get("id").and_then(|i| i.as_i64()))
+            .collect();
+        assert_eq!(ids?, vec![1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn errors() -> Result<()> {
+        let d = br#"{"a": [1], "b": -1, "c": "x"}"#;
+        let doc = Document::from_slice(d)?;
+        let error = |r: Result<Element>| r.err().map(|e| e.error);
+        assert_eq!(error(doc.get("missing")), Some(ErrorType::KeyNotFound));
+        assert_eq!(
+            error(doc.get("a")?.get_idx(1)),
+            Some(ErrorType::IndexOutOfBounds)
+        );
+        assert_eq!(error(doc.get("a")?.get("x")), Some(ErrorType::ExpectedMap));
+        assert_eq!(error(doc.get_idx(0)), Some(ErrorType::ExpectedArray));
+        assert!(doc.get("b")?.as_u64().is_err());
+        assert!(doc.get("c")?.as_i64().is_err());
+        assert!(doc.get("b")?.as_str().is_err());
+        // the structure is always validated
+        assert!(Document::from_slice(br#"{"a": [1}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn validates_what_it_visits() -> Result<()> {
+        let d = br#"{"bad": ["\x", 1], "good": 2}"#;
+        let doc = Document::from_slice(d)?;
+        assert_eq!(doc.get("good")?.as_i64()?, 2);
+        assert!(doc.get("bad")?.get_idx(0)?.as_str().is_err());
+        assert!(doc.validate_all().is_err());
+        assert!(doc.root().to_owned_value().is_err());
+        Ok(())
+    }
+}
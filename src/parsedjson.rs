@@ -1 +1,680 @@
+use crate::json5;
+use crate::lenient;
+use crate::stage2::StackEntry;
+use crate::stringparse::read_str;
+use crate::value::borrowed::BorrowDeserializer;
+use crate::value::owned::OwnedDeserializer;
+use crate::Result;
+use crate::{
+    BorrowedValue, Deserializer, Error, ErrorType, Implementation, Input, Limits, OwnedValue,
+};
+#[cfg(feature = "serde_impl")]
+use serde_ext::Deserialize;
 
+// The memory stage 1 and stage 2 need for a document, kept around by a
+// `Parser` so the capacity can be reused.
+#[derive(Default)]
+pub(crate) struct Buffers {
+    pub(crate) structural_indexes: Vec<u32>,
+    pub(crate) counts: Vec<usize>,
+    pub(crate) stack: Vec<StackEntry>,
+    pub(crate) strings: Vec<u8>,
+    // The structural indexes of a whole input holding many documents, the
+    // ones of every document are copied to `structural_indexes` in turn.
+    pub(crate) documents: Vec<u32>,
+}
+
+/// What to do with objects that contain the same key more than once,
+/// see `Parser::duplicate_keys`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DuplicateKeys {
+    /// Fail with `ErrorType::DuplicateKey` at the second occurrence.
+    Error,
+    /// Keep the value of the first occurrence.
+    KeepFirst,
+    /// Keep the value of the last occurrence, the default.
+    KeepLast,
+}
+
+impl Default for DuplicateKeys {
+    fn default() -> Self {
+        DuplicateKeys::KeepLast
+    }
+}
+
+// Objects with up to this many members are checked for duplicate keys
+// without sorting them.
+const SMALL_OBJECT: usize = 16;
+
+// Applying the duplicate key policy for the builders that can't simply
+// overwrite earlier members, like serde and the tape.
+impl<'de> Deserializer<'de> {
+    // The structural closing the array or object opened at `idx`, the
+    // table is built the first time it is needed.
+    pub(crate) fn container_end(&mut self, idx: usize) -> usize {
+        self.index_container_ends();
+        self.container_ends[idx] as usize
+    }
+
+    // Builds the table of container ends unless we have it already.
+    pub(crate) fn index_container_ends(&mut self) {
+        if self.container_ends.is_empty() {
+            let mut ends = vec![0; self.structural_indexes.len()];
+            let mut stack = Vec::new();
+            for (i, s) in self.structural_indexes.iter().enumerate().skip(1) {
+                match self.input.bytes()[*s as usize] {
+                    b'{' | b'[' => stack.push(i),
+                    b'}' | b']' => {
+                        if let Some(start) = stack.pop() {
+                            ends[start] = i as u32;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            self.container_ends = ends;
+        }
+    }
+
+    // The structural the value starting at `idx` ends at.
+    pub(crate) fn value_end(&mut self, idx: usize) -> usize {
+        match self.input.bytes()[self.structural_indexes[idx] as usize] {
+            b'{' | b'[' => self.container_end(idx),
+            _ => idx,
+        }
+    }
+
+    // Moves from the `{` or `,` in front of an object member to the `,` or
+    // `}` after it without parsing it.
+    pub(crate) fn skip_member(&mut self) {
+        self.idx = self.value_end(self.idx + 3) + 1;
+        self.iidx = self.structural_indexes[self.idx] as usize;
+    }
+
+    // The structural index of the key of every member of the object at the
+    // current `{`. Unless we have the table of container ends already we
+    // walk the object instead of jumping over its members, so the table is
+    // only built once a member has to be skipped.
+    fn member_keys(&self, keys: &mut [usize]) {
+        let input = self.input.bytes();
+        if !self.container_ends.is_empty() {
+            let mut key = self.idx + 1;
+            for k in keys.iter_mut() {
+                // skip the key, `:`, value and `,`
+                let value = key + 2;
+                key = match input[self.structural_indexes[value] as usize] {
+                    b'{' | b'[' => self.container_ends[value] as usize,
+                    _ => value,
+                } + 2;
+                *k = key;
+            }
+            return;
+        }
+        let mut depth = 0;
+        let mut member = 0;
+        let mut idx = self.idx + 1;
+        while member < keys.len() {
+            match input[self.structural_indexes[idx] as usize] {
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => depth -= 1,
+                b',' if depth == 0 => {
+                    keys[member] = idx + 1;
+                    member += 1;
+                }
+                _ => (),
+            }
+            idx += 1;
+        }
+    }
+
+    // The text of the key at the structural `idx` as it is in the input,
+    // the closing quote is the last one in front of the `:` following it.
+    fn raw_key(&self, idx: usize) -> &[u8] {
+        let input = self.input.bytes();
+        let start = self.structural_indexes[idx] as usize + 1;
+        let colon = self.structural_indexes[idx + 1] as usize;
+        let end = input[start..colon]
+            .iter()
+            .rposition(|c| *c == b'"')
+            .map_or(start, |i| start + i);
+        &input[start..end]
+    }
+
+    // Keys are the same if they are after unescaping, only keys containing
+    // escapes need to be unescaped to tell.
+    fn same_key(&self, a: usize, b: usize) -> bool {
+        let (ka, kb) = (self.raw_key(a), self.raw_key(b));
+        if ka.contains(&b'\\') || kb.contains(&b'\\') {
+            let input = self.input.bytes();
+            read_str(input, self.structural_indexes[a] as usize)
+                == read_str(input, self.structural_indexes[b] as usize)
+        } else {
+            ka == kb
+        }
+    }
+
+    // Looks at the keys of the object with `len` members at the current
+    // `{` and returns the members to leave out according to the duplicate
+    // key policy, last one first. Nothing in the object has been parsed
+    // yet so the keys can be read from the input.
+    pub(crate) fn duplicate_members(&mut self, len: usize) -> Result<Vec<usize>> {
+        // Small objects are checked for duplicates by comparing every key
+        // with the ones before it, without allocating.
+        if len <= SMALL_OBJECT {
+            let mut keys = [0; SMALL_OBJECT];
+            let keys = &mut keys[..len];
+            keys[0] = self.idx + 1;
+            self.member_keys(&mut keys[1..]);
+            if !(1..len).any(|j| (0..j).any(|i| self.same_key(keys[i], keys[j]))) {
+                return Ok(Vec::new());
+            }
+        }
+        let mut members = vec![0; len];
+        members[0] = self.idx + 1;
+        self.member_keys(&mut members[1..]);
+        let input = self.input.bytes();
+        let mut keys: Vec<_> = members
+            .into_iter()
+            .enumerate()
+            .map(|(member, idx)| {
+                let key = read_str(input, self.structural_indexes[idx] as usize);
+                (key, member, idx)
+            })
+            .collect();
+        // the sort is stable, so equal keys end up next to each other in
+        // the order they appear in
+        keys.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut dropped = Vec::new();
+        // the first key that is a duplicate of one before it
+        let mut duplicate: Option<(usize, usize)> = None;
+        let mut start = 0;
+        while start < keys.len() {
+            let end = start
+                + keys[start..]
+                    .iter()
+                    .take_while(|k| k.0 == keys[start].0)
+                    .count();
+            if end - start > 1 {
+                match self.duplicate_keys {
+                    DuplicateKeys::Error => {
+                        let (_, member, idx) = keys[start + 1];
+                        if duplicate.map_or(true, |(m, _)| member < m) {
+                            duplicate = Some((member, idx));
+                        }
+                    }
+                    DuplicateKeys::KeepFirst => {
+                        dropped.extend(keys[start + 1..end].iter().map(|k| k.1));
+                    }
+                    DuplicateKeys::KeepLast => {
+                        dropped.extend(keys[start..end - 1].iter().map(|k| k.1));
+                    }
+                }
+            }
+            start = end;
+        }
+        if let Some((_, idx)) = duplicate {
+            return Err(self.error_at(idx, ErrorType::DuplicateKey));
+        }
+        dropped.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(dropped)
+    }
+
+    // The members to leave out of every object in the document, as pairs
+    // of the structural index of the `{` and the member, in order. This
+    // is for the `ondemand` documents, they look members up without
+    // building objects so they resolve duplicates once up front.
+    pub(crate) fn index_duplicate_members(&mut self) -> Result<Vec<(usize, usize)>> {
+        self.index_container_ends();
+        let mut res = Vec::new();
+        for idx in 1..self.structural_indexes.len() {
+            let len = self.counts[idx];
+            if len > 1 && self.input.bytes()[self.structural_indexes[idx] as usize] == b'{' {
+                self.idx = idx;
+                let dropped = stry!(self.duplicate_members(len));
+                res.extend(dropped.into_iter().rev().map(|member| (idx, member)));
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// A parser that can be used for many documents, it keeps the buffers
+/// used for parsing between documents so they only need to grow to the
+/// largest document instead of being allocated for every single one.
+///
+/// ```
+/// use simd_json::ValueTrait;
+/// let mut parser = simd_json::Parser::new();
+/// for doc in &[r#"{"id": 1}"#, r#"{"id": 2}"#] {
+///     let mut d = doc.as_bytes().to_vec();
+///     let v = parser.parse_owned(&mut d).unwrap();
+///     assert!(v.get("id").is_some());
+/// }
+/// ```
+pub struct Parser {
+    pub(crate) implementation: Implementation,
+    pub(crate) raw_numbers: bool,
+    pub(crate) limits: Limits,
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) lenient: bool,
+    pub(crate) json5: bool,
+    pub(crate) buffers: Buffers,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    /// Creates a parser using the best implementation supported by
+    /// the CPU.
+    pub fn new() -> Self {
+        Self {
+            implementation: Implementation::detect(),
+            raw_numbers: false,
+            limits: Limits::default(),
+            duplicate_keys: DuplicateKeys::default(),
+            lenient: false,
+            json5: false,
+            buffers: Buffers::default(),
+        }
+    }
+
+    /// Creates a parser using the given implementation.
+    pub fn with_implementation(implementation: Implementation) -> Result<Self> {
+        if implementation.is_supported() {
+            Ok(Self {
+                implementation,
+                raw_numbers: false,
+                limits: Limits::default(),
+                duplicate_keys: DuplicateKeys::default(),
+                lenient: false,
+                json5: false,
+                buffers: Buffers::default(),
+            })
+        } else {
+            Err(Error::generic(ErrorType::UnsupportedImplementation))
+        }
+    }
+
+    /// Keeps numbers as their validated source text instead of converting
+    /// them to `i64`, `u64` or `f64`. They end up as `RawNumber` values and
+    /// are written back out verbatim, so no precision is lost no matter how
+    /// many digits they have.
+    ///
+    /// ```
+    /// use simd_json::ValueTrait;
+    /// let mut parser = simd_json::Parser::new().raw_numbers(true);
+    /// let mut d = br#"[0.1000000000000000055511151231257827, 123456789012345678901234567890]"#.to_vec();
+    /// let v = parser.parse_owned(&mut d).unwrap();
+    /// assert_eq!(
+    ///     v.to_string(),
+    ///     "[0.1000000000000000055511151231257827,123456789012345678901234567890]"
+    /// );
+    /// ```
+    pub fn raw_numbers(mut self, raw_numbers: bool) -> Self {
+        self.raw_numbers = raw_numbers;
+        self
+    }
+
+    /// Sets the limits documents are checked against before any value is
+    /// built from them, see `Limits`.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets what to do with objects that contain the same key more than
+    /// once. This applies to the Value doms, the tape and serde, so they
+    /// agree on the value of a key. The one exception are structs
+    /// deserialized with serde under the default `KeepLast`, they fail with
+    /// serde's duplicate field error as they do with serde_json. The
+    /// `ondemand` documents aren't created by a parser and always return
+    /// the last value of a key.
+    ///
+    /// ```
+    /// use simd_json::{DuplicateKeys, ErrorType, Parser, ValueTrait};
+    /// let mut d = br#"{"a": 1, "a": 2}"#.to_vec();
+    /// let v = Parser::new().parse_owned(&mut d).unwrap();
+    /// assert_eq!(v.get("a").and_then(|a| a.as_i64()), Some(2));
+    ///
+    /// let mut d = br#"{"a": 1, "a": 2}"#.to_vec();
+    /// let mut parser = Parser::new().duplicate_keys(DuplicateKeys::KeepFirst);
+    /// let v = parser.parse_owned(&mut d).unwrap();
+    /// assert_eq!(v.get("a").and_then(|a| a.as_i64()), Some(1));
+    ///
+    /// let mut d = br#"{"a": 1, "a": 2}"#.to_vec();
+    /// let mut parser = Parser::new().duplicate_keys(DuplicateKeys::Error);
+    /// let e = parser.parse_owned(&mut d).unwrap_err();
+    /// assert_eq!(e.error_type(), &ErrorType::DuplicateKey);
+    /// assert_eq!(e.offset(), Some(9));
+    /// ```
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// Accepts a relaxed dialect of JSON as found in hand-edited config
+    /// files: `//` and `/* */` comments, trailing commas in arrays and
+    /// objects and the numbers `NaN`, `Infinity` and `-Infinity`. Since
+    /// the input is parsed in place anyway comments are overwritten with
+    /// whitespace in it. Off by default, which sticks to RFC 8259.
+    ///
+    /// ```
+    /// use simd_json::ValueTrait;
+    /// let mut d = br#"{
+    ///     // seconds
+    ///     "timeout": 30,
+    ///     "ratio": NaN, /* not measured yet */
+    ///     "hosts": ["a", "b",],
+    /// }"#.to_vec();
+    /// let mut parser = simd_json::Parser::new().lenient(true);
+    /// let v = parser.parse_owned(&mut d).unwrap();
+    /// assert_eq!(v.get("timeout").and_then(|t| t.as_u64()), Some(30));
+    /// assert!(v.get("ratio").and_then(|r| r.as_f64()).unwrap().is_nan());
+    /// assert_eq!(v.get("hosts").and_then(|h| h.as_array()).map(Vec::len), Some(2));
+    /// ```
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Parses documents as [JSON5](https://spec.json5.org) instead of JSON:
+    /// on top of comments and trailing commas it allows single quoted
+    /// strings, strings spanning lines by escaping the newline, unquoted
+    /// keys, hexadecimal numbers, numbers with a leading `+` or a leading
+    /// or trailing decimal point and `NaN` and `Infinity`. The values are
+    /// the same the JSON parser gives for the same data, limits and the
+    /// duplicate key policy apply as well.
+    ///
+    /// JSON5 is parsed by a scalar parser rather than the SIMD stages, so
+    /// this is meant for config files and the like, not for bulk data. The
+    /// input is left untouched. When reading a stream of documents a
+    /// document that isn't valid JSON5 ends the stream since there is no
+    /// telling where the next one would start.
+    ///
+    /// ```
+    /// use simd_json::ValueTrait;
+    /// let mut d = br#"{
+    ///     // JSON5 allows unquoted keys
+    ///     name: 'simd-json',
+    ///     version: +0x1F,
+    ///     ratio: .5,
+    ///     description: 'a line \
+    /// and more',
+    /// }"#.to_vec();
+    /// let mut parser = simd_json::Parser::new().json5(true);
+    /// let v = parser.parse_owned(&mut d).unwrap();
+    /// assert_eq!(v.get("name").and_then(|n| n.as_string()), Some("simd-json".to_string()));
+    /// assert_eq!(v.get("version").and_then(|n| n.as_i64()), Some(31));
+    /// assert_eq!(v.get("ratio").and_then(|n| n.as_f64()), Some(0.5));
+    /// assert_eq!(
+    ///     v.get("description").and_then(|n| n.as_string()),
+    ///     Some("a line and more".to_string())
+    /// );
+    /// ```
+    pub fn json5(mut self, json5: bool) -> Self {
+        self.json5 = json5;
+        self
+    }
+
+    /// Sets how deep arrays and objects can be nested, documents nested
+    /// deeper fail with `ErrorType::DepthLimit`. Parsing a value recurses
+    /// once per level, so this protects against running out of stack on
+    /// untrusted input. The default is 1024, this is a shorthand for
+    /// setting `Limits::max_depth`.
+    ///
+    /// ```
+    /// use simd_json::ErrorType;
+    /// let mut parser = simd_json::Parser::new().max_depth(2);
+    /// let mut d = b"[[1], {\"a\": 2}]".to_vec();
+    /// assert!(parser.parse_owned(&mut d).is_ok());
+    /// let mut d = b"[[[1]]]".to_vec();
+    /// let e = parser.parse_owned(&mut d).unwrap_err();
+    /// assert_eq!(e.error_type(), &ErrorType::DepthLimit);
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.limits.max_depth = max_depth;
+        self
+    }
+
+    /// Parses a slice of bytes into a borrowed Value dom, see
+    /// `to_borrowed_value`.
+    pub fn parse_borrowed<'de>(&mut self, input: &'de mut [u8]) -> Result<BorrowedValue<'de>> {
+        if self.json5 {
+            return json5::parse(self, input);
+        }
+        let de = stry!(self.deserializer(input));
+        let mut de = BorrowDeserializer::from_deserializer(de);
+        let r = de.parse();
+        de.into_deserializer().return_buffers(&mut self.buffers);
+        r
+    }
+
+    /// Parses a slice of bytes into an owned Value dom, see
+    /// `to_owned_value`.
+    pub fn parse_owned(&mut self, input: &mut [u8]) -> Result<OwnedValue> {
+        if self.json5 {
+            return json5::parse(self, input).map(OwnedValue::from);
+        }
+        let de = stry!(self.deserializer(input));
+        let mut de = OwnedDeserializer::from_deserializer(de);
+        let r = de.parse();
+        de.into_deserializer().return_buffers(&mut self.buffers);
+        r
+    }
+
+    /// Deserializes a slice of bytes using serde, see
+    /// `serde::from_slice`.
+    #[cfg(feature = "serde_impl")]
+    pub fn deserialize<'de, T>(&mut self, input: &'de mut [u8]) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        if self.json5 {
+            return json5::parse(self, input).and_then(T::deserialize);
+        }
+        let mut de = stry!(self.deserializer(input));
+        let r = T::deserialize(&mut de).map_err(|e| de.locate(e));
+        de.return_buffers(&mut self.buffers);
+        r
+    }
+
+    pub(crate) fn deserializer<'de>(&mut self, input: &'de mut [u8]) -> Result<Deserializer<'de>> {
+        if self.lenient {
+            stry!(lenient::strip_comments(input));
+        }
+        let input = Input::from_mut(input);
+        stry!(Deserializer::index(
+            input.bytes(),
+            self.implementation,
+            &self.limits,
+            self.lenient,
+            &mut self.buffers
+        ));
+        Ok(Deserializer::from_buffers(
+            input,
+            self.implementation,
+            self.raw_numbers,
+            self.duplicate_keys,
+            self.lenient,
+            &mut self.buffers,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Parser;
+    use crate::{to_owned_value, ErrorType, Implementation, OwnedValue, ValueTrait};
+
+    #[test]
+    fn reuses_buffers() {
+        let mut parser = Parser::new();
+        let big = format!("[{}1]", "1, ".repeat(1000));
+        let mut d = big.as_bytes().to_vec();
+        assert!(parser.parse_owned(&mut d).is_ok());
+        let capacity = parser.buffers.structural_indexes.capacity();
+        let ptr = parser.buffers.structural_indexes.as_ptr();
+        for doc in &[r#"{"a": [1, "b\nc"]}"#, "[]", "null", r#""x""#] {
+            let mut d1 = doc.as_bytes().to_vec();
+            let mut d2 = d1.clone();
+            let mut d3 = d1.clone();
+            assert_eq!(parser.parse_owned(&mut d1), to_owned_value(&mut d2));
+            assert_eq!(
+                OwnedValue::from(parser.parse_borrowed(&mut d3).unwrap()),
+                to_owned_value(doc.as_bytes().to_vec().as_mut_slice()).unwrap()
+            );
+        }
+        assert_eq!(capacity, parser.buffers.structural_indexes.capacity());
+        assert_eq!(ptr, parser.buffers.structural_indexes.as_ptr());
+    }
+
+    #[test]
+    fn errors_dont_poison() {
+        let mut parser = Parser::new();
+        let mut d = br#"{"a": }"#.to_vec();
+        assert!(parser.parse_owned(&mut d).is_err());
+        let mut d = br#"{"a": "\x"}"#.to_vec();
+        assert!(parser.parse_borrowed(&mut d).is_err());
+        let mut d = br#"{"a": 1}"#.to_vec();
+        assert!(parser.parse_owned(&mut d).is_ok());
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn deserialize() {
+        use serde_derive::Deserialize;
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Entry<'a> {
+            level: &'a str,
+            code: u64,
+        }
+        let mut parser = Parser::new();
+        for i in 0..10 {
+            let mut d = format!(r#"{{"level": "info", "code": {}}}"#, i).into_bytes();
+            let e: Entry = parser.deserialize(&mut d).unwrap();
+            assert_eq!(
+                e,
+                Entry {
+                    level: "info",
+                    code: i
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn implementations() {
+        for i in Implementation::ALL.iter().cloned() {
+            match Parser::with_implementation(i) {
+                Ok(mut parser) => {
+                    let mut d = br#"{"a": ["b\"", 1]}"#.to_vec();
+                    assert!(parser.parse_owned(&mut d).is_ok());
+                }
+                Err(e) => {
+                    assert!(!i.is_supported());
+                    assert_eq!(
+                        e,
+                        crate::Error::generic(ErrorType::UnsupportedImplementation)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn raw_numbers() {
+        let doc = r#"{"a": [0.1000000000000000055511151231257827, -123456789012345678901234567890, 1E+400, -0.0e-3, 0], "b": 7}"#;
+        for i in Implementation::ALL.iter().filter(|i| i.is_supported()) {
+            let mut parser = Parser::with_implementation(*i).unwrap().raw_numbers(true);
+            let mut d = doc.as_bytes().to_vec();
+            let owned = parser.parse_owned(&mut d).unwrap();
+            assert_eq!(
+                owned.to_string(),
+                r#"{"a":[0.1000000000000000055511151231257827,-123456789012345678901234567890,1E+400,-0.0e-3,0],"b":7}"#
+            );
+            let mut d = doc.as_bytes().to_vec();
+            let borrowed = parser.parse_borrowed(&mut d).unwrap();
+            assert_eq!(OwnedValue::from(borrowed), owned);
+            let mut d = doc.as_bytes().to_vec();
+            let tape = parser.parse_tape(&mut d).unwrap();
+            assert_eq!(OwnedValue::from(tape.root()), owned);
+            assert_eq!(
+                tape.root()
+                    .get("a")
+                    .unwrap()
+                    .get_idx(2)
+                    .unwrap()
+                    .as_raw_number(),
+                Some("1E+400")
+            );
+            let mut d = b"42".to_vec();
+            assert_eq!(
+                parser.parse_owned(&mut d),
+                Ok(OwnedValue::RawNumber("42".to_string()))
+            );
+        }
+
+        let mut d = doc.as_bytes().to_vec();
+        let v = Parser::new().raw_numbers(true).parse_owned(&mut d).unwrap();
+        let b = v.get("b").unwrap();
+        assert_eq!(b, &OwnedValue::RawNumber("7".to_string()));
+        assert_eq!(b.as_i64(), Some(7));
+        assert_eq!(b.as_u64(), Some(7));
+        assert_eq!(b.cast_f64(), Some(7.0));
+        let a = v.get("a").and_then(|a| a.as_array()).unwrap();
+        assert_eq!(a[0].as_i64(), None);
+        assert_eq!(a[0].as_f64(), Some(0.1));
+        assert_eq!(a[1].as_i64(), None);
+    }
+
+    #[test]
+    fn raw_numbers_are_validated() {
+        let mut parser = Parser::new().raw_numbers(true);
+        for doc in &[
+            "01",
+            "-",
+            "1.",
+            "1.e2",
+            ".5",
+            "1e",
+            "1e+",
+            "+1",
+            "1.5x",
+            "[1,-]",
+            "[1a]",
+            "-01",
+            r#"{"a": 0x1}"#,
+        ] {
+            let mut d = doc.as_bytes().to_vec();
+            assert!(parser.parse_owned(&mut d).is_err(), "{}", doc);
+            let mut d = doc.as_bytes().to_vec();
+            assert!(parser.parse_borrowed(&mut d).is_err(), "{}", doc);
+        }
+        let mut d = b"[1, 2]\n[3e1]\n".to_vec();
+        let r: Vec<_> = parser.parse_many_owned(&mut d).collect();
+        assert_eq!(
+            r,
+            vec![
+                Ok(OwnedValue::Array(vec![
+                    OwnedValue::RawNumber("1".to_string()),
+                    OwnedValue::RawNumber("2".to_string())
+                ])),
+                Ok(OwnedValue::Array(vec![OwnedValue::RawNumber(
+                    "3e1".to_string()
+                )]))
+            ]
+        );
+        let r = parser.parse_owned_reader(&b"-12345678901234567890123"[..]);
+        assert_eq!(
+            r,
+            Ok(OwnedValue::RawNumber(
+                "-12345678901234567890123".to_string()
+            ))
+        );
+    }
+}
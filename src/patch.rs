@@ -0,0 +1,472 @@
+//! JSON Patch ([RFC 6902](https://tools.ietf.org/html/rfc6902)) for
+//! `OwnedValue`.
+//!
+//! A patch is a JSON array of operations, like the ones `diff` produces:
+//!
+//! ```
+//! use simd_json::{json, patch};
+//!
+//! let mut doc = json!({"a": [1, 2], "b": "c"});
+//! let p = json!([
+//!     {"op": "test", "path": "/b", "value": "c"},
+//!     {"op": "add", "path": "/a/1", "value": 3},
+//!     {"op": "move", "from": "/b", "path": "/d"}
+//! ]);
+//! patch::apply(&mut doc, &p).unwrap();
+//! assert_eq!(doc, json!({"a": [1, 3, 2], "d": "c"}));
+//!
+//! let to = json!({"a": [1, 2], "b": "c"});
+//! let p = patch::diff(&doc, &to);
+//! patch::apply(&mut doc, &p).unwrap();
+//! assert_eq!(doc, to);
+//! ```
+use crate::error::escape;
+use crate::value::owned::{Map, Value};
+use crate::value::{equal_nodes, pointer, ObjectTrait};
+use crate::{Error, ErrorType, Result};
+use std::borrow::Cow;
+
+enum Operation<'p> {
+    Add(Vec<Cow<'p, str>>, &'p Value),
+    Remove(Vec<Cow<'p, str>>),
+    Replace(Vec<Cow<'p, str>>, &'p Value),
+    Move(Vec<Cow<'p, str>>, Vec<Cow<'p, str>>),
+    Copy(Vec<Cow<'p, str>>, Vec<Cow<'p, str>>),
+    Test(Vec<Cow<'p, str>>, &'p Value),
+}
+
+/// Applies a patch to a document. The operations are applied in order
+/// and either all of them succeed or the document is left untouched.
+///
+/// Fails with:
+/// - `InvalidPatch` if the patch isn't an array of operations, an
+///   operation misses a member or something is moved into itself
+/// - `InvalidPointer` if a path is no JSON Pointer or an array index in
+///   it is invalid
+/// - `KeyNotFound` if a value that is removed, replaced, moved, copied or
+///   tested, or the parent of one that is added, doesn't exist
+/// - `IndexOutOfBounds` if an array index is past its end
+/// - `ExpectedMap` if a value is added to something that is neither an
+///   array nor an object
+/// - `TestFailed` if a `test` doesn't hold
+pub fn apply(doc: &mut Value, patch: &Value) -> Result<()> {
+    let ops = match patch {
+        Value::Array(ops) => ops,
+        _ => return Err(Error::generic(ErrorType::InvalidPatch)),
+    };
+    let ops: Vec<Operation> = stry!(ops.iter().map(operation).collect());
+    // applied to a copy so a failing operation leaves nothing behind
+    let mut res = doc.clone();
+    for op in ops {
+        stry!(op.apply(&mut res));
+    }
+    *doc = res;
+    Ok(())
+}
+
+fn operation(op: &Value) -> Result<Operation<'_>> {
+    let invalid = || Error::generic(ErrorType::InvalidPatch);
+    let o = match op {
+        Value::Object(o) => o,
+        _ => return Err(invalid()),
+    };
+    let pointer = |key: &str| match o.get(key) {
+        Some(Value::String(p)) => {
+            pointer::tokens(p).ok_or_else(|| Error::generic(ErrorType::InvalidPointer))
+        }
+        _ => Err(invalid()),
+    };
+    let value = || o.get("value").ok_or_else(invalid);
+    let op = match o.get("op") {
+        Some(Value::String(op)) => op.as_str(),
+        _ => return Err(invalid()),
+    };
+    Ok(match op {
+        "add" => Operation::Add(stry!(pointer("path")), stry!(value())),
+        "remove" => Operation::Remove(stry!(pointer("path"))),
+        "replace" => Operation::Replace(stry!(pointer("path")), stry!(value())),
+        "move" => Operation::Move(stry!(pointer("from")), stry!(pointer("path"))),
+        "copy" => Operation::Copy(stry!(pointer("from")), stry!(pointer("path"))),
+        "test" => Operation::Test(stry!(pointer("path")), stry!(value())),
+        _ => return Err(invalid()),
+    })
+}
+
+impl<'p> Operation<'p> {
+    fn apply(self, doc: &mut Value) -> Result<()> {
+        match self {
+            Operation::Add(path, value) => add(doc, &path, value.clone()),
+            Operation::Remove(path) => remove(doc, &path).map(|_| ()),
+            Operation::Replace(path, value) => {
+                *stry!(get_mut(doc, &path)) = value.clone();
+                Ok(())
+            }
+            Operation::Move(from, path) => {
+                if from == path {
+                    return get_mut(doc, &path).map(|_| ());
+                }
+                if path.starts_with(&from) {
+                    return Err(Error::generic(ErrorType::InvalidPatch));
+                }
+                let value = stry!(remove(doc, &from));
+                add(doc, &path, value)
+            }
+            Operation::Copy(from, path) => {
+                let value = stry!(get_mut(doc, &from)).clone();
+                add(doc, &path, value)
+            }
+            Operation::Test(path, value) => {
+                if equal_nodes(stry!(get_mut(doc, &path)), value) {
+                    Ok(())
+                } else {
+                    Err(Error::generic(ErrorType::TestFailed))
+                }
+            }
+        }
+    }
+}
+
+fn get_mut<'v>(doc: &'v mut Value, path: &[Cow<str>]) -> Result<&'v mut Value> {
+    pointer::get_mut(doc, path).ok_or_else(|| Error::generic(ErrorType::KeyNotFound))
+}
+
+// The index of an array element a token refers to, up to the element
+// after the last one.
+fn index(token: &str, len: usize) -> Result<usize> {
+    match pointer::index(token, len) {
+        Some(i) if i <= len => Ok(i),
+        Some(_) => Err(Error::generic(ErrorType::IndexOutOfBounds)),
+        None => Err(Error::generic(ErrorType::InvalidPointer)),
+    }
+}
+
+fn add(doc: &mut Value, path: &[Cow<str>], value: Value) -> Result<()> {
+    let (last, parent) = match path.split_last() {
+        Some(p) => p,
+        None => {
+            *doc = value;
+            return Ok(());
+        }
+    };
+    match stry!(get_mut(doc, parent)) {
+        Value::Object(m) => {
+            m.insert(last.to_string(), value);
+        }
+        Value::Array(a) => {
+            let i = stry!(index(last, a.len()));
+            a.insert(i, value);
+        }
+        _ => return Err(Error::generic(ErrorType::ExpectedMap)),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &[Cow<str>]) -> Result<Value> {
+    let (last, parent) = match path.split_last() {
+        Some(p) => p,
+        None => return Err(Error::generic(ErrorType::InvalidPatch)),
+    };
+    match stry!(get_mut(doc, parent)) {
+        Value::Object(m) => {
+            ObjectTrait::remove(m, last).ok_or_else(|| Error::generic(ErrorType::KeyNotFound))
+        }
+        Value::Array(a) => {
+            let i = stry!(index(last, a.len()));
+            if i == a.len() {
+                return Err(Error::generic(ErrorType::IndexOutOfBounds));
+            }
+            Ok(a.remove(i))
+        }
+        _ => Err(Error::generic(ErrorType::KeyNotFound)),
+    }
+}
+
+/// A patch that turns `from` into `to`. Objects are compared key by key,
+/// values that differ in both are compared in turn. Arrays get the fewest
+/// additions, removals and changes of elements, where changed arrays and
+/// objects are compared in turn. Arrays with more than a million pairs of
+/// differing elements between them are compared element by element
+/// instead. Numbers are the same if their values are.
+pub fn diff(from: &Value, to: &Value) -> Value {
+    let mut ops = Vec::new();
+    diff_into(from, to, &mut String::new(), &mut ops);
+    Value::Array(ops)
+}
+
+fn op(op: &str, path: &str, value: Option<&Value>) -> Value {
+    let mut m = Map::new();
+    m.insert("op".to_string(), Value::from(op));
+    m.insert("path".to_string(), Value::from(path));
+    if let Some(v) = value {
+        m.insert("value".to_string(), v.clone());
+    }
+    Value::Object(m)
+}
+
+fn diff_into(from: &Value, to: &Value, path: &mut String, ops: &mut Vec<Value>) {
+    if equal_nodes(from, to) {
+        return;
+    }
+    let len = path.len();
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, v) in a.iter() {
+                path.push('/');
+                path.push_str(&escape(k));
+                match b.get(k) {
+                    Some(w) => diff_into(v, w, path, ops),
+                    None => ops.push(op("remove", path, None)),
+                }
+                path.truncate(len);
+            }
+            for (k, w) in b.iter() {
+                if !a.contains_key(k) {
+                    path.push('/');
+                    path.push_str(&escape(k));
+                    ops.push(op("add", path, Some(w)));
+                    path.truncate(len);
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            let prefix = a
+                .iter()
+                .zip(b.iter())
+                .take_while(|(v, w)| equal_nodes(*v, *w))
+                .count();
+            let suffix = a[prefix..]
+                .iter()
+                .rev()
+                .zip(b[prefix..].iter().rev())
+                .take_while(|(v, w)| equal_nodes(*v, *w))
+                .count();
+            let a = &a[prefix..a.len() - suffix];
+            let b = &b[prefix..b.len() - suffix];
+            let at = |i: usize, path: &mut String| {
+                path.truncate(len);
+                path.push('/');
+                path.push_str(&(prefix + i).to_string());
+            };
+            // `k` is where the next element goes in the array as it is
+            // after the operations so far
+            let mut k = 0;
+            for edit in edits(a, b) {
+                at(k, path);
+                match edit {
+                    Edit::Keep => k += 1,
+                    Edit::Change(i, j) => {
+                        diff_into(&a[i], &b[j], path, ops);
+                        k += 1;
+                    }
+                    Edit::Remove => ops.push(op("remove", path, None)),
+                    Edit::Add(j) => {
+                        ops.push(op("add", path, Some(&b[j])));
+                        k += 1;
+                    }
+                }
+            }
+            path.truncate(len);
+        }
+        _ => ops.push(op("replace", path, Some(to))),
+    }
+}
+
+// How many elements two arrays can have, multiplied, to still get the
+// fewest edits. Finding those takes time and memory in that order, larger
+// arrays are compared element by element.
+const MAX_EDIT_MATRIX: usize = 1 << 20;
+
+enum Edit {
+    Keep,
+    Change(usize, usize),
+    Remove,
+    Add(usize),
+}
+
+// The fewest edits turning `a` into `b`, in order.
+fn edits(a: &[Value], b: &[Value]) -> Vec<Edit> {
+    let (n, m) = (a.len(), b.len());
+    let mut res = Vec::new();
+    if n.saturating_mul(m) > MAX_EDIT_MATRIX {
+        let common = n.min(m);
+        res.extend((0..common).map(|i| Edit::Change(i, i)));
+        res.extend((common..n).map(|_| Edit::Remove));
+        res.extend((common..m).map(Edit::Add));
+        return res;
+    }
+    // cost[i * (m + 1) + j] is the number of edits from a[i..] to b[j..]
+    let w = m + 1;
+    let mut cost = vec![0u32; (n + 1) * w];
+    let mut same = vec![false; n * m];
+    for i in (0..=n).rev() {
+        for j in (0..=m).rev() {
+            cost[i * w + j] = if i == n {
+                (m - j) as u32
+            } else if j == m {
+                (n - i) as u32
+            } else if equal_nodes(&a[i], &b[j]) {
+                same[i * m + j] = true;
+                cost[(i + 1) * w + j + 1]
+            } else {
+                1 + cost[(i + 1) * w + j + 1]
+                    .min(cost[(i + 1) * w + j])
+                    .min(cost[i * w + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        let here = cost[i * w + j];
+        if i < n && j < m && same[i * m + j] {
+            res.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if i < n && j < m && here == 1 + cost[(i + 1) * w + j + 1] {
+            res.push(Edit::Change(i, j));
+            i += 1;
+            j += 1;
+        } else if i < n && here == 1 + cost[(i + 1) * w + j] {
+            res.push(Edit::Remove);
+            i += 1;
+        } else {
+            res.push(Edit::Add(j));
+            j += 1;
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "serde_impl")]
+    use super::*;
+
+    #[cfg(feature = "serde_impl")]
+    fn error(doc: Value, patch: Value) -> ErrorType {
+        let mut res = doc.clone();
+        let e = apply(&mut res, &patch).unwrap_err();
+        // nothing is applied if something fails
+        assert_eq!(res, doc);
+        e.error
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn errors() {
+        let doc = json!({"a": [1, 2], "b": {"c": "d"}, "e": 1});
+        let err = |p: Value| error(doc.clone(), p);
+        assert_eq!(err(json!({})), ErrorType::InvalidPatch);
+        assert_eq!(err(json!([1])), ErrorType::InvalidPatch);
+        assert_eq!(err(json!([{"path": "/a"}])), ErrorType::InvalidPatch);
+        assert_eq!(
+            err(json!([{"op": "nop", "path": "/a"}])),
+            ErrorType::InvalidPatch
+        );
+        assert_eq!(
+            err(json!([{"op": "add", "path": "/x"}])),
+            ErrorType::InvalidPatch
+        );
+        assert_eq!(
+            err(json!([{"op": "add", "path": 1, "value": 1}])),
+            ErrorType::InvalidPatch
+        );
+        assert_eq!(
+            err(json!([{"op": "move", "path": "/x"}])),
+            ErrorType::InvalidPatch
+        );
+        assert_eq!(
+            err(json!([{"op": "remove", "path": "x"}])),
+            ErrorType::InvalidPointer
+        );
+        assert_eq!(
+            err(json!([{"op": "add", "path": "/a/01", "value": 1}])),
+            ErrorType::InvalidPointer
+        );
+        assert_eq!(
+            err(json!([{"op": "remove", "path": "/x"}])),
+            ErrorType::KeyNotFound
+        );
+        assert_eq!(
+            err(json!([{"op": "add", "path": "/x/y", "value": 1}])),
+            ErrorType::KeyNotFound
+        );
+        assert_eq!(
+            err(json!([{"op": "remove", "path": "/a/2"}])),
+            ErrorType::IndexOutOfBounds
+        );
+        assert_eq!(
+            err(json!([{"op": "add", "path": "/a/3", "value": 1}])),
+            ErrorType::IndexOutOfBounds
+        );
+        assert_eq!(
+            err(json!([{"op": "add", "path": "/e/x", "value": 1}])),
+            ErrorType::ExpectedMap
+        );
+        assert_eq!(
+            err(json!([{"op": "move", "from": "/b", "path": "/b/x"}])),
+            ErrorType::InvalidPatch
+        );
+        assert_eq!(
+            err(json!([{"op": "remove", "path": ""}])),
+            ErrorType::InvalidPatch
+        );
+        // the first operation is applied to a copy only
+        assert_eq!(
+            err(json!([
+                {"op": "remove", "path": "/a"},
+                {"op": "test", "path": "/e", "value": 2}
+            ])),
+            ErrorType::TestFailed
+        );
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn moves() {
+        let mut doc = json!({"a": {"b": 1}, "ab": 2});
+        // `/ab` starts with `/a` as a string but isn't inside it
+        let p = json!([
+            {"op": "move", "from": "/a", "path": "/ab"},
+            {"op": "move", "from": "/ab", "path": "/ab"}
+        ]);
+        apply(&mut doc, &p).unwrap();
+        assert_eq!(doc, json!({"ab": {"b": 1}}));
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn diffs() {
+        let d = |a: Value, b: Value| diff(&a, &b);
+        assert_eq!(d(json!(1), json!(1.0)), json!([]));
+        assert_eq!(
+            d(json!({"a": 1}), json!([1])),
+            json!([{"op": "replace", "path": "", "value": [1]}])
+        );
+        assert_eq!(
+            d(json!({"a/b": 1, "c": 2}), json!({"c": 2, "d~": 3})),
+            json!([
+                {"op": "remove", "path": "/a~1b"},
+                {"op": "add", "path": "/d~0", "value": 3}
+            ])
+        );
+        assert_eq!(
+            d(json!([1, 2, 3, 4]), json!([1, 4])),
+            json!([
+                {"op": "remove", "path": "/1"},
+                {"op": "remove", "path": "/1"}
+            ])
+        );
+        assert_eq!(
+            d(json!([1, 4]), json!([0, 1, 2, 3, 4])),
+            json!([
+                {"op": "add", "path": "/0", "value": 0},
+                {"op": "add", "path": "/2", "value": 2},
+                {"op": "add", "path": "/3", "value": 3}
+            ])
+        );
+        assert_eq!(
+            d(json!([1, {"a": [2]}, 3]), json!([1, {"a": [2, 5]}, 3])),
+            json!([{"op": "add", "path": "/1/a/1", "value": 5}])
+        );
+    }
+}
@@ -0,0 +1,767 @@
+//! Parsing from a `std::io::Read` without reading the whole input into
+//! memory first.
+//!
+//! The input is read in chunks and stage 1 indexes every full 64 byte
+//! block as soon as it arrives, carrying its state (are we inside a
+//! string, did the block end in an odd run of backslashes, is the next
+//! character a pseudo structural, the UTF-8 validation state) over to the
+//! next block. This way no byte has to be indexed twice and, when reading
+//! a stream of documents, only the document we're currently at has to be
+//! kept around: once the structural indexes tell us where a document ends
+//! we run stage 2 on it, hand out the value and drop its bytes.
+//!
+//! For lenient parsing comments are stripped from every chunk before it
+//! is indexed, a comment that isn't complete yet holds back indexing
+//! until we've read its end.
+//!
+//! JSON5 doesn't go through stage 1 at all, a stream of JSON5 documents
+//! is parsed again from the start whenever the buffer doubled in size
+//! until the first document in it is complete.
+use crate::json5::{self, Next};
+use crate::lenient::{self, Comments};
+use crate::stage1::Indexer;
+use crate::value::owned::OwnedDeserializer;
+use crate::{
+    BorrowedValue, Deserializer, Error, ErrorType, Input, OwnedValue, Parser, Result,
+    SIMDJSON_PADDING,
+};
+#[cfg(feature = "serde_impl")]
+use serde_ext::de::DeserializeOwned;
+use std::io::{self, Read};
+#[cfg(feature = "serde_impl")]
+use std::marker::PhantomData;
+
+// How much we try to read at once, a multiple of the 64 byte block size.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+struct Chunks<'p, R> {
+    parser: &'p mut Parser,
+    reader: R,
+    indexer: Indexer,
+    // The input that was read, `buf[..indexed]` went through stage 1.
+    // Everything in front of `start` belongs to documents we've handed
+    // out already, it is only dropped when we read the next chunk.
+    buf: Vec<u8>,
+    indexed: usize,
+    start: usize,
+    // For lenient parsing, `buf[..blanked]` had its comments stripped.
+    comments: Comments,
+    blanked: usize,
+    // The structural indexes stage 1 found in `buf`, without the extra
+    // root element. The ones in front of `sstart` belong to documents we've
+    // handed out.
+    structural_indexes: Vec<u32>,
+    sstart: usize,
+    // How far we've looked through `structural_indexes` for the end of the
+    // current document and how deep we're nested there.
+    scanned: usize,
+    depth: usize,
+    // For JSON5, the size of the buffer when we last found the current
+    // document to be incomplete.
+    tried: usize,
+    eof: bool,
+}
+
+impl<'p, R: Read> Chunks<'p, R> {
+    fn new(parser: &'p mut Parser, reader: R) -> Self {
+        let indexer = Indexer::new(parser.implementation);
+        Self {
+            parser,
+            reader,
+            indexer,
+            buf: Vec::new(),
+            indexed: 0,
+            start: 0,
+            comments: Comments::default(),
+            blanked: 0,
+            structural_indexes: Vec::new(),
+            sstart: 0,
+            scanned: 0,
+            depth: 0,
+            tried: 0,
+            eof: false,
+        }
+    }
+
+    // Reads the next chunk into the buffer, what we've handed out is
+    // dropped first.
+    fn read(&mut self) -> Result<()> {
+        self.compact();
+        let len = self.buf.len();
+        self.buf.resize(len + CHUNK_SIZE, 0);
+        let read = loop {
+            match self.reader.read(&mut self.buf[len..]) {
+                Ok(read) => break read,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buf.truncate(len);
+                    return Err(Error::generic(ErrorType::Io(e.to_string())));
+                }
+            }
+        };
+        self.buf.truncate(len + read);
+        self.eof = read == 0;
+        Ok(())
+    }
+
+    // Reads the next chunk and indexes all the blocks we have in full, or
+    // everything that's left once we reach the end of the input.
+    fn fill(&mut self) -> Result<()> {
+        stry!(self.read());
+        let len = self.buf.len();
+        let ready = if self.parser.lenient {
+            stry!(self.strip_comments());
+            self.blanked
+        } else {
+            len
+        };
+        unsafe {
+            while self.indexed + 64 <= ready {
+                self.indexer.block(
+                    self.buf.get_unchecked(self.indexed..),
+                    self.indexed,
+                    &mut self.structural_indexes,
+                );
+                self.indexed += 64;
+            }
+            if self.eof {
+                // The last (partial) block is padded with whitespace, same as
+                // we do for slices.
+                if self.indexed < len {
+                    let mut tmpbuf: [u8; 64] = [0x20; 64];
+                    tmpbuf[..len - self.indexed].copy_from_slice(&self.buf[self.indexed..]);
+                    self.indexer
+                        .block(&tmpbuf, self.indexed, &mut self.structural_indexes);
+                    self.indexed += 64;
+                }
+                let buf = &self.buf;
+                stry!(self
+                    .indexer
+                    .finish(self.indexed, &mut self.structural_indexes)
+                    .map_err(|e| Error::stage1(buf, e)));
+            }
+            let buf = &self.buf;
+            self.indexer.check().map_err(|e| Error::stage1(buf, e))
+        }
+    }
+
+    // Strips the comments from what was read since the last call.
+    fn strip_comments(&mut self) -> Result<()> {
+        match self.comments.blank(&mut self.buf, self.blanked, self.eof) {
+            Ok(blanked) => {
+                self.blanked = blanked;
+                Ok(())
+            }
+            Err(offset) => Err(Error::at(
+                &self.buf,
+                0,
+                offset,
+                ErrorType::UnterminatedComment,
+            )),
+        }
+    }
+
+    // Called before reading more of a document we haven't found the end
+    // of, fails once what we have of it is larger than the limit. It could
+    // still end in the last block as its structurals aren't flattened yet.
+    fn check_size(&self) -> Result<()> {
+        let start = self
+            .structural_indexes
+            .get(self.sstart)
+            .map_or(self.indexed, |i| *i as usize);
+        if self.indexed.saturating_sub(64).saturating_sub(start)
+            > self.parser.limits.max_document_size
+        {
+            Err(Error::generic(ErrorType::DocumentSizeLimit))
+        } else {
+            Ok(())
+        }
+    }
+
+    // Looks for the end of the document starting at the structural index
+    // `sstart`, returns the number of structural indexes belonging to it
+    // and the offset right after it. A document that is an object or array
+    // ends with the structural closing it, any other ends where the next
+    // structural starts.
+    fn document_end(&mut self) -> Option<(usize, usize)> {
+        let first = *self.structural_indexes.get(self.sstart)? as usize;
+        match self.buf[first] {
+            b'{' | b'[' => {
+                while self.scanned < self.structural_indexes.len() {
+                    let idx = self.structural_indexes[self.scanned] as usize;
+                    self.scanned += 1;
+                    match self.buf[idx] {
+                        b'{' | b'[' => self.depth += 1,
+                        b'}' | b']' => {
+                            self.depth -= 1;
+                            if self.depth == 0 {
+                                return Some((self.scanned - self.sstart, idx + 1));
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                None
+            }
+            _ => self
+                .structural_indexes
+                .get(self.sstart + 1)
+                .map(|next| (1, *next as usize)),
+        }
+    }
+
+    // Runs stage 2 over the buffer from `start` to `end` using the `count`
+    // structural indexes from `sstart` and hands the deserializer to `f`.
+    fn document<T, F>(&mut self, count: usize, end: usize, f: F) -> Result<T>
+    where
+        F: for<'de> FnOnce(Deserializer<'de>) -> (Deserializer<'de>, Result<T>),
+    {
+        let buffers = &mut self.parser.buffers;
+        let start = self.start;
+        let input = Input::from_mut(&mut self.buf[start..end]);
+        buffers.structural_indexes.clear();
+        buffers.structural_indexes.push(0); // push extra root element
+        buffers.structural_indexes.extend(
+            self.structural_indexes[self.sstart..self.sstart + count]
+                .iter()
+                .map(|i| *i - start as u32),
+        );
+        if self.parser.lenient {
+            lenient::drop_trailing_commas(input.bytes(), &mut buffers.structural_indexes);
+        }
+
+        stry!(Deserializer::validate_into(
+            input.bytes(),
+            &buffers.structural_indexes,
+            &self.parser.limits,
+            self.parser.lenient,
+            &mut buffers.counts,
+            &mut buffers.stack,
+        ));
+        buffers.strings.clear();
+        buffers.strings.reserve(end - start + SIMDJSON_PADDING);
+
+        let de = Deserializer::from_buffers(
+            input,
+            self.parser.implementation,
+            self.parser.raw_numbers,
+            self.parser.duplicate_keys,
+            self.parser.lenient,
+            buffers,
+        );
+        let (de, r) = f(de);
+        de.return_buffers(buffers);
+        r
+    }
+
+    // Moves past the buffer up to `end` together with the next `count`
+    // structural indexes.
+    fn consume(&mut self, count: usize, end: usize) {
+        self.start = end;
+        self.sstart += count;
+        self.scanned = self.sstart;
+        self.depth = 0;
+    }
+
+    // Drops what was consumed from the buffer and the structural indexes,
+    // this happens once per chunk so handing out many small documents
+    // doesn't move the rest of the chunk every time.
+    fn compact(&mut self) {
+        let start = self.start;
+        if start > 0 {
+            self.buf.drain(..start);
+            self.indexed -= start;
+            self.blanked = self.blanked.saturating_sub(start);
+            self.start = 0;
+        }
+        if self.sstart > 0 {
+            self.structural_indexes.drain(..self.sstart);
+            self.scanned -= self.sstart;
+            self.sstart = 0;
+        }
+        if start > 0 {
+            for idx in &mut self.structural_indexes {
+                *idx -= start as u32;
+            }
+        }
+    }
+
+    // Reads the whole input and parses it as a single document.
+    fn read_one<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: for<'de> FnOnce(Deserializer<'de>) -> (Deserializer<'de>, Result<T>),
+    {
+        while !self.eof {
+            if self.buf.len() > self.parser.limits.max_document_size {
+                return Err(Error::generic(ErrorType::DocumentSizeLimit));
+            }
+            stry!(self.fill());
+        }
+        // a valid JSON file cannot have zero structural indexes
+        if self.structural_indexes.is_empty() {
+            return Err(Error::generic(ErrorType::EOF));
+        }
+        let count = self.structural_indexes.len();
+        let end = self.buf.len();
+        self.document(count, end, f)
+    }
+
+    // Reads up to the end of the next document and parses it. Errors from
+    // reading or from stage 1 end the stream since we can't tell where the
+    // next document would start, invalid documents only fail themselves.
+    fn next_document<T, F>(&mut self, f: F) -> Option<Result<T>>
+    where
+        F: for<'de> FnOnce(Deserializer<'de>) -> (Deserializer<'de>, Result<T>),
+    {
+        loop {
+            let end = match self.document_end() {
+                Some(end) => Some(end),
+                None if self.eof && self.sstart < self.structural_indexes.len() => {
+                    Some((self.structural_indexes.len() - self.sstart, self.buf.len()))
+                }
+                None if self.eof => return None,
+                None => None,
+            };
+            if let Some((count, end)) = end {
+                let r = self.document(count, end, f);
+                self.consume(count, end);
+                return Some(r);
+            }
+            if self.sstart == self.structural_indexes.len() {
+                // Whitespace between documents, all we have to keep is the
+                // last block as its structurals aren't flattened yet.
+                let end = self.indexed.saturating_sub(64).max(self.start);
+                self.consume(0, end);
+            }
+            if let Err(e) = self.check_size().and_then(|_| self.fill()) {
+                self.eof = true;
+                self.buf.clear();
+                self.structural_indexes.clear();
+                self.start = 0;
+                self.sstart = 0;
+                return Some(Err(e));
+            }
+        }
+    }
+
+    // Reads the whole input and parses it as a single JSON5 document.
+    fn read_one_json5<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: for<'de> FnOnce(BorrowedValue<'de>) -> Result<T>,
+    {
+        while !self.eof {
+            if self.buf.len() > self.parser.limits.max_document_size {
+                return Err(Error::generic(ErrorType::DocumentSizeLimit));
+            }
+            stry!(self.read());
+        }
+        json5::parse(self.parser, &self.buf).and_then(f)
+    }
+
+    // Reads up to the end of the next JSON5 document and parses it. Any
+    // error ends the stream, without stage 1 we can't tell where the next
+    // document would start.
+    fn next_json5<T, F>(&mut self, f: F) -> Option<Result<T>>
+    where
+        F: for<'de> FnOnce(BorrowedValue<'de>) -> Result<T>,
+    {
+        // only called once, when we return
+        let mut f = Some(f);
+        loop {
+            if self.eof || self.buf.len() - self.start >= 2 * self.tried {
+                let rest = &self.buf[self.start..];
+                let next = match json5::next(self.parser, rest, self.eof) {
+                    Ok(Next::Document(v, end)) => Ok(f.take().map(|f| (f(v), end))),
+                    Ok(Next::End) => return None,
+                    Ok(Next::Incomplete) => Ok(None),
+                    Err(e) => Err(e),
+                };
+                match next {
+                    Ok(Some((r, end))) => {
+                        self.start += end;
+                        self.tried = 0;
+                        return Some(r);
+                    }
+                    Ok(None) => self.tried = self.buf.len() - self.start,
+                    Err(e) => {
+                        self.eof = true;
+                        self.buf.clear();
+                        self.start = 0;
+                        return Some(Err(e));
+                    }
+                }
+            }
+            let r = if self.buf.len() - self.start > self.parser.limits.max_document_size {
+                Err(Error::generic(ErrorType::DocumentSizeLimit))
+            } else {
+                self.read()
+            };
+            if let Err(e) = r {
+                self.eof = true;
+                self.buf.clear();
+                self.start = 0;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+fn parse_owned(de: Deserializer) -> (Deserializer, Result<OwnedValue>) {
+    let mut de = OwnedDeserializer::from_deserializer(de);
+    let r = de.parse();
+    (de.into_deserializer(), r)
+}
+
+#[cfg(feature = "serde_impl")]
+fn deserialize<T: DeserializeOwned>(mut de: Deserializer) -> (Deserializer, Result<T>) {
+    let r = T::deserialize(&mut de).map_err(|e| de.locate(e));
+    (de, r)
+}
+
+/// Iterator over the documents read from a `std::io::Read`, created by
+/// `Parser::parse_many_owned_reader`.
+pub struct ParseManyReader<'p, R> {
+    chunks: Chunks<'p, R>,
+}
+
+impl<'p, R: Read> Iterator for ParseManyReader<'p, R> {
+    type Item = Result<OwnedValue>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.chunks.parser.json5 {
+            self.chunks.next_json5(|v| Ok(OwnedValue::from(v)))
+        } else {
+            self.chunks.next_document(parse_owned)
+        }
+    }
+}
+
+/// Iterator deserializing the documents read from a `std::io::Read`
+/// using serde, created by `Parser::deserialize_many_reader`.
+#[cfg(feature = "serde_impl")]
+pub struct DeserializeManyReader<'p, R, T> {
+    chunks: Chunks<'p, R>,
+    _value: PhantomData<T>,
+}
+
+#[cfg(feature = "serde_impl")]
+impl<'p, R, T> Iterator for DeserializeManyReader<'p, R, T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.chunks.parser.json5 {
+            self.chunks.next_json5(|v| T::deserialize(v))
+        } else {
+            self.chunks.next_document(deserialize)
+        }
+    }
+}
+
+impl Parser {
+    /// Reads a single document from `reader` and parses it into an owned
+    /// Value dom. Stage 1 runs on the input while it is read so there is
+    /// no second pass over it, the document itself has to fit into memory
+    /// though.
+    ///
+    /// There is no borrowed variant since there is nothing to borrow from.
+    pub fn parse_owned_reader<R: Read>(&mut self, reader: R) -> Result<OwnedValue> {
+        if self.json5 {
+            return Chunks::new(self, reader).read_one_json5(|v| Ok(OwnedValue::from(v)));
+        }
+        Chunks::new(self, reader).read_one(parse_owned)
+    }
+
+    /// Reads a single document from `reader` and deserializes it using
+    /// serde, see `parse_owned_reader`.
+    #[cfg(feature = "serde_impl")]
+    pub fn deserialize_reader<T, R>(&mut self, reader: R) -> Result<T>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        if self.json5 {
+            return Chunks::new(self, reader).read_one_json5(|v| T::deserialize(v));
+        }
+        Chunks::new(self, reader).read_one(deserialize)
+    }
+
+    /// Reads a stream of documents (say newline delimited JSON or just
+    /// concatenated documents) from `reader` and parses them one by one
+    /// into owned Value doms. Only the document that is currently parsed is
+    /// kept in memory.
+    ///
+    /// A document is handed out once the input following it was read, or
+    /// the reader is exhausted. An invalid document yields an error and
+    /// parsing continues with the next one, I/O errors and errors found
+    /// while indexing (say invalid UTF-8 or an unterminated string) end
+    /// the stream.
+    ///
+    /// ```
+    /// let input = &b"{\"id\": 1} {\"id\": 2}\n[3]"[..];
+    /// let mut parser = simd_json::Parser::new();
+    /// let docs: Vec<_> = parser.parse_many_owned_reader(input).collect();
+    /// assert_eq!(docs.len(), 3);
+    /// ```
+    pub fn parse_many_owned_reader<R: Read>(&mut self, reader: R) -> ParseManyReader<'_, R> {
+        ParseManyReader {
+            chunks: Chunks::new(self, reader),
+        }
+    }
+
+    /// Reads a stream of documents from `reader` and deserializes them
+    /// one by one using serde, see `parse_many_owned_reader`.
+    #[cfg(feature = "serde_impl")]
+    pub fn deserialize_many_reader<T, R>(&mut self, reader: R) -> DeserializeManyReader<'_, R, T>
+    where
+        T: DeserializeOwned,
+        R: Read,
+    {
+        DeserializeManyReader {
+            chunks: Chunks::new(self, reader),
+            _value: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{to_owned_value, Error, ErrorType, Implementation, OwnedValue, Parser};
+    use std::io::{self, Read};
+
+    // Hands out the input a few bytes at a time so documents, strings and
+    // UTF-8 sequences get split between reads.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        step: usize,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.input.len());
+            buf[..n].copy_from_slice(&self.input[..n]);
+            self.input = &self.input[n..];
+            Ok(n)
+        }
+    }
+
+    fn parsers() -> Vec<Parser> {
+        Implementation::ALL
+            .iter()
+            .filter(|i| i.is_supported())
+            .map(|i| Parser::with_implementation(*i).unwrap())
+            .collect()
+    }
+
+    fn docs() -> Vec<String> {
+        let mut docs = Vec::new();
+        for i in 0..50 {
+            docs.push(format!(
+                "{{\"id\": {}, \"name\": \"n\\\"ä\\\\\", \"tags\": [\"x\", \"😀\", {}], \"nested\": {{\"a\": [[], {{}}]}}}}",
+                i,
+                i as f64 / 3.0
+            ));
+            docs.push(i.to_string());
+            docs.push(format!("\"str {}\"", i));
+            docs.push("true".to_string());
+            docs.push("null".to_string());
+            docs.push(format!("[{}]", i));
+        }
+        docs
+    }
+
+    // The documents separated by different kinds of whitespace, or not at
+    // all where that is unambiguous.
+    fn stream(docs: &[String]) -> String {
+        let mut s = String::new();
+        for (i, d) in docs.iter().enumerate() {
+            s.push_str(d);
+            s.push_str(match i % 4 {
+                0 => "",
+                1 => " ",
+                2 => "\n",
+                _ => "\r\n\t ",
+            });
+        }
+        s
+    }
+
+    #[test]
+    fn single_document() {
+        let input = format!("[{}]", docs().join(","));
+        let mut d = input.as_bytes().to_vec();
+        let expected = to_owned_value(&mut d).unwrap();
+        for mut parser in parsers() {
+            for step in &[1, 7, 64, 100, 4096] {
+                let r = Trickle {
+                    input: input.as_bytes(),
+                    step: *step,
+                };
+                assert_eq!(Ok(expected.clone()), parser.parse_owned_reader(r));
+            }
+        }
+    }
+
+    #[test]
+    fn single_document_errors() {
+        for mut parser in parsers() {
+            let mut error = |input: &[u8]| parser.parse_owned_reader(input).err();
+            assert_eq!(error(b""), Some(Error::generic(ErrorType::EOF)));
+            assert_eq!(error(b"   "), Some(Error::generic(ErrorType::EOF)));
+            let e = error(b"[\"abc]").unwrap();
+            assert_eq!(e.error_type(), &ErrorType::UnterminatedString);
+            assert_eq!(e.offset(), Some(1));
+            let e = error(b"[\"\xff\"]").unwrap();
+            assert_eq!(e.error_type(), &ErrorType::InvalidUTF8);
+            assert_eq!(e.offset(), Some(2));
+            assert!(parser.parse_owned_reader(&b"[1, 2"[..]).is_err());
+            assert!(parser.parse_owned_reader(&b"1"[..]).is_ok());
+        }
+    }
+
+    #[test]
+    fn many_documents() {
+        let docs = docs();
+        let input = stream(&docs);
+        let expected: Vec<_> = docs
+            .iter()
+            .map(|d| to_owned_value(&mut d.as_bytes().to_vec()).unwrap())
+            .collect();
+        for mut parser in parsers() {
+            for step in &[1, 3, 64, 65, 1000, 1 << 20] {
+                let r = Trickle {
+                    input: input.as_bytes(),
+                    step: *step,
+                };
+                let values: Vec<_> = parser
+                    .parse_many_owned_reader(r)
+                    .map(|v| v.unwrap())
+                    .collect();
+                assert_eq!(expected, values, "step {}", step);
+            }
+        }
+    }
+
+    #[test]
+    fn many_documents_errors() {
+        for mut parser in parsers() {
+            let r: Vec<_> = parser
+                .parse_many_owned_reader(&b"[1] {\"a\" 2} [3]\n"[..])
+                .map(|r| r.is_ok())
+                .collect();
+            assert_eq!(r, vec![true, false, true]);
+
+            let r: Vec<_> = parser
+                .parse_many_owned_reader(&b" \n\t "[..])
+                .map(|r| r.is_ok())
+                .collect();
+            assert!(r.is_empty());
+
+            let r: Vec<_> = parser
+                .parse_many_owned_reader(&b"[1] [\"abc"[..])
+                .map(|r| r.is_ok())
+                .collect();
+            assert_eq!(r, vec![false]);
+        }
+    }
+
+    #[test]
+    fn whitespace_doesnt_pile_up() {
+        struct Spaces(usize);
+        impl Read for Spaces {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.0.min(buf.len());
+                for b in &mut buf[..n] {
+                    *b = b' ';
+                }
+                self.0 -= n;
+                Ok(n)
+            }
+        }
+        let mut parser = Parser::new();
+        let mut docs = parser.parse_many_owned_reader(Spaces(1 << 24).chain(&b"[1]"[..]));
+        assert_eq!(
+            docs.next(),
+            Some(Ok(OwnedValue::Array(vec![OwnedValue::I64(1)])))
+        );
+        assert!(docs.chunks.buf.capacity() < 1 << 20);
+        assert_eq!(docs.next(), None);
+    }
+
+    #[test]
+    fn consumed_documents_are_dropped() {
+        let input = "[1] ".repeat(1 << 18);
+        for json5 in &[false, true] {
+            let mut parser = Parser::new().json5(*json5);
+            let mut docs = parser.parse_many_owned_reader(input.as_bytes());
+            for _ in 0..1 << 18 {
+                assert_eq!(
+                    docs.next(),
+                    Some(Ok(OwnedValue::Array(vec![OwnedValue::I64(1)])))
+                );
+                assert!(docs.chunks.buf.len() <= 2 * super::CHUNK_SIZE);
+            }
+            assert_eq!(docs.next(), None);
+        }
+    }
+
+    #[test]
+    fn io_errors() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "broken"))
+            }
+        }
+        let mut parser = Parser::new();
+        let r = parser.parse_owned_reader(Broken);
+        assert_eq!(r, Err(Error::generic(ErrorType::Io("broken".to_string()))));
+        let mut docs = parser.parse_many_owned_reader((&b"[1] "[..]).chain(Broken));
+        assert!(docs.next().unwrap().is_err());
+        assert_eq!(docs.next(), None);
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn deserialize() {
+        use serde_derive::Deserialize;
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Point {
+            x: i64,
+            y: String,
+        }
+        let mut parser = Parser::new();
+        let p: Point = parser
+            .deserialize_reader(&b"{\"x\": 1, \"y\": \"a\\nb\"}"[..])
+            .unwrap();
+        assert_eq!(
+            p,
+            Point {
+                x: 1,
+                y: "a\nb".to_string()
+            }
+        );
+        let r: Vec<Option<Point>> = parser
+            .deserialize_many_reader(
+                &b"{\"x\": 1, \"y\": \"a\"}\n{\"x\": \"1\"}\n{\"x\": 2, \"y\": \"b\"}"[..],
+            )
+            .map(|r| r.ok())
+            .collect();
+        assert_eq!(
+            r,
+            vec![
+                Some(Point {
+                    x: 1,
+                    y: "a".to_string()
+                }),
+                None,
+                Some(Point {
+                    x: 2,
+                    y: "b".to_string()
+                })
+            ]
+        );
+    }
+}
@@ -0,0 +1,4 @@
+mod deser;
+mod stage1;
+
+pub(crate) use self::stage1::Stage1;
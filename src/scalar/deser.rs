@@ -0,0 +1,69 @@
+use crate::stringparse::*;
+use crate::*;
+use std::borrow::Cow;
+
+impl<'de> Deserializer<'de> {
+    // Byte by byte version of the SIMD string parsing, same as there we
+    // borrow from the input if the string contains no escapes and unescape
+    // into `strings` otherwise.
+    pub(crate) unsafe fn parse_str_scalar(&mut self) -> Result<Cow<'de, str>> {
+        // Add 1 to skip the initial "
+        let idx = self.iidx + 1;
+
+        let src: &[u8] = self.input.bytes().get_unchecked(idx..);
+        let mut src_i: usize = 0;
+        loop {
+            match src.get(src_i) {
+                Some(b'"') => {
+                    let v = self.input.part(idx, idx + src_i) as *const [u8] as *const str;
+                    return Ok(Cow::Borrowed(&*v));
+                }
+                Some(b'\\') => break,
+                Some(_) => src_i += 1,
+                None => return Err(self.error(ErrorType::Syntax)),
+            }
+        }
+        let len = src_i;
+
+        let mut dst_i: usize = 0;
+        let dst: &mut [u8] = &mut self.strings;
+
+        loop {
+            match src.get(src_i) {
+                Some(b'"') => return Ok(self.unescaped_str(idx, len, dst_i)),
+                Some(b'\\') => {
+                    let escape_char: u8 = *src.get_unchecked(src_i + 1);
+                    if escape_char == b'u' {
+                        let (o, s) = if let Ok(r) = handle_unicode_codepoint(
+                            src.get_unchecked(src_i..),
+                            dst.get_unchecked_mut(dst_i..),
+                        ) {
+                            r
+                        } else {
+                            return Err(self.error(ErrorType::InvlaidUnicodeCodepoint));
+                        };
+                        if o == 0 {
+                            return Err(self.error(ErrorType::InvlaidUnicodeCodepoint));
+                        };
+                        src_i += s;
+                        dst_i += o;
+                    } else {
+                        let escape_result: u8 = *ESCAPE_MAP.get_unchecked(escape_char as usize);
+                        if escape_result == 0 {
+                            return Err(self.error(ErrorType::InvalidEscape));
+                        }
+                        *dst.get_unchecked_mut(dst_i) = escape_result;
+                        src_i += 2;
+                        dst_i += 1;
+                    }
+                }
+                Some(c) => {
+                    *dst.get_unchecked_mut(dst_i) = *c;
+                    src_i += 1;
+                    dst_i += 1;
+                }
+                None => return Err(self.error(ErrorType::Syntax)),
+            }
+        }
+    }
+}
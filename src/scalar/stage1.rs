@@ -0,0 +1,184 @@
+use crate::stage1::{
+    finalize_structurals, find_odd_backslash_sequences, flatten_bits, prefix_xor, BlockState,
+};
+use crate::*;
+
+// The bit masks the SIMD implementations compute with a handful of
+// instructions, one bit per byte of the 64 byte block.
+struct BlockBits {
+    backslash: u64,
+    quote: u64,
+    control: u64,
+    whitespace: u64,
+    structurals: u64,
+}
+
+#[cfg_attr(not(feature = "no-inline"), inline)]
+fn classify_block(block: &[u8]) -> BlockBits {
+    let mut bits = BlockBits {
+        backslash: 0,
+        quote: 0,
+        control: 0,
+        whitespace: 0,
+        structurals: 0,
+    };
+    for (i, c) in block.iter().enumerate() {
+        let bit = 1u64 << i;
+        match c {
+            b'\\' => bits.backslash |= bit,
+            b'"' => bits.quote |= bit,
+            b' ' | b'\t' | b'\n' | b'\r' => bits.whitespace |= bit,
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => bits.structurals |= bit,
+            _ => (),
+        }
+        // whitespace (other than space) is also a control character
+        if *c <= 0x1F {
+            bits.control |= bit;
+        }
+    }
+    bits
+}
+
+// Stage 1 for one document, fed 64 byte block by 64 byte block, see the
+// avx2 implementation.
+pub(crate) struct Stage1 {
+    state: BlockState,
+    // The start of a UTF-8 sequence that continues in the next block.
+    utf8_pending: [u8; 3],
+    utf8_pending_len: usize,
+    utf8_error: bool,
+}
+
+impl Stage1 {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: BlockState::default(),
+            utf8_pending: [0; 3],
+            utf8_pending_len: 0,
+            utf8_error: false,
+        }
+    }
+
+    pub(crate) fn block(&mut self, block: &[u8], idx: usize, structural_indexes: &mut Vec<u32>) {
+        let block = &block[..64];
+        self.check_utf8(block);
+        let state = &mut self.state;
+        let bits = classify_block(block);
+
+        let odd_ends: u64 =
+            find_odd_backslash_sequences(bits.backslash, &mut state.prev_iter_ends_odd_backslash);
+
+        let quote_bits: u64 = bits.quote & !odd_ends;
+        let quote_mask: u64 = prefix_xor(quote_bits) ^ state.prev_iter_inside_quote;
+        state.error_mask |= quote_mask & bits.control;
+        state.prev_iter_inside_quote = ((quote_mask as i64) >> 63) as u64;
+
+        // take the previous iterations structural bits, not our current iteration,
+        // and flatten
+        flatten_bits(structural_indexes, idx as u32, state.structurals);
+
+        state.structurals = finalize_structurals(
+            bits.structurals,
+            bits.whitespace,
+            quote_mask,
+            quote_bits,
+            &mut state.prev_iter_ends_pseudo_pred,
+        );
+    }
+
+    pub(crate) fn finish(
+        &mut self,
+        idx: usize,
+        structural_indexes: &mut Vec<u32>,
+    ) -> std::result::Result<(), ErrorType> {
+        if self.state.prev_iter_inside_quote != 0 {
+            return Err(ErrorType::Syntax);
+        }
+        // finally, flatten out the remaining structurals from the last iteration
+        flatten_bits(structural_indexes, idx as u32, self.state.structurals);
+        self.state.structurals = 0;
+        // a sequence that is cut off by the end of the input
+        if self.utf8_pending_len != 0 {
+            self.utf8_error = true;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check(&self) -> std::result::Result<(), ErrorType> {
+        if self.state.error_mask != 0 {
+            return Err(ErrorType::Syntax);
+        }
+        if self.utf8_error {
+            Err(ErrorType::InvalidUTF8)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Without SIMD there is no point in a fancy UTF-8 validation, we let
+    // the standard library validate the block together with the start of a
+    // sequence the previous block ended in.
+    fn check_utf8(&mut self, block: &[u8]) {
+        if self.utf8_pending_len == 0 && block.is_ascii() {
+            return;
+        }
+        let mut buf = [0u8; 67];
+        let pending = self.utf8_pending_len;
+        buf[..pending].copy_from_slice(&self.utf8_pending[..pending]);
+        buf[pending..pending + 64].copy_from_slice(block);
+        let buf = &buf[..pending + 64];
+        self.utf8_pending_len = 0;
+        if let Err(e) = std::str::from_utf8(buf) {
+            if e.error_len().is_none() {
+                let rest = &buf[e.valid_up_to()..];
+                self.utf8_pending[..rest.len()].copy_from_slice(rest);
+                self.utf8_pending_len = rest.len();
+            } else {
+                self.utf8_error = true;
+            }
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    pub(crate) unsafe fn find_structural_bits_scalar(
+        input: &[u8],
+        structural_indexes: &mut Vec<u32>,
+    ) -> std::result::Result<(), ErrorType> {
+        let len = input.len();
+        // 6 is a heuristic number to estimate it turns out a rate of 1/6 structural caracters lears
+        // almost never to relocations.
+        structural_indexes.clear();
+        structural_indexes.reserve(len / 6);
+        structural_indexes.push(0); // push extra root element
+
+        let mut stage1 = Stage1::new();
+        let mut idx: usize = 0;
+
+        let mut tmpbuf: [u8; 64] = [0x20; 64];
+        while idx < len {
+            if idx + 64 <= len {
+                stage1.block(input.get_unchecked(idx..idx + 64), idx, structural_indexes);
+            } else {
+                tmpbuf
+                    .get_unchecked_mut(..len - idx)
+                    .copy_from_slice(input.get_unchecked(idx..));
+                stage1.block(&tmpbuf, idx, structural_indexes);
+            }
+            idx += 64;
+        }
+        stage1.finish(idx, structural_indexes)?;
+
+        // a valid JSON file cannot have zero structural indexes - we should have
+        // found something (note that we compare to 1 as we always add the root!)
+        if structural_indexes.len() == 1 {
+            return Err(ErrorType::EOF);
+        }
+
+        if structural_indexes.last() > Some(&(len as u32)) {
+            return Err(ErrorType::InternalError);
+        }
+
+        stage1.check()
+    }
+}
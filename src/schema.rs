@@ -0,0 +1,797 @@
+//! JSON Schema validation for `OwnedValue` and `BorrowedValue`.
+//!
+//! A schema is compiled once from an `OwnedValue` into a `Schema` that
+//! can then validate any value implementing `ValueTrait`, so documents
+//! can be checked right after parsing them. The supported keywords are a
+//! subset of [draft 7](https://json-schema.org/specification-links.html)
+//! and 2019-09:
+//!
+//! - `type`, `enum` and `const`
+//! - `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum` and
+//!   `multipleOf` for numbers
+//! - `minLength`, `maxLength` and `pattern` for strings
+//! - `items`, `additionalItems`, `minItems`, `maxItems`, `uniqueItems` and
+//!   `contains` for arrays
+//! - `properties`, `patternProperties`, `additionalProperties`,
+//!   `required`, `minProperties` and `maxProperties` for objects
+//! - `allOf`, `anyOf`, `oneOf` and `not`
+//! - `$ref` to a JSON Pointer into the same schema, such as
+//!   `#/definitions/name`, keywords next to it apply as well
+//!
+//! `true` and `false` are schemas that accept everything and nothing.
+//! Other keywords, such as `$schema` or `format`, are ignored. Patterns
+//! use the syntax of the [regex](https://docs.rs/regex) crate and match
+//! anywhere in a string unless they are anchored. As it depends on regex
+//! the module is only built with the `schema` feature.
+//!
+//! ```
+//! use simd_json::{json, Schema};
+//!
+//! let schema = Schema::compile(&json!({
+//!     "type": "object",
+//!     "properties": {"name": {"type": "string"}, "age": {"minimum": 0}},
+//!     "required": ["name"]
+//! }))
+//! .unwrap();
+//!
+//! let mut d = br#"{"age": -1}"#.to_vec();
+//! let v = simd_json::to_borrowed_value(&mut d).unwrap();
+//! let violations = schema.validate(&v);
+//! assert_eq!(violations.len(), 2);
+//! assert_eq!(violations[0].instance, "/age");
+//! assert_eq!(violations[0].schema, "/properties/age/minimum");
+//! assert_eq!(violations[1].instance, "");
+//! assert_eq!(violations[1].schema, "/required");
+//! ```
+
+use crate::error::escape;
+use crate::value::owned::Value;
+use crate::value::{compare_numbers, equal_nodes, number_of, Number};
+use crate::value::{ArrayTrait, ObjectTrait, ValueTrait};
+use crate::{Error, ErrorType, Result};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A compiled JSON Schema, see the module documentation for the supported
+/// keywords.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    // the root is the first node, `$ref`s point to others by their index
+    nodes: Vec<Node>,
+}
+
+/// A part of a value that doesn't match its schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// The JSON Pointer of the value that doesn't match.
+    pub instance: String,
+    /// The JSON Pointer of the keyword in the schema that doesn't hold,
+    /// or of the `false` schema. For keywords reached through a `$ref`
+    /// this is where the keyword is, not the path taken to it.
+    pub schema: String,
+    /// What is wrong, in words.
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}': {}", self.instance, self.message)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Type {
+    Null,
+    Boolean,
+    Object,
+    Array,
+    Number,
+    String,
+    Integer,
+}
+
+#[derive(Clone, Debug)]
+enum Items {
+    // every element
+    All(usize),
+    // the element at the same index
+    Tuple(Vec<usize>),
+}
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    // the JSON Pointer of the schema, violations are reported relative to it
+    path: String,
+    // the `false` schema
+    reject: bool,
+    types: Option<Vec<Type>>,
+    enumeration: Option<Vec<Value>>,
+    constant: Option<Value>,
+    minimum: Option<Number>,
+    maximum: Option<Number>,
+    exclusive_minimum: Option<Number>,
+    exclusive_maximum: Option<Number>,
+    multiple_of: Option<Number>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    pattern: Option<Regex>,
+    items: Option<Items>,
+    additional_items: Option<usize>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    unique_items: bool,
+    contains: Option<usize>,
+    properties: HashMap<String, usize>,
+    pattern_properties: Vec<(Regex, usize)>,
+    additional_properties: Option<usize>,
+    required: Vec<String>,
+    min_properties: Option<usize>,
+    max_properties: Option<usize>,
+    reference: Option<usize>,
+    all_of: Vec<usize>,
+    any_of: Vec<usize>,
+    one_of: Vec<usize>,
+    not: Option<usize>,
+}
+
+impl Schema {
+    /// Compiles a schema. Fails with `ErrorType::InvalidSchema` holding
+    /// the JSON Pointer of the first part of the schema that isn't valid,
+    /// such as a keyword with a value of the wrong type, a pattern that
+    /// doesn't compile or a `$ref` to something outside of the schema.
+    /// Schemas that refer to themselves without looking at a part of the
+    /// value, like `{"$ref": "#"}`, fail as well as they would never stop
+    /// validating.
+    pub fn compile(schema: &Value) -> Result<Self> {
+        let mut c = Compiler {
+            root: schema,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+        };
+        stry!(c.compile(schema, String::new()));
+        stry!(check_cycles(&c.nodes));
+        Ok(Self { nodes: c.nodes })
+    }
+
+    /// All the violations of the schema in `value`, empty if it is valid.
+    /// Members of objects are checked in the order the object iterates
+    /// them in.
+    pub fn validate<V: ValueTrait>(&self, value: &V) -> Vec<Violation> {
+        let mut out = Vec::new();
+        self.check(0, value, &mut String::new(), &mut out, true);
+        out
+    }
+
+    /// Whether `value` is valid, this stops at the first violation.
+    pub fn is_valid<V: ValueTrait>(&self, value: &V) -> bool {
+        self.matches(0, value)
+    }
+
+    fn matches<V: ValueTrait>(&self, id: usize, value: &V) -> bool {
+        self.check(id, value, &mut String::new(), &mut Vec::new(), false)
+    }
+
+    // Checks the value against the node at `instance`, returns whether it
+    // is valid. Violations are added to `out`, if `all` is false this
+    // stops at the first one.
+    fn check<V: ValueTrait>(
+        &self,
+        id: usize,
+        v: &V,
+        instance: &mut String,
+        out: &mut Vec<Violation>,
+        all: bool,
+    ) -> bool {
+        let node = &self.nodes[id];
+        let len = out.len();
+        macro_rules! fail {
+            ($keyword:expr, $($arg:tt)*) => {{
+                out.push(Violation {
+                    instance: instance.clone(),
+                    schema: format!("{}/{}", node.path, $keyword),
+                    message: format!($($arg)*),
+                });
+                if !all {
+                    return false;
+                }
+            }};
+        }
+        macro_rules! child {
+            ($id:expr, $v:expr, $segment:expr) => {{
+                let len = instance.len();
+                instance.push('/');
+                instance.push_str($segment);
+                let valid = self.check($id, $v, instance, out, all);
+                instance.truncate(len);
+                if !valid && !all {
+                    return false;
+                }
+            }};
+        }
+
+        if node.reject {
+            out.push(Violation {
+                instance: instance.clone(),
+                schema: node.path.clone(),
+                message: "no value is allowed".to_string(),
+            });
+            return false;
+        }
+        if let Some(types) = &node.types {
+            if !types.iter().any(|t| t.matches(v)) {
+                let names: Vec<&str> = types.iter().map(|t| t.name()).collect();
+                fail!("type", "expected {}", names.join(" or "));
+            }
+        }
+        if let Some(values) = &node.enumeration {
+            if !values.iter().any(|e| equal_nodes(v, e)) {
+                fail!("enum", "expected one of {}", Value::Array(values.clone()));
+            }
+        }
+        if let Some(c) = &node.constant {
+            if !equal_nodes(v, c) {
+                fail!("const", "expected {}", c);
+            }
+        }
+
+        if let Some(n) = number_of(v) {
+            let cmp = |bound: Number| compare_numbers(n, bound);
+            if let Some(b) = node.minimum {
+                if cmp(b) == Some(Ordering::Less) {
+                    fail!("minimum", "expected at least {}", b);
+                }
+            }
+            if let Some(b) = node.maximum {
+                if cmp(b) == Some(Ordering::Greater) {
+                    fail!("maximum", "expected at most {}", b);
+                }
+            }
+            if let Some(b) = node.exclusive_minimum {
+                if cmp(b) != Some(Ordering::Greater) {
+                    fail!("exclusiveMinimum", "expected more than {}", b);
+                }
+            }
+            if let Some(b) = node.exclusive_maximum {
+                if cmp(b) != Some(Ordering::Less) {
+                    fail!("exclusiveMaximum", "expected less than {}", b);
+                }
+            }
+            if let Some(m) = node.multiple_of {
+                if !is_multiple(n, m) {
+                    fail!("multipleOf", "expected a multiple of {}", m);
+                }
+            }
+        }
+
+        if let Some(s) = v.as_str() {
+            // lengths are in code points, not bytes
+            let chars = || s.chars().count();
+            if let Some(min) = node.min_length {
+                if chars() < min {
+                    fail!("minLength", "expected at least {} characters", min);
+                }
+            }
+            if let Some(max) = node.max_length {
+                if chars() > max {
+                    fail!("maxLength", "expected at most {} characters", max);
+                }
+            }
+            if let Some(re) = &node.pattern {
+                if !re.is_match(s) {
+                    fail!("pattern", "expected to match {}", re);
+                }
+            }
+        }
+
+        if let Some(a) = v.as_array() {
+            match &node.items {
+                Some(Items::All(s)) => {
+                    for (i, e) in a.iter().enumerate() {
+                        child!(*s, e, &i.to_string());
+                    }
+                }
+                Some(Items::Tuple(ss)) => {
+                    for (i, e) in a.iter().enumerate() {
+                        if let Some(s) = ss.get(i).copied().or(node.additional_items) {
+                            child!(s, e, &i.to_string());
+                        }
+                    }
+                }
+                None => (),
+            }
+            if let Some(min) = node.min_items {
+                if a.len() < min {
+                    fail!("minItems", "expected at least {} elements", min);
+                }
+            }
+            if let Some(max) = node.max_items {
+                if a.len() > max {
+                    fail!("maxItems", "expected at most {} elements", max);
+                }
+            }
+            if node.unique_items {
+                let elements: Vec<&V> = a.iter().collect();
+                let duplicate = (0..elements.len()).find_map(|j| {
+                    (0..j)
+                        .find(|i| equal_nodes(elements[*i], elements[j]))
+                        .map(|i| (i, j))
+                });
+                if let Some((i, j)) = duplicate {
+                    fail!(
+                        "uniqueItems",
+                        "expected unique elements, {} and {} are the same",
+                        i,
+                        j
+                    );
+                }
+            }
+            if let Some(s) = node.contains {
+                if !a.iter().any(|e| self.matches(s, e)) {
+                    fail!("contains", "expected an element matching the schema");
+                }
+            }
+        }
+
+        if let Some(m) = v.as_object() {
+            for (k, e) in m.iter() {
+                let key = escape(k);
+                let mut known = false;
+                if let Some(s) = node.properties.get(k) {
+                    known = true;
+                    child!(*s, e, &key);
+                }
+                for (re, s) in &node.pattern_properties {
+                    if re.is_match(k) {
+                        known = true;
+                        child!(*s, e, &key);
+                    }
+                }
+                if let (false, Some(s)) = (known, node.additional_properties) {
+                    child!(s, e, &key);
+                }
+            }
+            for k in &node.required {
+                if m.get(k).is_none() {
+                    fail!("required", "missing the member \"{}\"", k);
+                }
+            }
+            if let Some(min) = node.min_properties {
+                if m.len() < min {
+                    fail!("minProperties", "expected at least {} members", min);
+                }
+            }
+            if let Some(max) = node.max_properties {
+                if m.len() > max {
+                    fail!("maxProperties", "expected at most {} members", max);
+                }
+            }
+        }
+
+        if let Some(r) = node.reference {
+            if !self.check(r, v, instance, out, all) && !all {
+                return false;
+            }
+        }
+        for s in &node.all_of {
+            if !self.check(*s, v, instance, out, all) && !all {
+                return false;
+            }
+        }
+        if !node.any_of.is_empty() && !node.any_of.iter().any(|s| self.matches(*s, v)) {
+            fail!("anyOf", "expected to match at least one of the schemas");
+        }
+        if !node.one_of.is_empty() {
+            let matched = node.one_of.iter().filter(|s| self.matches(**s, v)).count();
+            if matched != 1 {
+                fail!(
+                    "oneOf",
+                    "expected to match exactly one of the schemas, matched {}",
+                    matched
+                );
+            }
+        }
+        if let Some(s) = node.not {
+            if self.matches(s, v) {
+                fail!("not", "expected not to match the schema");
+            }
+        }
+        out.len() == len
+    }
+}
+
+impl Type {
+    fn matches<V: ValueTrait>(self, v: &V) -> bool {
+        match self {
+            Type::Null => v.is_null(),
+            Type::Boolean => v.is_bool(),
+            Type::Object => v.is_object(),
+            Type::Array => v.is_array(),
+            Type::Number => number_of(v).is_some(),
+            Type::String => v.as_str().is_some(),
+            // a float without a fractional part is an integer as well
+            Type::Integer => match number_of(v) {
+                Some(Number::Int(_)) => true,
+                Some(Number::Float(f)) => f.fract() == 0.0,
+                None => false,
+            },
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Null => "null",
+            Type::Boolean => "boolean",
+            Type::Object => "object",
+            Type::Array => "array",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Integer => "integer",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "null" => Type::Null,
+            "boolean" => Type::Boolean,
+            "object" => Type::Object,
+            "array" => Type::Array,
+            "number" => Type::Number,
+            "string" => Type::String,
+            "integer" => Type::Integer,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::Int(i) => write!(f, "{}", i),
+            Number::Float(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+fn is_multiple(n: Number, m: Number) -> bool {
+    match (n, m) {
+        (Number::Int(n), Number::Int(m)) => n % m == 0,
+        _ => {
+            let to_f64 = |n: Number| match n {
+                Number::Int(i) => i as f64,
+                Number::Float(f) => f,
+            };
+            // decimal fractions aren't exact in binary, so the quotient
+            // only has to be within rounding of a whole number
+            let q = to_f64(n) / to_f64(m);
+            q.is_finite() && (q - q.round()).abs() <= q.abs() * std::f64::EPSILON
+        }
+    }
+}
+
+struct Compiler<'s> {
+    root: &'s Value,
+    nodes: Vec<Node>,
+    // the nodes by the path of their schema, so a schema that is referred
+    // to more than once, or by itself, is only compiled once
+    ids: HashMap<String, usize>,
+}
+
+fn invalid(path: &str) -> Error {
+    Error::generic(ErrorType::InvalidSchema(path.to_string()))
+}
+
+impl<'s> Compiler<'s> {
+    fn compile(&mut self, schema: &'s Value, path: String) -> Result<usize> {
+        if let Some(id) = self.ids.get(&path) {
+            return Ok(*id);
+        }
+        let id = self.nodes.len();
+        self.nodes.push(Node::default());
+        self.ids.insert(path.clone(), id);
+        self.nodes[id] = stry!(self.node(schema, path));
+        Ok(id)
+    }
+
+    fn node(&mut self, schema: &'s Value, path: String) -> Result<Node> {
+        let m = match schema {
+            Value::Object(m) => m,
+            Value::Bool(accept) => {
+                return Ok(Node {
+                    path,
+                    reject: !accept,
+                    ..Node::default()
+                })
+            }
+            _ => return Err(invalid(&path)),
+        };
+        let at = |keyword: &str| format!("{}/{}", path, escape(keyword));
+        let mut node = Node::default();
+
+        if let Some(t) = m.get("type") {
+            let names: Vec<&Value> = match t {
+                Value::Array(a) => a.iter().collect(),
+                t => vec![t],
+            };
+            let types: Option<Vec<Type>> = names
+                .iter()
+                .map(|t| t.as_str().and_then(Type::from_name))
+                .collect();
+            node.types = Some(stry!(types.ok_or_else(|| invalid(&at("type")))));
+        }
+        if let Some(e) = m.get("enum") {
+            match e {
+                Value::Array(a) => node.enumeration = Some(a.clone()),
+                _ => return Err(invalid(&at("enum"))),
+            }
+        }
+        node.constant = m.get("const").cloned();
+
+        let number = |keyword: &str| match m.get(keyword) {
+            Some(v) => number_of(v).map(Some).ok_or_else(|| invalid(&at(keyword))),
+            None => Ok(None),
+        };
+        node.minimum = stry!(number("minimum"));
+        node.maximum = stry!(number("maximum"));
+        node.exclusive_minimum = stry!(number("exclusiveMinimum"));
+        node.exclusive_maximum = stry!(number("exclusiveMaximum"));
+        node.multiple_of = stry!(number("multipleOf"));
+        if let Some(m) = node.multiple_of {
+            if compare_numbers(m, Number::Int(0)) != Some(Ordering::Greater) {
+                return Err(invalid(&at("multipleOf")));
+            }
+        }
+
+        let count = |keyword: &str| match m.get(keyword) {
+            Some(v) => count_of(v).map(Some).ok_or_else(|| invalid(&at(keyword))),
+            None => Ok(None),
+        };
+        node.min_length = stry!(count("minLength"));
+        node.max_length = stry!(count("maxLength"));
+        node.min_items = stry!(count("minItems"));
+        node.max_items = stry!(count("maxItems"));
+        node.min_properties = stry!(count("minProperties"));
+        node.max_properties = stry!(count("maxProperties"));
+
+        if let Some(p) = m.get("pattern") {
+            let re = p.as_str().and_then(|p| Regex::new(p).ok());
+            node.pattern = Some(stry!(re.ok_or_else(|| invalid(&at("pattern")))));
+        }
+        if let Some(u) = m.get("uniqueItems") {
+            node.unique_items = stry!(u.as_bool().ok_or_else(|| invalid(&at("uniqueItems"))));
+        }
+        if let Some(r) = m.get("required") {
+            let names: Option<Vec<String>> = match r {
+                Value::Array(a) => a.iter().map(|n| n.as_str().map(String::from)).collect(),
+                _ => None,
+            };
+            node.required = stry!(names.ok_or_else(|| invalid(&at("required"))));
+        }
+
+        if let Some(i) = m.get("items") {
+            node.items = Some(match i {
+                Value::Array(a) => Items::Tuple(stry!(self.schemas(a, &at("items")))),
+                i => Items::All(stry!(self.compile(i, at("items")))),
+            });
+        }
+        if let Some(s) = m.get("additionalItems") {
+            node.additional_items = Some(stry!(self.compile(s, at("additionalItems"))));
+        }
+        if let Some(s) = m.get("contains") {
+            node.contains = Some(stry!(self.compile(s, at("contains"))));
+        }
+        if let Some(p) = m.get("properties") {
+            let p = stry!(p.as_object().ok_or_else(|| invalid(&at("properties"))));
+            for (k, s) in p.iter() {
+                let id = stry!(self.compile(s, format!("{}/{}", at("properties"), escape(k))));
+                node.properties.insert(k.clone(), id);
+            }
+        }
+        if let Some(p) = m.get("patternProperties") {
+            let path = at("patternProperties");
+            let p = stry!(p.as_object().ok_or_else(|| invalid(&path)));
+            for (k, s) in p.iter() {
+                let path = format!("{}/{}", path, escape(k));
+                let re = stry!(Regex::new(k).map_err(|_| invalid(&path)));
+                let id = stry!(self.compile(s, path));
+                node.pattern_properties.push((re, id));
+            }
+        }
+        if let Some(s) = m.get("additionalProperties") {
+            node.additional_properties = Some(stry!(self.compile(s, at("additionalProperties"))));
+        }
+
+        if let Some(r) = m.get("$ref") {
+            let target = r
+                .as_str()
+                .and_then(|r| r.strip_prefix('#'))
+                .and_then(percent_decode);
+            let target = stry!(target.ok_or_else(|| invalid(&at("$ref"))));
+            let schema = stry!(self
+                .root
+                .pointer(&target)
+                .ok_or_else(|| invalid(&at("$ref"))));
+            node.reference = Some(stry!(self.compile(schema, target)));
+        }
+        node.all_of = stry!(self.applicator(m.get("allOf"), &at("allOf")));
+        node.any_of = stry!(self.applicator(m.get("anyOf"), &at("anyOf")));
+        node.one_of = stry!(self.applicator(m.get("oneOf"), &at("oneOf")));
+        if let Some(s) = m.get("not") {
+            node.not = Some(stry!(self.compile(s, at("not"))));
+        }
+
+        node.path = path;
+        Ok(node)
+    }
+
+    // `allOf`, `anyOf` and `oneOf` take a non-empty array of schemas.
+    fn applicator(&mut self, schemas: Option<&'s Value>, path: &str) -> Result<Vec<usize>> {
+        match schemas {
+            None => Ok(Vec::new()),
+            Some(Value::Array(a)) if !a.is_empty() => self.schemas(a, path),
+            Some(_) => Err(invalid(path)),
+        }
+    }
+
+    fn schemas(&mut self, schemas: &'s [Value], path: &str) -> Result<Vec<usize>> {
+        schemas
+            .iter()
+            .enumerate()
+            .map(|(i, s)| self.compile(s, format!("{}/{}", path, i)))
+            .collect()
+    }
+}
+
+// Sizes have to be non-negative integers, `2.0` is one as well.
+fn count_of(v: &Value) -> Option<usize> {
+    match number_of(v) {
+        Some(Number::Int(i)) if i >= 0 => Some(i.min(usize::max_value() as i128) as usize),
+        Some(Number::Float(f)) if f >= 0.0 && f.fract() == 0.0 => Some(f as usize),
+        _ => None,
+    }
+}
+
+// The fragment of a `$ref` is URI encoded.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut res = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            res.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            res.push(b);
+        }
+    }
+    String::from_utf8(res).ok()
+}
+
+// Finds schemas that get back to themselves through `$ref`, `allOf`,
+// `anyOf`, `oneOf` or `not`, all of which look at the same value, so
+// validating would never end.
+fn check_cycles(nodes: &[Node]) -> Result<()> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        New,
+        Visiting,
+        Done,
+    }
+    fn visit(nodes: &[Node], id: usize, state: &mut [State]) -> Result<()> {
+        match state[id] {
+            State::Done => return Ok(()),
+            State::Visiting => return Err(invalid(&nodes[id].path)),
+            State::New => state[id] = State::Visiting,
+        }
+        let n = &nodes[id];
+        for next in n
+            .reference
+            .iter()
+            .chain(n.all_of.iter())
+            .chain(n.any_of.iter())
+            .chain(n.one_of.iter())
+            .chain(n.not.iter())
+        {
+            stry!(visit(nodes, *next, state));
+        }
+        state[id] = State::Done;
+        Ok(())
+    }
+    let mut state = vec![State::New; nodes.len()];
+    for id in 0..nodes.len() {
+        stry!(visit(nodes, id, &mut state));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn value(input: &str) -> Value {
+        crate::to_owned_value(&mut input.as_bytes().to_vec()).unwrap()
+    }
+
+    fn error(schema: Value) -> String {
+        match Schema::compile(&schema).unwrap_err().error {
+            ErrorType::InvalidSchema(path) => path,
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error(value(r#"1"#)), "");
+        assert_eq!(error(value(r#"{"type": "text"}"#)), "/type");
+        assert_eq!(error(value(r#"{"type": ["string", 1]}"#)), "/type");
+        assert_eq!(error(value(r#"{"minimum": "1"}"#)), "/minimum");
+        assert_eq!(error(value(r#"{"multipleOf": 0}"#)), "/multipleOf");
+        assert_eq!(error(value(r#"{"minLength": -1}"#)), "/minLength");
+        assert_eq!(error(value(r#"{"maxItems": 1.5}"#)), "/maxItems");
+        assert_eq!(error(value(r#"{"pattern": "("}"#)), "/pattern");
+        assert_eq!(error(value(r#"{"required": ["a", 1]}"#)), "/required");
+        assert_eq!(error(value(r#"{"anyOf": []}"#)), "/anyOf");
+        assert_eq!(
+            error(value(r#"{"properties": {"a/b": {"items": [true, 2]}}}"#)),
+            "/properties/a~1b/items/1"
+        );
+        assert_eq!(
+            error(value(r#"{"patternProperties": {"[": {}}}"#)),
+            "/patternProperties/["
+        );
+        assert_eq!(error(value(r#"{"$ref": "other.json#/a"}"#)), "/$ref");
+        assert_eq!(error(value(r##"{"$ref": "#/definitions/a"}"##)), "/$ref");
+        assert_eq!(error(value(r##"{"not": {"$ref": "#/not"}}"##)), "/not");
+        assert_eq!(
+            error(value(
+                r##"{"definitions": {"a": {"allOf": [{"$ref": "#"}]}}, "$ref": "#/definitions/a"}"##
+            )),
+            ""
+        );
+    }
+
+    #[test]
+    fn refs() {
+        let schema = Schema::compile(&value(
+            r##"{
+            "definitions": {
+                "tree": {
+                    "type": "object",
+                    "properties": {"children": {"items": {"$ref": "#/definitions/tree"}}},
+                    "required": ["name"]
+                },
+                "a%b": {"type": "string"},
+                "c~d": {"type": "string"}
+            },
+            "properties": {
+                "tree": {"$ref": "#/definitions/tree"},
+                "x": {"$ref": "#/definitions/a%25b"},
+                "y": {"$ref": "#/definitions/c~0d", "maxLength": 1}
+            }
+        }"##,
+        ))
+        .unwrap();
+        let v = value(r#"{"tree": {"name": 1, "children": [{"name": 2}, {"children": []}]}}"#);
+        let violations = schema.validate(&v);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].instance, "/tree/children/1");
+        assert_eq!(violations[0].schema, "/definitions/tree/required");
+        assert!(schema.is_valid(&value(r#"{"x": "a", "y": "b"}"#)));
+        assert!(!schema.is_valid(&value(r#"{"x": 1}"#)));
+        assert!(!schema.is_valid(&value(r#"{"y": "bc"}"#)));
+    }
+
+    #[test]
+    fn numbers() {
+        assert!(is_multiple(Number::Int(10), Number::Int(5)));
+        assert!(!is_multiple(Number::Int(10), Number::Int(3)));
+        assert!(is_multiple(Number::Float(0.3), Number::Float(0.1)));
+        assert!(is_multiple(Number::Float(0.0075), Number::Float(0.0001)));
+        assert!(!is_multiple(Number::Float(0.00751), Number::Float(0.0001)));
+        assert!(!is_multiple(Number::Float(1e308), Number::Float(0.123)));
+        let schema = Schema::compile(&value(r#"{"maximum": 9007199254740992}"#)).unwrap();
+        assert!(schema.is_valid(&Value::from(9007199254740992u64)));
+        assert!(!schema.is_valid(&Value::from(9007199254740993u64)));
+    }
+}
@@ -6,16 +6,26 @@
 /// However if have to use serde for other readons or are psrsing
 /// directly to structs this is th4 place to go.
 mod de;
+mod number;
+mod se;
 mod value;
 use crate::numberparse::Number;
-use crate::{stry, Deserializer, Error, ErrorType, Result};
+use crate::{stry, Deserializer, Error, ErrorType, Parser, Result};
+use serde_ext::de::DeserializeOwned;
 use serde_ext::Deserialize;
 use std::fmt;
+use std::io::Read;
 
+pub use self::number::RawNumber;
+pub use self::se::{
+    to_string, to_string_pretty, to_vec, to_vec_pretty, to_writer, to_writer_pretty,
+};
 pub use self::value::*;
 
 /// parses a byte slice using a serde deserializer.
 /// note that the slice will be rewritten in the process.
+/// JSON5 documents are deserialized with a `Parser` set to
+/// `Parser::json5` and `Parser::deserialize`.
 #[cfg_attr(not(feature = "no-inline"), inline(always))]
 pub fn from_slice<'a, T>(s: &'a mut [u8]) -> Result<T>
 where
@@ -23,7 +33,7 @@ where
 {
     let mut deserializer = stry!(Deserializer::from_slice(s));
 
-    T::deserialize(&mut deserializer)
+    T::deserialize(&mut deserializer).map_err(|e| deserializer.locate(e))
 }
 /// parses a str  using a serde deserializer.
 /// note that the slice will be rewritten in the process and
@@ -35,7 +45,42 @@ where
 {
     let mut deserializer = stry!(Deserializer::from_slice(unsafe { s.as_bytes_mut() }));
 
-    T::deserialize(&mut deserializer)
+    T::deserialize(&mut deserializer).map_err(|e| deserializer.locate(e))
+}
+
+/// parses an immutable byte slice using a serde deserializer.
+/// the slice is left untouched, strings containing escapes
+/// get copied.
+#[cfg_attr(not(feature = "no-inline"), inline(always))]
+pub fn from_slice_ref<'a, T>(s: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = stry!(Deserializer::from_slice_ref(s));
+
+    T::deserialize(&mut deserializer).map_err(|e| deserializer.locate(e))
+}
+
+/// parses an immutable str using a serde deserializer.
+/// the str is left untouched, strings containing escapes
+/// get copied.
+#[cfg_attr(not(feature = "no-inline"), inline(always))]
+pub fn from_str_ref<'a, T>(s: &'a str) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_slice_ref(s.as_bytes())
+}
+
+/// parses a document read from a `std::io::Read` using a serde
+/// deserializer. stage 1 runs while the input is read, see
+/// `Parser::deserialize_reader`.
+pub fn from_reader<R, T>(rdr: R) -> Result<T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    Parser::new().deserialize_reader(rdr)
 }
 
 impl std::error::Error for Error {}
@@ -54,13 +99,23 @@ impl serde_ext::ser::Error for Error {
 
 // Functions purely used by serde
 impl<'de> Deserializer<'de> {
+    // Errors raised by serde itself, like a missing field, know nothing
+    // about the input, they are placed at the value we stopped at.
+    pub(crate) fn locate(&self, e: Error) -> Error {
+        if e.offset().is_some() {
+            e
+        } else {
+            self.error(e.error)
+        }
+    }
+
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
     fn next(&mut self) -> Result<u8> {
         unsafe {
             self.idx += 1;
             if let Some(idx) = self.structural_indexes.get(self.idx) {
                 self.iidx = *idx as usize;
-                let r = *self.input.get_unchecked(self.iidx);
+                let r = *self.input.bytes().get_unchecked(self.iidx);
                 Ok(r)
             } else {
                 Err(self.error(ErrorType::Syntax))
@@ -71,7 +126,7 @@ impl<'de> Deserializer<'de> {
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
     fn peek(&self) -> Result<u8> {
         if let Some(idx) = self.structural_indexes.get(self.idx + 1) {
-            unsafe { Ok(*self.input.get_unchecked(*idx as usize)) }
+            unsafe { Ok(*self.input.bytes().get_unchecked(*idx as usize)) }
         } else {
             Err(self.error(ErrorType::UnexpectedEnd))
         }
@@ -82,10 +137,12 @@ impl<'de> Deserializer<'de> {
         match self.next_() {
             b'-' => match stry!(self.parse_number(true)) {
                 Number::I64(n) => Ok(n),
+                Number::Raw(n) => n.parse().map_err(|_| self.error(ErrorType::ExpectedSigned)),
                 _ => Err(self.error(ErrorType::ExpectedSigned)),
             },
             b'0'..=b'9' => match stry!(self.parse_number(false)) {
                 Number::I64(n) => Ok(n),
+                Number::Raw(n) => n.parse().map_err(|_| self.error(ErrorType::ExpectedSigned)),
                 _ => Err(self.error(ErrorType::ExpectedSigned)),
             },
             _ => Err(self.error(ErrorType::ExpectedSigned)),
@@ -97,6 +154,10 @@ impl<'de> Deserializer<'de> {
         match self.next_() {
             b'0'..=b'9' => match stry!(self.parse_number(false)) {
                 Number::I64(n) => Ok(n as u64),
+                Number::U64(n) => Ok(n),
+                Number::Raw(n) => n
+                    .parse()
+                    .map_err(|_| self.error(ErrorType::ExpectedUnsigned)),
                 _ => Err(self.error(ErrorType::ExpectedUnsigned)),
             },
             _ => Err(self.error(ErrorType::ExpectedUnsigned)),
@@ -108,10 +169,14 @@ impl<'de> Deserializer<'de> {
             b'-' => match stry!(self.parse_number(true)) {
                 Number::F64(n) => Ok(n),
                 Number::I64(n) => Ok(n as f64),
+                Number::U64(n) => Ok(n as f64),
+                Number::Raw(n) => n.parse().map_err(|_| self.error(ErrorType::ExpectedFloat)),
             },
-            b'0'..=b'9' => match stry!(self.parse_number(false)) {
+            b'0'..=b'9' | b'N' | b'I' => match stry!(self.parse_number(false)) {
                 Number::F64(n) => Ok(n),
                 Number::I64(n) => Ok(n as f64),
+                Number::U64(n) => Ok(n as f64),
+                Number::Raw(n) => n.parse().map_err(|_| self.error(ErrorType::ExpectedFloat)),
             },
             _ => Err(self.error(ErrorType::ExpectedFloat)),
         }
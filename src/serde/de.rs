@@ -1,7 +1,22 @@
+use super::number::{RawNumberAccess, TOKEN};
 use crate::numberparse::Number;
 use crate::*;
 use serde_ext::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
 use serde_ext::forward_to_deserialize_any;
+use std::borrow::Cow;
+
+// Strings that could be borrowed from the input are handed to the visitor
+// as such, the ones we had to copy to unescape them as owned.
+#[cfg_attr(not(feature = "no-inline"), inline(always))]
+fn visit_cow_str<'de, V>(s: Cow<'de, str>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    match s {
+        Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        Cow::Owned(s) => visitor.visit_string(s),
+    }
+}
 
 impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     type Error = Error;
@@ -18,7 +33,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             b'"' => {
                 // We don't do the short string optimisation as serde requires
                 // additional checks
-                visitor.visit_borrowed_str(stry!(self.parse_str_()))
+                visit_cow_str(stry!(self.parse_str_()), visitor)
             }
             b'n' => visitor.visit_unit(),
             b't' => visitor.visit_bool(true),
@@ -26,13 +41,17 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             b'-' => match stry!(self.parse_number(true)) {
                 Number::F64(n) => visitor.visit_f64(n),
                 Number::I64(n) => visitor.visit_i64(n),
+                Number::U64(n) => visitor.visit_u64(n),
+                Number::Raw(n) => visitor.visit_map(RawNumberAccess::new(n)),
             },
-            b'0'..=b'9' => match stry!(self.parse_number(false)) {
+            b'0'..=b'9' | b'N' | b'I' => match stry!(self.parse_number(false)) {
                 Number::F64(n) => visitor.visit_f64(n),
                 Number::I64(n) => visitor.visit_i64(n),
+                Number::U64(n) => visitor.visit_u64(n),
+                Number::Raw(n) => visitor.visit_map(RawNumberAccess::new(n)),
             },
             b'[' => visitor.visit_seq(CommaSeparated::new(&mut self)),
-            b'{' => visitor.visit_map(CommaSeparated::new(&mut self)),
+            b'{' => visitor.visit_map(stry!(CommaSeparated::map(&mut self))),
             _c => Err(self.error(ErrorType::UnexpectedCharacter)),
         }
     }
@@ -75,10 +94,10 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
         if let Some(next) = self.structural_indexes.get(self.idx + 1) {
             if *next as usize - self.iidx < 32 {
-                return visitor.visit_borrowed_str(stry!(self.parse_str_()));
+                return visit_cow_str(stry!(self.parse_str_()), visitor);
             }
         }
-        visitor.visit_borrowed_str(stry!(self.parse_str_()))
+        visit_cow_str(stry!(self.parse_str_()), visitor)
     }
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
@@ -91,10 +110,10 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
         if let Some(next) = self.structural_indexes.get(self.idx + 1) {
             if *next as usize - self.iidx < 32 {
-                return visitor.visit_str(stry!(self.parse_str_()));
+                return visitor.visit_str(&stry!(self.parse_str_()));
             }
         }
-        visitor.visit_str(stry!(self.parse_str_()))
+        visitor.visit_str(&stry!(self.parse_str_()))
     }
 
     // The `parse_signed` function is generic over the integer type `T` so here
@@ -279,10 +298,18 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     // As is done here, serializers are encouraged to treat newtype structs as
     // insignificant wrappers around the data they contain. That means not
     // parsing anything other than the contained value.
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        // `RawNumber` asks for the text of the number
+        if name == TOKEN {
+            if let b'-' | b'0'..=b'9' = stry!(self.peek()) {
+                stry!(self.next());
+                let n = stry!(self.parse_number_raw());
+                return visitor.visit_map(RawNumberAccess::new(n));
+            }
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -294,7 +321,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         // Parse the opening bracket of the sequence.
         if stry!(self.next()) == b'{' {
             // Give the visitor access to each element of the sequence.
-            visitor.visit_map(CommaSeparated::new(&mut self))
+            visitor.visit_map(stry!(CommaSeparated::map(&mut self)))
         } else {
             Err(self.error(ErrorType::ExpectedMap))
         }
@@ -302,7 +329,7 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn deserialize_struct<V>(
-        self,
+        mut self,
         _name: &'static str,
         _fields: &'static [&'static str],
         visitor: V,
@@ -310,7 +337,11 @@ impl<'a, 'de> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        if stry!(self.next()) == b'{' {
+            visitor.visit_map(stry!(CommaSeparated::map(&mut self)))
+        } else {
+            Err(self.error(ErrorType::ExpectedMap))
+        }
     }
 
     forward_to_deserialize_any! {
@@ -327,6 +358,10 @@ struct CommaSeparated<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
     first: bool,
     len: usize,
+    // For objects, the members left out because their key appears more
+    // than once, last one first, and the index of the next member.
+    dropped: Vec<usize>,
+    member: usize,
 }
 
 impl<'a, 'de> CommaSeparated<'a, 'de> {
@@ -336,8 +371,24 @@ impl<'a, 'de> CommaSeparated<'a, 'de> {
             first: true,
             len: de.count_elements(),
             de,
+            dropped: Vec::new(),
+            member: 0,
         }
     }
+
+    // Objects need to know about duplicate keys up front, serde would hand
+    // the first occurrence to the visitor before we see the last one. With
+    // the default policy every member is handed on: visitors for maps
+    // insert them so the last one wins, structs fail with serde's
+    // duplicate field error, the same as with serde_json.
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    fn map(de: &'a mut Deserializer<'de>) -> Result<Self> {
+        let mut map = Self::new(de);
+        if map.len > 1 && map.de.duplicate_keys != DuplicateKeys::KeepLast {
+            map.dropped = stry!(map.de.duplicate_members(map.len));
+        }
+        Ok(map)
+    }
 }
 
 // `SeqAccess` is provided to the `Visitor` to give it the ability to iterate
@@ -379,15 +430,22 @@ impl<'de, 'a> MapAccess<'de> for CommaSeparated<'a, 'de> {
     where
         K: DeserializeSeed<'de>,
     {
-        if self.len == 0 {
-            if self.first {
-                self.de.skip();
+        loop {
+            if self.len == 0 {
+                if self.first {
+                    self.de.skip();
+                }
+                return Ok(None);
             }
-            Ok(None)
-        } else {
             self.len -= 1;
             self.first = false;
-            seed.deserialize(&mut *self.de).map(Some)
+            self.member += 1;
+            if self.dropped.last() == Some(&(self.member - 1)) {
+                self.dropped.pop();
+                self.de.skip_member();
+            } else {
+                return seed.deserialize(&mut *self.de).map(Some);
+            }
         }
     }
 
@@ -406,6 +464,6 @@ impl<'de, 'a> MapAccess<'de> for CommaSeparated<'a, 'de> {
 
     #[cfg_attr(not(feature = "no-inline"), inline)]
     fn size_hint(&self) -> Option<usize> {
-        Some(self.len)
+        Some(self.len - self.dropped.len())
     }
 }
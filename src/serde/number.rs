@@ -0,0 +1,286 @@
+use crate::value::owned::Value as OwnedValue;
+use crate::{stry, Error, ErrorType, Parser, Result};
+use serde_ext::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde_ext::ser::{self, SerializeStruct};
+use serde_ext::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// The struct and field name `serde_json` uses to pass numbers through serde
+// in its `arbitrary_precision` mode: a struct with a single field holding
+// the text of the number.
+pub(crate) const TOKEN: &str = "$serde_json::private::Number";
+
+/// A number kept as its source text, so it can be passed through serde
+/// without losing precision.
+///
+/// Numbers are handed to serde the same way `serde_json` does with its
+/// `arbitrary_precision` feature. Deserializing from a `Parser` with
+/// `raw_numbers` enabled, or from a Value holding a `RawNumber`, keeps
+/// every digit. Other deserializers hand us already converted numbers
+/// which are formatted back into text.
+///
+/// ```
+/// use simd_json::serde::RawNumber;
+/// let mut d = br#"[123456789012345678901234567890, 1.5]"#.to_vec();
+/// let v: Vec<RawNumber> = simd_json::serde::from_slice(&mut d).unwrap();
+/// assert_eq!(v[0].as_str(), "123456789012345678901234567890");
+/// assert_eq!(v[1].as_str(), "1.5");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RawNumber(String);
+
+impl RawNumber {
+    /// The text of the number.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for RawNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for RawNumber {
+    type Err = Error;
+
+    /// Validates `s` as a JSON number.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut d = s.as_bytes().to_vec();
+        match stry!(Parser::new().raw_numbers(true).parse_owned(&mut d)) {
+            OwnedValue::RawNumber(n) => Ok(RawNumber(n)),
+            _ => Err(Error::generic(ErrorType::ExpectedNumber)),
+        }
+    }
+}
+
+impl From<RawNumber> for OwnedValue {
+    fn from(n: RawNumber) -> Self {
+        OwnedValue::RawNumber(n.0)
+    }
+}
+
+pub(crate) fn serialize_raw_number<S>(
+    n: &str,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut s = serializer.serialize_struct(TOKEN, 1)?;
+    s.serialize_field(TOKEN, n)?;
+    s.end()
+}
+
+impl Serialize for RawNumber {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_raw_number(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawNumber {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Our deserializer picks up the name and keeps the text, for all
+        // others this ends up in `visit_newtype_struct`.
+        deserializer.deserialize_newtype_struct(TOKEN, RawNumberVisitor)
+    }
+}
+
+struct RawNumberVisitor;
+
+impl<'de> Visitor<'de> for RawNumberVisitor {
+    type Value = RawNumber;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON number")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<RawNumber, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_i64<E>(self, value: i64) -> std::result::Result<RawNumber, E>
+    where
+        E: de::Error,
+    {
+        Ok(RawNumber(value.to_string()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> std::result::Result<RawNumber, E>
+    where
+        E: de::Error,
+    {
+        Ok(RawNumber(value.to_string()))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> std::result::Result<RawNumber, E>
+    where
+        E: de::Error,
+    {
+        if value.is_finite() {
+            Ok(RawNumber(ryu::Buffer::new().format(value).to_string()))
+        } else {
+            Err(de::Error::invalid_value(
+                de::Unexpected::Float(value),
+                &self,
+            ))
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<RawNumber, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        match map.next_key::<String>()? {
+            Some(ref k) if k == TOKEN => map.next_value().map(RawNumber),
+            _ => Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        }
+    }
+}
+
+// Hands a raw number to a visitor as the single field struct `serde_json`
+// uses for its arbitrary precision numbers.
+pub(crate) struct RawNumberAccess<T> {
+    number: Option<T>,
+}
+
+impl<T> RawNumberAccess<T> {
+    pub(crate) fn new(number: T) -> Self {
+        Self {
+            number: Some(number),
+        }
+    }
+}
+
+impl<'de, T> MapAccess<'de> for RawNumberAccess<T>
+where
+    T: IntoDeserializer<'de, Error>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.number.is_some() {
+            seed.deserialize(TOKEN.into_deserializer()).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.number.take() {
+            Some(n) => seed.deserialize(n.into_deserializer()),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+}
+
+// Turns the field of a `TOKEN` struct passed to the Value serializer into
+// a raw number.
+pub(crate) fn raw_number_field<T: ?Sized>(value: &T) -> Result<String>
+where
+    T: ser::Serialize,
+{
+    match stry!(crate::serde::to_owned_value(value)) {
+        OwnedValue::String(s) => {
+            let n: RawNumber = stry!(s.parse());
+            Ok(n.into_string())
+        }
+        _ => Err(Error::generic(ErrorType::InvalidNumber)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RawNumber;
+    use crate::serde::{from_owned_value, from_slice, to_owned_value};
+    use crate::{OwnedValue, Parser};
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Payment {
+        amount: RawNumber,
+        id: u64,
+        rate: Option<RawNumber>,
+    }
+
+    #[test]
+    fn keeps_digits() {
+        let doc = r#"{"amount": 0.1000000000000000055511151231257827, "id": 3, "rate": -1e-400}"#;
+        let expected = Payment {
+            amount: "0.1000000000000000055511151231257827".parse().unwrap(),
+            id: 3,
+            rate: Some("-1e-400".parse().unwrap()),
+        };
+        let mut d = doc.as_bytes().to_vec();
+        assert_eq!(from_slice::<Payment>(&mut d).unwrap(), expected);
+        let mut d = doc.as_bytes().to_vec();
+        let p: Payment = Parser::new().raw_numbers(true).deserialize(&mut d).unwrap();
+        assert_eq!(p, expected);
+        let mut d = doc.as_bytes().to_vec();
+        let v: OwnedValue = Parser::new().raw_numbers(true).deserialize(&mut d).unwrap();
+        assert_eq!(
+            v.to_string(),
+            r#"{"amount":0.1000000000000000055511151231257827,"id":3,"rate":-1e-400}"#
+        );
+        let mut d = br#"[1, null]"#.to_vec();
+        let v: Vec<Option<RawNumber>> = from_slice(&mut d).unwrap();
+        assert_eq!(v, vec![Some("1".parse().unwrap()), None]);
+    }
+
+    #[test]
+    fn values() {
+        let p = Payment {
+            amount: "123456789012345678901234567890".parse().unwrap(),
+            id: 1,
+            rate: None,
+        };
+        let v = to_owned_value(&p).unwrap();
+        assert_eq!(
+            v.to_string(),
+            r#"{"amount":123456789012345678901234567890,"id":1,"rate":null}"#
+        );
+        assert_eq!(from_owned_value::<Payment>(v).unwrap(), p);
+        let v: OwnedValue = from_owned_value(OwnedValue::from(p.amount.clone())).unwrap();
+        assert_eq!(v, OwnedValue::from(p.amount));
+    }
+
+    #[test]
+    fn converted_numbers() {
+        let v: RawNumber = from_owned_value(OwnedValue::F64(1.5)).unwrap();
+        assert_eq!(v.as_str(), "1.5");
+        let v: RawNumber = from_owned_value(OwnedValue::U64(u64::max_value())).unwrap();
+        assert_eq!(v.as_str(), "18446744073709551615");
+        assert!(from_owned_value::<RawNumber>(OwnedValue::from("1")).is_err());
+        let mut d = br#""1""#.to_vec();
+        assert!(from_slice::<RawNumber>(&mut d).is_err());
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!("-0.5e7".parse::<RawNumber>().unwrap().to_string(), "-0.5e7");
+        assert!("01".parse::<RawNumber>().is_err());
+        assert!("\"1\"".parse::<RawNumber>().is_err());
+        assert!("[1]".parse::<RawNumber>().is_err());
+    }
+}
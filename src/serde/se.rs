@@ -0,0 +1,938 @@
+// Serializes anything implementing `Serialize` to JSON text, the output
+// goes through the generators in `value::generator` so strings are escaped
+// the same way, and as fast, as when writing a Value.
+
+use crate::serde::number::{raw_number_field, TOKEN};
+use crate::value::generator::{
+    BaseGenerator, DumpGenerator, PrettyGenerator, PrettyWriterGenerator, WriterGenerator,
+};
+use crate::{stry, Error, ErrorType, OwnedValue, Result};
+use serde_ext::ser::{self, Serialize};
+use std::io;
+use std::io::Write;
+
+type Impossible = ser::Impossible<(), Error>;
+
+/// Serializes `value` to a compact JSON string.
+///
+/// ```
+/// use serde_derive::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+/// let s = simd_json::serde::to_string(&Point { x: 1, y: -2 }).unwrap();
+/// assert_eq!(s, r#"{"x":1,"y":-2}"#);
+/// ```
+pub fn to_string<T>(value: &T) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    let mut s = Serializer(DumpGenerator::<OwnedValue>::new());
+    stry!(value.serialize(&mut s));
+    Ok(s.0.consume())
+}
+
+/// Serializes `value` to a JSON string indented by two spaces.
+pub fn to_string_pretty<T>(value: &T) -> Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    let mut s = Serializer(PrettyGenerator::<OwnedValue>::new(2));
+    stry!(value.serialize(&mut s));
+    Ok(s.0.consume())
+}
+
+/// Serializes `value` to a compact JSON byte vector.
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    to_string(value).map(String::into_bytes)
+}
+
+/// Serializes `value` to a JSON byte vector indented by two spaces.
+pub fn to_vec_pretty<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    to_string_pretty(value).map(String::into_bytes)
+}
+
+/// Serializes `value` as compact JSON into `writer`.
+pub fn to_writer<W, T>(mut writer: W, value: &T) -> Result<()>
+where
+    W: Write,
+    T: Serialize + ?Sized,
+{
+    let mut s = Serializer(WriterGenerator::<W, OwnedValue>::new(&mut writer));
+    value.serialize(&mut s)
+}
+
+/// Serializes `value` as JSON indented by two spaces into `writer`.
+pub fn to_writer_pretty<W, T>(mut writer: W, value: &T) -> Result<()>
+where
+    W: Write,
+    T: Serialize + ?Sized,
+{
+    let mut s = Serializer(PrettyWriterGenerator::<W, OwnedValue>::new(&mut writer, 2));
+    value.serialize(&mut s)
+}
+
+fn io_error(e: io::Error) -> Error {
+    Error::generic(ErrorType::Io(e.to_string()))
+}
+
+struct Serializer<G>(G);
+
+// Arrays and objects are written the same way the Value generators do it,
+// so the pretty printers only have to hook `new_line`, `indent` and
+// `dedent`.
+impl<G> Serializer<G>
+where
+    G: BaseGenerator,
+{
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn write(&mut self, slice: &[u8]) -> Result<()> {
+        self.0.write(slice).map_err(io_error)
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn write_string(&mut self, string: &str) -> Result<()> {
+        self.0.write_string(string).map_err(io_error)
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn write_int<I: itoa::Integer>(&mut self, num: I) -> Result<()> {
+        self.0.write_int(num).map_err(io_error)
+    }
+
+    // JSON has no representation for NaN or infinity, those are written
+    // as `null` like serde_json does.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn write_float(&mut self, num: f64) -> Result<()> {
+        if num.is_finite() {
+            self.0.write_float(num).map_err(io_error)
+        } else {
+            self.write(b"null")
+        }
+    }
+
+    fn begin(&mut self, ch: u8) -> Result<()> {
+        self.0.indent();
+        self.0.write_char(ch).map_err(io_error)
+    }
+
+    fn element(&mut self, first: bool) -> Result<()> {
+        if !first {
+            stry!(self.0.write_char(b',').map_err(io_error));
+        }
+        self.0.new_line().map_err(io_error)
+    }
+
+    fn key_separator(&mut self) -> Result<()> {
+        self.0.write_min(b": ", b':').map_err(io_error)
+    }
+
+    fn end(&mut self, ch: u8, empty: bool) -> Result<()> {
+        self.0.dedent();
+        if !empty {
+            stry!(self.0.new_line().map_err(io_error));
+        }
+        self.0.write_char(ch).map_err(io_error)
+    }
+
+    // Enum variants with data are written as an object with the variant
+    // name as the only key.
+    fn begin_variant(&mut self, variant: &str) -> Result<()> {
+        stry!(self.begin(b'{'));
+        stry!(self.element(true));
+        stry!(self.write_string(variant));
+        self.key_separator()
+    }
+
+    fn end_variant(&mut self) -> Result<()> {
+        self.end(b'}', false)
+    }
+}
+
+impl<'s, G> ser::Serializer for &'s mut Serializer<G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'s, G>;
+    type SerializeTuple = Compound<'s, G>;
+    type SerializeTupleStruct = Compound<'s, G>;
+    type SerializeTupleVariant = Compound<'s, G>;
+    type SerializeMap = Compound<'s, G>;
+    type SerializeStruct = Compound<'s, G>;
+    type SerializeStructVariant = Compound<'s, G>;
+
+    #[inline]
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.write(if value { b"true" } else { b"false" })
+    }
+
+    #[inline]
+    fn serialize_i8(self, value: i8) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_i16(self, value: i16) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_i32(self, value: i32) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        self.write_int(value)
+    }
+
+    #[inline]
+    fn serialize_f32(self, value: f32) -> Result<()> {
+        if value.is_finite() {
+            let mut buffer = ryu::Buffer::new();
+            self.write(buffer.format(value).as_bytes())
+        } else {
+            self.write(b"null")
+        }
+    }
+
+    #[inline]
+    fn serialize_f64(self, value: f64) -> Result<()> {
+        self.write_float(value)
+    }
+
+    #[inline]
+    fn serialize_char(self, value: char) -> Result<()> {
+        let mut buf = [0; 4];
+        self.write_string(value.encode_utf8(&mut buf))
+    }
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.write_string(value)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        use serde_ext::ser::SerializeSeq;
+        let mut seq = stry!(self.serialize_seq(Some(value.len())));
+        for byte in value {
+            stry!(seq.serialize_element(byte));
+        }
+        seq.end()
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<()> {
+        self.write(b"null")
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.write_string(variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: Serialize,
+    {
+        stry!(self.begin_variant(variant));
+        stry!(value.serialize(&mut *self));
+        self.end_variant()
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    #[inline]
+    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        stry!(self.begin(b'['));
+        Ok(Compound::Map {
+            ser: self,
+            first: true,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        stry!(self.begin_variant(variant));
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        stry!(self.begin(b'{'));
+        Ok(Compound::Map {
+            ser: self,
+            first: true,
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        if name == TOKEN {
+            Ok(Compound::Number { ser: self })
+        } else {
+            self.serialize_map(Some(len))
+        }
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        stry!(self.begin_variant(variant));
+        self.serialize_map(Some(len))
+    }
+}
+
+enum Compound<'s, G> {
+    Map {
+        ser: &'s mut Serializer<G>,
+        first: bool,
+    },
+    // a `RawNumber`, its text is written as is
+    Number {
+        ser: &'s mut Serializer<G>,
+    },
+}
+
+impl<'s, G> Compound<'s, G>
+where
+    G: BaseGenerator,
+{
+    fn element(&mut self) -> Result<&mut Serializer<G>> {
+        match self {
+            Compound::Map { ser, first } => {
+                stry!(ser.element(*first));
+                *first = false;
+                Ok(ser)
+            }
+            Compound::Number { .. } => Err(Error::generic(ErrorType::InvalidNumber)),
+        }
+    }
+
+    fn finish(self, ch: u8) -> Result<&'s mut Serializer<G>> {
+        match self {
+            Compound::Map { ser, first } => {
+                stry!(ser.end(ch, first));
+                Ok(ser)
+            }
+            Compound::Number { ser } => Ok(ser),
+        }
+    }
+}
+
+impl<'s, G> ser::SerializeSeq for Compound<'s, G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        value.serialize(stry!(self.element()))
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::finish(self, b']').map(|_| ())
+    }
+}
+
+impl<'s, G> ser::SerializeTuple for Compound<'s, G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'s, G> ser::SerializeTupleStruct for Compound<'s, G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'s, G> ser::SerializeTupleVariant for Compound<'s, G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        stry!(Compound::finish(self, b']')).end_variant()
+    }
+}
+
+impl<'s, G> ser::SerializeMap for Compound<'s, G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        key.serialize(MapKeySerializer {
+            ser: stry!(self.element()),
+        })
+    }
+
+    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        match self {
+            Compound::Map { ser, .. } => {
+                stry!(ser.key_separator());
+                value.serialize(&mut **ser)
+            }
+            Compound::Number { .. } => Err(Error::generic(ErrorType::InvalidNumber)),
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::finish(self, b'}').map(|_| ())
+    }
+}
+
+impl<'s, G> ser::SerializeStruct for Compound<'s, G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        match self {
+            Compound::Map { .. } => ser::SerializeMap::serialize_entry(self, key, value),
+            Compound::Number { ser } => {
+                if key == TOKEN {
+                    let n = stry!(raw_number_field(value));
+                    ser.write(n.as_bytes())
+                } else {
+                    Err(Error::generic(ErrorType::InvalidNumber))
+                }
+            }
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl<'s, G> ser::SerializeStructVariant for Compound<'s, G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        stry!(Compound::finish(self, b'}')).end_variant()
+    }
+}
+
+// Object keys have to be strings, like serde_json integers, bools and chars
+// are written as strings as well.
+struct MapKeySerializer<'s, G> {
+    ser: &'s mut Serializer<G>,
+}
+
+fn key_must_be_a_string() -> Error {
+    Error::generic(ErrorType::KeyMustBeAString)
+}
+
+impl<'s, G> MapKeySerializer<'s, G>
+where
+    G: BaseGenerator,
+{
+    fn write_int<I: itoa::Integer>(self, num: I) -> Result<()> {
+        stry!(self.ser.write(b"\""));
+        stry!(self.ser.write_int(num));
+        self.ser.write(b"\"")
+    }
+}
+
+impl<'s, G> ser::Serializer for MapKeySerializer<'s, G>
+where
+    G: BaseGenerator,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Impossible;
+    type SerializeTuple = Impossible;
+    type SerializeTupleStruct = Impossible;
+    type SerializeTupleVariant = Impossible;
+    type SerializeMap = Impossible;
+    type SerializeStruct = Impossible;
+    type SerializeStructVariant = Impossible;
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.ser.write_string(value)
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.ser.write_string(variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, value: bool) -> Result<()> {
+        self.ser
+            .write(if value { b"\"true\"" } else { b"\"false\"" })
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        self.write_int(value)
+    }
+
+    fn serialize_f32(self, _value: f32) -> Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _value: f64) -> Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_char(self, value: char) -> Result<()> {
+        let mut buf = [0; 4];
+        self.ser.write_string(value.encode_utf8(&mut buf))
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: Serialize,
+    {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_must_be_a_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{to_string, to_string_pretty, to_vec, to_writer, to_writer_pretty};
+    use crate::serde::RawNumber;
+    use crate::{ErrorType, OwnedValue};
+    use serde_derive::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Unit;
+
+    #[derive(Serialize)]
+    struct Newtype(u8);
+
+    #[derive(Serialize)]
+    struct Tuple(i32, String);
+
+    #[derive(Serialize)]
+    enum Enum {
+        Unit,
+        Newtype(i64),
+        Tuple(u8, bool),
+        Struct { a: f32, b: Option<()> },
+        EmptyTuple(),
+        EmptyStruct {},
+    }
+
+    #[derive(Serialize)]
+    struct Everything {
+        unit: Unit,
+        newtype: Newtype,
+        tuple: Tuple,
+        enums: Vec<Enum>,
+        map: BTreeMap<u32, char>,
+        empty_map: BTreeMap<String, u8>,
+        empty_vec: Vec<u8>,
+        nested: Vec<Vec<u8>>,
+        string: &'static str,
+        floats: (f32, f64, f64, f64),
+        ints: (i8, i16, i32, i64, u8, u16, u32, u64),
+        option: Option<Box<Everything>>,
+    }
+
+    fn everything() -> Everything {
+        let mut map = BTreeMap::new();
+        map.insert(1, 'a');
+        map.insert(20, '"');
+        Everything {
+            unit: Unit,
+            newtype: Newtype(7),
+            tuple: Tuple(-1, "tuple".to_string()),
+            enums: vec![
+                Enum::Unit,
+                Enum::Newtype(-5),
+                Enum::Tuple(1, true),
+                Enum::Struct { a: 0.1, b: None },
+                Enum::EmptyTuple(),
+                Enum::EmptyStruct {},
+            ],
+            map,
+            empty_map: BTreeMap::new(),
+            empty_vec: vec![],
+            nested: vec![vec![], vec![1, 2], vec![3]],
+            string:
+                "esc\"aped\\ \n\t\u{1f} ünïcødé 🦀 and a string long enough to hit the simd path",
+            floats: (1.1, 1.0, -0.0, std::f64::NAN),
+            ints: (
+                i8::min_value(),
+                i16::min_value(),
+                i32::min_value(),
+                i64::min_value(),
+                u8::max_value(),
+                u16::max_value(),
+                u32::max_value(),
+                u64::max_value(),
+            ),
+            option: Some(Box::new(Everything {
+                unit: Unit,
+                newtype: Newtype(0),
+                tuple: Tuple(0, String::new()),
+                enums: vec![],
+                map: BTreeMap::new(),
+                empty_map: BTreeMap::new(),
+                empty_vec: vec![],
+                nested: vec![],
+                string: "",
+                floats: (0.0, 12345.678, -2.5, std::f64::INFINITY),
+                ints: (0, 0, 0, 0, 0, 0, 0, 0),
+                option: None,
+            })),
+        }
+    }
+
+    #[test]
+    fn matches_serde_json() {
+        let v = everything();
+        assert_eq!(to_string(&v).unwrap(), serde_json::to_string(&v).unwrap());
+        assert_eq!(to_vec(&v).unwrap(), serde_json::to_vec(&v).unwrap());
+        assert_eq!(
+            to_string_pretty(&v).unwrap(),
+            serde_json::to_string_pretty(&v).unwrap()
+        );
+        let bytes = serde_bytes::Bytes::new(b"bytes");
+        assert_eq!(
+            to_string(&bytes).unwrap(),
+            serde_json::to_string(&bytes).unwrap()
+        );
+    }
+
+    #[test]
+    fn writer() {
+        let v = everything();
+        let mut w = Vec::new();
+        to_writer(&mut w, &v).unwrap();
+        assert_eq!(w, serde_json::to_vec(&v).unwrap());
+        let mut w = Vec::new();
+        to_writer_pretty(&mut w, &v).unwrap();
+        assert_eq!(w, serde_json::to_vec_pretty(&v).unwrap());
+    }
+
+    #[test]
+    fn values_and_raw_numbers() {
+        let mut d = br#"{"a": [1, 2.5, -3e-7, null, true], "b": {"c": "d"}}"#.to_vec();
+        let v = crate::to_owned_value(&mut d).unwrap();
+        assert_eq!(to_string(&v).unwrap(), v.to_string());
+        assert_eq!(to_string_pretty(&v).unwrap(), v.to_string_pp());
+        // floats are formatted by `ryu` like in the Value generators
+        let f = OwnedValue::from(1e300);
+        assert_eq!(to_string(&1e300).unwrap(), f.to_string());
+        let n: RawNumber = "123456789012345678901234567890".parse().unwrap();
+        assert_eq!(to_string(&n).unwrap(), "123456789012345678901234567890");
+        assert_eq!(
+            to_string(&OwnedValue::from(n)).unwrap(),
+            "123456789012345678901234567890"
+        );
+    }
+
+    #[test]
+    fn wide_ints_and_bool_keys() {
+        let v = (i128::min_value(), u128::max_value(), -1i128, 0u128);
+        assert_eq!(to_string(&v).unwrap(), serde_json::to_string(&v).unwrap());
+        let mut m = BTreeMap::new();
+        m.insert(i128::min_value(), 1);
+        m.insert(7, 2);
+        assert_eq!(to_string(&m).unwrap(), serde_json::to_string(&m).unwrap());
+        let mut m = BTreeMap::new();
+        m.insert(u128::max_value(), 1);
+        assert_eq!(to_string(&m).unwrap(), serde_json::to_string(&m).unwrap());
+        let mut m = BTreeMap::new();
+        m.insert(false, 1);
+        m.insert(true, 2);
+        assert_eq!(to_string(&m).unwrap(), r#"{"false":1,"true":2}"#);
+        assert_eq!(to_string(&m).unwrap(), serde_json::to_string(&m).unwrap());
+    }
+
+    #[test]
+    fn bad_keys() {
+        let mut m = BTreeMap::new();
+        m.insert(vec![1], 1);
+        assert_eq!(
+            to_string(&m).map_err(|e| e.error),
+            Err(ErrorType::KeyMustBeAString)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::serde::number::{RawNumberAccess, TOKEN};
 use crate::value::borrowed::{Map, Value};
 use crate::Error;
 use serde_ext::de::{
@@ -21,6 +22,8 @@ impl<'de> de::Deserializer<'de> for Value<'de> {
             Value::Null => visitor.visit_unit(),
             Value::Bool(b) => visitor.visit_bool(b),
             Value::I64(n) => visitor.visit_i64(n),
+            Value::U64(n) => visitor.visit_u64(n),
+            Value::RawNumber(n) => visitor.visit_map(RawNumberAccess::new(n)),
             Value::F64(n) => visitor.visit_f64(n),
             Value::String(s) => match s {
                 Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
@@ -60,7 +63,7 @@ impl<'de, 'a> SeqAccess<'de> for Array<'a, 'de> {
     }
 }
 struct Object<'de, 'a: 'de> {
-    i: halfbrown::Iter<'de, Cow<'a, str>, Value<'a>>,
+    i: <&'de Map<'a> as IntoIterator>::IntoIter,
     v: &'de Value<'a>,
 }
 
@@ -206,12 +209,7 @@ impl<'de> Visitor<'de> for ValueVisitor {
     where
         E: de::Error,
     {
-        use std::i64;
-        if value <= i64::MAX as u64 {
-            Ok(Value::I64(value as i64))
-        } else {
-            Err(E::custom(format!("Integer out of range: {}", value)))
-        }
+        Ok(Value::from(value))
     }
 
     /****************** f64 ******************/
@@ -303,6 +301,19 @@ impl<'de> Visitor<'de> for ValueVisitor {
         let size = map.size_hint().unwrap_or_default();
 
         let mut m = Map::with_capacity(size);
+        match map.next_key::<&str>()? {
+            // a raw number passed as a struct, see `RawNumber`
+            Some(k) if k == TOKEN => {
+                return map
+                    .next_value::<String>()
+                    .map(|n| Value::RawNumber(n.into()))
+            }
+            Some(k) => {
+                let v = map.next_value()?;
+                m.insert(k.into(), v);
+            }
+            None => return Ok(Value::Object(m)),
+        }
         while let Some(k) = map.next_key::<&str>()? {
             let v = map.next_value()?;
             m.insert(k.into(), v);
@@ -1,3 +1,4 @@
+use crate::serde::number::{RawNumberAccess, TOKEN};
 use crate::value::owned::{Map, Value};
 use crate::{stry, Error};
 use serde::de::{
@@ -22,6 +23,8 @@ impl<'de> de::Deserializer<'de> for Value {
             Value::Null => visitor.visit_unit(),
             Value::Bool(b) => visitor.visit_bool(b),
             Value::I64(n) => visitor.visit_i64(n),
+            Value::U64(n) => visitor.visit_u64(n),
+            Value::RawNumber(n) => visitor.visit_map(RawNumberAccess::new(n)),
             Value::F64(n) => visitor.visit_f64(n),
             Value::String(s) => visitor.visit_string(s),
             Value::Array(a) => visit_array(a, visitor),
@@ -498,12 +501,7 @@ impl<'de> Visitor<'de> for ValueVisitor {
     where
         E: de::Error,
     {
-        use std::i64;
-        if value <= i64::MAX as u64 {
-            Ok(Value::I64(value as i64))
-        } else {
-            Err(E::custom(format!("Integer out of range: {}", value)))
-        }
+        Ok(Value::from(value))
     }
 
     /****************** f64 ******************/
@@ -595,6 +593,15 @@ impl<'de> Visitor<'de> for ValueVisitor {
         let size = map.size_hint().unwrap_or_default();
 
         let mut m = Map::with_capacity(size);
+        match map.next_key::<String>()? {
+            // a raw number passed as a struct, see `RawNumber`
+            Some(ref k) if k == TOKEN => return map.next_value().map(Value::RawNumber),
+            Some(k) => {
+                let v = map.next_value()?;
+                m.insert(k, v);
+            }
+            None => return Ok(Value::Object(m)),
+        }
         while let Some(k) = map.next_key()? {
             let v = map.next_value()?;
             m.insert(k, v);
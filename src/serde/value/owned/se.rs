@@ -1,4 +1,5 @@
 use super::to_value;
+use crate::serde::number::{raw_number_field, serialize_raw_number, TOKEN};
 use crate::value::owned::{Map, Value};
 use crate::{stry, Error, ErrorType, Result};
 use serde::ser::{self, Serialize};
@@ -16,6 +17,8 @@ impl Serialize for Value {
             Value::Null => serializer.serialize_unit(),
             Value::F64(f) => serializer.serialize_f64(*f),
             Value::I64(i) => serializer.serialize_i64(*i),
+            Value::U64(i) => serializer.serialize_u64(*i),
+            Value::RawNumber(n) => serialize_raw_number(n, serializer),
             Value::String(s) => serializer.serialize_str(&s),
             Value::Array(v) => {
                 let mut seq = serializer.serialize_seq(Some(v.len()))?;
@@ -102,7 +105,7 @@ impl serde::Serializer for Serializer {
 
     #[inline]
     fn serialize_u64(self, value: u64) -> Result<Value> {
-        Ok(Value::I64(value as i64))
+        Ok(Value::from(value))
     }
 
     #[cfg(feature = "arbitrary_precision")]
@@ -235,8 +238,7 @@ impl serde::Serializer for Serializer {
 
     fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
         match name {
-            #[cfg(feature = "arbitrary_precision")]
-            ::number::TOKEN => Ok(SerializeMap::Number { out_value: None }),
+            TOKEN => Ok(SerializeMap::Number { out_value: None }),
             #[cfg(feature = "raw_value")]
             ::raw::TOKEN => Ok(SerializeMap::RawValue { out_value: None }),
             _ => self.serialize_map(Some(len)),
@@ -268,6 +270,7 @@ pub struct SerializeTupleVariant {
 
 pub enum SerializeMap {
     Map { map: Map, next_key: Option<String> },
+    Number { out_value: Option<Value> },
 }
 
 pub struct SerializeStructVariant {
@@ -360,7 +363,6 @@ impl serde::ser::SerializeMap for SerializeMap {
                 *next_key = Some(stry!(key.serialize(MapKeySerializer {})));
                 Ok(())
             }
-            #[cfg(feature = "arbitrary_precision")]
             SerializeMap::Number { .. } => unreachable!(),
             #[cfg(feature = "raw_value")]
             SerializeMap::RawValue { .. } => unreachable!(),
@@ -383,7 +385,6 @@ impl serde::ser::SerializeMap for SerializeMap {
                 map.insert(key, stry!(to_value(&value)));
                 Ok(())
             }
-            #[cfg(feature = "arbitrary_precision")]
             SerializeMap::Number { .. } => unreachable!(),
             #[cfg(feature = "raw_value")]
             SerializeMap::RawValue { .. } => unreachable!(),
@@ -393,7 +394,6 @@ impl serde::ser::SerializeMap for SerializeMap {
     fn end(self) -> Result<Value> {
         match self {
             SerializeMap::Map { map, .. } => Ok(Value::Object(map)),
-            #[cfg(feature = "arbitrary_precision")]
             SerializeMap::Number { .. } => unreachable!(),
             #[cfg(feature = "raw_value")]
             SerializeMap::RawValue { .. } => unreachable!(),
@@ -599,13 +599,12 @@ impl serde::ser::SerializeStruct for SerializeMap {
                 stry!(serde::ser::SerializeMap::serialize_key(self, key));
                 serde::ser::SerializeMap::serialize_value(self, value)
             }
-            #[cfg(feature = "arbitrary_precision")]
             SerializeMap::Number { ref mut out_value } => {
-                if key == ::number::TOKEN {
-                    *out_value = Some(value.serialize(NumberValueEmitter)?);
+                if key == TOKEN {
+                    *out_value = Some(Value::RawNumber(stry!(raw_number_field(value))));
                     Ok(())
                 } else {
-                    Err(invalid_number())
+                    Err(Error::generic(ErrorType::InvalidNumber))
                 }
             }
             #[cfg(feature = "raw_value")]
@@ -623,9 +622,8 @@ impl serde::ser::SerializeStruct for SerializeMap {
     fn end(self) -> Result<Value> {
         match self {
             SerializeMap::Map { .. } => serde::ser::SerializeMap::end(self),
-            #[cfg(feature = "arbitrary_precision")]
             SerializeMap::Number { out_value, .. } => {
-                Ok(out_value.expect("number value was not emitted"))
+                out_value.ok_or_else(|| Error::generic(ErrorType::InvalidNumber))
             }
             #[cfg(feature = "raw_value")]
             SerializeMap::RawValue { out_value, .. } => {
@@ -0,0 +1,5 @@
+mod deser;
+mod stage1;
+mod utf8check;
+
+pub(crate) use self::stage1::Stage1;
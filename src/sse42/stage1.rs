@@ -0,0 +1,313 @@
+#![allow(dead_code)]
+use crate::sse42::utf8check::*;
+use crate::stage1::{finalize_structurals, find_odd_backslash_sequences, flatten_bits, BlockState};
+use crate::*;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+use std::mem;
+
+// Same as the avx2 version but we need four registers to hold a 64 byte
+// block.
+#[derive(Debug)]
+struct SimdInput {
+    v0: __m128i,
+    v1: __m128i,
+    v2: __m128i,
+    v3: __m128i,
+}
+
+#[target_feature(enable = "sse4.2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn fill_input(ptr: &[u8]) -> SimdInput {
+    #[allow(clippy::cast_ptr_alignment)]
+    SimdInput {
+        v0: _mm_loadu_si128(ptr.as_ptr() as *const __m128i),
+        v1: _mm_loadu_si128(ptr.as_ptr().add(16) as *const __m128i),
+        v2: _mm_loadu_si128(ptr.as_ptr().add(32) as *const __m128i),
+        v3: _mm_loadu_si128(ptr.as_ptr().add(48) as *const __m128i),
+    }
+}
+
+#[target_feature(enable = "sse4.2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn check_utf8(input: &SimdInput, has_error: &mut __m128i, previous: &mut ProcessedUtfBytes) {
+    let highbit: __m128i = _mm_set1_epi8(static_cast_i8!(0x80u8));
+    let or: __m128i = _mm_or_si128(
+        _mm_or_si128(input.v0, input.v1),
+        _mm_or_si128(input.v2, input.v3),
+    );
+    if _mm_testz_si128(or, highbit) == 1 {
+        // it is ascii, we just check continuation
+        *has_error = _mm_or_si128(
+            _mm_cmpgt_epi8(
+                previous.carried_continuations,
+                _mm_setr_epi8(9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1),
+            ),
+            *has_error,
+        );
+    } else {
+        // it is not ascii so we have to do heavy work
+        *previous = check_utf8_bytes(input.v0, &previous, has_error);
+        *previous = check_utf8_bytes(input.v1, &previous, has_error);
+        *previous = check_utf8_bytes(input.v2, &previous, has_error);
+        *previous = check_utf8_bytes(input.v3, &previous, has_error);
+    }
+}
+
+#[target_feature(enable = "sse4.2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn movemask(v0: __m128i, v1: __m128i, v2: __m128i, v3: __m128i) -> u64 {
+    let r0: u64 = u64::from(static_cast_u32!(_mm_movemask_epi8(v0)));
+    let r1: u64 = u64::from(static_cast_u32!(_mm_movemask_epi8(v1)));
+    let r2: u64 = u64::from(static_cast_u32!(_mm_movemask_epi8(v2)));
+    let r3: u64 = u64::from(static_cast_u32!(_mm_movemask_epi8(v3)));
+    r0 | (r1 << 16) | (r2 << 32) | (r3 << 48)
+}
+
+/// a straightforward comparison of a mask against input.
+#[target_feature(enable = "sse4.2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn cmp_mask_against_input(input: &SimdInput, m: u8) -> u64 {
+    let mask: __m128i = _mm_set1_epi8(m as i8);
+    movemask(
+        _mm_cmpeq_epi8(input.v0, mask),
+        _mm_cmpeq_epi8(input.v1, mask),
+        _mm_cmpeq_epi8(input.v2, mask),
+        _mm_cmpeq_epi8(input.v3, mask),
+    )
+}
+
+// find all values less than or equal than the content of maxval (using unsigned arithmetic)
+#[target_feature(enable = "sse4.2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn unsigned_lteq_against_input(input: &SimdInput, maxval: __m128i) -> u64 {
+    movemask(
+        _mm_cmpeq_epi8(_mm_max_epu8(maxval, input.v0), maxval),
+        _mm_cmpeq_epi8(_mm_max_epu8(maxval, input.v1), maxval),
+        _mm_cmpeq_epi8(_mm_max_epu8(maxval, input.v2), maxval),
+        _mm_cmpeq_epi8(_mm_max_epu8(maxval, input.v3), maxval),
+    )
+}
+
+// See the avx2 implementation for a description of what is going on here.
+#[target_feature(enable = "sse4.2,pclmulqdq")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn find_quote_mask_and_bits(
+    input: &SimdInput,
+    odd_ends: u64,
+    prev_iter_inside_quote: &mut u64,
+    quote_bits: &mut u64,
+    error_mask: &mut u64,
+) -> u64 {
+    *quote_bits = cmp_mask_against_input(&input, b'"');
+    *quote_bits &= !odd_ends;
+    // remove from the valid quoted region the unescapted characters.
+    #[allow(overflowing_literals)]
+    let mut quote_mask: u64 = _mm_cvtsi128_si64(_mm_clmulepi64_si128(
+        _mm_set_epi64x(0, static_cast_i64!(*quote_bits)),
+        _mm_set1_epi8(0xFF),
+        0,
+    )) as u64;
+    quote_mask ^= *prev_iter_inside_quote;
+    // All Unicode characters may be placed within the
+    // quotation marks, except for the characters that MUST be escaped:
+    // quotation mark, reverse solidus, and the control characters (U+0000
+    //through U+001F).
+    // https://tools.ietf.org/html/rfc8259
+    let unescaped: u64 = unsigned_lteq_against_input(&input, _mm_set1_epi8(0x1F));
+    *error_mask |= quote_mask & unescaped;
+    *prev_iter_inside_quote = static_cast_u64!(static_cast_i64!(quote_mask) >> 63);
+    quote_mask
+}
+
+#[target_feature(enable = "sse4.2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn classify(v: __m128i) -> __m128i {
+    // see the avx2 implementation for the meaning of those tables
+    let low_nibble_mask: __m128i = _mm_setr_epi8(16, 0, 0, 0, 0, 0, 0, 0, 0, 8, 12, 1, 2, 9, 0, 0);
+    let high_nibble_mask: __m128i = _mm_setr_epi8(8, 0, 18, 4, 0, 1, 0, 1, 0, 0, 0, 3, 2, 1, 0, 0);
+    _mm_and_si128(
+        _mm_shuffle_epi8(low_nibble_mask, v),
+        _mm_shuffle_epi8(
+            high_nibble_mask,
+            _mm_and_si128(_mm_srli_epi32(v, 4), _mm_set1_epi8(0x7f)),
+        ),
+    )
+}
+
+#[target_feature(enable = "sse4.2")]
+#[cfg_attr(not(feature = "no-inline"), inline)]
+unsafe fn find_whitespace_and_structurals(
+    input: &SimdInput,
+    whitespace: &mut u64,
+    structurals: &mut u64,
+) {
+    let structural_shufti_mask: __m128i = _mm_set1_epi8(0x7);
+    let whitespace_shufti_mask: __m128i = _mm_set1_epi8(0x18);
+    let zero: __m128i = _mm_setzero_si128();
+
+    let c0: __m128i = classify(input.v0);
+    let c1: __m128i = classify(input.v1);
+    let c2: __m128i = classify(input.v2);
+    let c3: __m128i = classify(input.v3);
+
+    *structurals = !movemask(
+        _mm_cmpeq_epi8(_mm_and_si128(c0, structural_shufti_mask), zero),
+        _mm_cmpeq_epi8(_mm_and_si128(c1, structural_shufti_mask), zero),
+        _mm_cmpeq_epi8(_mm_and_si128(c2, structural_shufti_mask), zero),
+        _mm_cmpeq_epi8(_mm_and_si128(c3, structural_shufti_mask), zero),
+    );
+
+    *whitespace = !movemask(
+        _mm_cmpeq_epi8(_mm_and_si128(c0, whitespace_shufti_mask), zero),
+        _mm_cmpeq_epi8(_mm_and_si128(c1, whitespace_shufti_mask), zero),
+        _mm_cmpeq_epi8(_mm_and_si128(c2, whitespace_shufti_mask), zero),
+        _mm_cmpeq_epi8(_mm_and_si128(c3, whitespace_shufti_mask), zero),
+    );
+}
+
+// Stage 1 for one document, fed 64 byte block by 64 byte block, see the
+// avx2 implementation.
+pub(crate) struct Stage1 {
+    state: BlockState,
+    has_error: __m128i,
+    previous: ProcessedUtfBytes,
+}
+
+impl Stage1 {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: BlockState::default(),
+            // An all zero vector, see `ProcessedUtfBytes::default`
+            has_error: unsafe { mem::zeroed() },
+            previous: ProcessedUtfBytes::default(),
+        }
+    }
+
+    #[target_feature(enable = "sse4.2,pclmulqdq,popcnt")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn block(
+        &mut self,
+        block: &[u8],
+        idx: usize,
+        structural_indexes: &mut Vec<u32>,
+    ) {
+        let state = &mut self.state;
+        let input: SimdInput = fill_input(block);
+        check_utf8(&input, &mut self.has_error, &mut self.previous);
+        // detect odd sequences of backslashes
+        let odd_ends: u64 = find_odd_backslash_sequences(
+            cmp_mask_against_input(&input, b'\\'),
+            &mut state.prev_iter_ends_odd_backslash,
+        );
+
+        // detect insides of quote pairs ("quote_mask") and also our quote_bits
+        // themselves
+        let mut quote_bits: u64 = 0;
+        let quote_mask: u64 = find_quote_mask_and_bits(
+            &input,
+            odd_ends,
+            &mut state.prev_iter_inside_quote,
+            &mut quote_bits,
+            &mut state.error_mask,
+        );
+
+        // take the previous iterations structural bits, not our current iteration,
+        // and flatten
+        flatten_bits(structural_indexes, idx as u32, state.structurals);
+
+        let mut whitespace: u64 = 0;
+        let mut structurals: u64 = 0;
+        find_whitespace_and_structurals(&input, &mut whitespace, &mut structurals);
+
+        // fixup structurals to reflect quotes and add pseudo-structural characters
+        state.structurals = finalize_structurals(
+            structurals,
+            whitespace,
+            quote_mask,
+            quote_bits,
+            &mut state.prev_iter_ends_pseudo_pred,
+        );
+    }
+
+    #[target_feature(enable = "sse4.2,pclmulqdq,popcnt")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn finish(
+        &mut self,
+        idx: usize,
+        structural_indexes: &mut Vec<u32>,
+    ) -> std::result::Result<(), ErrorType> {
+        if self.state.prev_iter_inside_quote != 0 {
+            return Err(ErrorType::Syntax);
+        }
+        // finally, flatten out the remaining structurals from the last iteration
+        flatten_bits(structural_indexes, idx as u32, self.state.structurals);
+        self.state.structurals = 0;
+        Ok(())
+    }
+
+    #[target_feature(enable = "sse4.2")]
+    #[cfg_attr(not(feature = "no-inline"), inline)]
+    pub(crate) unsafe fn check(&self) -> std::result::Result<(), ErrorType> {
+        if self.state.error_mask != 0 {
+            return Err(ErrorType::Syntax);
+        }
+
+        if _mm_testz_si128(self.has_error, self.has_error) != 0 {
+            Ok(())
+        } else {
+            Err(ErrorType::InvalidUTF8)
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    #[target_feature(enable = "sse4.2,pclmulqdq,popcnt")]
+    pub(crate) unsafe fn find_structural_bits_sse42(
+        input: &[u8],
+        structural_indexes: &mut Vec<u32>,
+    ) -> std::result::Result<(), ErrorType> {
+        let len = input.len();
+        // 6 is a heuristic number to estimate it turns out a rate of 1/6 structural caracters lears
+        // almost never to relocations.
+        structural_indexes.clear();
+        structural_indexes.reserve(len / 6);
+        structural_indexes.push(0); // push extra root element
+
+        let mut stage1 = Stage1::new();
+
+        let lenminus64: usize = if len < 64 { 0 } else { len as usize - 64 };
+        let mut idx: usize = 0;
+
+        // The last (partial) block gets copied into a padded buffer so we
+        // don't read past the end of the input.
+        let mut tmpbuf: [u8; 64] = [0x20; 64];
+        while idx < len {
+            if idx < lenminus64 {
+                stage1.block(input.get_unchecked(idx..), idx, structural_indexes);
+            } else {
+                tmpbuf
+                    .as_mut_ptr()
+                    .copy_from(input.as_ptr().add(idx), len as usize - idx);
+                stage1.block(&tmpbuf, idx, structural_indexes);
+            }
+            idx += 64;
+        }
+        stage1.finish(idx, structural_indexes)?;
+
+        // a valid JSON file cannot have zero structural indexes - we should have
+        // found something (note that we compare to 1 as we always add the root!)
+        if structural_indexes.len() == 1 {
+            return Err(ErrorType::EOF);
+        }
+
+        if structural_indexes.last() > Some(&(len as u32)) {
+            return Err(ErrorType::InternalError);
+        }
+
+        stage1.check()
+    }
+}
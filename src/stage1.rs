@@ -1,83 +1,49 @@
-#![allow(dead_code)]
+//! Stage 1 finds the structural characters of a document. The SIMD heavy
+//! lifting (classifying characters, finding quotes and validating UTF-8)
+//! lives in the per implementation modules (`avx2`, `sse42` and `scalar`),
+//! this module holds the bit manipulation they share and the dispatch.
 use crate::portability::*;
-use crate::utf8check::*;
-use crate::*;
-#[cfg(target_arch = "x86")]
-use std::arch::x86::*;
-#[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
-
-use std::mem;
-
-#[derive(Debug)]
-struct SimdInput {
-    lo: __m256i,
-    hi: __m256i,
+use crate::{Deserializer, ErrorType, Implementation};
+
+// The state stage 1 carries from one 64 byte block to the next. Together
+// with the UTF-8 state of the implementation this is all we need to resume
+// indexing at the next block, so the input doesn't have to be in memory as
+// a whole.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BlockState {
+    // does the last iteration end with an odd-length sequence of backslashes?
+    // either 0 or 1, but a 64-bit value
+    pub(crate) prev_iter_ends_odd_backslash: u64,
+    // does the previous iteration end inside a double-quote pair?
+    // either all zeros or all ones
+    pub(crate) prev_iter_inside_quote: u64,
+    // does the previous iteration end on something that is a predecessor of a
+    // pseudo-structural character - i.e. whitespace or a structural character
+    // effectively the very first char is considered to follow "whitespace" for
+    // the purposes of pseudo-structural character detection so we initialize
+    // to 1
+    pub(crate) prev_iter_ends_pseudo_pred: u64,
+    // structurals are persistent state across blocks as we flatten them on the
+    // subsequent block. This is harmless on the first block as structurals==0
+    // and is done for performance reasons; we can hide some of the latency of
+    // the expensive carryless multiply in the previous step with this work
+    pub(crate) structurals: u64,
+    // for unescaped characters within strings (ASCII code points < 0x20)
+    pub(crate) error_mask: u64,
 }
 
-fn fill_input(ptr: &[u8]) -> SimdInput {
-    unsafe {
-        #[allow(clippy::cast_ptr_alignment)]
-        SimdInput {
-            lo: _mm256_loadu_si256(ptr.as_ptr() as *const __m256i),
-            hi: _mm256_loadu_si256(ptr.as_ptr().add(32) as *const __m256i),
+impl Default for BlockState {
+    fn default() -> Self {
+        Self {
+            prev_iter_ends_odd_backslash: 0,
+            prev_iter_inside_quote: 0,
+            prev_iter_ends_pseudo_pred: 1,
+            structurals: 0,
+            error_mask: 0,
         }
     }
 }
 
-#[cfg_attr(not(feature = "no-inline"), inline(always))]
-unsafe fn check_utf8(
-    input: &SimdInput,
-    has_error: &mut __m256i,
-    previous: &mut AvxProcessedUtfBytes,
-) {
-    let highbit: __m256i = _mm256_set1_epi8(static_cast_i8!(0x80u8));
-    if (_mm256_testz_si256(_mm256_or_si256(input.lo, input.hi), highbit)) == 1 {
-        // it is ascii, we just check continuation
-        *has_error = _mm256_or_si256(
-            _mm256_cmpgt_epi8(
-                previous.carried_continuations,
-                _mm256_setr_epi8(
-                    9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9,
-                    9, 9, 9, 9, 9, 1,
-                ),
-            ),
-            *has_error,
-        );
-    } else {
-        // it is not ascii so we have to do heavy work
-        *previous = avxcheck_utf8_bytes(input.lo, &previous, has_error);
-        *previous = avxcheck_utf8_bytes(input.hi, &previous, has_error);
-    }
-}
-
-/// a straightforward comparison of a mask against input. 5 uops; would be
-/// cheaper in AVX512.
-#[cfg_attr(not(feature = "no-inline"), inline(always))]
-fn cmp_mask_against_input(input: &SimdInput, m: u8) -> u64 {
-    unsafe {
-        let mask: __m256i = _mm256_set1_epi8(m as i8);
-        let cmp_res_0: __m256i = _mm256_cmpeq_epi8(input.lo, mask);
-        let res_0: u64 = u64::from(static_cast_u32!(_mm256_movemask_epi8(cmp_res_0)));
-        let cmp_res_1: __m256i = _mm256_cmpeq_epi8(input.hi, mask);
-        let res_1: u64 = _mm256_movemask_epi8(cmp_res_1) as u64;
-        res_0 | (res_1 << 32)
-    }
-}
-
-// find all values less than or equal than the content of maxval (using unsigned arithmetic)
-#[cfg_attr(not(feature = "no-inline"), inline(always))]
-fn unsigned_lteq_against_input(input: &SimdInput, maxval: __m256i) -> u64 {
-    unsafe {
-        let cmp_res_0: __m256i = _mm256_cmpeq_epi8(_mm256_max_epu8(maxval, input.lo), maxval);
-        // TODO: c++ uses static cast, here what are the implications?
-        let res_0: u64 = u64::from(static_cast_u32!(_mm256_movemask_epi8(cmp_res_0)));
-        let cmp_res_1: __m256i = _mm256_cmpeq_epi8(_mm256_max_epu8(maxval, input.hi), maxval);
-        let res_1: u64 = _mm256_movemask_epi8(cmp_res_1) as u64;
-        res_0 | (res_1 << 32)
-    }
-}
-
 // return a bitvector indicating where we have characters that end an odd-length
 // sequence of backslashes (and thus change the behavior of the next character
 // to follow). A even-length sequence of backslashes, and, for that matter, the
@@ -88,11 +54,10 @@ fn unsigned_lteq_against_input(input: &SimdInput, maxval: __m256i) -> u64 {
 // backslashes, which modifies our subsequent search for odd-length
 // sequences of backslashes in an obvious way.
 #[cfg_attr(not(feature = "no-inline"), inline(always))]
-fn find_odd_backslash_sequences(input: &SimdInput, prev_iter_ends_odd_backslash: &mut u64) -> u64 {
+pub fn find_odd_backslash_sequences(bs_bits: u64, prev_iter_ends_odd_backslash: &mut u64) -> u64 {
     const EVEN_BITS: u64 = 0x5555_5555_5555_5555;
     const ODD_BITS: u64 = !EVEN_BITS;
 
-    let bs_bits: u64 = cmp_mask_against_input(&input, b'\\');
     let start_edges: u64 = bs_bits & !(bs_bits << 1);
     // flip lowest if we have an odd-length run at the end of the prior
     // iteration
@@ -119,188 +84,19 @@ fn find_odd_backslash_sequences(input: &SimdInput, prev_iter_ends_odd_backslash:
     odd_ends
 }
 
-// return both the quote mask (which is a half-open mask that covers the first
-// quote in an unescaped quote pair and everything in the quote pair) and the
-// quote bits, which are the simple unescaped quoted bits.
-//
-// We also update the prev_iter_inside_quote value to tell the next iteration
-// whether we finished the final iteration inside a quote pair; if so, this
-// inverts our behavior of whether we're inside quotes for the next iteration.
-//
-// Note that we don't do any error checking to see if we have backslash
-// sequences outside quotes; these
-// backslash sequences (of any length) will be detected elsewhere.
+// Turns the quote bits into a mask that covers everything from an opening
+// quote up to (but not including) the closing one. This is what a carry-less
+// multiplication with an all ones vector gives us, the SIMD implementations
+// use `pclmulqdq` for it.
 #[cfg_attr(not(feature = "no-inline"), inline(always))]
-unsafe fn find_quote_mask_and_bits(
-    input: &SimdInput,
-    odd_ends: u64,
-    prev_iter_inside_quote: &mut u64,
-    quote_bits: &mut u64,
-    error_mask: &mut u64,
-) -> u64 {
-    *quote_bits = cmp_mask_against_input(&input, b'"');
-    *quote_bits &= !odd_ends;
-    // remove from the valid quoted region the unescapted characters.
-    #[allow(overflowing_literals)]
-    let mut quote_mask: u64 = _mm_cvtsi128_si64(_mm_clmulepi64_si128(
-        _mm_set_epi64x(0, static_cast_i64!(*quote_bits)),
-        _mm_set1_epi8(0xFF),
-        0,
-    )) as u64;
-    quote_mask ^= *prev_iter_inside_quote;
-    // All Unicode characters may be placed within the
-    // quotation marks, except for the characters that MUST be escaped:
-    // quotation mark, reverse solidus, and the control characters (U+0000
-    //through U+001F).
-    // https://tools.ietf.org/html/rfc8259
-    let unescaped: u64 = unsigned_lteq_against_input(&input, _mm256_set1_epi8(0x1F));
-    *error_mask |= quote_mask & unescaped;
-    // right shift of a signed value expected to be well-defined and standard
-    // compliant as of C++20,
-    // John Regher from Utah U. says this is fine code
-    *prev_iter_inside_quote = static_cast_u64!(static_cast_i64!(quote_mask) >> 63);
-    quote_mask
-}
-
-#[cfg_attr(not(feature = "no-inline"), inline(always))]
-unsafe fn find_whitespace_and_structurals(
-    input: &SimdInput,
-    whitespace: &mut u64,
-    structurals: &mut u64,
-) {
-    // do a 'shufti' to detect structural JSON characters
-    // they are
-    // * `{` 0x7b
-    // * `}` 0x7d
-    // * `:` 0x3a
-    // * `[` 0x5b
-    // * `]` 0x5d
-    // * `,` 0x2c
-    // these go into the first 3 buckets of the comparison (1/2/4)
-
-    // we are also interested in the four whitespace characters:
-    // * space 0x20
-    // * linefeed 0x0a
-    // * horizontal tab 0x09
-    // * carriage return 0x0d
-    // these go into the next 2 buckets of the comparison (8/16)
-
-    // TODO: const?
-    let low_nibble_mask: __m256i = _mm256_setr_epi8(
-        16, 0, 0, 0, 0, 0, 0, 0, 0, 8, 12, 1, 2, 9, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 8, 12, 1, 2,
-        9, 0, 0,
-    );
-    // TODO: const?
-    let high_nibble_mask: __m256i = _mm256_setr_epi8(
-        8, 0, 18, 4, 0, 1, 0, 1, 0, 0, 0, 3, 2, 1, 0, 0, 8, 0, 18, 4, 0, 1, 0, 1, 0, 0, 0, 3, 2, 1,
-        0, 0,
-    );
-
-    let structural_shufti_mask: __m256i = _mm256_set1_epi8(0x7);
-    let whitespace_shufti_mask: __m256i = _mm256_set1_epi8(0x18);
-
-    let v_lo: __m256i = _mm256_and_si256(
-        _mm256_shuffle_epi8(low_nibble_mask, input.lo),
-        _mm256_shuffle_epi8(
-            high_nibble_mask,
-            _mm256_and_si256(_mm256_srli_epi32(input.lo, 4), _mm256_set1_epi8(0x7f)),
-        ),
-    );
-
-    let v_hi: __m256i = _mm256_and_si256(
-        _mm256_shuffle_epi8(low_nibble_mask, input.hi),
-        _mm256_shuffle_epi8(
-            high_nibble_mask,
-            _mm256_and_si256(_mm256_srli_epi32(input.hi, 4), _mm256_set1_epi8(0x7f)),
-        ),
-    );
-    let tmp_lo: __m256i = _mm256_cmpeq_epi8(
-        _mm256_and_si256(v_lo, structural_shufti_mask),
-        _mm256_set1_epi8(0),
-    );
-    let tmp_hi: __m256i = _mm256_cmpeq_epi8(
-        _mm256_and_si256(v_hi, structural_shufti_mask),
-        _mm256_set1_epi8(0),
-    );
-
-    let structural_res_0: u64 = u64::from(static_cast_u32!(_mm256_movemask_epi8(tmp_lo)));
-    let structural_res_1: u64 = _mm256_movemask_epi8(tmp_hi) as u64;
-    *structurals = !(structural_res_0 | (structural_res_1 << 32));
-
-    let tmp_ws_lo: __m256i = _mm256_cmpeq_epi8(
-        _mm256_and_si256(v_lo, whitespace_shufti_mask),
-        _mm256_set1_epi8(0),
-    );
-    let tmp_ws_hi: __m256i = _mm256_cmpeq_epi8(
-        _mm256_and_si256(v_hi, whitespace_shufti_mask),
-        _mm256_set1_epi8(0),
-    );
-
-    let ws_res_0: u64 = u64::from(static_cast_u32!(_mm256_movemask_epi8(tmp_ws_lo)));
-    let ws_res_1: u64 = _mm256_movemask_epi8(tmp_ws_hi) as u64;
-    *whitespace = !(ws_res_0 | (ws_res_1 << 32));
-}
-
-// flatten out values in 'bits' assuming that they are are to have values of idx
-// plus their position in the bitvector, and store these indexes at
-// base_ptr[base] incrementing base as we go
-// will potentially store extra values beyond end of valid bits, so base_ptr
-// needs to be large enough to handle this
-//TODO: usize was u32 here does this matter?
-#[cfg_attr(not(feature = "no-inline"), inline(always))]
-fn flatten_bits(base: &mut Vec<u32>, idx: u32, mut bits: u64) {
-    let cnt: usize = hamming(bits) as usize;
-    let mut l = base.len();
-    let idx_minus_64 = idx.wrapping_sub(64);
-    let idx_64_v = unsafe {
-        _mm256_set_epi32(
-            static_cast_i32!(idx_minus_64),
-            static_cast_i32!(idx_minus_64),
-            static_cast_i32!(idx_minus_64),
-            static_cast_i32!(idx_minus_64),
-            static_cast_i32!(idx_minus_64),
-            static_cast_i32!(idx_minus_64),
-            static_cast_i32!(idx_minus_64),
-            static_cast_i32!(idx_minus_64),
-        )
-    };
-
-    // We're doing some trickery here.
-    // We reserve 64 extra entries, because we've at most 64 bit to set
-    // then we trunctate the base to the next base (that we calcuate above)
-    // We later indiscriminatory writre over the len we set but that's OK
-    // since we ensure we reserve the needed space
-    base.reserve(64);
-    unsafe {
-        base.set_len(l + cnt);
-    }
-
-    while bits != 0 {
-        unsafe {
-            let v0 = static_cast_i32!(trailingzeroes(bits));
-            bits &= bits.wrapping_sub(1);
-            let v1 = static_cast_i32!(trailingzeroes(bits));
-            bits &= bits.wrapping_sub(1);
-            let v2 = static_cast_i32!(trailingzeroes(bits));
-            bits &= bits.wrapping_sub(1);
-            let v3 = static_cast_i32!(trailingzeroes(bits));
-            bits &= bits.wrapping_sub(1);
-            let v4 = static_cast_i32!(trailingzeroes(bits));
-            bits &= bits.wrapping_sub(1);
-            let v5 = static_cast_i32!(trailingzeroes(bits));
-            bits &= bits.wrapping_sub(1);
-            let v6 = static_cast_i32!(trailingzeroes(bits));
-            bits &= bits.wrapping_sub(1);
-            let v7 = static_cast_i32!(trailingzeroes(bits));
-            bits &= bits.wrapping_sub(1);
-
-            let v: __m256i = _mm256_set_epi32(v7, v6, v5, v4, v3, v2, v1, v0);
-            let v: __m256i = _mm256_add_epi32(idx_64_v, v);
-            #[allow(clippy::cast_ptr_alignment)]
-            _mm256_storeu_si256(base.as_mut_ptr().add(l) as *mut __m256i, v);
-        }
-        l += 8;
-    }
+pub fn prefix_xor(mut bits: u64) -> u64 {
+    bits ^= bits << 1;
+    bits ^= bits << 2;
+    bits ^= bits << 4;
+    bits ^= bits << 8;
+    bits ^= bits << 16;
+    bits ^= bits << 32;
+    bits
 }
 
 // return a updated structural bit vector with quoted contents cleared out and
@@ -310,7 +106,7 @@ fn flatten_bits(base: &mut Vec<u32>, idx: u32, mut bits: u64) {
 // the next iteration
 // will have a pseudo-structural character at its start)
 #[cfg_attr(not(feature = "no-inline"), inline(always))]
-fn finalize_structurals(
+pub fn finalize_structurals(
     mut structurals: u64,
     whitespace: u64,
     quote_mask: u64,
@@ -345,158 +141,161 @@ fn finalize_structurals(
     structurals
 }
 
-//WARN_UNUSED
-/*never_inline*/
-//#[inline(never)]
-impl<'de> Deserializer<'de> {
-    //#[inline(never)]
-    pub unsafe fn find_structural_bits(input: &[u8]) -> std::result::Result<Vec<u32>, ErrorType> {
-        let len = input.len();
-        // 6 is a heuristic number to estimate it turns out a rate of 1/6 structural caracters lears
-        // almost never to relocations.
-        let mut structural_indexes = Vec::with_capacity(len / 6);
-        structural_indexes.push(0); // push extra root element
-
-        let mut has_error: __m256i = _mm256_setzero_si256();
-        let mut previous = AvxProcessedUtfBytes::default();
-        // we have padded the input out to 64 byte multiple with the remainder being
-        // zeros
-
-        // persistent state across loop
-        // does the last iteration end with an odd-length sequence of backslashes?
-        // either 0 or 1, but a 64-bit value
-        let mut prev_iter_ends_odd_backslash: u64 = 0;
-        // does the previous iteration end inside a double-quote pair?
-        let mut prev_iter_inside_quote: u64 = 0;
-        // either all zeros or all ones
-        // does the previous iteration end on something that is a predecessor of a
-        // pseudo-structural character - i.e. whitespace or a structural character
-        // effectively the very first char is considered to follow "whitespace" for
-        // the
-        // purposes of pseudo-structural character detection so we initialize to 1
-        let mut prev_iter_ends_pseudo_pred: u64 = 1;
-
-        // structurals are persistent state across loop as we flatten them on the
-        // subsequent iteration into our array pointed to be base_ptr.
-        // This is harmless on the first iteration as structurals==0
-        // and is done for performance reasons; we can hide some of the latency of the
-        // expensive carryless multiply in the previous step with this work
-        let mut structurals: u64 = 0;
-
-        let lenminus64: usize = if len < 64 { 0 } else { len as usize - 64 };
-        let mut idx: usize = 0;
-        let mut error_mask: u64 = 0; // for unescaped characters within strings (ASCII code points < 0x20)
-
-        while idx < lenminus64 {
-            /*
-            #ifndef _MSC_VER
-              __builtin_prefetch(buf + idx + 128);
-            #endif
-             */
-            let input: SimdInput = fill_input(input.get_unchecked(idx as usize..));
-            check_utf8(&input, &mut has_error, &mut previous);
-            // detect odd sequences of backslashes
-            let odd_ends: u64 =
-                find_odd_backslash_sequences(&input, &mut prev_iter_ends_odd_backslash);
-
-            // detect insides of quote pairs ("quote_mask") and also our quote_bits
-            // themselves
-            let mut quote_bits: u64 = 0;
-            let quote_mask: u64 = find_quote_mask_and_bits(
-                &input,
-                odd_ends,
-                &mut prev_iter_inside_quote,
-                &mut quote_bits,
-                &mut error_mask,
-            );
-
-            // take the previous iterations structural bits, not our current iteration,
-            // and flatten
-            flatten_bits(&mut structural_indexes, idx as u32, structurals);
+// flatten out values in 'bits' assuming that they are are to have values of idx
+// plus their position in the bitvector, and store these indexes at
+// base_ptr[base] incrementing base as we go
+// This is the portable version, the avx2 implementation has its own that
+// stores eight indexes at a time.
+#[cfg_attr(not(feature = "no-inline"), inline(always))]
+pub fn flatten_bits(base: &mut Vec<u32>, idx: u32, mut bits: u64) {
+    let cnt: usize = hamming(bits) as usize;
+    let mut l = base.len();
+    let idx_minus_64 = idx.wrapping_sub(64);
 
-            let mut whitespace: u64 = 0;
-            find_whitespace_and_structurals(&input, &mut whitespace, &mut structurals);
+    base.reserve(cnt);
+    unsafe {
+        base.set_len(l + cnt);
+    }
 
-            // fixup structurals to reflect quotes and add pseudo-structural characters
-            structurals = finalize_structurals(
-                structurals,
-                whitespace,
-                quote_mask,
-                quote_bits,
-                &mut prev_iter_ends_pseudo_pred,
-            );
-            idx += 64;
+    while bits != 0 {
+        unsafe {
+            *base.get_unchecked_mut(l) = idx_minus_64.wrapping_add(trailingzeroes(bits));
         }
+        bits &= bits.wrapping_sub(1);
+        l += 1;
+    }
+}
 
-        // we use a giant copy-paste which is ugly.
-        // but otherwise the string needs to be properly padded or else we
-        // risk invalidating the UTF-8 checks.
-        if idx < len {
-            let mut tmpbuf: [u8; 64] = [0x20; 64];
-            tmpbuf
-                .as_mut_ptr()
-                .copy_from(input.as_ptr().add(idx), len as usize - idx);
-            let input: SimdInput = fill_input(&tmpbuf);
+// Stage 1 of one of the implementations for input that is handed to us
+// one 64 byte block at a time instead of as a whole, the structural indexes
+// lag one block behind as the structurals get flattened on the next block
+// (or by `finish`).
+pub(crate) enum Indexer {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Avx2(crate::avx2::Stage1),
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Sse42(crate::sse42::Stage1),
+    Scalar(crate::scalar::Stage1),
+}
 
-            check_utf8(&input, &mut has_error, &mut previous);
+impl Indexer {
+    pub(crate) fn new(implementation: Implementation) -> Self {
+        match implementation {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Implementation::Avx2 => Indexer::Avx2(crate::avx2::Stage1::new()),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Implementation::Sse42 => Indexer::Sse42(crate::sse42::Stage1::new()),
+            _ => Indexer::Scalar(crate::scalar::Stage1::new()),
+        }
+    }
 
-            // detect odd sequences of backslashes
-            let odd_ends: u64 =
-                find_odd_backslash_sequences(&input, &mut prev_iter_ends_odd_backslash);
+    // `block` has to hold at least 64 bytes, the implementation has to be
+    // supported by the CPU.
+    pub(crate) unsafe fn block(
+        &mut self,
+        block: &[u8],
+        idx: usize,
+        structural_indexes: &mut Vec<u32>,
+    ) {
+        match self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Indexer::Avx2(s) => s.block(block, idx, structural_indexes),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Indexer::Sse42(s) => s.block(block, idx, structural_indexes),
+            Indexer::Scalar(s) => s.block(block, idx, structural_indexes),
+        }
+    }
 
-            // detect insides of quote pairs ("quote_mask") and also our quote_bits
-            // themselves
-            let mut quote_bits: u64 = 0;
-            let quote_mask: u64 = find_quote_mask_and_bits(
-                &input,
-                odd_ends,
-                &mut prev_iter_inside_quote,
-                &mut quote_bits,
-                &mut error_mask,
-            );
+    pub(crate) unsafe fn finish(
+        &mut self,
+        idx: usize,
+        structural_indexes: &mut Vec<u32>,
+    ) -> std::result::Result<(), ErrorType> {
+        match self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Indexer::Avx2(s) => s.finish(idx, structural_indexes),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Indexer::Sse42(s) => s.finish(idx, structural_indexes),
+            Indexer::Scalar(s) => s.finish(idx, structural_indexes),
+        }
+    }
 
-            // take the previous iterations structural bits, not our current iteration,
-            // and flatten
-            flatten_bits(&mut structural_indexes, idx as u32, structurals);
+    pub(crate) unsafe fn check(&self) -> std::result::Result<(), ErrorType> {
+        match self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Indexer::Avx2(s) => s.check(),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Indexer::Sse42(s) => s.check(),
+            Indexer::Scalar(s) => s.check(),
+        }
+    }
+}
 
-            let mut whitespace: u64 = 0;
-            find_whitespace_and_structurals(&input, &mut whitespace, &mut structurals);
+impl<'de> Deserializer<'de> {
+    /// Finds the structural characters in the input using the best
+    /// implementation the CPU supports.
+    pub unsafe fn find_structural_bits(input: &[u8]) -> std::result::Result<Vec<u32>, ErrorType> {
+        Deserializer::find_structural_bits_with(input, Implementation::detect())
+    }
 
-            // fixup structurals to reflect quotes and add pseudo-structural characters
-            structurals = finalize_structurals(
-                structurals,
-                whitespace,
-                quote_mask,
-                quote_bits,
-                &mut prev_iter_ends_pseudo_pred,
-            );
-            idx += 64;
-        }
-        // This test isn't in upstream, for some reason the error mask is et for then.
-        if prev_iter_inside_quote != 0 {
-            return Err(ErrorType::Syntax);
-        }
-        // finally, flatten out the remaining structurals from the last iteration
-        flatten_bits(&mut structural_indexes, idx as u32, structurals);
+    /// Finds the structural characters in the input using the given
+    /// implementation, the caller has to ensure it is supported by the CPU.
+    pub unsafe fn find_structural_bits_with(
+        input: &[u8],
+        implementation: Implementation,
+    ) -> std::result::Result<Vec<u32>, ErrorType> {
+        let mut structural_indexes = Vec::new();
+        Deserializer::find_structural_bits_into(input, implementation, &mut structural_indexes)?;
+        Ok(structural_indexes)
+    }
 
-        // a valid JSON file cannot have zero structural indexes - we should have
-        // found something (note that we compare to 1 as we always add the root!)
-        if structural_indexes.len() == 1 {
-            return Err(ErrorType::EOF);
+    // Same as `find_structural_bits_with` but reuses the given vector.
+    pub(crate) unsafe fn find_structural_bits_into(
+        input: &[u8],
+        implementation: Implementation,
+        structural_indexes: &mut Vec<u32>,
+    ) -> std::result::Result<(), ErrorType> {
+        match implementation {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Implementation::Avx2 => {
+                Deserializer::find_structural_bits_avx2(input, structural_indexes)
+            }
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Implementation::Sse42 => {
+                Deserializer::find_structural_bits_sse42(input, structural_indexes)
+            }
+            _ => Deserializer::find_structural_bits_scalar(input, structural_indexes),
         }
+    }
+}
 
-        if structural_indexes.last() > Some(&(len as u32)) {
-            return Err(ErrorType::InternalError);
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        if error_mask != 0 {
-            return Err(ErrorType::Syntax);
-        }
+    #[test]
+    fn prefix_xor_marks_quoted_region() {
+        // quotes at 1 and 4 cover 1..4
+        assert_eq!(prefix_xor(0b1_0010), 0b0_1110);
+        assert_eq!(prefix_xor(0), 0);
+        assert_eq!(prefix_xor(1), !0);
+    }
 
-        if _mm256_testz_si256(has_error, has_error) != 0 {
-            Ok(structural_indexes)
-        } else {
-            Err(ErrorType::InvalidUTF8)
+    #[test]
+    fn implementations_agree() {
+        // long enough to span a few full 64 byte blocks
+        let input = format!(
+            "[{}0]",
+            r#"{"a": [1, 2.5, "x\"y", true, null], "b\\": {"c": "é"}}, "#.repeat(5)
+        );
+        let input = input.as_bytes();
+        let reference = unsafe {
+            Deserializer::find_structural_bits_with(input, Implementation::Scalar).unwrap()
+        };
+        assert!(reference.len() > 64);
+        for i in Implementation::ALL.iter().filter(|i| i.is_supported()) {
+            let r = unsafe { Deserializer::find_structural_bits_with(input, *i).unwrap() };
+            assert_eq!(reference, r, "{:?}", i);
         }
     }
 }
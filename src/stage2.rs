@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 use crate::charutils::*;
-use crate::{Deserializer, Error, ErrorType, Result, SIMDJSON_PADDING};
+use crate::lenient;
+use crate::{stry, Deserializer, Error, ErrorType, Limits, Result, SIMDJSON_PADDING};
 //use crate::portability::*;
 
 #[cfg_attr(not(feature = "no-inline"), inline(always))]
@@ -74,19 +75,57 @@ enum State {
     MainArraySwitch,
 }
 #[derive(Debug)]
-enum StackState {
+pub(crate) enum StackState {
     Start,
     Object,
     Array,
 }
 
+// The container we are in, where it started and how many elements we have
+// seen so far.
+pub(crate) type StackEntry = (StackState, usize, usize);
+
 impl<'de> Deserializer<'de> {
     pub fn validate(input: &[u8], structural_indexes: &[u32]) -> Result<Vec<usize>> {
-        let mut counts = Vec::with_capacity(structural_indexes.len());
-        let mut stack = Vec::with_capacity(structural_indexes.len());
+        let mut counts = Vec::new();
+        let mut stack = Vec::new();
+        stry!(Deserializer::validate_into(
+            input,
+            structural_indexes,
+            &Limits::default(),
+            false,
+            &mut counts,
+            &mut stack
+        ));
+        Ok(counts)
+    }
+
+    // Same as `validate` but reuses the given vectors for the counts and
+    // the stack. Besides validating the document this checks it against
+    // the limits, before anything is allocated based on the counts.
+    // Lenient parsing allows for `NaN` and `Infinity`, `-Infinity` is
+    // left for the number parser like any other number.
+    pub(crate) fn validate_into(
+        input: &[u8],
+        structural_indexes: &[u32],
+        limits: &Limits,
+        lenient: bool,
+        counts: &mut Vec<usize>,
+        stack: &mut Vec<StackEntry>,
+    ) -> Result<()> {
+        if input.len() > limits.max_document_size {
+            return Err(Error::generic(ErrorType::DocumentSizeLimit));
+        }
+        counts.clear();
+        counts.reserve(structural_indexes.len());
+        stack.clear();
+        // we never go deeper than `max_depth`, so the stack does not need
+        // to be larger than that
+        let stack_len = structural_indexes.len().min(limits.max_depth);
+        stack.reserve(stack_len);
         unsafe {
             counts.set_len(structural_indexes.len());
-            stack.set_len(structural_indexes.len());
+            stack.set_len(stack_len);
         }
 
         let mut depth = 0;
@@ -105,7 +144,13 @@ impl<'de> Deserializer<'de> {
         let mut si = structural_indexes.iter().skip(1).peekable();
         macro_rules! update_char {
             () => {
-                idx = *stry!(si.next().ok_or_else(|| (Error::generic(ErrorType::Syntax)))) as usize;
+                idx = *stry!(si.next().ok_or_else(|| Error::at(
+                    input,
+                    i,
+                    input.len(),
+                    ErrorType::Syntax
+                )
+                .with_pointer(input, structural_indexes))) as usize;
                 i += 1;
                 c = unsafe { *input.get_unchecked(idx) };
             };
@@ -191,12 +236,42 @@ impl<'de> Deserializer<'de> {
             }};
         }
 
+        // Enters an array or object, `$state` is where we continue once it
+        // is closed again.
+        macro_rules! scope_begin {
+            ($state:expr) => {
+                if depth >= limits.max_depth {
+                    fail!(ErrorType::DepthLimit);
+                }
+                unsafe {
+                    *stack.get_unchecked_mut(depth) = ($state, last_start, cnt);
+                }
+                depth += 1;
+                last_start = i;
+                cnt = 1;
+            };
+        }
+
+        // Strings are not structural, the next structural character after
+        // the opening quote only gives us an upper bound for their length.
+        // They are only measured if that is over the limit.
+        macro_rules! check_string {
+            () => {
+                let end = si.peek().map_or(input.len(), |i| **i as usize);
+                if unlikely!(end - idx - 1 > limits.max_string_length)
+                    && string_len(input, idx) > limits.max_string_length
+                {
+                    fail!(ErrorType::StringLengthLimit);
+                }
+            };
+        }
+
         macro_rules! fail {
             () => {
-                return Err(Error::new(i, idx, c as char, ErrorType::InternalError));
+                fail!(ErrorType::InternalError);
             };
             ($t:expr) => {
-                return Err(Error::new(i, idx, c as char, $t));
+                return Err(Error::at(input, i, idx, $t).with_pointer(input, structural_indexes));
             };
         }
         // State start, we pull this outside of the
@@ -204,12 +279,7 @@ impl<'de> Deserializer<'de> {
         update_char!();
         match c {
             b'{' => {
-                unsafe {
-                    *stack.get_unchecked_mut(depth) = (StackState::Start, last_start, cnt);
-                }
-                depth += 1;
-                last_start = i;
-                cnt = 1;
+                scope_begin!(StackState::Start);
 
                 update_char!();
                 match c {
@@ -226,12 +296,7 @@ impl<'de> Deserializer<'de> {
                 }
             }
             b'[' => {
-                unsafe {
-                    *stack.get_unchecked_mut(depth) = (StackState::Start, last_start, cnt);
-                }
-                depth += 1;
-                last_start = i;
-                cnt = 1;
+                scope_begin!(StackState::Start);
 
                 update_char!();
                 if c == b']' {
@@ -242,8 +307,9 @@ impl<'de> Deserializer<'de> {
                 }
             }
             b'"' => {
+                check_string!();
                 if si.next().is_none() {
-                    return Ok(counts);
+                    return Ok(());
                 } else {
                     fail!(ErrorType::TrailingCharacters);
                 }
@@ -258,7 +324,7 @@ impl<'de> Deserializer<'de> {
                     }
                 };
                 if si.next().is_none() {
-                    return Ok(counts);
+                    return Ok(());
                 } else {
                     fail!(ErrorType::TrailingCharacters);
                 }
@@ -273,7 +339,7 @@ impl<'de> Deserializer<'de> {
                     }
                 };
                 if si.next().is_none() {
-                    return Ok(counts);
+                    return Ok(());
                 } else {
                     fail!(ErrorType::TrailingCharacters);
                 }
@@ -288,14 +354,24 @@ impl<'de> Deserializer<'de> {
                     }
                 };
                 if si.next().is_none() {
-                    return Ok(counts);
+                    return Ok(());
+                } else {
+                    fail!(ErrorType::TrailingCharacters);
+                }
+            }
+            b'N' | b'I' if lenient => {
+                if lenient::non_finite(unsafe { input.get_unchecked(idx..) }).is_none() {
+                    fail!(ErrorType::InvalidNumber);
+                }
+                if si.next().is_none() {
+                    return Ok(());
                 } else {
                     fail!(ErrorType::TrailingCharacters);
                 }
             }
             b'-' | b'0'..=b'9' => {
                 if si.next().is_none() {
-                    return Ok(counts);
+                    return Ok(());
                 } else {
                     fail!(ErrorType::TrailingCharacters);
                 }
@@ -310,13 +386,21 @@ impl<'de> Deserializer<'de> {
             match state {
                 ////////////////////////////// OBJECT STATES /////////////////////////////
                 ObjectKey => {
+                    // we're at the key, `cnt` is the number of keys so far
+                    if unlikely!(cnt > limits.max_keys) {
+                        fail!(ErrorType::KeyCountLimit);
+                    }
+                    check_string!();
                     update_char!();
                     if unlikely!(c != b':') {
                         fail!(ErrorType::ExpectedObjectColon);
                     }
                     update_char!();
                     match c {
-                        b'"' => object_continue!(),
+                        b'"' => {
+                            check_string!();
+                            object_continue!();
+                        }
 
                         b't' => {
                             if !is_valid_true_atom(unsafe { input.get_unchecked(idx..) }) {
@@ -339,24 +423,19 @@ impl<'de> Deserializer<'de> {
                         b'-' | b'0'..=b'9' => {
                             object_continue!();
                         }
-                        b'{' => {
-                            unsafe {
-                                *stack.get_unchecked_mut(depth) =
-                                    (StackState::Object, last_start, cnt);
+                        b'N' | b'I' if lenient => {
+                            if lenient::non_finite(unsafe { input.get_unchecked(idx..) }).is_none()
+                            {
+                                fail!(ErrorType::InvalidNumber);
                             }
-                            depth += 1;
-                            last_start = i;
-                            cnt = 1;
+                            object_continue!();
+                        }
+                        b'{' => {
+                            scope_begin!(StackState::Object);
                             object_begin!();
                         }
                         b'[' => {
-                            unsafe {
-                                *stack.get_unchecked_mut(depth) =
-                                    (StackState::Object, last_start, cnt);
-                            }
-                            depth += 1;
-                            last_start = i;
-                            cnt = 1;
+                            scope_begin!(StackState::Object);
                             array_begin!();
                         }
                         _c => {
@@ -367,7 +446,7 @@ impl<'de> Deserializer<'de> {
                 ////////////////////////////// COMMON STATE /////////////////////////////
                 ScopeEnd => {
                     if depth == 0 {
-                        return Err(Error::generic(ErrorType::Syntax));
+                        fail!(ErrorType::Syntax);
                     }
                     depth -= 1;
                     unsafe {
@@ -386,7 +465,7 @@ impl<'de> Deserializer<'de> {
                         StackState::Array => array_continue!(),
                         StackState::Start => {
                             if si.next().is_none() {
-                                return Ok(counts);
+                                return Ok(());
                             } else {
                                 fail!();
                             }
@@ -398,8 +477,14 @@ impl<'de> Deserializer<'de> {
                 MainArraySwitch => {
                     // we call update char on all paths in, so we can peek at c on the
                     // on paths that can accept a close square brace (post-, and at start)
+                    if unlikely!(cnt > limits.max_elements) {
+                        fail!(ErrorType::ElementCountLimit);
+                    }
                     match c {
-                        b'"' => array_continue!(),
+                        b'"' => {
+                            check_string!();
+                            array_continue!();
+                        }
                         b't' => {
                             if !is_valid_true_atom(unsafe { input.get_unchecked(idx..) }) {
                                 fail!(ErrorType::ExpectedBoolean); // TODO: better error
@@ -421,24 +506,19 @@ impl<'de> Deserializer<'de> {
                         b'-' | b'0'..=b'9' => {
                             array_continue!();
                         }
-                        b'{' => {
-                            unsafe {
-                                *stack.get_unchecked_mut(depth) =
-                                    (StackState::Array, last_start, cnt);
+                        b'N' | b'I' if lenient => {
+                            if lenient::non_finite(unsafe { input.get_unchecked(idx..) }).is_none()
+                            {
+                                fail!(ErrorType::InvalidNumber);
                             }
-                            depth += 1;
-                            last_start = i;
-                            cnt = 1;
+                            array_continue!();
+                        }
+                        b'{' => {
+                            scope_begin!(StackState::Array);
                             object_begin!();
                         }
                         b'[' => {
-                            unsafe {
-                                *stack.get_unchecked_mut(depth) =
-                                    (StackState::Array, last_start, cnt);
-                            }
-                            depth += 1;
-                            last_start = i;
-                            cnt = 1;
+                            scope_begin!(StackState::Array);
                             array_begin!();
                         }
                         _c => {
@@ -479,3 +559,18 @@ mod test {
         assert!(!is_valid_null_atom(b" ull    "));
     }
 }
+
+// The length in bytes of the string starting at the quote at `idx`, as it
+// is written in the input.
+fn string_len(input: &[u8], idx: usize) -> usize {
+    let mut escaped = false;
+    for (i, c) in input[idx + 1..].iter().enumerate() {
+        match *c {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return i,
+            _ => (),
+        }
+    }
+    input.len() - idx - 1
+}
@@ -1,5 +1,6 @@
 use crate::charutils::*;
 use crate::error::*;
+use std::borrow::Cow;
 //use crate::Result;
 
 // begin copypasta
@@ -73,3 +74,58 @@ pub fn handle_unicode_codepoint(
     let offset: usize = codepoint_to_utf8(code_point, dst_ptr);
     Ok((offset, src_offset))
 }
+
+// Reads the string starting at the quote at `start` without touching the
+// input, for the few places that need a string before or without the
+// string parsers. Strings without escapes are borrowed.
+pub(crate) fn read_str(input: &[u8], start: usize) -> Cow<'_, str> {
+    fn hex(input: &[u8], i: usize) -> Option<u32> {
+        let s = std::str::from_utf8(input.get(i..i + 4)?).ok()?;
+        u32::from_str_radix(s, 16).ok()
+    }
+    let s = input.get(start + 1..).unwrap_or_default();
+    match s.iter().position(|c| *c == b'"' || *c == b'\\') {
+        Some(end) if s[end] == b'"' => return String::from_utf8_lossy(&s[..end]),
+        _ => (),
+    }
+    let mut res = Vec::new();
+    let mut i = start + 1;
+    while let Some(c) = input.get(i) {
+        i += 1;
+        let c = match *c {
+            b'"' => break,
+            b'\\' => {
+                i += 1;
+                match input.get(i - 1) {
+                    Some(b'b') => 0x08,
+                    Some(b'f') => 0x0c,
+                    Some(b'n') => b'\n',
+                    Some(b'r') => b'\r',
+                    Some(b't') => b'\t',
+                    Some(b'u') => {
+                        let mut cp = hex(input, i).unwrap_or(0xFFFD);
+                        i += 4;
+                        if (0xD800..0xDC00).contains(&cp)
+                            && input.get(i..i + 2) == Some(&b"\\u"[..])
+                        {
+                            if let Some(low) = hex(input, i + 2) {
+                                cp = 0x10000
+                                    + ((cp - 0xD800) << 10)
+                                    + (low.wrapping_sub(0xDC00) & 0x3FF);
+                                i += 6;
+                            }
+                        }
+                        let c = std::char::from_u32(cp).unwrap_or('\u{FFFD}');
+                        res.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        continue;
+                    }
+                    Some(c) => *c,
+                    None => break,
+                }
+            }
+            c => c,
+        };
+        res.push(c);
+    }
+    Cow::Owned(String::from_utf8_lossy(&res).into_owned())
+}
@@ -0,0 +1,726 @@
+//! A flat representation of a parsed document that can be navigated
+//! without building a tree of values.
+//!
+//! The tape is a sequence of 64 bit words, the upper 8 bits of a word hold
+//! the type tag and the lower 56 bits the payload:
+//!
+//! * `n`, `t` and `f` - null, true and false, no payload.
+//! * `l`, `u` and `d` - a signed integer, an unsigned integer (only used
+//!   for values above `i64::MAX`) or a float, the following word holds the
+//!   value (the bits of the `i64`, `u64` or `f64`).
+//! * `"` - a string, the payload is the offset of the string in the string
+//!   arena, the following word holds its length in bytes.
+//! * `r` - a raw number (see `Parser::raw_numbers`), stored like a string.
+//! * `[` and `{` - the start of an array or object, the lower 32 bits of
+//!   the payload hold the index of the word following the matching `]` or
+//!   `}`, the upper 24 bits the number of elements (saturating at
+//!   `0xFF_FFFF`). Object members are stored as the key string followed by
+//!   the value.
+//! * `]` and `}` - the end of an array or object, the payload is the index
+//!   of the matching start.
+//!
+//! Since every array and object knows where it ends, skipping over a
+//! subtree is a single lookup no matter how large it is.
+//!
+//! ```
+//! let mut d = br#"{"user": {"id": 42, "tags": ["a", "b"]}, "n": null}"#.to_vec();
+//! let tape = simd_json::to_tape(&mut d).unwrap();
+//! let user = tape.root().get("user").unwrap();
+//! assert_eq!(user.get("id").and_then(|id| id.as_i64()), Some(42));
+//! let tags: Vec<_> = user.get("tags").unwrap().as_array().unwrap().iter().collect();
+//! assert_eq!(tags[1].as_str(), Some("b"));
+//! ```
+use crate::json5;
+use crate::numberparse::Number;
+use crate::value::borrowed::Value as BorrowedValue;
+use crate::value::owned::{Map, Value as OwnedValue};
+use crate::value::ValueType;
+use crate::{Deserializer, ErrorType, Parser, Result};
+
+const TAG_SHIFT: u64 = 56;
+const PAYLOAD_MASK: u64 = (1 << TAG_SHIFT) - 1;
+const END_MASK: u64 = 0xFFFF_FFFF;
+const COUNT_SHIFT: u64 = 32;
+const MAX_COUNT: usize = 0xFF_FFFF;
+
+/// A parsed document as a flat sequence of 64 bit words plus an arena
+/// holding the strings, see the module documentation for the layout.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Tape {
+    words: Vec<u64>,
+    strings: String,
+}
+
+impl Tape {
+    /// Creates an empty tape, useful to be filled by
+    /// `Parser::parse_tape_into`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The root of the document. Panics if the tape is empty.
+    pub fn root(&self) -> Node<'_> {
+        Node { tape: self, idx: 0 }
+    }
+
+    /// The words of the tape.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// The string arena, the string words point into it.
+    pub fn strings(&self) -> &str {
+        &self.strings
+    }
+
+    /// Empties the tape keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.strings.clear();
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn push(&mut self, tag: u8, payload: u64) {
+        self.words.push(u64::from(tag) << TAG_SHIFT | payload);
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn push_str(&mut self, tag: u8, s: &str) {
+        let offset = self.strings.len() as u64;
+        self.strings.push_str(s);
+        self.push(tag, offset);
+        self.words.push(s.len() as u64);
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn push_number(&mut self, n: Number) {
+        match n {
+            Number::I64(i) => {
+                self.push(b'l', 0);
+                self.words.push(i as u64);
+            }
+            Number::U64(i) => {
+                self.push(b'u', 0);
+                self.words.push(i);
+            }
+            Number::F64(f) => {
+                self.push(b'd', 0);
+                self.words.push(f.to_bits());
+            }
+            Number::Raw(s) => self.push_str(b'r', s),
+        }
+    }
+
+    // Pushes the start of an array or object, it is completed by `end`.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn start(&mut self, tag: u8) -> usize {
+        let idx = self.words.len();
+        self.push(tag, 0);
+        idx
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn end(&mut self, start: usize, tag: u8, count: usize) {
+        self.push(tag, start as u64);
+        let end = self.words.len() as u64;
+        let count = count.min(MAX_COUNT) as u64;
+        self.words[start] |= count << COUNT_SHIFT | end;
+    }
+
+    // Writes a value that was parsed without the structural indexes, as
+    // JSON5 documents are.
+    fn push_value(&mut self, v: &BorrowedValue) {
+        match v {
+            BorrowedValue::Null => self.push(b'n', 0),
+            BorrowedValue::Bool(true) => self.push(b't', 0),
+            BorrowedValue::Bool(false) => self.push(b'f', 0),
+            BorrowedValue::I64(i) => self.push_number(Number::I64(*i)),
+            BorrowedValue::U64(i) => self.push_number(Number::U64(*i)),
+            BorrowedValue::F64(f) => self.push_number(Number::F64(*f)),
+            BorrowedValue::RawNumber(s) => self.push_str(b'r', s),
+            BorrowedValue::String(s) => self.push_str(b'"', s),
+            BorrowedValue::Array(a) => {
+                let start = self.start(b'[');
+                for v in a {
+                    self.push_value(v);
+                }
+                self.end(start, b']', a.len());
+            }
+            BorrowedValue::Object(o) => {
+                let start = self.start(b'{');
+                for (k, v) in o {
+                    self.push_str(b'"', k);
+                    self.push_value(v);
+                }
+                self.end(start, b'}', o.len());
+            }
+        }
+    }
+}
+
+/// A cursor pointing at a value on a tape.
+#[derive(Debug, Clone, Copy)]
+pub struct Node<'t> {
+    tape: &'t Tape,
+    idx: usize,
+}
+
+impl<'t> Node<'t> {
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn word(&self) -> u64 {
+        self.tape.words[self.idx]
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn payload(&self) -> u64 {
+        self.word() & PAYLOAD_MASK
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn next_word(&self) -> u64 {
+        self.tape.words[self.idx + 1]
+    }
+
+    /// The type tag of this node.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    pub fn tag(&self) -> u8 {
+        (self.word() >> TAG_SHIFT) as u8
+    }
+
+    /// The index of this node on the tape.
+    pub fn index(&self) -> usize {
+        self.idx
+    }
+
+    /// The index of the first word after this node, for arrays and
+    /// objects this skips the whole subtree.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    pub fn end(&self) -> usize {
+        match self.tag() {
+            b'[' | b'{' => (self.payload() & END_MASK) as usize,
+            b'l' | b'u' | b'd' | b'"' | b'r' => self.idx + 2,
+            _ => self.idx + 1,
+        }
+    }
+
+    pub fn kind(&self) -> ValueType {
+        match self.tag() {
+            b'n' => ValueType::Null,
+            b't' | b'f' => ValueType::Bool,
+            b'l' => ValueType::I64,
+            b'u' => ValueType::U64,
+            b'd' => ValueType::F64,
+            b'r' => ValueType::RawNumber,
+            b'"' => ValueType::String,
+            b'[' => ValueType::Array,
+            _ => ValueType::Object,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.tag() == b'n'
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.tag() {
+            b't' => Some(true),
+            b'f' => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.tag() {
+            b'l' => Some(self.next_word() as i64),
+            b'r' => self.text().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.tag() {
+            b'l' if (self.next_word() as i64) >= 0 => Some(self.next_word()),
+            b'u' => Some(self.next_word()),
+            b'r' => self.text().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.tag() {
+            b'd' => Some(f64::from_bits(self.next_word())),
+            b'r' => self.text().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn cast_f64(&self) -> Option<f64> {
+        match self.tag() {
+            b'd' => Some(f64::from_bits(self.next_word())),
+            b'l' => Some(self.next_word() as i64 as f64),
+            b'u' => Some(self.next_word() as f64),
+            b'r' => self.text().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The string this node holds, borrowed from the string arena.
+    pub fn as_str(&self) -> Option<&'t str> {
+        match self.tag() {
+            b'"' => Some(self.text()),
+            _ => None,
+        }
+    }
+
+    /// The source text of a raw number.
+    pub fn as_raw_number(&self) -> Option<&'t str> {
+        match self.tag() {
+            b'r' => Some(self.text()),
+            _ => None,
+        }
+    }
+
+    // The text of a string or raw number in the string arena.
+    fn text(&self) -> &'t str {
+        let start = self.payload() as usize;
+        let len = self.next_word() as usize;
+        &self.tape.strings[start..start + len]
+    }
+
+    pub fn as_array(&self) -> Option<Array<'t>> {
+        match self.tag() {
+            b'[' => Some(Array { node: *self }),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<Object<'t>> {
+        match self.tag() {
+            b'{' => Some(Object { node: *self }),
+            _ => None,
+        }
+    }
+
+    /// Looks up a key if this node is an object.
+    pub fn get(&self, k: &str) -> Option<Node<'t>> {
+        self.as_object().and_then(|o| o.get(k))
+    }
+
+    /// Looks up an index if this node is an array.
+    pub fn get_idx(&self, i: usize) -> Option<Node<'t>> {
+        self.as_array().and_then(|a| a.get(i))
+    }
+
+    // The number of elements of an array or object.
+    fn count(&self) -> usize {
+        ((self.payload() >> COUNT_SHIFT) as usize) & MAX_COUNT
+    }
+}
+
+/// An array on a tape.
+#[derive(Debug, Clone, Copy)]
+pub struct Array<'t> {
+    node: Node<'t>,
+}
+
+impl<'t> Array<'t> {
+    /// The number of elements, this only has to walk the array if it has
+    /// more than 2^24 - 1 elements.
+    pub fn len(&self) -> usize {
+        match self.node.count() {
+            MAX_COUNT => self.iter().count(),
+            count => count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node.count() == 0
+    }
+
+    /// The element at index `i`, the elements before it are skipped
+    /// without looking into them.
+    pub fn get(&self, i: usize) -> Option<Node<'t>> {
+        self.iter().nth(i)
+    }
+
+    pub fn iter(&self) -> ArrayIter<'t> {
+        ArrayIter {
+            tape: self.node.tape,
+            idx: self.node.idx + 1,
+        }
+    }
+}
+
+impl<'t> IntoIterator for Array<'t> {
+    type Item = Node<'t>;
+    type IntoIter = ArrayIter<'t>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the elements of an array on a tape.
+pub struct ArrayIter<'t> {
+    tape: &'t Tape,
+    idx: usize,
+}
+
+impl<'t> Iterator for ArrayIter<'t> {
+    type Item = Node<'t>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = Node {
+            tape: self.tape,
+            idx: self.idx,
+        };
+        if node.tag() == b']' {
+            None
+        } else {
+            self.idx = node.end();
+            Some(node)
+        }
+    }
+}
+
+/// An object on a tape.
+#[derive(Debug, Clone, Copy)]
+pub struct Object<'t> {
+    node: Node<'t>,
+}
+
+impl<'t> Object<'t> {
+    /// The number of members, this only has to walk the object if it has
+    /// more than 2^24 - 1 members.
+    pub fn len(&self) -> usize {
+        match self.node.count() {
+            MAX_COUNT => self.iter().count(),
+            count => count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node.count() == 0
+    }
+
+    /// The value for the key `k`, values of other keys are skipped
+    /// without looking into them. Repeated keys are resolved by the
+    /// duplicate key policy when the tape is built, so this is the same
+    /// value the Value doms keep.
+    pub fn get(&self, k: &str) -> Option<Node<'t>> {
+        self.iter().find(|(key, _)| *key == k).map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> ObjectIter<'t> {
+        ObjectIter {
+            tape: self.node.tape,
+            idx: self.node.idx + 1,
+        }
+    }
+}
+
+impl<'t> IntoIterator for Object<'t> {
+    type Item = (&'t str, Node<'t>);
+    type IntoIter = ObjectIter<'t>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the members of an object on a tape.
+pub struct ObjectIter<'t> {
+    tape: &'t Tape,
+    idx: usize,
+}
+
+impl<'t> Iterator for ObjectIter<'t> {
+    type Item = (&'t str, Node<'t>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = Node {
+            tape: self.tape,
+            idx: self.idx,
+        };
+        let key = key.as_str()?;
+        let value = Node {
+            tape: self.tape,
+            idx: self.idx + 2,
+        };
+        self.idx = value.end();
+        Some((key, value))
+    }
+}
+
+impl<'t> From<Node<'t>> for OwnedValue {
+    fn from(node: Node<'t>) -> Self {
+        match node.tag() {
+            b'n' => OwnedValue::Null,
+            b't' => OwnedValue::Bool(true),
+            b'f' => OwnedValue::Bool(false),
+            b'l' => OwnedValue::I64(node.next_word() as i64),
+            b'u' => OwnedValue::U64(node.next_word()),
+            b'r' => OwnedValue::RawNumber(node.text().to_string()),
+            b'd' => OwnedValue::F64(f64::from_bits(node.next_word())),
+            b'"' => OwnedValue::String(node.as_str().unwrap_or_default().to_string()),
+            b'[' => OwnedValue::Array(
+                node.as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(Self::from)
+                    .collect(),
+            ),
+            _ => {
+                let mut m = Map::with_capacity(node.count());
+                for (k, v) in node.as_object().into_iter().flatten() {
+                    m.insert(k.to_string(), Self::from(v));
+                }
+                OwnedValue::Object(m)
+            }
+        }
+    }
+}
+
+// Writes the document onto the tape, it walks the structurals the same way
+// the Value deserializers do.
+struct TapeDeserializer<'de, 't> {
+    de: Deserializer<'de>,
+    tape: &'t mut Tape,
+}
+
+impl<'de, 't> TapeDeserializer<'de, 't> {
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn parse(&mut self) -> Result<()> {
+        match self.de.next_() {
+            b'"' => {
+                let s = stry!(self.de.parse_str_());
+                self.tape.push_str(b'"', &s);
+            }
+            b'n' => self.tape.push(b'n', 0),
+            b't' => self.tape.push(b't', 0),
+            b'f' => self.tape.push(b'f', 0),
+            b'-' => self
+                .tape
+                .push_number(stry!(self.de.parse_number_root(true))),
+            b'0'..=b'9' => self
+                .tape
+                .push_number(stry!(self.de.parse_number_root(false))),
+            b'[' => stry!(self.parse_array()),
+            b'{' => stry!(self.parse_map()),
+            _c => return Err(self.de.error(ErrorType::UnexpectedCharacter)),
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn parse_value(&mut self) -> Result<()> {
+        match self.de.next_() {
+            b'"' => {
+                let s = stry!(self.de.parse_str_());
+                self.tape.push_str(b'"', &s);
+            }
+            b'n' => self.tape.push(b'n', 0),
+            b't' => self.tape.push(b't', 0),
+            b'f' => self.tape.push(b'f', 0),
+            b'-' => self.tape.push_number(stry!(self.de.parse_number(true))),
+            b'0'..=b'9' => self.tape.push_number(stry!(self.de.parse_number(false))),
+            b'[' => stry!(self.parse_array()),
+            b'{' => stry!(self.parse_map()),
+            _c => return Err(self.de.error(ErrorType::UnexpectedCharacter)),
+        }
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn parse_array(&mut self) -> Result<()> {
+        let es = self.de.count_elements();
+        let start = self.tape.start(b'[');
+        if unlikely!(es == 0) {
+            self.de.skip();
+        } else {
+            for _i in 0..es {
+                stry!(self.parse_value());
+                self.de.skip();
+            }
+        }
+        self.tape.end(start, b']', es);
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn parse_map(&mut self) -> Result<()> {
+        let es = self.de.count_elements();
+        let start = self.tape.start(b'{');
+        if unlikely!(es == 0) {
+            self.de.skip();
+            self.tape.end(start, b'}', 0);
+            return Ok(());
+        }
+        // Duplicate members are left out here so every key is on the tape
+        // only once and lookups can stop at the first match.
+        let mut dropped = if es > 1 {
+            stry!(self.de.duplicate_members(es))
+        } else {
+            Vec::new()
+        };
+        let len = es - dropped.len();
+        for member in 0..es {
+            if dropped.last() == Some(&member) {
+                dropped.pop();
+                self.de.skip_member();
+                continue;
+            }
+            self.de.skip();
+            let key = stry!(self.de.parse_str_());
+            self.tape.push_str(b'"', &key);
+            self.de.skip();
+            stry!(self.parse_value());
+            self.de.skip();
+        }
+        self.tape.end(start, b'}', len);
+        Ok(())
+    }
+}
+
+/// Parses a slice of bytes into a tape. This function will rewrite the
+/// slice to de-escape strings.
+pub fn to_tape(s: &mut [u8]) -> Result<Tape> {
+    let mut tape = Tape::new();
+    stry!(Parser::new().parse_tape_into(s, &mut tape));
+    Ok(tape)
+}
+
+impl Parser {
+    /// Parses a slice of bytes into a tape, see `to_tape`.
+    pub fn parse_tape(&mut self, input: &mut [u8]) -> Result<Tape> {
+        let mut tape = Tape::new();
+        stry!(self.parse_tape_into(input, &mut tape));
+        Ok(tape)
+    }
+
+    /// Parses a slice of bytes into the given tape reusing its memory, the
+    /// tape is cleared first.
+    pub fn parse_tape_into(&mut self, input: &mut [u8], tape: &mut Tape) -> Result<()> {
+        tape.clear();
+        if self.json5 {
+            tape.push_value(&stry!(json5::parse(self, input)));
+            return Ok(());
+        }
+        let de = stry!(self.deserializer(input));
+        let mut de = TapeDeserializer { de, tape };
+        let r = de.parse();
+        de.de.return_buffers(&mut self.buffers);
+        r
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{to_owned_value, ValueTrait};
+
+    fn check(input: &str) {
+        let expected = to_owned_value(&mut input.as_bytes().to_vec()).unwrap();
+        let tape = to_tape(&mut input.as_bytes().to_vec()).unwrap();
+        assert_eq!(expected, OwnedValue::from(tape.root()), "{}", input);
+        assert_eq!(tape.root().end(), tape.words().len());
+    }
+
+    #[test]
+    fn same_as_value() {
+        check("null");
+        check("true");
+        check("-12");
+        check("1.5");
+        check("\"a\\\"b\\u00e4\"");
+        check("[]");
+        check("{}");
+        check("[1, [2, [3, []]], {\"a\": {\"b\": [true, false, null]}}, \"x\"]");
+        check("{\"a\": 1, \"b\": [1.5, -2], \"c\\n\": {\"d\": {}}, \"e\": \"f\"}");
+    }
+
+    #[test]
+    fn layout() {
+        let mut d = br#"[1, "ab", {"k": null}]"#.to_vec();
+        let tape = to_tape(&mut d).unwrap();
+        let w = tape.words();
+        let tag = |i: usize| (w[i] >> TAG_SHIFT) as u8;
+        let tags: Vec<u8> = vec![b'[', b'l', 0, b'"', 0, b'{', b'"', 0, b'n', b'}', b']'];
+        assert_eq!(w.len(), tags.len());
+        for (i, t) in tags.iter().enumerate().filter(|(_, t)| **t != 0) {
+            assert_eq!(tag(i), *t, "word {}", i);
+        }
+        // start -> end, count
+        assert_eq!(w[0] & PAYLOAD_MASK, 3 << COUNT_SHIFT | 11);
+        assert_eq!(w[5] & PAYLOAD_MASK, 1 << COUNT_SHIFT | 10);
+        // end -> start
+        assert_eq!(w[9] & PAYLOAD_MASK, 5);
+        assert_eq!(w[10] & PAYLOAD_MASK, 0);
+        // numbers and strings
+        assert_eq!(w[2], 1);
+        assert_eq!(tape.strings(), "abk");
+        assert_eq!(w[3] & PAYLOAD_MASK, 0);
+        assert_eq!(w[4], 2);
+        assert_eq!(w[6] & PAYLOAD_MASK, 2);
+        assert_eq!(w[7], 1);
+    }
+
+    #[test]
+    fn navigate() {
+        let mut d = br#"{"skip": [[1, 2, [3]], {"x": "y"}], "users": [{"id": 1, "name": "a"}, {"id": 2, "name": "b"}], "n": -1.5}"#.to_vec();
+        let tape = to_tape(&mut d).unwrap();
+        let root = tape.root();
+        assert_eq!(root.kind() as u8, ValueType::Object as u8);
+        let o = root.as_object().unwrap();
+        assert_eq!(o.len(), 3);
+        let keys: Vec<_> = o.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec!["skip", "users", "n"]);
+
+        let skip = root.get("skip").unwrap();
+        let users = root.get("users").unwrap();
+        // skipping the first member lands right on the key of the second
+        assert_eq!(skip.end() + 2, users.index());
+
+        let users = users.as_array().unwrap();
+        assert_eq!(users.len(), 2);
+        assert!(!users.is_empty());
+        let names: Vec<_> = users
+            .iter()
+            .filter_map(|u| u.get("name").and_then(|n| n.as_str()))
+            .collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(root.get_idx(0).map(|n| n.index()), None);
+        assert_eq!(
+            users
+                .get(1)
+                .and_then(|u| u.get("id"))
+                .and_then(|i| i.as_u64()),
+            Some(2)
+        );
+        assert!(users.get(2).is_none());
+        assert_eq!(root.get("n").and_then(|n| n.as_f64()), Some(-1.5));
+        assert_eq!(root.get("n").and_then(|n| n.as_i64()), None);
+        assert!(root.get("missing").is_none());
+        let nested = skip.get_idx(0).and_then(|a| a.get_idx(2));
+        assert_eq!(
+            nested.and_then(|a| a.get_idx(0)).and_then(|i| i.as_i64()),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn reuse() {
+        let mut parser = Parser::new();
+        let mut tape = Tape::new();
+        for (input, ok) in &[("[1, 2]", true), ("[1, ", false), ("{\"a\": \"b\"}", true)] {
+            let mut d = input.as_bytes().to_vec();
+            let r = parser.parse_tape_into(&mut d, &mut tape);
+            assert_eq!(r.is_ok(), *ok);
+            if *ok {
+                let expected = to_owned_value(&mut input.as_bytes().to_vec()).unwrap();
+                assert_eq!(expected, OwnedValue::from(tape.root()));
+            }
+        }
+        let v = OwnedValue::from(tape.root());
+        assert_eq!(
+            v.get("a").and_then(|s| s.as_string()),
+            Some("b".to_string())
+        );
+    }
+}
@@ -11,18 +11,276 @@
 /// we do not require prior knowledge sbout string comtent to to take advantage
 /// of it.
 pub mod borrowed;
-mod generator;
+pub(crate) mod generator;
 pub mod owned;
+pub(crate) mod pointer;
 
-pub use self::borrowed::{to_value as to_borrowed_value, Value as BorrowedValue};
-pub use self::owned::{to_value as to_owned_value, Value as OwnedValue};
+pub use self::borrowed::{
+    to_value as to_borrowed_value, to_value_ref as to_borrowed_value_ref, Value as BorrowedValue,
+};
+pub use self::owned::{
+    from_reader as to_owned_value_from_reader, to_value as to_owned_value,
+    to_value_ref as to_owned_value_ref, Value as OwnedValue,
+};
+use crate::{Error, ErrorType, Result};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::hash::Hash;
+use std::ops::Index;
+
+// The map objects are kept in: a `halfbrown::HashMap` by default or, with
+// the `preserve_order` feature, an `indexmap::IndexMap` that keeps keys in
+// the order they were inserted in.
+#[cfg(not(feature = "preserve_order"))]
+pub(crate) type ObjectMap<K, V> = halfbrown::HashMap<K, V>;
+#[cfg(feature = "preserve_order")]
+pub(crate) type ObjectMap<K, V> = indexmap::IndexMap<K, V>;
+#[cfg(not(feature = "preserve_order"))]
+type MapIter<'i, K, V> = halfbrown::Iter<'i, K, V>;
+#[cfg(feature = "preserve_order")]
+type MapIter<'i, K, V> = indexmap::map::Iter<'i, K, V>;
+#[cfg(not(feature = "preserve_order"))]
+type MapKeys<'i, K, V> = halfbrown::Keys<'i, K, V>;
+#[cfg(feature = "preserve_order")]
+type MapKeys<'i, K, V> = indexmap::map::Keys<'i, K, V>;
+#[cfg(not(feature = "preserve_order"))]
+type MapValues<'i, K, V> = halfbrown::Values<'i, K, V>;
+#[cfg(feature = "preserve_order")]
+type MapValues<'i, K, V> = indexmap::map::Values<'i, K, V>;
+
+// Inserts a key the map doesn't contain yet, halfbrown can skip looking
+// for it.
+#[cfg_attr(not(feature = "no-inline"), inline(always))]
+pub(crate) fn insert_new<K: Eq + Hash, V>(map: &mut ObjectMap<K, V>, key: K, value: V) {
+    #[cfg(not(feature = "preserve_order"))]
+    map.insert_nocheck(key, value);
+    #[cfg(feature = "preserve_order")]
+    map.insert(key, value);
+}
+
+// The keys of an object seen so far, as 64 buckets they hash to. A key
+// whose bucket is still empty is known to be new, so objects are built
+// without looking up their keys and the duplicate key policy only has to
+// be applied once a bucket is hit a second time.
+#[derive(Default)]
+pub(crate) struct SeenKeys(u64);
+
+impl SeenKeys {
+    // Records the key, returns if a key seen before might be the same.
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    pub(crate) fn insert(&mut self, key: &str) -> bool {
+        let h = key.bytes().fold(key.len() as u64, |h, c| {
+            h.wrapping_mul(31).wrapping_add(u64::from(c))
+        });
+        let bit = 1 << (h.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 58);
+        let seen = self.0 & bit != 0;
+        self.0 |= bit;
+        seen
+    }
+}
+
+/// The map an object is stored in, lets generic code work with the
+/// objects of any `ValueTrait` implementation.
+pub trait ObjectTrait {
+    /// The type keys are stored as.
+    type Key: Borrow<str> + From<String> + Hash + Eq;
+    /// The type of the values in the map.
+    type Element;
+    /// The iterator over the members returned by `iter`.
+    type Iter<'i>: Iterator<Item = (&'i str, &'i Self::Element)>
+    where
+        Self: 'i;
+    /// The iterator over the keys returned by `keys`.
+    type Keys<'i>: Iterator<Item = &'i str>
+    where
+        Self: 'i;
+    /// The iterator over the values returned by `values`.
+    type Values<'i>: Iterator<Item = &'i Self::Element>
+    where
+        Self: 'i;
+
+    fn get(&self, k: &str) -> Option<&Self::Element>;
+    fn get_mut(&mut self, k: &str) -> Option<&mut Self::Element>;
+    /// Inserts a value, returning the one previously stored under the key.
+    fn insert<K, V>(&mut self, k: K, v: V) -> Option<Self::Element>
+    where
+        K: Into<Self::Key>,
+        V: Into<Self::Element>;
+    /// Removes a key, with the `preserve_order` feature the remaining keys
+    /// keep their order.
+    fn remove(&mut self, k: &str) -> Option<Self::Element>;
+    fn iter(&self) -> Self::Iter<'_>;
+    fn keys(&self) -> Self::Keys<'_>;
+    fn values(&self) -> Self::Values<'_>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K, V> ObjectTrait for ObjectMap<K, V>
+where
+    K: Borrow<str> + From<String> + Hash + Eq,
+{
+    type Key = K;
+    type Element = V;
+    type Iter<'i>
+        = ObjectIter<'i, K, V>
+    where
+        Self: 'i;
+    type Keys<'i>
+        = ObjectKeys<'i, K, V>
+    where
+        Self: 'i;
+    type Values<'i>
+        = MapValues<'i, K, V>
+    where
+        Self: 'i;
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn get(&self, k: &str) -> Option<&V> {
+        ObjectMap::get(self, k)
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn get_mut(&mut self, k: &str) -> Option<&mut V> {
+        ObjectMap::get_mut(self, k)
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn insert<K1, V1>(&mut self, k: K1, v: V1) -> Option<V>
+    where
+        K1: Into<K>,
+        V1: Into<V>,
+    {
+        ObjectMap::insert(self, k.into(), v.into())
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn remove(&mut self, k: &str) -> Option<V> {
+        #[cfg(not(feature = "preserve_order"))]
+        let v = ObjectMap::remove(self, k);
+        #[cfg(feature = "preserve_order")]
+        let v = ObjectMap::shift_remove(self, k);
+        v
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn iter(&self) -> ObjectIter<'_, K, V> {
+        ObjectIter(ObjectMap::iter(self))
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn keys(&self) -> ObjectKeys<'_, K, V> {
+        ObjectKeys(ObjectMap::keys(self))
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn values(&self) -> MapValues<'_, K, V> {
+        ObjectMap::values(self)
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn len(&self) -> usize {
+        ObjectMap::len(self)
+    }
+}
+
+/// Iterator over the members of an object, see `ObjectTrait::iter`.
+pub struct ObjectIter<'i, K, V>(MapIter<'i, K, V>);
+
+impl<'i, K: Borrow<str>, V> Iterator for ObjectIter<'i, K, V> {
+    type Item = (&'i str, &'i V);
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (k.borrow(), v))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Iterator over the keys of an object, see `ObjectTrait::keys`.
+pub struct ObjectKeys<'i, K, V>(MapKeys<'i, K, V>);
+
+impl<'i, K: Borrow<str>, V> Iterator for ObjectKeys<'i, K, V> {
+    type Item = &'i str;
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Borrow::borrow)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// The vector an array is stored in, lets generic code work with the
+/// arrays of any `ValueTrait` implementation.
+pub trait ArrayTrait {
+    /// The type of the elements in the array.
+    type Element;
+    /// The iterator over the elements returned by `iter`.
+    type Iter<'i>: Iterator<Item = &'i Self::Element>
+    where
+        Self: 'i;
+
+    fn get(&self, i: usize) -> Option<&Self::Element>;
+    fn get_mut(&mut self, i: usize) -> Option<&mut Self::Element>;
+    fn push<V>(&mut self, v: V)
+    where
+        V: Into<Self::Element>;
+    fn pop(&mut self) -> Option<Self::Element>;
+    /// Removes the element at `i`, moving the ones after it up. Panics if
+    /// `i` is out of bounds, like `Vec::remove`.
+    fn remove(&mut self, i: usize) -> Self::Element;
+    fn iter(&self) -> Self::Iter<'_>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> ArrayTrait for Vec<T> {
+    type Element = T;
+    type Iter<'i>
+        = std::slice::Iter<'i, T>
+    where
+        Self: 'i;
+
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn get(&self, i: usize) -> Option<&T> {
+        <[T]>::get(self, i)
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        <[T]>::get_mut(self, i)
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn push<V>(&mut self, v: V)
+    where
+        V: Into<T>,
+    {
+        Vec::push(self, v.into())
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn pop(&mut self) -> Option<T> {
+        Vec::pop(self)
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn remove(&mut self, i: usize) -> T {
+        Vec::remove(self, i)
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn iter(&self) -> std::slice::Iter<'_, T> {
+        <[T]>::iter(self)
+    }
+    #[cfg_attr(not(feature = "no-inline"), inline(always))]
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+}
 
 #[derive(Copy, Clone, Debug)]
 pub enum ValueType {
     Null,
     Bool,
     I64,
+    U64,
     F64,
+    RawNumber,
     String,
     Array,
     Object,
@@ -54,9 +312,16 @@ pub trait ValueTrait:
     + From<String>
     + From<bool>
     + From<()>
+    + Index<usize, Output = Self>
+    + for<'k> Index<&'k str, Output = Self>
 {
-    type Map;
-    type Array;
+    type Map: ObjectTrait<Element = Self>;
+    type Array: ArrayTrait<Element = Self>;
+
+    /// An empty array.
+    fn array() -> Self;
+    /// An empty object.
+    fn object() -> Self;
 
     fn get(&self, k: &str) -> Option<&Self>;
     fn get_mut(&mut self, k: &str) -> Option<&mut Self>;
@@ -76,10 +341,10 @@ pub trait ValueTrait:
     }
 
     fn as_u64(&self) -> Option<u64> {
-        self.as_i64().map(|u| u as u64)
+        self.as_i64().and_then(|i| if i >= 0 { Some(i as u64) } else { None })
     }
     fn is_u64(&self) -> bool {
-        self.as_i64().is_some()
+        self.as_u64().is_some()
     }
 
     fn as_f64(&self) -> Option<f64>;
@@ -88,9 +353,12 @@ pub trait ValueTrait:
     }
     fn cast_f64(&self) -> Option<f64>;
 
-    fn as_string(&self) -> Option<String>;
+    fn as_str(&self) -> Option<&str>;
+    fn as_string(&self) -> Option<String> {
+        self.as_str().map(String::from)
+    }
     fn is_string(&self) -> bool {
-        self.as_string().is_some()
+        self.as_str().is_some()
     }
 
     fn is_array(&self) -> bool {
@@ -104,4 +372,185 @@ pub trait ValueTrait:
     }
     fn as_object(&self) -> Option<&Self::Map>;
     fn as_object_mut(&mut self) -> Option<&mut Self::Map>;
+
+    /// The number of elements in an array or keys in an object, `None`
+    /// for any other value.
+    fn len(&self) -> Option<usize> {
+        if let Some(a) = self.as_array() {
+            Some(a.len())
+        } else {
+            self.as_object().map(ObjectTrait::len)
+        }
+    }
+    /// Whether an array or object is empty, `None` for any other value.
+    fn is_empty(&self) -> Option<bool> {
+        self.len().map(|l| l == 0)
+    }
+
+    /// Inserts into an object, returning the value previously stored under
+    /// the key. Fails with `ExpectedMap` if this isn't an object.
+    fn insert<K, V>(&mut self, k: K, v: V) -> Result<Option<Self>>
+    where
+        K: Into<<Self::Map as ObjectTrait>::Key>,
+        V: Into<Self>,
+    {
+        match self.as_object_mut() {
+            Some(m) => Ok(m.insert(k, v)),
+            None => Err(Error::generic(ErrorType::ExpectedMap)),
+        }
+    }
+    /// Removes a key from an object. Fails with `ExpectedMap` if this
+    /// isn't an object.
+    fn remove(&mut self, k: &str) -> Result<Option<Self>> {
+        match self.as_object_mut() {
+            Some(m) => Ok(m.remove(k)),
+            None => Err(Error::generic(ErrorType::ExpectedMap)),
+        }
+    }
+    /// Appends to an array. Fails with `ExpectedArray` if this isn't an
+    /// array.
+    fn push<V>(&mut self, v: V) -> Result<()>
+    where
+        V: Into<Self>,
+    {
+        match self.as_array_mut() {
+            Some(a) => {
+                a.push(v);
+                Ok(())
+            }
+            None => Err(Error::generic(ErrorType::ExpectedArray)),
+        }
+    }
+    /// Removes the last element of an array. Fails with `ExpectedArray` if
+    /// this isn't an array.
+    fn pop(&mut self) -> Result<Option<Self>> {
+        match self.as_array_mut() {
+            Some(a) => Ok(a.pop()),
+            None => Err(Error::generic(ErrorType::ExpectedArray)),
+        }
+    }
+
+    /// Looks up the value an RFC 6901 JSON Pointer like `/a/b/0` refers
+    /// to, `None` if the pointer is invalid or there is no such value.
+    /// ```
+    /// use simd_json::{json, ValueTrait};
+    ///
+    /// let v = json!({"a": [{"b/c": 1}]});
+    /// assert_eq!(v.pointer("/a/0/b~1c").and_then(|v| v.as_i64()), Some(1));
+    /// assert_eq!(v.pointer(""), Some(&v));
+    /// assert!(v.pointer("/a/1").is_none());
+    /// ```
+    fn pointer(&self, pointer: &str) -> Option<&Self> {
+        pointer::get(self, &pointer::tokens(pointer)?)
+    }
+    /// Like `pointer` but returns a mutable reference.
+    fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Self> {
+        pointer::get_mut(self, &pointer::tokens(pointer)?)
+    }
+    /// Sets the value a JSON Pointer refers to, returning the one it
+    /// replaces. Objects missing on the way are created and `-`, or the
+    /// length of an array, appends to it.
+    ///
+    /// Fails with `InvalidPointer` if the pointer or an array index in it
+    /// is invalid, `IndexOutOfBounds` for an index past the end of an array
+    /// and `ExpectedMap` if a value on the way is neither an array nor an
+    /// object.
+    /// ```
+    /// use simd_json::{json, ValueTrait};
+    ///
+    /// let mut v = json!({"a": [1]});
+    /// v.pointer_set("/a/-", 2).unwrap();
+    /// v.pointer_set("/b/c", "d".to_string()).unwrap();
+    /// assert_eq!(v, json!({"a": [1, 2], "b": {"c": "d"}}));
+    /// ```
+    fn pointer_set<V>(&mut self, pointer: &str, v: V) -> Result<Option<Self>>
+    where
+        V: Into<Self>,
+    {
+        match pointer::tokens(pointer) {
+            Some(tokens) => pointer::set(self, &tokens, v.into()),
+            None => Err(Error::generic(ErrorType::InvalidPointer)),
+        }
+    }
+    /// Removes the value a JSON Pointer refers to, array elements after it
+    /// move up. `None` if there is no such value, the root can't be
+    /// removed.
+    fn pointer_remove(&mut self, pointer: &str) -> Option<Self> {
+        pointer::remove(self, &pointer::tokens(pointer)?)
+    }
+}
+
+// Numbers as they are compared by JSONPath, JSON Patch, JSON Merge Patch
+// and JSON Schema, every integer a value can hold fits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+// The number a value holds, if it is one.
+pub(crate) fn number_of<V: ValueTrait>(v: &V) -> Option<Number> {
+    match v.kind() {
+        ValueType::I64 | ValueType::U64 | ValueType::F64 | ValueType::RawNumber => v
+            .as_i64()
+            .map(|i| Number::Int(i128::from(i)))
+            .or_else(|| v.as_u64().map(|u| Number::Int(i128::from(u))))
+            .or_else(|| v.as_f64().map(Number::Float)),
+        _ => None,
+    }
+}
+
+// Compares numbers by their value, no matter how they are stored.
+pub(crate) fn compare_numbers(a: Number, b: Number) -> Option<Ordering> {
+    match (a, b) {
+        (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+        (Number::Int(a), Number::Float(b)) => compare_int_float(a, b),
+        (Number::Float(a), Number::Int(b)) => compare_int_float(b, a).map(Ordering::reverse),
+        (Number::Float(a), Number::Float(b)) => a.partial_cmp(&b),
+    }
+}
+
+// Compares exactly, converting the integer to a float would round it once
+// it is above 2^53. Floats in the range of i128 are compared by their
+// integral part as an integer and then by their fraction.
+fn compare_int_float(a: i128, b: f64) -> Option<Ordering> {
+    // 2^127, the first float above i128::MAX
+    const LIMIT: f64 = 170_141_183_460_469_231_731_687_303_715_884_105_728.0;
+    if b.is_nan() {
+        None
+    } else if b >= LIMIT {
+        Some(Ordering::Less)
+    } else if b < -LIMIT {
+        Some(Ordering::Greater)
+    } else {
+        let integral = b.trunc();
+        match a.cmp(&(integral as i128)) {
+            Ordering::Equal => 0.0.partial_cmp(&(b - integral)),
+            o => Some(o),
+        }
+    }
+}
+
+// Whether two values are the same JSON, numbers are equal if their values
+// are, no matter how they are stored. JSON Patch, JSON Merge Patch and
+// JSON Schema use this as well, the latter with values of different types.
+pub(crate) fn equal_nodes<A: ValueTrait, B: ValueTrait>(a: &A, b: &B) -> bool {
+    if let (Some(a), Some(b)) = (number_of(a), number_of(b)) {
+        return compare_numbers(a, b) == Some(Ordering::Equal);
+    }
+    if let (Some(a), Some(b)) = (a.as_array(), b.as_array()) {
+        return a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| equal_nodes(a, b));
+    }
+    if let (Some(a), Some(b)) = (a.as_object(), b.as_object()) {
+        return a.len() == b.len()
+            && a.iter()
+                .all(|(k, a)| b.get(k).map_or(false, |b| equal_nodes(a, b)));
+    }
+    if let (Some(a), Some(b)) = (a.as_str(), b.as_str()) {
+        return a == b;
+    }
+    if let (Some(a), Some(b)) = (a.as_bool(), b.as_bool()) {
+        return a == b;
+    }
+    a.is_null() && b.is_null()
 }
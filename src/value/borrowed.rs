@@ -4,14 +4,15 @@ mod cmp;
 mod from;
 mod serialize;
 
-use crate::value::{ValueTrait, ValueType};
-use crate::{stry, unlikely, Deserializer, ErrorType, Result};
-use halfbrown::HashMap;
+use crate::value::{insert_new, ObjectMap, SeenKeys, ValueTrait, ValueType};
+use crate::{stry, unlikely, Deserializer, DuplicateKeys, ErrorType, Result};
 use std::borrow::Cow;
 use std::fmt;
 use std::ops::Index;
 
-pub type Map<'v> = HashMap<Cow<'v, str>, Value<'v>>;
+/// The map objects are stored in, keys keep the order of the input with
+/// the `preserve_order` feature.
+pub type Map<'v> = ObjectMap<Cow<'v, str>, Value<'v>>;
 
 /// Parses a slice of butes into a Value dom. This function will
 /// rewrite the slice to de-escape strings.
@@ -22,12 +23,23 @@ pub fn to_value<'v>(s: &'v mut [u8]) -> Result<Value<'v>> {
     BorrowDeserializer::from_deserializer(de).parse()
 }
 
+/// Parses an immutable slice of bytes into a Value dom. The slice is
+/// left untouched, strings without escapes reference it while strings
+/// with escapes get copied.
+pub fn to_value_ref<'v>(s: &'v [u8]) -> Result<Value<'v>> {
+    let de = stry!(Deserializer::from_slice_ref(s));
+    BorrowDeserializer::from_deserializer(de).parse()
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value<'v> {
     Null,
     Bool(bool),
     F64(f64),
     I64(i64),
+    U64(u64),
+    /// A number kept as its source text, see `Parser::raw_numbers`.
+    RawNumber(Cow<'v, str>),
     String(Cow<'v, str>),
     Array(Vec<Value<'v>>),
     Object(Map<'v>),
@@ -37,6 +49,14 @@ impl<'v> ValueTrait for Value<'v> {
     type Map = Map<'v>;
     type Array = Vec<Value<'v>>;
 
+    fn array() -> Self {
+        Value::Array(Vec::new())
+    }
+
+    fn object() -> Self {
+        Value::Object(Map::new())
+    }
+
     fn get(&self, k: &str) -> Option<&Value<'v>> {
         match self {
             Value::Object(m) => m.get(k),
@@ -57,6 +77,8 @@ impl<'v> ValueTrait for Value<'v> {
             Value::Bool(_) => ValueType::Bool,
             Value::F64(_) => ValueType::F64,
             Value::I64(_) => ValueType::I64,
+            Value::U64(_) => ValueType::U64,
+            Value::RawNumber(_) => ValueType::RawNumber,
             Value::String(_) => ValueType::String,
             Value::Array(_) => ValueType::Array,
             Value::Object(_) => ValueType::Object,
@@ -80,6 +102,8 @@ impl<'v> ValueTrait for Value<'v> {
     fn as_i64(&self) -> Option<i64> {
         match self {
             Value::I64(i) => Some(*i),
+            Value::U64(i) if *i <= i64::max_value() as u64 => Some(*i as i64),
+            Value::RawNumber(n) => n.parse().ok(),
             _ => None,
         }
     }
@@ -87,6 +111,8 @@ impl<'v> ValueTrait for Value<'v> {
     fn as_u64(&self) -> Option<u64> {
         match self {
             Value::I64(i) if *i >= 0 => Some(*i as u64),
+            Value::U64(i) => Some(*i),
+            Value::RawNumber(n) => n.parse().ok(),
             _ => None,
         }
     }
@@ -94,6 +120,7 @@ impl<'v> ValueTrait for Value<'v> {
     fn as_f64(&self) -> Option<f64> {
         match self {
             Value::F64(i) => Some(*i),
+            Value::RawNumber(n) => n.parse().ok(),
             _ => None,
         }
     }
@@ -102,13 +129,15 @@ impl<'v> ValueTrait for Value<'v> {
         match self {
             Value::F64(i) => Some(*i),
             Value::I64(i) => Some(*i as f64),
+            Value::U64(i) => Some(*i as f64),
+            Value::RawNumber(n) => n.parse().ok(),
             _ => None,
         }
     }
 
-    fn as_string(&self) -> Option<String> {
+    fn as_str(&self) -> Option<&str> {
         match self {
-            Value::String(s) => Some(s.to_string()),
+            Value::String(s) => Some(s),
             _ => None,
         }
     }
@@ -148,6 +177,8 @@ impl<'v> fmt::Display for Value<'v> {
             Value::Null => write!(f, "null"),
             Value::Bool(b) => write!(f, "{}", b),
             Value::I64(n) => write!(f, "{}", n),
+            Value::U64(n) => write!(f, "{}", n),
+            Value::RawNumber(n) => write!(f, "{}", n),
             Value::F64(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
             Value::Array(a) => write!(f, "{:?}", a),
@@ -164,13 +195,21 @@ impl<'v> Index<&str> for Value<'v> {
     }
 }
 
+impl<'v> Index<usize> for Value<'v> {
+    type Output = Value<'v>;
+    fn index(&self, index: usize) -> &Value<'v> {
+        static NULL: Value = Value::Null;
+        self.as_array().and_then(|a| a.get(index)).unwrap_or(&NULL)
+    }
+}
+
 impl<'v> Default for Value<'v> {
     fn default() -> Self {
         Value::Null
     }
 }
 
-struct BorrowDeserializer<'de> {
+pub(crate) struct BorrowDeserializer<'de> {
     de: Deserializer<'de>,
 }
 impl<'de> BorrowDeserializer<'de> {
@@ -178,12 +217,16 @@ impl<'de> BorrowDeserializer<'de> {
         Self { de }
     }
 
+    pub fn into_deserializer(self) -> Deserializer<'de> {
+        self.de
+    }
+
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
     pub fn parse(&mut self) -> Result<Value<'de>> {
         match self.de.next_() {
             b'"' => self.de.parse_str_().map(Value::from),
             b'-' => self.de.parse_number_root(true).map(Value::from),
-            b'0'..=b'9' => self.de.parse_number_root(false).map(Value::from),
+            b'0'..=b'9' | b'N' | b'I' => self.de.parse_number_root(false).map(Value::from),
             b'n' => Ok(Value::Null),
             b't' => Ok(Value::Bool(true)),
             b'f' => Ok(Value::Bool(false)),
@@ -198,7 +241,7 @@ impl<'de> BorrowDeserializer<'de> {
         match self.de.next_() {
             b'"' => self.de.parse_str_().map(Value::from),
             b'-' => self.de.parse_number_(true).map(Value::from),
-            b'0'..=b'9' => self.de.parse_number_(false).map(Value::from),
+            b'0'..=b'9' | b'N' | b'I' => self.de.parse_number_(false).map(Value::from),
             b'n' => Ok(Value::Null),
             b't' => Ok(Value::Bool(true)),
             b'f' => Ok(Value::Bool(false)),
@@ -239,13 +282,36 @@ impl<'de> BorrowDeserializer<'de> {
         // Since we checked if it's empty we know that we at least have one
         // element so we eat this
 
+        let mut seen = SeenKeys::default();
         for _ in 0..es {
             self.de.skip();
+            let key_idx = self.de.idx;
             let key = stry!(self.de.parse_str_());
             // We have to call parse short str twice since parse_short_str
             // does not move the cursor forward
             self.de.skip();
-            res.insert_nocheck(key.into(), stry!(self.parse_value()));
+            let value = stry!(self.parse_value());
+            if unlikely!(seen.insert(&key)) {
+                // the key may be a duplicate, only now do we need to look
+                match self.de.duplicate_keys {
+                    DuplicateKeys::KeepLast => {
+                        res.insert(key.into(), value);
+                    }
+                    DuplicateKeys::KeepFirst => {
+                        if !res.contains_key(&*key) {
+                            insert_new(&mut res, key.into(), value);
+                        }
+                    }
+                    DuplicateKeys::Error => {
+                        if res.contains_key(&*key) {
+                            return Err(self.de.error_at(key_idx, ErrorType::DuplicateKey));
+                        }
+                        insert_new(&mut res, key.into(), value);
+                    }
+                }
+            } else {
+                insert_new(&mut res, key.into(), value);
+            }
             self.de.skip();
         }
         Ok(Value::Object(res))
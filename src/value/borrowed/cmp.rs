@@ -83,6 +83,43 @@ impl<'a> PartialEq<i64> for Value<'a> {
     }
 }
 
+impl<'a> PartialEq<u8> for Value<'a> {
+    fn eq(&self, other: &u8) -> bool {
+        match self {
+            Value::I64(i) => i == &i64::from(*other),
+            _ => false,
+        }
+    }
+}
+
+impl<'a> PartialEq<u16> for Value<'a> {
+    fn eq(&self, other: &u16) -> bool {
+        match self {
+            Value::I64(i) => i == &i64::from(*other),
+            _ => false,
+        }
+    }
+}
+
+impl<'a> PartialEq<u32> for Value<'a> {
+    fn eq(&self, other: &u32) -> bool {
+        match self {
+            Value::I64(i) => i == &i64::from(*other),
+            _ => false,
+        }
+    }
+}
+
+impl<'a> PartialEq<u64> for Value<'a> {
+    fn eq(&self, other: &u64) -> bool {
+        match self {
+            Value::I64(i) => *i >= 0 && *i as u64 == *other,
+            Value::U64(i) => i == other,
+            _ => false,
+        }
+    }
+}
+
 impl<'a> PartialEq<f32> for Value<'a> {
     fn eq(&self, other: &f32) -> bool {
         match self {
@@ -4,12 +4,14 @@ use crate::OwnedValue;
 use std::borrow::Cow;
 use std::iter::FromIterator;
 
-impl<'a> From<Number> for Value<'a> {
+impl<'a> From<Number<'a>> for Value<'a> {
     #[inline]
-    fn from(n: Number) -> Self {
+    fn from(n: Number<'a>) -> Self {
         match n {
             Number::F64(n) => Value::F64(n),
             Number::I64(n) => Value::I64(n),
+            Number::U64(n) => Value::U64(n),
+            Number::Raw(n) => Value::RawNumber(Cow::Borrowed(n)),
         }
     }
 }
@@ -21,6 +23,8 @@ impl<'a> From<OwnedValue> for Value<'a> {
             OwnedValue::Bool(b) => Value::Bool(b),
             OwnedValue::F64(f) => Value::F64(f),
             OwnedValue::I64(i) => Value::I64(i),
+            OwnedValue::U64(i) => Value::U64(i),
+            OwnedValue::RawNumber(n) => Value::RawNumber(Cow::Owned(n)),
             OwnedValue::String(s) => Value::from(s.to_string()),
             OwnedValue::Array(a) => {
                 Value::Array(a.into_iter().map(|v| v.into()).collect::<Vec<Value>>())
@@ -47,6 +51,13 @@ impl<'v> From<String> for Value<'v> {
     }
 }
 
+impl<'v> From<Cow<'v, str>> for Value<'v> {
+    #[inline]
+    fn from(s: Cow<'v, str>) -> Self {
+        Value::String(s)
+    }
+}
+
 /********* atoms **********/
 impl<'v> From<bool> for Value<'v> {
     #[inline]
@@ -142,7 +153,12 @@ impl<'v> From<u32> for Value<'v> {
 impl<'v> From<u64> for Value<'v> {
     #[inline]
     fn from(i: u64) -> Self {
-        Value::I64(i as i64)
+        // values that fit are stored as I64, the same way the parser does
+        if i <= i64::max_value() as u64 {
+            Value::I64(i as i64)
+        } else {
+            Value::U64(i)
+        }
     }
 }
 
@@ -170,7 +186,7 @@ impl<'v> From<&u32> for Value<'v> {
 impl<'v> From<&u64> for Value<'v> {
     #[inline]
     fn from(i: &u64) -> Self {
-        Value::I64(*i as i64)
+        Value::from(*i)
     }
 }
 
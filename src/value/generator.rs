@@ -4,8 +4,10 @@
 //
 // https://github.com/maciejhirsz/json-rust/blob/master/src/codegen.rs
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 use crate::portability::trailingzeroes;
 use crate::value::ValueTrait;
+use crate::Implementation;
 #[cfg(target_arch = "x86")]
 use std::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
@@ -46,6 +48,92 @@ static ESCAPED: [u8; 256] = [
     __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // F
 ];
 
+/// Returns the number of leading bytes of `string` that can be written
+/// without escaping. The SIMD implementations only look at full blocks, so
+/// this stops either at the first character that needs escaping or at the
+/// start of the last partial block.
+#[inline(always)]
+fn clean_prefix_len(string: &[u8]) -> usize {
+    match Implementation::detect() {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Implementation::Avx2 => unsafe { clean_prefix_len_avx2(string) },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Implementation::Sse42 => unsafe { clean_prefix_len_sse42(string, 0) },
+        _ => 0,
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+unsafe fn clean_prefix_len_avx2(string: &[u8]) -> usize {
+    let len = string.len();
+    let mut idx = 0;
+    // Looking at the table above the lower 5 bits are entirely
+    // quote characters that gives us a bitmask of 0x1f for that
+    // region, only quote (`"`) and backslash (`\`) are not in
+    // this range.
+    let zero = _mm256_set1_epi8(0);
+    let lower_quote_range = _mm256_set1_epi8(0x1F as i8);
+    let quote = _mm256_set1_epi8(b'"' as i8);
+    let backslash = _mm256_set1_epi8(b'\\' as i8);
+    while len - idx >= 32 {
+        // Load 32 bytes of data;
+        #[allow(clippy::cast_ptr_alignment)]
+        let data: __m256i = _mm256_loadu_si256(string.as_ptr().add(idx) as *const __m256i);
+        // Test the data against being backslash and quote.
+        let bs_or_quote = _mm256_or_si256(
+            _mm256_cmpeq_epi8(data, backslash),
+            _mm256_cmpeq_epi8(data, quote),
+        );
+        // Now mask the data with the quote range (0x1F).
+        let in_quote_range = _mm256_and_si256(data, lower_quote_range);
+        // then test of the data is unchanged. aka: xor it with the
+        // Any field that was inside the quote range it will be zero
+        // now.
+        let is_unchanged = _mm256_xor_si256(data, in_quote_range);
+        let in_range = _mm256_cmpeq_epi8(is_unchanged, zero);
+        let quote_bits = _mm256_movemask_epi8(_mm256_or_si256(bs_or_quote, in_range));
+        if quote_bits != 0 {
+            return idx + trailingzeroes(quote_bits as u64) as usize;
+        }
+        idx += 32;
+    }
+    // The case where we have a 16+ byte block we repeate the same
+    // logic as above but with only 16 bytes
+    clean_prefix_len_sse42(string, idx)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "sse4.2")]
+unsafe fn clean_prefix_len_sse42(string: &[u8], mut idx: usize) -> usize {
+    let len = string.len();
+    let zero = _mm_set1_epi8(0);
+    let lower_quote_range = _mm_set1_epi8(0x1F as i8);
+    let quote = _mm_set1_epi8(b'"' as i8);
+    let backslash = _mm_set1_epi8(b'\\' as i8);
+    while len - idx >= 16 {
+        // Load 16 bytes of data;
+        #[allow(clippy::cast_ptr_alignment)]
+        let data: __m128i = _mm_loadu_si128(string.as_ptr().add(idx) as *const __m128i);
+        // Test the data against being backslash and quote.
+        let bs_or_quote =
+            _mm_or_si128(_mm_cmpeq_epi8(data, backslash), _mm_cmpeq_epi8(data, quote));
+        // Now mask the data with the quote range (0x1F).
+        let in_quote_range = _mm_and_si128(data, lower_quote_range);
+        // then test of the data is unchanged. aka: xor it with the
+        // Any field that was inside the quote range it will be zero
+        // now.
+        let is_unchanged = _mm_xor_si128(data, in_quote_range);
+        let in_range = _mm_cmpeq_epi8(is_unchanged, zero);
+        let quote_bits = _mm_movemask_epi8(_mm_or_si128(bs_or_quote, in_range));
+        if quote_bits != 0 {
+            return idx + trailingzeroes(quote_bits as u64) as usize;
+        }
+        idx += 16;
+    }
+    idx
+}
+
 pub trait BaseGenerator {
     type T: Write;
     fn get_writer(&mut self) -> &mut Self::T;
@@ -93,91 +181,24 @@ pub trait BaseGenerator {
     fn write_string(&mut self, string: &str) -> io::Result<()> {
         stry!(self.write_char(b'"'));
         let mut string = string.as_bytes();
-        let mut len = string.len();
-        let mut idx = 0;
-
-        unsafe {
-            // Looking at the table above the lower 5 bits are entirely
-            // quote characters that gives us a bitmask of 0x1f for that
-            // region, only quote (`"`) and backslash (`\`) are not in
-            // this range.
-            let zero = _mm256_set1_epi8(0);
-            let lower_quote_range = _mm256_set1_epi8(0x1F as i8);
-            let quote = _mm256_set1_epi8(b'"' as i8);
-            let backslash = _mm256_set1_epi8(b'\\' as i8);
-            while len - idx >= 32 {
-                // Load 32 bytes of data;
-                #[allow(clippy::cast_ptr_alignment)]
-                let data: __m256i = _mm256_loadu_si256(string.as_ptr().add(idx) as *const __m256i);
-                // Test the data against being backslash and quote.
-                let bs_or_quote = _mm256_or_si256(
-                    _mm256_cmpeq_epi8(data, backslash),
-                    _mm256_cmpeq_epi8(data, quote),
-                );
-                // Now mask the data with the quote range (0x1F).
-                let in_quote_range = _mm256_and_si256(data, lower_quote_range);
-                // then test of the data is unchanged. aka: xor it with the
-                // Any field that was inside the quote range it will be zero
-                // now.
-                let is_unchanged = _mm256_xor_si256(data, in_quote_range);
-                let in_range = _mm256_cmpeq_epi8(is_unchanged, zero);
-                let quote_bits = _mm256_movemask_epi8(_mm256_or_si256(bs_or_quote, in_range));
-                if quote_bits != 0 {
-                    let quote_dist = trailingzeroes(quote_bits as u64) as usize;
-                    stry!(self.get_writer().write_all(&string[0..idx + quote_dist]));
-                    let ch = string[idx + quote_dist];
-                    match ESCAPED[ch as usize] {
+        loop {
+            let idx = clean_prefix_len(string);
+            match string.get(idx) {
+                Some(ch) if ESCAPED[*ch as usize] > 0 => {
+                    stry!(self.get_writer().write_all(&string[0..idx]));
+                    match ESCAPED[*ch as usize] {
                         b'u' => stry!(write!(self.get_writer(), "\\u{:04x}", ch)),
 
                         escape => stry!(self.write(&[b'\\', escape])),
                     };
-                    string = &string[idx + quote_dist + 1..];
-                    idx = 0;
-                    len = string.len();
-                } else {
-                    idx += 32;
+                    string = &string[idx + 1..];
                 }
-            }
-            // The case where we have a 16+ byte block
-            // we repeate the same logic as above but with
-            // only 16 bytes
-            let zero = _mm_set1_epi8(0);
-            let lower_quote_range = _mm_set1_epi8(0x1F as i8);
-            let quote = _mm_set1_epi8(b'"' as i8);
-            let backslash = _mm_set1_epi8(b'\\' as i8);
-            while len - idx > 16 {
-                // Load 16 bytes of data;
-                #[allow(clippy::cast_ptr_alignment)]
-                let data: __m128i = _mm_loadu_si128(string.as_ptr().add(idx) as *const __m128i);
-                // Test the data against being backslash and quote.
-                let bs_or_quote =
-                    _mm_or_si128(_mm_cmpeq_epi8(data, backslash), _mm_cmpeq_epi8(data, quote));
-                // Now mask the data with the quote range (0x1F).
-                let in_quote_range = _mm_and_si128(data, lower_quote_range);
-                // then test of the data is unchanged. aka: xor it with the
-                // Any field that was inside the quote range it will be zero
-                // now.
-                let is_unchanged = _mm_xor_si128(data, in_quote_range);
-                let in_range = _mm_cmpeq_epi8(is_unchanged, zero);
-                let quote_bits = _mm_movemask_epi8(_mm_or_si128(bs_or_quote, in_range));
-                if quote_bits != 0 {
-                    let quote_dist = trailingzeroes(quote_bits as u64) as usize;
-                    stry!(self.get_writer().write_all(&string[0..idx + quote_dist]));
-                    let ch = string[idx + quote_dist];
-                    match ESCAPED[ch as usize] {
-                        b'u' => stry!(write!(self.get_writer(), "\\u{:04x}", ch)),
-
-                        escape => stry!(self.write(&[b'\\', escape])),
-                    };
-                    string = &string[idx + quote_dist + 1..];
-                    idx = 0;
-                    len = string.len();
-                } else {
-                    idx += 16;
+                _ => {
+                    stry!(self.get_writer().write_all(&string[0..idx]));
+                    string = &string[idx..];
+                    break;
                 }
             }
-            stry!(self.get_writer().write_all(&string[0..idx]));
-            string = &string[idx..];
         }
         // Legacy code to handle the remainder of the code
         for (index, ch) in string.iter().enumerate() {
@@ -198,7 +219,7 @@ pub trait BaseGenerator {
     }
 
     #[inline(always)]
-    fn write_int(&mut self, num: i64) -> io::Result<()> {
+    fn write_int<I: itoa::Integer>(&mut self, num: I) -> io::Result<()> {
         itoa::write(self.get_writer(), num).map(|_| ())
         //self.write(num.to_string().as_bytes())
     }
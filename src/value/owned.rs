@@ -4,13 +4,15 @@ mod cmp;
 mod from;
 mod serialize;
 
-use crate::value::{ValueTrait, ValueType};
-use crate::{stry, unlikely, Deserializer, ErrorType, Result};
-use halfbrown::HashMap;
+use crate::value::{insert_new, ObjectMap, SeenKeys, ValueTrait, ValueType};
+use crate::{stry, unlikely, Deserializer, DuplicateKeys, ErrorType, Parser, Result};
 use std::fmt;
+use std::io::Read;
 use std::ops::Index;
 
-pub type Map = HashMap<String, Value>;
+/// The map objects are stored in, keys keep the order of the input with
+/// the `preserve_order` feature.
+pub type Map = ObjectMap<String, Value>;
 
 /// Parses a slice of bytes into a Value dom. This function will
 /// rewrite the slice to de-escape strings.
@@ -22,11 +24,27 @@ pub fn to_value(s: &mut [u8]) -> Result<Value> {
     OwnedDeserializer::from_deserializer(de).parse()
 }
 
+/// Parses an immutable slice of bytes into a Value dom, leaving the
+/// slice untouched.
+pub fn to_value_ref(s: &[u8]) -> Result<Value> {
+    let de = stry!(Deserializer::from_slice_ref(s));
+    OwnedDeserializer::from_deserializer(de).parse()
+}
+
+/// Reads a document from a `std::io::Read` and parses it into a Value
+/// dom, see `Parser::parse_owned_reader`.
+pub fn from_reader<R: Read>(reader: R) -> Result<Value> {
+    Parser::new().parse_owned_reader(reader)
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
     Null,
     Bool(bool),
     I64(i64),
+    U64(u64),
+    /// A number kept as its source text, see `Parser::raw_numbers`.
+    RawNumber(String),
     F64(f64),
     String(String),
     Array(Vec<Value>),
@@ -37,6 +55,14 @@ impl ValueTrait for Value {
     type Map = Map;
     type Array = Vec<Value>;
 
+    fn array() -> Self {
+        Value::Array(Vec::new())
+    }
+
+    fn object() -> Self {
+        Value::Object(Map::new())
+    }
+
     fn get(&self, k: &str) -> Option<&Value> {
         match self {
             Value::Object(m) => m.get(k),
@@ -57,6 +83,8 @@ impl ValueTrait for Value {
             Value::Bool(_) => ValueType::Bool,
             Value::F64(_) => ValueType::F64,
             Value::I64(_) => ValueType::I64,
+            Value::U64(_) => ValueType::U64,
+            Value::RawNumber(_) => ValueType::RawNumber,
             Value::String(_) => ValueType::String,
             Value::Array(_) => ValueType::Array,
             Value::Object(_) => ValueType::Object,
@@ -80,6 +108,8 @@ impl ValueTrait for Value {
     fn as_i64(&self) -> Option<i64> {
         match self {
             Value::I64(i) => Some(*i),
+            Value::U64(i) if *i <= i64::max_value() as u64 => Some(*i as i64),
+            Value::RawNumber(n) => n.parse().ok(),
             _ => None,
         }
     }
@@ -87,6 +117,8 @@ impl ValueTrait for Value {
     fn as_u64(&self) -> Option<u64> {
         match self {
             Value::I64(i) if *i >= 0 => Some(*i as u64),
+            Value::U64(i) => Some(*i),
+            Value::RawNumber(n) => n.parse().ok(),
             _ => None,
         }
     }
@@ -94,6 +126,7 @@ impl ValueTrait for Value {
     fn as_f64(&self) -> Option<f64> {
         match self {
             Value::F64(i) => Some(*i),
+            Value::RawNumber(n) => n.parse().ok(),
             _ => None,
         }
     }
@@ -102,13 +135,15 @@ impl ValueTrait for Value {
         match self {
             Value::F64(i) => Some(*i),
             Value::I64(i) => Some(*i as f64),
+            Value::U64(i) => Some(*i as f64),
+            Value::RawNumber(n) => n.parse().ok(),
             _ => None,
         }
     }
 
-    fn as_string(&self) -> Option<String> {
+    fn as_str(&self) -> Option<&str> {
         match self {
-            Value::String(s) => Some(s.to_string()),
+            Value::String(s) => Some(s),
             _ => None,
         }
     }
@@ -148,6 +183,8 @@ impl fmt::Display for Value {
             Value::Bool(false) => f.write_str("false"),
             Value::Bool(true) => f.write_str("true"),
             Value::I64(n) => f.write_str(&n.to_string()),
+            Value::U64(n) => f.write_str(&n.to_string()),
+            Value::RawNumber(n) => f.write_str(n),
             Value::F64(n) => f.write_str(&n.to_string()),
             Value::String(s) => write!(f, "{}", s),
             Value::Array(a) => write!(f, "{:?}", a),
@@ -164,13 +201,21 @@ impl Index<&str> for Value {
     }
 }
 
+impl Index<usize> for Value {
+    type Output = Value;
+    fn index(&self, index: usize) -> &Value {
+        static NULL: Value = Value::Null;
+        self.as_array().and_then(|a| a.get(index)).unwrap_or(&NULL)
+    }
+}
+
 impl Default for Value {
     fn default() -> Self {
         Value::Null
     }
 }
 
-struct OwnedDeserializer<'de> {
+pub(crate) struct OwnedDeserializer<'de> {
     de: Deserializer<'de>,
 }
 
@@ -178,6 +223,11 @@ impl<'de> OwnedDeserializer<'de> {
     pub fn from_deserializer(de: Deserializer<'de>) -> Self {
         Self { de }
     }
+
+    pub fn into_deserializer(self) -> Deserializer<'de> {
+        self.de
+    }
+
     #[cfg_attr(not(feature = "no-inline"), inline(always))]
     pub fn parse(&mut self) -> Result<Value> {
         match self.de.next_() {
@@ -186,7 +236,7 @@ impl<'de> OwnedDeserializer<'de> {
             b't' => Ok(Value::Bool(true)),
             b'f' => Ok(Value::Bool(false)),
             b'-' => self.de.parse_number_root(true).map(Value::from),
-            b'0'..=b'9' => self.de.parse_number_root(false).map(Value::from),
+            b'0'..=b'9' | b'N' | b'I' => self.de.parse_number_root(false).map(Value::from),
             b'[' => self.parse_array(),
             b'{' => self.parse_map(),
             _c => Err(self.de.error(ErrorType::UnexpectedCharacter)),
@@ -201,7 +251,7 @@ impl<'de> OwnedDeserializer<'de> {
             b't' => Ok(Value::Bool(true)),
             b'f' => Ok(Value::Bool(false)),
             b'-' => self.de.parse_number(true).map(Value::from),
-            b'0'..=b'9' => self.de.parse_number(false).map(Value::from),
+            b'0'..=b'9' | b'N' | b'I' => self.de.parse_number(false).map(Value::from),
             b'[' => self.parse_array(),
             b'{' => self.parse_map(),
             _c => Err(self.de.error(ErrorType::UnexpectedCharacter)),
@@ -239,13 +289,36 @@ impl<'de> OwnedDeserializer<'de> {
         // Since we checked if it's empty we know that we at least have one
         // element so we eat this
 
+        let mut seen = SeenKeys::default();
         for _ in 0..es {
             self.de.skip();
+            let key_idx = self.de.idx;
             let key = stry!(self.de.parse_str_());
             // We have to call parse short str twice since parse_short_str
             // does not move the cursor forward
             self.de.skip();
-            res.insert_nocheck(key.into(), stry!(self.parse_value()));
+            let value = stry!(self.parse_value());
+            if unlikely!(seen.insert(&key)) {
+                // the key may be a duplicate, only now do we need to look
+                match self.de.duplicate_keys {
+                    DuplicateKeys::KeepLast => {
+                        res.insert(key.into(), value);
+                    }
+                    DuplicateKeys::KeepFirst => {
+                        if !res.contains_key(&*key) {
+                            insert_new(&mut res, key.into(), value);
+                        }
+                    }
+                    DuplicateKeys::Error => {
+                        if res.contains_key(&*key) {
+                            return Err(self.de.error_at(key_idx, ErrorType::DuplicateKey));
+                        }
+                        insert_new(&mut res, key.into(), value);
+                    }
+                }
+            } else {
+                insert_new(&mut res, key.into(), value);
+            }
             self.de.skip();
         }
         Ok(Value::Object(res))
@@ -1,14 +1,17 @@
 use super::Value;
 use crate::numberparse::Number;
 use crate::BorrowedValue;
+use std::borrow::Cow;
 use std::iter::FromIterator;
 
-impl From<Number> for Value {
+impl From<Number<'_>> for Value {
     #[inline]
-    fn from(n: Number) -> Self {
+    fn from(n: Number<'_>) -> Self {
         match n {
             Number::F64(n) => Value::F64(n),
             Number::I64(n) => Value::I64(n),
+            Number::U64(n) => Value::U64(n),
+            Number::Raw(n) => Value::RawNumber(n.to_owned()),
         }
     }
 }
@@ -20,6 +23,8 @@ impl From<crate::BorrowedValue<'_>> for Value {
             BorrowedValue::Bool(b) => Value::Bool(b),
             BorrowedValue::F64(f) => Value::F64(f),
             BorrowedValue::I64(i) => Value::I64(i),
+            BorrowedValue::U64(i) => Value::U64(i),
+            BorrowedValue::RawNumber(n) => Value::RawNumber(n.into_owned()),
             BorrowedValue::String(s) => Value::from(s.to_string()),
             BorrowedValue::Array(a) => {
                 Value::Array(a.into_iter().map(|v| v.into()).collect::<Vec<Value>>())
@@ -53,6 +58,12 @@ impl From<&String> for Value {
     }
 }
 
+impl From<Cow<'_, str>> for Value {
+    fn from(s: Cow<'_, str>) -> Self {
+        Value::String(s.into_owned())
+    }
+}
+
 /********* atoms **********/
 
 impl From<bool> for Value {
@@ -137,7 +148,12 @@ impl From<u32> for Value {
 
 impl From<u64> for Value {
     fn from(i: u64) -> Self {
-        Value::I64(i as i64)
+        // values that fit are stored as I64, the same way the parser does
+        if i <= i64::max_value() as u64 {
+            Value::I64(i as i64)
+        } else {
+            Value::U64(i)
+        }
     }
 }
 
@@ -161,7 +177,7 @@ impl From<&u32> for Value {
 
 impl From<&u64> for Value {
     fn from(i: &u64) -> Self {
-        Value::I64(*i as i64)
+        Value::from(*i)
     }
 }
 
@@ -81,6 +81,8 @@ trait Generator: BaseGenerator {
             Value::Null => self.write(b"null"),
             Value::String(ref string) => self.write_string(string),
             Value::I64(number) => self.write_int(number),
+            Value::U64(number) => self.write_int(number),
+            Value::RawNumber(ref number) => self.write(number.as_bytes()),
             Value::F64(number) => self.write_float(number),
             Value::Bool(true) => self.write(b"true"),
             Value::Bool(false) => self.write(b"false"),
@@ -153,6 +155,18 @@ mod test {
     fn bool_false() {
         assert_eq!(Value::Bool(false).to_string(), "false")
     }
+    #[test]
+    fn numbers() {
+        assert_eq!(Value::I64(-42).to_string(), "-42");
+        assert_eq!(
+            Value::U64(u64::max_value()).to_string(),
+            "18446744073709551615"
+        );
+        assert_eq!(
+            Value::Array(vec![Value::U64(1 << 63)]).to_string_pp(),
+            "[\n  9223372036854775808\n]"
+        );
+    }
     fn assert_str(from: &str, to: &str) {
         assert_eq!(Value::String(from.into()).to_string(), to)
     }
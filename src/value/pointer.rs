@@ -0,0 +1,187 @@
+//! RFC 6901 JSON Pointers, used by the `pointer` methods of `ValueTrait`.
+use crate::value::{ArrayTrait, ObjectTrait, ValueTrait, ValueType};
+use crate::{Error, ErrorType, Result};
+use std::borrow::Cow;
+use std::mem;
+
+// Splits a pointer into its unescaped reference tokens, `None` if it
+// doesn't start with a `/` or has a `~` that isn't followed by `0` or `1`.
+pub(crate) fn tokens(pointer: &str) -> Option<Vec<Cow<'_, str>>> {
+    if pointer.is_empty() {
+        Some(Vec::new())
+    } else if pointer.starts_with('/') {
+        pointer[1..].split('/').map(unescape).collect()
+    } else {
+        None
+    }
+}
+
+fn unescape(token: &str) -> Option<Cow<'_, str>> {
+    if !token.contains('~') {
+        return Some(Cow::Borrowed(token));
+    }
+    let mut res = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next() {
+                Some('0') => res.push('~'),
+                Some('1') => res.push('/'),
+                _ => return None,
+            },
+            c => res.push(c),
+        }
+    }
+    Some(Cow::Owned(res))
+}
+
+// The array index a token stands for, `-` is the element after the last
+// one. Leading zeros aren't allowed.
+pub(crate) fn index(token: &str, len: usize) -> Option<usize> {
+    if token == "-" {
+        return Some(len);
+    }
+    let b = token.as_bytes();
+    if b.is_empty() || (b.len() > 1 && b[0] == b'0') || !b.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+// Like `index` but the element after the last one is allowed.
+fn index_to_set(token: &str, len: usize) -> Result<usize> {
+    match index(token, len) {
+        Some(i) if i <= len => Ok(i),
+        Some(_) => Err(Error::generic(ErrorType::IndexOutOfBounds)),
+        None => Err(Error::generic(ErrorType::InvalidPointer)),
+    }
+}
+
+pub(crate) fn get<'v, V: ValueTrait>(mut v: &'v V, tokens: &[Cow<str>]) -> Option<&'v V> {
+    for t in tokens {
+        v = if let Some(a) = v.as_array() {
+            a.get(index(t, a.len())?)?
+        } else {
+            v.as_object()?.get(t)?
+        };
+    }
+    Some(v)
+}
+
+pub(crate) fn get_mut<'v, V: ValueTrait>(
+    mut v: &'v mut V,
+    tokens: &[Cow<str>],
+) -> Option<&'v mut V> {
+    for t in tokens {
+        v = match v.kind() {
+            ValueType::Array => {
+                let a = v.as_array_mut()?;
+                let i = index(t, a.len())?;
+                a.get_mut(i)?
+            }
+            _ => v.as_object_mut()?.get_mut(t)?,
+        };
+    }
+    Some(v)
+}
+
+// The value under `token`, an empty object is created for it if there is
+// none yet.
+fn entry<'v, V: ValueTrait>(v: &'v mut V, token: &str) -> Result<&'v mut V> {
+    let missing = || Error::generic(ErrorType::ExpectedMap);
+    match v.kind() {
+        ValueType::Array => {
+            let a = stry!(v.as_array_mut().ok_or_else(missing));
+            let i = stry!(index_to_set(token, a.len()));
+            if i == a.len() {
+                a.push(V::object());
+            }
+            a.get_mut(i).ok_or_else(missing)
+        }
+        ValueType::Object => {
+            let m = stry!(v.as_object_mut().ok_or_else(missing));
+            if m.get(token).is_none() {
+                m.insert(token.to_string(), V::object());
+            }
+            m.get_mut(token).ok_or_else(missing)
+        }
+        _ => Err(missing()),
+    }
+}
+
+pub(crate) fn set<V: ValueTrait>(v: &mut V, tokens: &[Cow<str>], value: V) -> Result<Option<V>> {
+    let (last, path) = match tokens.split_last() {
+        Some(t) => t,
+        None => return Ok(Some(mem::replace(v, value))),
+    };
+    let mut v = v;
+    for t in path {
+        v = stry!(entry(v, t));
+    }
+    let missing = || Error::generic(ErrorType::ExpectedMap);
+    match v.kind() {
+        ValueType::Array => {
+            let a = stry!(v.as_array_mut().ok_or_else(missing));
+            let i = stry!(index_to_set(last, a.len()));
+            match a.get_mut(i) {
+                Some(old) => Ok(Some(mem::replace(old, value))),
+                None => {
+                    a.push(value);
+                    Ok(None)
+                }
+            }
+        }
+        ValueType::Object => {
+            let m = stry!(v.as_object_mut().ok_or_else(missing));
+            Ok(m.insert(last.to_string(), value))
+        }
+        _ => Err(missing()),
+    }
+}
+
+pub(crate) fn remove<V: ValueTrait>(v: &mut V, tokens: &[Cow<str>]) -> Option<V> {
+    let (last, path) = tokens.split_last()?;
+    let v = get_mut(v, path)?;
+    match v.kind() {
+        ValueType::Array => {
+            let a = v.as_array_mut()?;
+            match index(last, a.len()) {
+                Some(i) if i < a.len() => Some(a.remove(i)),
+                _ => None,
+            }
+        }
+        _ => v.as_object_mut()?.remove(last),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unescaping() {
+        assert_eq!(tokens(""), Some(vec![]));
+        assert_eq!(tokens("/"), Some(vec![Cow::Borrowed("")]));
+        assert_eq!(tokens("/a/b"), Some(vec![Cow::Borrowed("a"), "b".into()]));
+        assert_eq!(tokens("/a~1b/m~0n"), Some(vec!["a/b".into(), "m~n".into()]));
+        // escapes are read left to right, `~01` is `~1` and not `/`
+        assert_eq!(tokens("/~01"), Some(vec!["~1".into()]));
+        assert_eq!(tokens("a"), None);
+        assert_eq!(tokens("/~"), None);
+        assert_eq!(tokens("/~2"), None);
+        assert_eq!(tokens("/a/~/b"), None);
+    }
+
+    #[test]
+    fn indexes() {
+        assert_eq!(index("0", 3), Some(0));
+        assert_eq!(index("10", 3), Some(10));
+        assert_eq!(index("-", 3), Some(3));
+        assert_eq!(index("01", 3), None);
+        assert_eq!(index("", 3), None);
+        assert_eq!(index("+1", 3), None);
+        assert_eq!(index("-1", 3), None);
+        assert_eq!(index("1e0", 3), None);
+        assert_eq!(index("99999999999999999999999", 3), None);
+    }
+}
@@ -0,0 +1,22 @@
+use std::fs;
+use std::path::PathBuf;
+
+// The documents of the JSON checker suite in `dir` (data/pass or
+// data/fail) together with their paths, the ones jsonchecker.rs excludes
+// are left out here as well.
+pub fn documents(dir: &str) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| !p.to_string_lossy().contains("_EXCLUDE"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+    files
+        .into_iter()
+        .map(|p| {
+            let d = fs::read(&p).unwrap();
+            (p, d)
+        })
+        .collect()
+}
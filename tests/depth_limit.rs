@@ -0,0 +1,181 @@
+use simd_json::{ErrorType, OwnedValue, Parser};
+
+// `depth` levels of arrays, objects or both alternating around a number.
+fn nested(depth: usize, open: &[&str], close: &[&str]) -> Vec<u8> {
+    let mut s = String::new();
+    for i in 0..depth {
+        s.push_str(open[i % open.len()]);
+    }
+    s.push('1');
+    for i in (0..depth).rev() {
+        s.push_str(close[i % close.len()]);
+    }
+    s.into_bytes()
+}
+
+fn arrays(depth: usize) -> Vec<u8> {
+    nested(depth, &["["], &["]"])
+}
+
+fn objects(depth: usize) -> Vec<u8> {
+    nested(depth, &[r#"{"a":"#], &["}"])
+}
+
+fn mixed(depth: usize) -> Vec<u8> {
+    nested(depth, &["[", r#"{"a": "#], &["]", "}"])
+}
+
+// Debug builds use a lot more stack per level than release builds, so
+// documents nested right up to the limit are parsed on a thread that has
+// plenty of it.
+fn with_stack<F: FnOnce() + Send + 'static>(f: F) {
+    std::thread::Builder::new()
+        .stack_size(128 << 20)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn assert_depth_limit(r: simd_json::Result<OwnedValue>) {
+    assert_eq!(r.unwrap_err().error_type(), &ErrorType::DepthLimit);
+}
+
+#[test]
+fn default_limit() {
+    with_stack(default_limit_);
+}
+
+fn default_limit_() {
+    for doc in &[arrays, objects, mixed] {
+        let mut d = doc(1024);
+        let v = simd_json::to_owned_value(&mut d).unwrap();
+        let mut d = doc(1024);
+        assert_eq!(
+            OwnedValue::from(simd_json::to_borrowed_value(&mut d).unwrap()),
+            v
+        );
+        #[cfg(feature = "serde_impl")]
+        {
+            let mut d = doc(1024);
+            assert_eq!(
+                simd_json::serde::from_slice::<OwnedValue>(&mut d).unwrap(),
+                v
+            );
+            let mut d = doc(1024);
+            assert!(simd_json::serde::from_slice::<serde_json::Value>(&mut d).is_ok());
+            let mut d = doc(1025);
+            assert_depth_limit(simd_json::serde::from_slice(&mut d));
+        }
+
+        let mut d = doc(1025);
+        assert_depth_limit(simd_json::to_owned_value(&mut d));
+        let d = doc(1025);
+        assert_depth_limit(simd_json::to_owned_value_ref(&d));
+        assert_depth_limit(simd_json::to_owned_value_from_reader(&d[..]));
+        let e = simd_json::ondemand::Document::from_slice(&d).err().unwrap();
+        assert_eq!(e.error_type(), &ErrorType::DepthLimit);
+        let mut d = doc(1025);
+        let e = simd_json::to_tape(&mut d).err().unwrap();
+        assert_eq!(e.error_type(), &ErrorType::DepthLimit);
+    }
+}
+
+// Without a limit these would overflow the stack.
+#[test]
+fn very_deep() {
+    for doc in &[arrays, objects, mixed] {
+        let mut d = doc(1_000_000);
+        assert_depth_limit(simd_json::to_owned_value(&mut d));
+        let mut d = doc(1_000_000);
+        let r = simd_json::to_borrowed_value(&mut d).map(OwnedValue::from);
+        assert_depth_limit(r);
+        #[cfg(feature = "serde_impl")]
+        {
+            let mut d = doc(1_000_000);
+            assert_depth_limit(simd_json::serde::from_slice(&mut d));
+            let mut d = doc(1_000_000);
+            let e = simd_json::serde::from_slice::<serde_json::Value>(&mut d).unwrap_err();
+            assert_eq!(e.error_type(), &ErrorType::DepthLimit);
+        }
+    }
+    // unclosed documents hit the limit before the missing end
+    let mut d = vec![b'['; 1_000_000];
+    assert_depth_limit(simd_json::to_owned_value(&mut d));
+}
+
+#[test]
+fn error_location() {
+    let mut d = mixed(2000);
+    let e = simd_json::to_owned_value(&mut d).unwrap_err();
+    // 512 arrays and 512 objects are fine, the next array is one too many
+    assert_eq!(e.offset(), Some(512 + 512 * r#"{"a": "#.len()));
+    assert_eq!(e.pointer().map(|p| p.len()), Some(512 * 2 + 512 * 2));
+}
+
+#[test]
+fn configured_limit() {
+    let mut parser = Parser::new().max_depth(3);
+    for depth in 0..=3 {
+        for doc in &[arrays, objects, mixed] {
+            let mut d = doc(depth);
+            assert!(parser.parse_owned(&mut d).is_ok());
+            let mut d = doc(depth);
+            assert!(parser.parse_borrowed(&mut d).is_ok());
+            #[cfg(feature = "serde_impl")]
+            {
+                let mut d = doc(depth);
+                assert!(parser.deserialize::<OwnedValue>(&mut d).is_ok());
+            }
+        }
+    }
+    for doc in &[arrays, objects, mixed] {
+        let mut d = doc(4);
+        assert_depth_limit(parser.parse_owned(&mut d));
+        #[cfg(feature = "serde_impl")]
+        {
+            let mut d = doc(4);
+            assert_depth_limit(parser.deserialize(&mut d));
+        }
+        let d = doc(4);
+        assert_depth_limit(parser.parse_owned_reader(&d[..]));
+    }
+
+    // scalars are not nested at all
+    let mut parser = Parser::new().max_depth(0);
+    let mut d = b"1".to_vec();
+    assert!(parser.parse_owned(&mut d).is_ok());
+    let mut d = b"[]".to_vec();
+    assert_depth_limit(parser.parse_owned(&mut d));
+
+    // the limit can be raised above the default
+    with_stack(|| {
+        let mut parser = Parser::new().max_depth(usize::max_value());
+        let mut d = arrays(1100);
+        assert!(parser.parse_owned(&mut d).is_ok());
+    });
+}
+
+#[test]
+fn many_documents() {
+    let mut parser = Parser::new().max_depth(2);
+    let mut d = b"[[1]]\n[[[1]]]\n{\"a\": [1]}\n".to_vec();
+    let docs: Vec<_> = parser.parse_many_owned(&mut d).collect();
+    assert_eq!(docs.len(), 3);
+    assert!(docs[0].is_ok());
+    assert_eq!(
+        docs[1].as_ref().unwrap_err().error_type(),
+        &ErrorType::DepthLimit
+    );
+    assert!(docs[2].is_ok());
+
+    let input = &b"[[1]] [[[1]]] {\"a\": [1]}"[..];
+    let docs: Vec<_> = parser.parse_many_owned_reader(input).collect();
+    assert_eq!(docs.len(), 3);
+    assert!(docs[0].is_ok());
+    assert_eq!(
+        docs[1].as_ref().unwrap_err().error_type(),
+        &ErrorType::DepthLimit
+    );
+    assert!(docs[2].is_ok());
+}
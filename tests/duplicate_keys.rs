@@ -0,0 +1,251 @@
+#[cfg(feature = "serde_impl")]
+use serde::Deserialize;
+use simd_json::{DuplicateKeys, ErrorType, OwnedValue, Parser};
+#[cfg(feature = "serde_impl")]
+use std::collections::HashMap;
+
+#[cfg(feature = "serde_impl")]
+#[derive(Deserialize, Debug, PartialEq)]
+struct Point {
+    x: u8,
+    y: u8,
+}
+
+// The expected value, parsed with the default parser.
+fn value(input: &str) -> OwnedValue {
+    simd_json::to_owned_value(&mut input.as_bytes().to_vec()).unwrap()
+}
+
+// Parses the input with every value builder and the serde path and checks
+// they agree with `expected`.
+fn check(policy: DuplicateKeys, input: &str, expected: &OwnedValue) {
+    let mut parser = Parser::new().duplicate_keys(policy);
+    let mut d = input.as_bytes().to_vec();
+    assert_eq!(&parser.parse_owned(&mut d).unwrap(), expected);
+    let mut d = input.as_bytes().to_vec();
+    let v = parser.parse_borrowed(&mut d).map(OwnedValue::from).unwrap();
+    assert_eq!(&v, expected);
+    #[cfg(feature = "serde_impl")]
+    {
+        let mut d = input.as_bytes().to_vec();
+        assert_eq!(&parser.deserialize::<OwnedValue>(&mut d).unwrap(), expected);
+        let mut d = input.as_bytes().to_vec();
+        let v: serde_json::Value = parser.deserialize(&mut d).unwrap();
+        assert_eq!(v.to_string(), expected.to_string());
+    }
+    assert_eq!(
+        &parser.parse_owned_reader(input.as_bytes()).unwrap(),
+        expected
+    );
+    let mut d = input.as_bytes().to_vec();
+    let tape = parser.parse_tape(&mut d).unwrap();
+    assert_eq!(&OwnedValue::from(tape.root()), expected);
+}
+
+// Checks every path fails with a duplicate key at `offset`.
+fn check_err(input: &str, offset: usize, pointer: &str) {
+    let mut parser = Parser::new().duplicate_keys(DuplicateKeys::Error);
+    let errors = vec![
+        parser
+            .parse_owned(&mut input.as_bytes().to_vec())
+            .unwrap_err(),
+        parser
+            .parse_borrowed(&mut input.as_bytes().to_vec())
+            .map(OwnedValue::from)
+            .unwrap_err(),
+        parser.parse_owned_reader(input.as_bytes()).unwrap_err(),
+        parser
+            .parse_tape(&mut input.as_bytes().to_vec())
+            .unwrap_err(),
+    ];
+    #[cfg(feature = "serde_impl")]
+    let errors = {
+        let mut errors = errors;
+        errors.push(
+            parser
+                .deserialize::<OwnedValue>(&mut input.as_bytes().to_vec())
+                .unwrap_err(),
+        );
+        errors.push(
+            parser
+                .deserialize::<serde_json::Value>(&mut input.as_bytes().to_vec())
+                .unwrap_err(),
+        );
+        errors
+    };
+    for e in errors {
+        assert_eq!(e.error_type(), &ErrorType::DuplicateKey);
+        assert_eq!(e.offset(), Some(offset));
+        assert_eq!(e.pointer(), Some(pointer));
+    }
+}
+
+#[test]
+fn keep_last() {
+    let p = DuplicateKeys::KeepLast;
+    assert_eq!(DuplicateKeys::default(), p);
+    check(
+        p,
+        r#"{"a": 1, "b": 2, "a": 3}"#,
+        &value(r#"{"a": 3, "b": 2}"#),
+    );
+    check(p, r#"{"a": 1, "a": 2, "a": 3}"#, &value(r#"{"a": 3}"#));
+    check(
+        p,
+        r#"{"a": {"b": 1, "b": [2]}, "a": {"c": 1, "c": {"d": 2}}}"#,
+        &value(r#"{"a": {"c": {"d": 2}}}"#),
+    );
+    check(
+        p,
+        r#"[{"a": 1, "a": 2}, {"a": 3}]"#,
+        &value(r#"[{"a": 2}, {"a": 3}]"#),
+    );
+    // keys are compared after unescaping
+    check(p, r#"{"a": 1, "\u0061": 2}"#, &value(r#"{"a": 2}"#));
+}
+
+#[test]
+fn keep_first() {
+    let p = DuplicateKeys::KeepFirst;
+    check(
+        p,
+        r#"{"a": 1, "b": 2, "a": 3}"#,
+        &value(r#"{"a": 1, "b": 2}"#),
+    );
+    check(p, r#"{"a": 1, "a": 2, "a": 3}"#, &value(r#"{"a": 1}"#));
+    check(
+        p,
+        r#"{"a": {"b": 1, "b": [2]}, "a": {"c": 1, "c": {"d": 2}}}"#,
+        &value(r#"{"a": {"b": 1}}"#),
+    );
+    check(
+        p,
+        r#"[{"a": 1, "a": 2}, {"a": 3}]"#,
+        &value(r#"[{"a": 1}, {"a": 3}]"#),
+    );
+    check(p, r#"{"a": 1, "\u0061": 2}"#, &value(r#"{"a": 1}"#));
+}
+
+#[test]
+fn error() {
+    check(
+        DuplicateKeys::Error,
+        r#"{"a": 1, "b": {"a": 2}, "c": [{"a": 3}]}"#,
+        &value(r#"{"a": 1, "b": {"a": 2}, "c": [{"a": 3}]}"#),
+    );
+    check_err(r#"{"a": 1, "a": 2}"#, 9, "");
+    check_err(r#"{"b": 1, "a": 2, "b": 3, "a": 4}"#, 17, "");
+    check_err(r#"{"\u0061": 1, "a": 2}"#, 14, "");
+    check_err(r#"[1, {"x": {"a": 1, "a": 2}}]"#, 19, "/1/x");
+}
+
+#[cfg(feature = "serde_impl")]
+#[test]
+fn structs_and_maps() {
+    let input = r#"{"x": 1, "y": 2, "x": 3}"#;
+    let mut parser = Parser::new();
+    // structs fail on a repeated field by default, like with serde_json
+    let e = parser
+        .deserialize::<Point>(&mut input.as_bytes().to_vec())
+        .unwrap_err();
+    assert_eq!(
+        e.error_type(),
+        &ErrorType::Serde("duplicate field `x`".to_string())
+    );
+    assert!(serde_json::from_str::<Point>(input).is_err());
+    let m: HashMap<String, u8> = parser.deserialize(&mut input.as_bytes().to_vec()).unwrap();
+    assert_eq!(m.len(), 2);
+    assert_eq!(m["x"], 3);
+
+    let mut parser = Parser::new().duplicate_keys(DuplicateKeys::KeepFirst);
+    let p: Point = parser.deserialize(&mut input.as_bytes().to_vec()).unwrap();
+    assert_eq!(p, Point { x: 1, y: 2 });
+
+    let mut parser = Parser::new().duplicate_keys(DuplicateKeys::Error);
+    let e = parser
+        .deserialize::<Point>(&mut input.as_bytes().to_vec())
+        .unwrap_err();
+    assert_eq!(e.error_type(), &ErrorType::DuplicateKey);
+    let e = parser
+        .deserialize::<HashMap<String, u8>>(&mut input.as_bytes().to_vec())
+        .unwrap_err();
+    assert_eq!(e.error_type(), &ErrorType::DuplicateKey);
+}
+
+#[test]
+fn large_objects() {
+    // more members than are compared pairwise
+    let members: Vec<String> = (0..40)
+        .map(|i| format!(r#""k{}": {}"#, i % 30, i))
+        .collect();
+    let input = format!(
+        r#"{{"o": {{{}}}, "k1": [{{"k1": 1}}]}}"#,
+        members.join(", ")
+    );
+    let check = |policy: DuplicateKeys, first: usize| {
+        let members: Vec<String> = (0..30)
+            .map(|i| format!(r#""k{}":{}"#, i, if i < 10 { i + first } else { i }))
+            .collect();
+        let expected = format!(r#"{{"o":{{{}}},"k1":[{{"k1":1}}]}}"#, members.join(","));
+        let mut parser = Parser::new().duplicate_keys(policy);
+        #[cfg(feature = "serde_impl")]
+        {
+            let mut d = input.as_bytes().to_vec();
+            let v: serde_json::Value = parser.deserialize(&mut d).unwrap();
+            assert_eq!(
+                v,
+                serde_json::from_str::<serde_json::Value>(&expected).unwrap()
+            );
+        }
+        let expected = value(&expected);
+        let mut d = input.as_bytes().to_vec();
+        assert_eq!(parser.parse_owned(&mut d).unwrap(), expected);
+        #[cfg(feature = "serde_impl")]
+        {
+            let mut d = input.as_bytes().to_vec();
+            assert_eq!(parser.deserialize::<OwnedValue>(&mut d).unwrap(), expected);
+        }
+    };
+    check(DuplicateKeys::KeepLast, 30);
+    check(DuplicateKeys::KeepFirst, 0);
+    check_err(&input, input.find(r#""k0": 30"#).unwrap(), "/o");
+}
+
+#[test]
+fn lookups() {
+    let input = r#"{"a": 1, "b": {"c": 2}, "a": 3}"#;
+    let mut d = input.as_bytes().to_vec();
+    let tape = Parser::new().parse_tape(&mut d).unwrap();
+    assert_eq!(tape.root().get("a").and_then(|a| a.as_i64()), Some(3));
+    assert_eq!(tape.root().as_object().map(|o| o.len()), Some(2));
+    let mut d = input.as_bytes().to_vec();
+    let mut parser = Parser::new().duplicate_keys(DuplicateKeys::KeepFirst);
+    let tape = parser.parse_tape(&mut d).unwrap();
+    assert_eq!(tape.root().get("a").and_then(|a| a.as_i64()), Some(1));
+    assert_eq!(tape.root().as_object().map(|o| o.len()), Some(2));
+    assert_eq!(
+        tape.root()
+            .get("b")
+            .and_then(|b| b.get("c"))
+            .and_then(|c| c.as_i64()),
+        Some(2)
+    );
+
+    let doc = simd_json::ondemand::Document::from_slice(input.as_bytes()).unwrap();
+    assert_eq!(doc.get("a").and_then(|a| a.as_i64()), Ok(3));
+    assert_eq!(doc.root().len(), Ok(2));
+    assert_eq!(doc.root().to_owned_value(), Ok(value(input)));
+    let keys: Vec<_> = doc
+        .root()
+        .as_object()
+        .unwrap()
+        .map(|m| m.unwrap().0.into_owned())
+        .collect();
+    assert_eq!(keys, vec!["b", "a"]);
+    let input = r#"[{"a": [{"b": 1, "b": 2}], "a": {"c": 1, "c": 2, "d": 3}}]"#;
+    let doc = simd_json::ondemand::Document::from_slice(input.as_bytes()).unwrap();
+    let a = doc.get_idx(0).and_then(|o| o.get("a")).unwrap();
+    assert_eq!(a.len(), Ok(2));
+    assert_eq!(a.get("c").and_then(|c| c.as_i64()), Ok(2));
+    assert_eq!(doc.root().to_owned_value(), Ok(value(input)));
+}
@@ -0,0 +1,128 @@
+use proptest::prelude::*;
+use simd_json::{OwnedValue, Parser};
+use std::fs::File;
+use std::io::Read;
+
+// Compares every float the parser produces against `str::parse::<f64>` on
+// the text of the same number, read back through raw number mode.
+fn compare(parsed: &OwnedValue, raw: &OwnedValue) -> usize {
+    match (parsed, raw) {
+        (OwnedValue::F64(f), OwnedValue::RawNumber(text)) => {
+            let expected: f64 = text.parse().unwrap();
+            assert_eq!(f.to_bits(), expected.to_bits(), "parsing {}", text);
+            1
+        }
+        (OwnedValue::Array(a), OwnedValue::Array(b)) => {
+            assert_eq!(a.len(), b.len());
+            a.iter().zip(b.iter()).map(|(a, b)| compare(a, b)).sum()
+        }
+        (OwnedValue::Object(a), OwnedValue::Object(b)) => {
+            assert_eq!(a.len(), b.len());
+            a.iter().map(|(k, v)| compare(v, &b[k])).sum()
+        }
+        _ => 0,
+    }
+}
+
+macro_rules! floats {
+    ($name:ident, $file:expr) => {
+        #[test]
+        fn $name() {
+            let mut d = Vec::new();
+            File::open($file).unwrap().read_to_end(&mut d).unwrap();
+            let mut raw = d.clone();
+            let parsed = simd_json::to_owned_value(&mut d).unwrap();
+            let raw = Parser::new()
+                .raw_numbers(true)
+                .parse_owned(&mut raw)
+                .unwrap();
+            assert!(compare(&parsed, &raw) > 0);
+        }
+    };
+}
+
+floats!(numbers, "data/numbers.json");
+floats!(canada, "data/canada.json");
+floats!(mesh, "data/mesh.json");
+
+#[test]
+fn hard_cases() {
+    let cases = [
+        "1e23",
+        "2.2250738585072011e-308",
+        "4.9406564584124654e-324",
+        "1.7976931348623157e308",
+        "9007199254740993.0",
+        "-0.0",
+        "0.1e-500",
+        "1.00000000000000011102230246251565404236316680908203125",
+        "1.00000000000000011102230246251565404236316680908203124",
+        "1.00000000000000011102230246251565404236316680908203126",
+        "7.3177701707893310e+15",
+        "123456789012345678901234567890e-10",
+        "18446744073709551616.0",
+        "12345678901234567e0",
+        "9223372036854775807e0",
+        "-9223372036854775809.5",
+        "1e0",
+        "[18446744073709551616.0, 99999999999999999999e-5, 0e0]",
+        "[0.1, 0.2, 0.30000000000000004, 1e-7, 5e-324]",
+    ];
+    for case in cases.iter() {
+        let mut d = case.as_bytes().to_vec();
+        let mut raw = d.clone();
+        let parsed = simd_json::to_owned_value(&mut d).unwrap();
+        let raw = Parser::new()
+            .raw_numbers(true)
+            .parse_owned(&mut raw)
+            .unwrap();
+        assert!(compare(&parsed, &raw) > 0, "{}", case);
+    }
+}
+
+#[test]
+fn out_of_range() {
+    let mut d = b"1e309".to_vec();
+    assert!(simd_json::to_owned_value(&mut d).is_err());
+    let mut d = b"-123456789012345678901234567890e300".to_vec();
+    assert!(simd_json::to_owned_value(&mut d).is_err());
+    let mut d = b"1e-400".to_vec();
+    assert_eq!(
+        simd_json::to_owned_value(&mut d).unwrap(),
+        OwnedValue::F64(0.0)
+    );
+}
+
+// Checks the float we parse is the one `str::parse::<f64>` gives us, at the
+// root and inside an array.
+fn differential(text: &str) -> std::result::Result<(), TestCaseError> {
+    let expected: f64 = text.parse().unwrap();
+    for input in &[text.to_string(), format!("[{}]", text)] {
+        let r = simd_json::to_owned_value(&mut input.as_bytes().to_vec());
+        if expected.is_infinite() {
+            prop_assert!(r.is_err(), "{}", input);
+            continue;
+        }
+        let f = match r {
+            Ok(OwnedValue::F64(f)) => f,
+            Ok(OwnedValue::Array(mut a)) => match a.pop() {
+                Some(OwnedValue::F64(f)) => f,
+                v => return Err(TestCaseError::fail(format!("{}: {:?}", input, v))),
+            },
+            r => return Err(TestCaseError::fail(format!("{}: {:?}", input, r))),
+        };
+        prop_assert_eq!(f.to_bits(), expected.to_bits(), "{}", input);
+    }
+    Ok(())
+}
+
+proptest! {
+    // Every number has a fraction or an exponent so it has to become a
+    // float, the mantissas go well past what fits into a `u64`.
+    #[test]
+    fn random_floats(
+        text in "-?(0|[1-9][0-9]{0,24})(\\.[0-9]{1,24}([eE][+-]?[0-9]{1,3})?|[eE][+-]?[0-9]{1,3})"
+    ) {
+        differential(&text)?;
+    }
+}
@@ -0,0 +1,103 @@
+use simd_json::{ArrayTrait, BorrowedValue, ErrorType, ObjectTrait, OwnedValue, ValueTrait};
+
+// Everything below is written once against `ValueTrait` and run for both
+// value types.
+
+fn count_strings<V: ValueTrait>(v: &V) -> usize {
+    if v.is_string() {
+        1
+    } else if let Some(a) = v.as_array() {
+        a.iter().map(count_strings).sum()
+    } else if let Some(o) = v.as_object() {
+        o.values().map(count_strings).sum()
+    } else {
+        0
+    }
+}
+
+fn sorted_keys<V: ValueTrait>(v: &V) -> Vec<String> {
+    let mut keys: Vec<String> = v
+        .as_object()
+        .map(|o| o.keys().map(String::from).collect())
+        .unwrap_or_default();
+    keys.sort();
+    keys
+}
+
+fn build<V: ValueTrait>() -> V {
+    let mut inner = V::array();
+    inner.push(1).unwrap();
+    inner.push("two".to_string()).unwrap();
+    inner.push(V::object()).unwrap();
+    let mut v = V::object();
+    assert!(v.insert("a".to_string(), inner).unwrap().is_none());
+    assert!(v.insert("b".to_string(), true).unwrap().is_none());
+    assert_eq!(
+        v.insert("b".to_string(), false)
+            .unwrap()
+            .and_then(|b| b.as_bool()),
+        Some(true)
+    );
+    v
+}
+
+fn check<V: ValueTrait>(mut v: V) {
+    assert_eq!(v.len(), Some(3));
+    assert_eq!(v.is_empty(), Some(false));
+    assert_eq!(sorted_keys(&v), vec!["a", "b", "c"]);
+    assert_eq!(count_strings(&v), 1);
+
+    assert_eq!(v["a"][0].as_i64(), Some(1));
+    assert_eq!(v["a"][1].as_str(), Some("two"));
+    assert_eq!(v["a"][1].as_string(), Some("two".to_string()));
+    assert_eq!(v["a"][2].is_empty(), Some(true));
+    assert!(v["a"][3].is_null());
+    assert!(v["b"][0].is_null());
+    assert!(v["missing"].is_null());
+    assert_eq!(v["a"].as_array().map(ArrayTrait::len), Some(3));
+    assert!(v["b"].len().is_none());
+
+    let e = v.push(1).unwrap_err();
+    assert_eq!(e.error_type(), &ErrorType::ExpectedArray);
+    let a = v.get_mut("a").unwrap();
+    assert_eq!(a.pop().unwrap().and_then(|o| o.len()), Some(0));
+    let e = a.insert("x".to_string(), 1).map(|_| ()).unwrap_err();
+    assert_eq!(e.error_type(), &ErrorType::ExpectedMap);
+    assert_eq!(
+        a.remove("x").map(|_| ()).unwrap_err().error_type(),
+        &ErrorType::ExpectedMap
+    );
+
+    assert_eq!(v.remove("c").unwrap().and_then(|c| c.as_f64()), Some(0.5));
+    assert!(v.remove("c").unwrap().is_none());
+    assert_eq!(sorted_keys(&v), vec!["a", "b"]);
+    let o = v.as_object().unwrap();
+    assert_eq!(o.iter().filter(|(_, v)| v.is_array()).count(), 1);
+    assert_eq!(o.get("b").and_then(|b| b.as_bool()), Some(false));
+}
+
+#[test]
+fn owned() {
+    let mut v: OwnedValue = build();
+    v.insert("c".to_string(), 0.5).unwrap();
+    let mut d = br#"{"a": [1, "two", {}], "b": false, "c": 0.5}"#.to_vec();
+    assert_eq!(v, simd_json::to_owned_value(&mut d).unwrap());
+    check(v);
+}
+
+#[test]
+fn borrowed() {
+    let mut v: BorrowedValue = build();
+    // borrowed values can take keys without copying them
+    v.insert("c", 0.5).unwrap();
+    let mut d = br#"{"a": [1, "two", {}], "b": false, "c": 0.5}"#.to_vec();
+    assert_eq!(v, simd_json::to_borrowed_value(&mut d).unwrap());
+    check(v);
+}
+
+#[test]
+fn parsed() {
+    let mut d = br#"{"a": [1, "two", {}], "b": false, "c": 0.5}"#.to_vec();
+    check(simd_json::to_owned_value(&mut d.clone()).unwrap());
+    check(simd_json::to_borrowed_value(&mut d).unwrap());
+}
@@ -0,0 +1,524 @@
+#[cfg(feature = "serde_impl")]
+use serde::Deserialize;
+use simd_json::{DuplicateKeys, ErrorType, Limits, OwnedValue, Parser, ValueTrait};
+use std::fs::{self, File};
+use std::io::{self, Read};
+
+fn json5() -> Parser {
+    Parser::new().json5(true)
+}
+
+// The expected value, parsed as plain JSON.
+fn value(input: &str) -> OwnedValue {
+    simd_json::to_owned_value(&mut input.as_bytes().to_vec()).unwrap()
+}
+
+fn read(path: &str) -> Vec<u8> {
+    let mut d = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut d).unwrap();
+    d
+}
+
+// Hands out the input one byte at a time so every token spans reads.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Read for Bytes<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.split_first() {
+            Some((c, rest)) if !buf.is_empty() => {
+                buf[0] = *c;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+// Parses the input in every way there is and checks they all agree,
+// returns the value.
+fn parse(input: &[u8]) -> OwnedValue {
+    let mut parser = json5();
+    let owned = parser.parse_owned(&mut input.to_vec()).unwrap();
+    let mut d = input.to_vec();
+    let borrowed = parser.parse_borrowed(&mut d).unwrap();
+    assert_eq!(OwnedValue::from(borrowed), owned);
+    #[cfg(feature = "serde_impl")]
+    {
+        let mut d = input.to_vec();
+        assert_eq!(parser.deserialize::<OwnedValue>(&mut d).unwrap(), owned);
+    }
+    let tape = parser.parse_tape(&mut input.to_vec()).unwrap();
+    assert_eq!(OwnedValue::from(tape.root()), owned);
+    assert_eq!(parser.parse_owned_reader(input).unwrap(), owned);
+    assert_eq!(parser.parse_owned_reader(Bytes(input)).unwrap(), owned);
+    let docs: Vec<_> = parser
+        .parse_many_owned_reader(Bytes(input))
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(docs, vec![owned.clone()]);
+    owned
+}
+
+fn fails(input: &[u8]) -> bool {
+    let mut parser = json5();
+    let failed = parser.parse_owned(&mut input.to_vec()).is_err()
+        && parser.parse_borrowed(&mut input.to_vec()).is_err()
+        && parser.parse_tape(&mut input.to_vec()).is_err()
+        && parser.parse_owned_reader(input).is_err()
+        && parser.parse_owned_reader(Bytes(input)).is_err();
+    #[cfg(feature = "serde_impl")]
+    let failed = failed
+        && parser
+            .deserialize::<OwnedValue>(&mut input.to_vec())
+            .is_err();
+    failed
+}
+
+// Every path agrees on the value of the document, and on the value of
+// the JSON next to it if there is one.
+macro_rules! pass {
+    ($file:ident) => {
+        #[test]
+        fn $file() {
+            let path = concat!("data/json5/pass/", stringify!($file));
+            let v = parse(&read(&format!("{}.json5", path)));
+            if let Ok(mut expected) = fs::read(format!("{}.json", path)) {
+                assert_eq!(simd_json::to_owned_value(&mut expected).unwrap(), v);
+            }
+        }
+    };
+}
+
+macro_rules! fail {
+    ($file:ident) => {
+        #[test]
+        fn $file() {
+            let path = concat!("data/json5/fail/", stringify!($file), ".json5");
+            assert!(fails(&read(path)));
+        }
+    };
+}
+
+pass!(block_comment_following_array_element);
+pass!(block_comment_following_top_level_value);
+pass!(block_comment_in_string);
+pass!(block_comment_preceding_top_level_value);
+pass!(block_comment_with_asterisks);
+pass!(byte_order_mark);
+pass!(comment_cr_line_terminator);
+pass!(comment_crlf_line_terminator);
+pass!(comment_ls_line_terminator);
+pass!(comment_ps_line_terminator);
+pass!(cr_new_lines);
+pass!(crlf_new_lines);
+pass!(double_quoted_string_with_single_quote);
+pass!(duplicate_keys);
+pass!(empty_array);
+pass!(empty_object);
+pass!(escaped_cr);
+pass!(escaped_cr_only);
+pass!(escaped_lf);
+pass!(escaped_ls_ps);
+pass!(escaped_single_quoted_string);
+pass!(escaped_unquoted_keys);
+pass!(escapes);
+pass!(exponents);
+pass!(float);
+pass!(float_leading_decimal_point);
+pass!(float_leading_decimal_point_with_exponent);
+pass!(float_leading_zero);
+pass!(float_trailing_decimal_point);
+pass!(float_trailing_decimal_point_with_exponent);
+pass!(float_with_exponent);
+pass!(hex_escapes);
+pass!(hexadecimal);
+pass!(hexadecimal_huge);
+pass!(hexadecimal_large);
+pass!(hexadecimal_lowercase_letter);
+pass!(hexadecimal_uppercase_x);
+pass!(hexadecimal_with_integer_exponent);
+pass!(infinity);
+pass!(inline_comment_at_end_without_newline);
+pass!(inline_comment_following_array_element);
+pass!(inline_comment_following_top_level_value);
+pass!(inline_comment_in_string);
+pass!(inline_comment_preceding_top_level_value);
+pass!(integer);
+pass!(integer_large);
+pass!(literal_followed_by_comment);
+pass!(literals);
+pass!(multi_line_string);
+pass!(negative_float);
+pass!(negative_float_leading_decimal_point);
+pass!(negative_hexadecimal);
+pass!(negative_infinity);
+pass!(negative_integer);
+pass!(negative_zero);
+pass!(nested);
+pass!(nested_trailing_commas);
+pass!(non_escape_characters);
+pass!(non_finite_in_containers);
+pass!(npm_package);
+pass!(null_escape_before_non_digit);
+pass!(positive_float);
+pass!(positive_float_leading_decimal_point);
+pass!(positive_hexadecimal);
+pass!(positive_infinity);
+pass!(positive_integer);
+pass!(positive_zero_float_trailing_decimal_point);
+pass!(quoted_keys);
+pass!(raw_separators_in_string);
+pass!(readme_example);
+pass!(regular_array);
+pass!(reserved_unquoted_key);
+pass!(single_quoted_string);
+pass!(single_quoted_string_with_double_quote);
+pass!(strings_with_comments);
+pass!(trailing_comma_array);
+pass!(trailing_comma_object);
+pass!(unicode_escapes);
+pass!(unicode_unquoted_keys);
+pass!(unquoted_keys);
+pass!(whitespace);
+pass!(zero_float);
+pass!(zero_integer);
+
+fail!(array_as_key);
+fail!(backtick_string);
+fail!(binary);
+fail!(block_comment_not_nested);
+fail!(comment_splits_value);
+fail!(decimal_point_without_digits_exponent);
+fail!(digit_separator);
+fail!(double_plus);
+fail!(double_sign);
+fail!(double_trailing_comma_array);
+fail!(double_trailing_comma_object);
+fail!(empty);
+fail!(escaped_digit_starting_key);
+fail!(escaped_invalid_key_char);
+fail!(exponent_sign_without_digits);
+fail!(exponent_without_digits);
+fail!(function_call);
+fail!(hexadecimal_empty);
+fail!(hexadecimal_invalid_digit);
+fail!(hexadecimal_with_fraction);
+fail!(incomplete_infinity);
+fail!(invalid_hex_escape);
+fail!(invalid_utf8);
+fail!(key_starting_with_digit);
+fail!(key_with_dash);
+fail!(key_with_space);
+fail!(key_without_colon);
+fail!(key_without_value);
+fail!(leading_comma_array);
+fail!(leading_zeros);
+fail!(literal_with_suffix);
+fail!(lone_decimal_point);
+fail!(lone_high_surrogate);
+fail!(lone_low_surrogate);
+fail!(lone_minus);
+fail!(lone_plus);
+fail!(lone_slash);
+fail!(lone_trailing_comma_array);
+fail!(lone_trailing_comma_object);
+fail!(lowercase_infinity);
+fail!(lowercase_nan);
+fail!(mismatched_quotes);
+fail!(misspelled_literal);
+fail!(nan_with_suffix);
+fail!(negative_leading_zero);
+fail!(no_comma_array);
+fail!(no_comma_object);
+fail!(noctal_with_leading_octal_digit);
+fail!(non_unicode_key_escape);
+fail!(number_with_letters);
+fail!(numeric_key);
+fail!(octal);
+fail!(octal_escape);
+fail!(octal_prefix);
+fail!(only_comments);
+fail!(short_hex_escape);
+fail!(short_unicode_escape);
+fail!(single_slash_comment);
+fail!(top_level_unterminated_block_comment);
+fail!(trailing_content);
+fail!(two_decimal_points);
+fail!(two_values);
+fail!(undefined);
+fail!(unescaped_cr_in_string);
+fail!(unescaped_lf_in_string);
+fail!(unpaired_high_surrogate);
+fail!(unterminated_array);
+fail!(unterminated_block_comment);
+fail!(unterminated_double_quoted_string);
+fail!(unterminated_object);
+fail!(unterminated_single_quoted_string);
+fail!(uppercase_literal);
+fail!(zero_followed_by_digit_escape);
+
+#[test]
+fn nan() {
+    for input in &["NaN", "-NaN", "+NaN"] {
+        let mut d = input.as_bytes().to_vec();
+        let v = json5().parse_owned(&mut d).unwrap();
+        assert!(v.as_f64().unwrap().is_nan(), "{}", input);
+        let v = json5().parse_owned_reader(input.as_bytes()).unwrap();
+        assert!(v.as_f64().unwrap().is_nan(), "{}", input);
+    }
+    let mut d = b"{a: NaN, b: [-NaN]}".to_vec();
+    let v = json5().parse_borrowed(&mut d).unwrap();
+    assert!(v["a"].as_f64().unwrap().is_nan());
+    assert!(v["b"].as_array().unwrap()[0].as_f64().unwrap().is_nan());
+}
+
+#[cfg(feature = "serde_impl")]
+#[test]
+fn serde() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config<'a> {
+        name: &'a str,
+        description: String,
+        port: u16,
+        ratio: f64,
+        hosts: Vec<String>,
+        debug: Option<bool>,
+    }
+    let input = r#"{
+        // borrowed as it has no escapes
+        name: 'server',
+        description: 'it\'s \
+fast',
+        port: 0x1F90,
+        ratio: .25,
+        hosts: ['a', "b",],
+        debug: null,
+    }"#;
+    let expected = Config {
+        name: "server",
+        description: "it's fast".to_string(),
+        port: 8080,
+        ratio: 0.25,
+        hosts: vec!["a".to_string(), "b".to_string()],
+        debug: None,
+    };
+    let mut d = input.as_bytes().to_vec();
+    assert_eq!(json5().deserialize::<Config>(&mut d).unwrap(), expected);
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Owned {
+        port: u16,
+        hosts: Vec<String>,
+    }
+    let input = "{port: +80, hosts: ['x']} // done";
+    let o: Owned = json5().deserialize_reader(Bytes(input.as_bytes())).unwrap();
+    assert_eq!(o.port, 80);
+    let docs: Vec<Owned> = json5()
+        .deserialize_many_reader(format!("{} {}", input, "\n{port: 1, hosts: []}").as_bytes())
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[1].port, 1);
+}
+
+// JSON5 is a superset of JSON: whatever passes strict parsing passes
+// JSON5 parsing with the same result, and of the documents that fail the
+// ones using what JSON5 added pass now.
+#[test]
+fn superset() {
+    for entry in fs::read_dir("data/pass").unwrap() {
+        let path = entry.unwrap().path();
+        let mut d = read(path.to_str().unwrap());
+        let v = parse(&d);
+        assert_eq!(Parser::new().parse_owned(&mut d).unwrap(), v, "{:?}", path);
+    }
+    let json5_only = [
+        "fail03.json",
+        "fail04.json",
+        "fail09.json",
+        "fail14.json",
+        "fail15.json",
+        "fail24.json",
+        "fail25.json",
+        "fail26.json",
+        "fail28.json",
+        "fail40_s64boverflow.json",
+        "fail41_toolarge.json",
+        "fail44.json",
+        "fail45.json",
+        "fail46.json",
+        "fail47.json",
+        "fail48.json",
+        "fail49.json",
+        "fail50.json",
+        "fail55.json",
+        "fail56.json",
+        "fail60.json",
+        "fail69.json",
+    ];
+    for entry in fs::read_dir("data/fail").unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if name.contains("EXCLUDE") {
+            continue;
+        }
+        let d = read(path.to_str().unwrap());
+        assert_eq!(!fails(&d), json5_only.contains(&&name[..]), "{}", name);
+    }
+}
+
+#[test]
+fn many() {
+    // documents can be split anywhere, including in the middle of a
+    // number or a character
+    let input = "12 'ä' {a: 1}[2,]/* x */+.5 Infinity\n// end\n";
+    let expected = vec![
+        value("12"),
+        value(r#""ä""#),
+        value(r#"{"a": 1}"#),
+        value("[2]"),
+        value("0.5"),
+        OwnedValue::from(std::f64::INFINITY),
+    ];
+    let mut parser = json5();
+    let docs: Vec<_> = parser
+        .parse_many_owned_reader(Bytes(input.as_bytes()))
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(docs, expected);
+    let docs: Vec<_> = parser
+        .parse_many_owned_reader(input.as_bytes())
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(docs, expected);
+
+    // an invalid document ends the stream
+    let docs: Vec<_> = parser
+        .parse_many_owned_reader(&b"[1] [2 3] [4]"[..])
+        .collect();
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[0], Ok(value("[1]")));
+    assert_eq!(
+        docs[1].as_ref().unwrap_err().error_type(),
+        &ErrorType::ExpectedArrayContent
+    );
+
+    // large documents span many reads
+    let big = format!("[{}]", "{a: 'b', c: 0x10},\n".repeat(20_000));
+    let docs: Vec<_> = parser
+        .parse_many_owned_reader(format!("{0} {0}", big).as_bytes())
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[1].as_array().unwrap().len(), 20_000);
+
+    // one document per line
+    let mut d = b"{a: 1,}\n\n// nothing\n[x]\n'y' /* z */\n".to_vec();
+    let docs: Vec<_> = parser.parse_many_owned(&mut d).collect();
+    assert_eq!(docs.len(), 3);
+    assert_eq!(docs[0], Ok(value(r#"{"a": 1}"#)));
+    assert!(docs[1].is_err());
+    assert_eq!(docs[2], Ok(value(r#""y""#)));
+    let mut d = b"{a: 1,}\n'y'".to_vec();
+    let docs: Vec<_> = parser
+        .parse_many_borrowed(&mut d)
+        .map(|v| OwnedValue::from(v.unwrap()))
+        .collect();
+    assert_eq!(docs, vec![value(r#"{"a": 1}"#), value(r#""y""#)]);
+    #[cfg(feature = "serde_impl")]
+    {
+        let mut d = b"1\n0x2\n".to_vec();
+        let docs: Vec<u8> = parser
+            .deserialize_many(&mut d)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(docs, vec![1, 2]);
+    }
+}
+
+#[test]
+fn errors() {
+    let error = |input: &str| {
+        let mut d = input.as_bytes().to_vec();
+        json5().parse_owned(&mut d).unwrap_err()
+    };
+    let e = error("{\n  a: [1, 2,\n    {'b/c': 0x}],\n}");
+    assert_eq!(e.error_type(), &ErrorType::InvalidNumber);
+    assert_eq!(e.offset(), Some(26));
+    assert_eq!((e.line(), e.column()), (3, 13));
+    assert_eq!(e.pointer(), Some("/a/2/b~1c"));
+    let e = error("{\\u0061: 'x\ny'}");
+    assert_eq!(e.error_type(), &ErrorType::Syntax);
+    assert_eq!(e.pointer(), Some("/a"));
+    assert_eq!(error("'abc").error_type(), &ErrorType::UnterminatedString);
+    assert_eq!(error("'abc").offset(), Some(0));
+    assert_eq!(
+        error("[1 /* x").error_type(),
+        &ErrorType::UnterminatedComment
+    );
+    assert_eq!(error("{a 1}").error_type(), &ErrorType::ExpectedObjectColon);
+    assert_eq!(error("{-: 1}").error_type(), &ErrorType::ExpectedObjectKey);
+    assert_eq!(error("['\\1']").error_type(), &ErrorType::InvalidEscape);
+    assert_eq!(error("[1] 2").error_type(), &ErrorType::TrailingCharacters);
+    assert_eq!(error("").error_type(), &ErrorType::EOF);
+    assert_eq!(error("nul").error_type(), &ErrorType::ExpectedNull);
+}
+
+#[test]
+fn limits() {
+    let check = |limits: Limits, input: &str, expected: ErrorType| {
+        let mut parser = json5().limits(limits);
+        let e = parser
+            .parse_owned(&mut input.as_bytes().to_vec())
+            .unwrap_err();
+        assert_eq!(e.error_type(), &expected, "{}", input);
+        let e = parser.parse_owned_reader(input.as_bytes()).unwrap_err();
+        assert_eq!(e.error_type(), &expected, "{}", input);
+    };
+    let limits = Limits {
+        max_depth: 2,
+        max_document_size: 64,
+        max_string_length: 3,
+        max_elements: 3,
+        max_keys: 2,
+    };
+    let mut parser = json5().limits(limits);
+    let mut d = b"{a: [1, 2, 'abc'], bcd: {}}".to_vec();
+    assert!(parser.parse_owned(&mut d).is_ok());
+    check(limits, "[[[]]]", ErrorType::DepthLimit);
+    check(
+        limits,
+        &format!("[{}]", " ".repeat(64)),
+        ErrorType::DocumentSizeLimit,
+    );
+    check(limits, "'abcd'", ErrorType::StringLengthLimit);
+    check(limits, "{abcd: 1}", ErrorType::StringLengthLimit);
+    check(limits, "[1, 2, 3, 4]", ErrorType::ElementCountLimit);
+    check(limits, "{a: 1, b: 2, c: 3}", ErrorType::KeyCountLimit);
+}
+
+#[test]
+fn duplicate_key_policy() {
+    let input = "{a: 1, 'a': 2, \"\\u0061\": 3, b: 4}";
+    let check = |policy: DuplicateKeys, expected: OwnedValue| {
+        let mut d = input.as_bytes().to_vec();
+        let mut parser = json5().duplicate_keys(policy);
+        assert_eq!(parser.parse_owned(&mut d).unwrap(), expected);
+    };
+    check(DuplicateKeys::KeepLast, value(r#"{"a": 3, "b": 4}"#));
+    check(DuplicateKeys::KeepFirst, value(r#"{"a": 1, "b": 4}"#));
+    let mut d = input.as_bytes().to_vec();
+    let mut parser = json5().duplicate_keys(DuplicateKeys::Error);
+    let e = parser.parse_owned(&mut d).unwrap_err();
+    assert_eq!(e.error_type(), &ErrorType::DuplicateKey);
+    assert_eq!(e.offset(), Some(7));
+}
+
+#[test]
+fn raw_numbers() {
+    // only numbers that are valid JSON keep their text
+    let mut d = b"[1.50, -0, 1e400, +1, .5, 5., 0x10]".to_vec();
+    let v = json5().raw_numbers(true).parse_owned(&mut d).unwrap();
+    assert_eq!(v.to_string(), "[1.50,-0,1e400,1,0.5,5.0,16]");
+}
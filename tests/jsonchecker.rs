@@ -1,7 +1,23 @@
 use simd_json;
+use simd_json::{Implementation, Parser};
 use std::fs::File;
 use std::io::Read;
 
+// Runs the input through every implementation the CPU supports.
+fn supported_implementations() -> impl Iterator<Item = Implementation> {
+    Implementation::ALL
+        .iter()
+        .cloned()
+        .filter(|i| i.is_supported())
+}
+
+fn to_owned_value_with(
+    input: &mut [u8],
+    implementation: Implementation,
+) -> simd_json::Result<simd_json::OwnedValue> {
+    Parser::with_implementation(implementation)?.parse_owned(input)
+}
+
 macro_rules! pass {
     ($file:ident) => {
         #[test]
@@ -19,7 +35,14 @@ macro_rules! pass {
             assert!(v2.is_ok());
             let v2 = v2.unwrap();
             let v1o: simd_json::OwnedValue = v1.clone().into();
-            assert_eq!(v2, v1o)
+            assert_eq!(v2, v1o);
+            for i in supported_implementations() {
+                let mut v3 = Vec::new();
+                let f = String::from(concat!("data/pass/", stringify!($file), ".json"));
+                File::open(f).unwrap().read_to_end(&mut v3).unwrap();
+                let v3 = to_owned_value_with(&mut v3, i);
+                assert_eq!(v2, v3.unwrap());
+            }
         }
     };
 }
@@ -38,6 +61,19 @@ macro_rules! fail {
             let v2 = simd_json::to_owned_value(&mut v2);
             dbg!(&v2);
             assert!(v2.is_err());
+            // everything but empty documents fails at a position in the input
+            let e = v2.unwrap_err();
+            if e.error_type() != &simd_json::ErrorType::EOF {
+                assert!(e.offset().is_some());
+                assert!(e.line() >= 1 && e.column() >= 1);
+            }
+            for i in supported_implementations() {
+                let mut v3 = Vec::new();
+                let f = String::from(concat!("data/fail/", stringify!($file), ".json"));
+                File::open(f).unwrap().read_to_end(&mut v3).unwrap();
+                let v3 = to_owned_value_with(&mut v3, i);
+                assert!(v3.is_err());
+            }
         }
     };
 }
@@ -72,6 +108,7 @@ pass!(pass13);
 pass!(pass14);
 pass!(pass15);
 pass!(pass16);
+pass!(pass17);
 
 // fail!(fail01_EXCLUDED);
 fail!(fail02);
@@ -0,0 +1,260 @@
+use simd_json::{BorrowedValue, ErrorType, JsonPath, OwnedValue};
+use std::fs;
+
+// Queries and documents follow the layout of the JSONPath comparison
+// (https://github.com/cburgmer/json-path-comparison): every query has a
+// directory with its `selector`, the `document.json` it runs against and
+// the `expected.json` consensus.
+
+fn read(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap()
+}
+
+// The value written with the keys of objects sorted.
+fn canonical(v: &OwnedValue) -> String {
+    match v {
+        OwnedValue::Object(m) => {
+            let mut members: Vec<_> = m
+                .iter()
+                .map(|(k, v)| format!("{:?}:{}", k, canonical(v)))
+                .collect();
+            members.sort();
+            format!("{{{}}}", members.join(","))
+        }
+        OwnedValue::Array(a) => {
+            let elements: Vec<_> = a.iter().map(canonical).collect();
+            format!("[{}]", elements.join(","))
+        }
+        v => v.to_string(),
+    }
+}
+
+// Sorts by the values written with their keys sorted, so the order of
+// keys in objects doesn't matter.
+fn sorted(mut v: Vec<OwnedValue>) -> Vec<OwnedValue> {
+    v.sort_by_key(canonical);
+    v
+}
+
+// Runs the query against both value types and checks they agree,
+// returns the result.
+fn query(dir: &str) -> Vec<OwnedValue> {
+    let selector = String::from_utf8(read(&format!("{}/selector", dir))).unwrap();
+    let path = JsonPath::compile(&selector).unwrap();
+    let mut d = read(&format!("{}/document.json", dir));
+    let owned = simd_json::to_owned_value(&mut d.clone()).unwrap();
+    let borrowed = simd_json::to_borrowed_value(&mut d).unwrap();
+    let res: Vec<OwnedValue> = path.query(&owned).into_iter().cloned().collect();
+    let b: Vec<OwnedValue> = path
+        .query::<BorrowedValue>(&borrowed)
+        .into_iter()
+        .map(|v| v.clone().into())
+        .collect();
+    assert_eq!(sorted(res.clone()), sorted(b));
+    res
+}
+
+fn expected(dir: &str) -> Vec<OwnedValue> {
+    let mut d = read(&format!("{}/expected.json", dir));
+    match simd_json::to_owned_value(&mut d).unwrap() {
+        OwnedValue::Array(a) => a,
+        _ => unreachable!(),
+    }
+}
+
+macro_rules! pass {
+    ($query:ident) => {
+        #[test]
+        fn $query() {
+            let dir = concat!("data/jsonpath/pass/", stringify!($query));
+            assert_eq!(query(dir), expected(dir));
+        }
+    };
+    // values of objects come in the order of the map
+    ($query:ident, unordered) => {
+        #[test]
+        fn $query() {
+            let dir = concat!("data/jsonpath/pass/", stringify!($query));
+            assert_eq!(sorted(query(dir)), sorted(expected(dir)));
+        }
+    };
+}
+
+macro_rules! fail {
+    ($query:ident) => {
+        #[test]
+        fn $query() {
+            let path = concat!("data/jsonpath/fail/", stringify!($query), "/selector");
+            let selector = String::from_utf8(read(path)).unwrap();
+            let e = JsonPath::compile(&selector).unwrap_err();
+            assert_eq!(e.error_type(), &ErrorType::InvalidJsonPath);
+            assert!(e.offset().map_or(false, |o| o <= selector.len()));
+        }
+    };
+}
+
+pass!(root);
+pass!(dot_notation);
+pass!(dot_notation_on_array);
+pass!(dot_notation_with_dash);
+pass!(dot_notation_with_number);
+pass!(dot_notation_with_non_ascii_key);
+pass!(dot_notation_with_underscore);
+pass!(dot_notation_on_missing_key);
+pass!(dot_notation_chained);
+pass!(dot_notation_with_wildcard_on_array);
+pass!(dot_notation_with_wildcard_on_object, unordered);
+pass!(dot_notation_with_wildcard_on_scalar);
+pass!(dot_notation_after_recursive_descent, unordered);
+pass!(
+    dot_notation_with_wildcard_after_recursive_descent,
+    unordered
+);
+pass!(dot_notation_with_wildcard_after_recursive_descent_on_scalar);
+pass!(bracket_notation);
+pass!(bracket_notation_with_double_quotes);
+pass!(bracket_notation_with_dot);
+pass!(bracket_notation_with_empty_string);
+pass!(bracket_notation_with_quoted_escaped_single_quote);
+pass!(bracket_notation_with_quoted_escaped_double_quote);
+pass!(bracket_notation_with_quoted_unicode_escape);
+pass!(bracket_notation_with_quoted_special_characters);
+pass!(bracket_notation_with_spaces);
+pass!(bracket_notation_with_wildcard_on_array);
+pass!(bracket_notation_with_wildcard_on_object, unordered);
+pass!(bracket_notation_with_wildcard_on_empty_array);
+pass!(bracket_notation_after_recursive_descent, unordered);
+pass!(bracket_notation_with_number_on_object);
+pass!(bracket_notation_with_string_on_array);
+pass!(array_index);
+pass!(array_index_negative);
+pass!(array_index_negative_out_of_bounds);
+pass!(array_index_out_of_bounds);
+pass!(array_index_on_empty_array);
+pass!(array_index_on_string);
+pass!(array_index_dot_notation);
+pass!(array_slice);
+pass!(array_slice_on_object);
+pass!(array_slice_with_open_end);
+pass!(array_slice_with_open_start);
+pass!(array_slice_with_open_start_and_end);
+pass!(array_slice_with_open_start_and_end_and_step_empty);
+pass!(array_slice_with_step);
+pass!(array_slice_with_step_1);
+pass!(array_slice_with_step_but_end_not_aligned);
+pass!(array_slice_with_step_0);
+pass!(array_slice_with_negative_step);
+pass!(array_slice_with_negative_step_only);
+pass!(array_slice_with_negative_start_and_end);
+pass!(array_slice_with_negative_start_and_positive_end);
+pass!(array_slice_with_large_number_for_end);
+pass!(array_slice_with_large_number_for_start);
+pass!(array_slice_with_start_after_end);
+pass!(array_slice_with_start_equal_to_end);
+pass!(array_slice_on_non_overlapping_array);
+pass!(array_slice_on_partially_overlapping_array);
+pass!(union);
+pass!(union_with_repeated_matches);
+pass!(union_with_keys);
+pass!(union_with_keys_on_object_without_key);
+pass!(union_with_slice_and_number);
+pass!(union_with_spaces);
+pass!(union_with_wildcard_and_number);
+pass!(union_with_keys_after_recursive_descent, unordered);
+pass!(union_with_filter);
+pass!(filter_expression_with_equals);
+pass!(filter_expression_with_equals_string);
+pass!(filter_expression_with_equals_string_with_single_quotes);
+pass!(filter_expression_with_equals_true);
+pass!(filter_expression_with_equals_false);
+pass!(filter_expression_with_equals_null);
+pass!(filter_expression_with_equals_on_array_of_numbers);
+pass!(filter_expression_with_equals_with_float);
+pass!(filter_expression_with_equals_with_exponent);
+pass!(filter_expression_with_equals_with_path_and_path);
+pass!(filter_expression_with_not_equals);
+pass!(filter_expression_with_greater_than);
+pass!(filter_expression_with_greater_than_or_equal);
+pass!(filter_expression_with_less_than);
+pass!(filter_expression_with_less_than_or_equal);
+pass!(filter_expression_with_less_than_string);
+pass!(filter_expression_with_and);
+pass!(filter_expression_with_or);
+pass!(filter_expression_with_and_before_or);
+pass!(filter_expression_with_parentheses);
+pass!(filter_expression_with_negation_and_equals);
+pass!(filter_expression_with_negation_and_existence);
+pass!(filter_expression_without_value);
+pass!(filter_expression_without_parens);
+pass!(filter_expression_with_spaces);
+pass!(filter_expression_with_bracket_notation);
+pass!(filter_expression_with_bracket_notation_with_index);
+pass!(filter_expression_with_nested_path);
+pass!(filter_expression_on_object);
+pass!(filter_expression_on_scalar);
+pass!(filter_expression_with_current_object);
+pass!(filter_expression_with_root_reference);
+pass!(filter_expression_with_non_singular_path);
+pass!(filter_expression_with_nested_filter);
+pass!(
+    filter_expression_after_dot_notation_with_wildcard_after_recursive_descent,
+    unordered
+);
+pass!(filter_expression_after_recursive_descent, unordered);
+pass!(alerting_rule);
+pass!(alerting_rule_with_compound_filter);
+pass!(rfc6901_names);
+pass!(goessner_authors);
+pass!(goessner_all_authors);
+pass!(goessner_store, unordered);
+pass!(goessner_prices, unordered);
+pass!(goessner_third_book);
+pass!(goessner_last_book);
+pass!(goessner_last_book_by_index);
+pass!(goessner_first_two_books);
+pass!(goessner_first_two_books_by_slice);
+pass!(goessner_books_with_isbn);
+pass!(goessner_cheap_books);
+pass!(goessner_everything_under_store_with_price);
+
+fail!(empty);
+fail!(missing_root);
+fail!(dot_notation_without_root);
+fail!(dot_notation_without_key);
+fail!(dot_notation_with_single_quotes);
+fail!(dot_notation_with_bracket);
+fail!(dot_notation_with_space);
+fail!(trailing_whitespace);
+fail!(recursive_descent);
+fail!(recursive_descent_after_dot_notation);
+fail!(triple_dot);
+fail!(root_followed_by_name);
+fail!(bracket_notation_empty);
+fail!(bracket_notation_unclosed);
+fail!(bracket_notation_unterminated_string);
+fail!(bracket_notation_with_unquoted_key);
+fail!(bracket_notation_with_invalid_escape);
+fail!(bracket_notation_with_lone_surrogate);
+fail!(bracket_notation_double_closed);
+fail!(bracket_notation_with_two_literals);
+fail!(union_with_empty_selector);
+fail!(union_only_commas);
+fail!(array_index_with_leading_zero);
+fail!(array_index_negative_zero);
+fail!(array_index_float);
+fail!(array_index_too_large);
+fail!(array_slice_with_too_many_colons);
+fail!(array_slice_with_leading_zero);
+fail!(filter_expression_empty);
+fail!(filter_expression_unclosed);
+fail!(filter_expression_with_single_equals);
+fail!(filter_expression_with_diamond);
+fail!(filter_expression_with_missing_operand);
+fail!(filter_expression_with_dangling_and);
+fail!(filter_expression_with_single_ampersand);
+fail!(filter_expression_with_literal_only);
+fail!(filter_expression_with_misspelled_literal);
+fail!(filter_expression_with_capitalized_literal);
+fail!(filter_expression_with_number_with_leading_zero);
+fail!(filter_expression_with_number_without_fraction);
+fail!(filter_expression_with_unbalanced_parentheses);
@@ -0,0 +1,280 @@
+#[cfg(feature = "serde_impl")]
+use serde::Deserialize;
+use simd_json::{ErrorType, OwnedValue, Parser, ValueTrait};
+use std::fs::{self, File};
+use std::io::{self, Read};
+
+fn lenient() -> Parser {
+    Parser::new().lenient(true)
+}
+
+// The expected value, parsed strictly.
+fn value(input: &str) -> OwnedValue {
+    simd_json::to_owned_value(&mut input.as_bytes().to_vec()).unwrap()
+}
+
+// An object with a single member.
+fn object(key: &str, v: OwnedValue) -> OwnedValue {
+    let mut o = OwnedValue::object();
+    o.insert(key.to_string(), v).unwrap();
+    o
+}
+
+// Hands out the input one byte at a time so every comment and string
+// spans reads.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Read for Bytes<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.split_first() {
+            Some((c, rest)) if !buf.is_empty() => {
+                buf[0] = *c;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+// Parses the input leniently in every way there is and checks the result
+// is `expected`, strict parsing has to reject it.
+fn check(input: &str, expected: &OwnedValue) {
+    let mut parser = lenient();
+    let mut d = input.as_bytes().to_vec();
+    assert_eq!(&parser.parse_owned(&mut d).unwrap(), expected);
+    let mut d = input.as_bytes().to_vec();
+    let v = parser.parse_borrowed(&mut d).map(OwnedValue::from).unwrap();
+    assert_eq!(&v, expected);
+    #[cfg(feature = "serde_impl")]
+    {
+        let mut d = input.as_bytes().to_vec();
+        assert_eq!(&parser.deserialize::<OwnedValue>(&mut d).unwrap(), expected);
+    }
+    assert_eq!(
+        &parser.parse_owned_reader(input.as_bytes()).unwrap(),
+        expected
+    );
+    let v = parser.parse_owned_reader(Bytes(input.as_bytes())).unwrap();
+    assert_eq!(&v, expected);
+
+    let mut d = input.as_bytes().to_vec();
+    assert!(Parser::new().parse_owned(&mut d).is_err());
+}
+
+fn check_err(input: &str, expected: ErrorType) {
+    let mut parser = lenient();
+    let mut d = input.as_bytes().to_vec();
+    let e = parser.parse_owned(&mut d).unwrap_err();
+    assert_eq!(e.error_type(), &expected);
+    let mut d = input.as_bytes().to_vec();
+    assert!(parser.parse_borrowed(&mut d).is_err());
+    #[cfg(feature = "serde_impl")]
+    {
+        let mut d = input.as_bytes().to_vec();
+        assert!(parser.deserialize::<OwnedValue>(&mut d).is_err());
+    }
+    assert!(parser.parse_owned_reader(input.as_bytes()).is_err());
+    assert!(parser.parse_owned_reader(Bytes(input.as_bytes())).is_err());
+}
+
+#[test]
+fn comments() {
+    check("// leading\n[1, 2]", &value("[1, 2]"));
+    check("[1, 2] // trailing", &value("[1, 2]"));
+    check("[1, 2] /* trailing */", &value("[1, 2]"));
+    check(
+        "/* a */ [ /* b */ 1 /* c */, // d\n 2 /**/] /***/",
+        &value("[1, 2]"),
+    );
+    check(
+        r#"{/* "a": 1, */ "b": /* : */ 2 // , "c": 3
+        }"#,
+        &value(r#"{"b": 2}"#),
+    );
+    // comments can contain anything
+    check("[1 /* \"ä\u{1}\\ */]", &value("[1]"));
+    // strings can contain what looks like comments
+    check(
+        r#"["http://example.com", "/* */", "\"//", "\\" // "
+        ]"#,
+        &value(r#"["http://example.com", "/* */", "\"//", "\\"]"#),
+    );
+    // comments are whitespace, not a place for values to be split at
+    let mut d = b"[1/**/2]".to_vec();
+    assert!(lenient().parse_owned(&mut d).is_err());
+}
+
+#[test]
+fn trailing_commas() {
+    check("[1, 2,]", &value("[1, 2]"));
+    check("[1, 2 , ]", &value("[1, 2]"));
+    check(
+        r#"{"a": 1, "b": [2, {},],}"#,
+        &value(r#"{"a": 1, "b": [2, {}]}"#),
+    );
+    check("[[1,],[],]", &value("[[1], []]"));
+    check("[1, // one\n2, /* done */]", &value("[1, 2]"));
+    for doc in &[
+        "[,]",
+        "[1,,]",
+        "[,1]",
+        "{,}",
+        r#"{"a": 1,,}"#,
+        r#"{"a":,}"#,
+        "[1],",
+    ] {
+        let mut d = doc.as_bytes().to_vec();
+        assert!(lenient().parse_owned(&mut d).is_err(), "{}", doc);
+        #[cfg(feature = "serde_impl")]
+        {
+            let mut d = doc.as_bytes().to_vec();
+            assert!(lenient().deserialize::<OwnedValue>(&mut d).is_err());
+        }
+    }
+}
+
+#[test]
+fn non_finite_numbers() {
+    let input = r#"{"a": NaN, "b": [Infinity, -Infinity], "c": -Infinity}"#;
+    let mut d = input.as_bytes().to_vec();
+    let v = lenient().parse_owned(&mut d).unwrap();
+    assert!(v["a"].as_f64().unwrap().is_nan());
+    let b = v["b"].as_array().unwrap();
+    assert_eq!(b[0].as_f64(), Some(std::f64::INFINITY));
+    assert_eq!(b[1].as_f64(), Some(std::f64::NEG_INFINITY));
+    assert_eq!(v["c"].as_f64(), Some(std::f64::NEG_INFINITY));
+    let inf = OwnedValue::from(std::f64::INFINITY);
+    let neg_inf = OwnedValue::from(std::f64::NEG_INFINITY);
+    check("[Infinity]", &OwnedValue::Array(vec![inf.clone()]));
+    check("Infinity", &inf);
+    check("-Infinity", &neg_inf);
+    check("{\"a\": -Infinity}", &object("a", neg_inf));
+    let mut d = b"NaN".to_vec();
+    assert!(lenient()
+        .parse_owned(&mut d)
+        .unwrap()
+        .as_f64()
+        .unwrap()
+        .is_nan());
+    let mut d = b"NaN".to_vec();
+    assert!(lenient()
+        .parse_borrowed(&mut d)
+        .unwrap()
+        .as_f64()
+        .unwrap()
+        .is_nan());
+
+    #[cfg(feature = "serde_impl")]
+    {
+        #[derive(Deserialize)]
+        struct Stats {
+            min: f64,
+            max: f64,
+            mean: f64,
+        }
+        let mut d = br#"{"min": -Infinity, "max": Infinity, "mean": NaN}"#.to_vec();
+        let s: Stats = lenient().deserialize(&mut d).unwrap();
+        assert_eq!(s.min, std::f64::NEG_INFINITY);
+        assert_eq!(s.max, std::f64::INFINITY);
+        assert!(s.mean.is_nan());
+    }
+
+    // raw numbers don't apply, there is no number to keep the text of
+    let mut d = b"[NaN, 1.50]".to_vec();
+    let v = lenient().raw_numbers(true).parse_owned(&mut d).unwrap();
+    assert!(v.as_array().unwrap()[0].as_f64().unwrap().is_nan());
+
+    check_err("[Nan]", ErrorType::InvalidNumber);
+    check_err("[NaNa]", ErrorType::InvalidNumber);
+    check_err("{\"a\": Infinit}", ErrorType::InvalidNumber);
+    check_err("[-Inf]", ErrorType::InvalidNumber);
+    check_err("[+Infinity]", ErrorType::InternalError);
+}
+
+#[test]
+fn errors() {
+    check_err("[1, /* 2 ]", ErrorType::UnterminatedComment);
+    let mut d = b"[1,\n /* 2,\n3]".to_vec();
+    let e = lenient().parse_owned(&mut d).unwrap_err();
+    assert_eq!(e.offset(), Some(5));
+    assert_eq!((e.line(), e.column()), (2, 2));
+    // comments don't move errors
+    let mut d = b"[1, /* x\n */ 2, // y\n x]".to_vec();
+    let e = lenient().parse_owned(&mut d).unwrap_err();
+    assert_eq!(e.offset(), Some(22));
+    assert_eq!((e.line(), e.column()), (3, 2));
+    assert_eq!(e.pointer(), Some("/2"));
+}
+
+#[test]
+fn many() {
+    let mut d = b"[1,] // one\n// nothing\n{\"a\": NaN /* a */}\n[2 /* two\n".to_vec();
+    let docs: Vec<_> = lenient().parse_many_owned(&mut d).collect();
+    assert_eq!(docs.len(), 3);
+    assert_eq!(docs[0].as_ref().unwrap(), &value("[1]"));
+    assert!(docs[1].as_ref().unwrap()["a"].as_f64().unwrap().is_nan());
+    assert_eq!(
+        docs[2].as_ref().unwrap_err().error_type(),
+        &ErrorType::UnterminatedComment
+    );
+
+    let input = "[1,] // one\n/* two\nthree */ {\"a\": Infinity,}[2]/**/3";
+    let mut parser = lenient();
+    let docs: Vec<_> = parser
+        .parse_many_owned_reader(Bytes(input.as_bytes()))
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(
+        docs,
+        vec![
+            value("[1]"),
+            object("a", OwnedValue::from(std::f64::INFINITY)),
+            value("[2]"),
+            value("3")
+        ]
+    );
+    let docs: Vec<_> = parser.parse_many_owned_reader(input.as_bytes()).collect();
+    assert_eq!(docs.len(), 4);
+
+    // long comments across the reader's chunks
+    let input = format!(
+        "[1, /*{}*/ 2, //{}\n 3]",
+        "x".repeat(100_000),
+        "y".repeat(100_000)
+    );
+    let v = parser.parse_owned_reader(input.as_bytes()).unwrap();
+    assert_eq!(v, value("[1, 2, 3]"));
+}
+
+// The relaxed dialect is a superset of JSON: whatever passes strict parsing
+// passes lenient parsing with the same result, and of the documents that
+// fail only the ones with trailing commas pass now.
+#[test]
+fn superset() {
+    for entry in fs::read_dir("data/pass").unwrap() {
+        let path = entry.unwrap().path();
+        let mut d = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut d).unwrap();
+        let mut d2 = d.clone();
+        let strict = Parser::new().parse_owned(&mut d).unwrap();
+        assert_eq!(
+            lenient().parse_owned(&mut d2).unwrap(),
+            strict,
+            "{:?}",
+            path
+        );
+    }
+    let trailing_commas = ["fail04.json", "fail09.json", "fail55.json", "fail56.json"];
+    for entry in fs::read_dir("data/fail").unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if name.contains("EXCLUDE") {
+            continue;
+        }
+        let mut d = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut d).unwrap();
+        let r = lenient().parse_owned(&mut d);
+        assert_eq!(r.is_ok(), trailing_commas.contains(&&name[..]), "{}", name);
+    }
+}
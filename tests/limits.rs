@@ -0,0 +1,229 @@
+use simd_json::{Error, ErrorType, Limits, Parser};
+
+// Runs the input through the entry points that take a `Parser` and checks
+// they all fail with `expected`, or succeed if it is `None`.
+fn check(parser: &mut Parser, input: &[u8], expected: Option<ErrorType>) {
+    let expected = expected.as_ref();
+    let mut d = input.to_vec();
+    let r = parser.parse_owned(&mut d);
+    assert_eq!(r.as_ref().err().map(Error::error_type), expected);
+    let mut d = input.to_vec();
+    let r = parser.parse_borrowed(&mut d);
+    assert_eq!(r.as_ref().err().map(Error::error_type), expected);
+    #[cfg(feature = "serde_impl")]
+    {
+        let mut d = input.to_vec();
+        let r = parser.deserialize::<simd_json::OwnedValue>(&mut d);
+        assert_eq!(r.as_ref().err().map(Error::error_type), expected);
+    }
+    let r = parser.parse_owned_reader(input);
+    assert_eq!(r.as_ref().err().map(Error::error_type), expected);
+}
+
+#[test]
+fn default_limits() {
+    let mut parser = Parser::new();
+    let long = format!("[\"{}\"]", "a".repeat(1 << 20));
+    check(&mut parser, long.as_bytes(), None);
+    let many = format!("[{}1]", "1,".repeat(100_000));
+    check(&mut parser, many.as_bytes(), None);
+    assert_eq!(Limits::default().max_depth, 1024);
+}
+
+#[test]
+fn document_size() {
+    let mut parser = Parser::new().limits(Limits {
+        max_document_size: 10,
+        ..Limits::default()
+    });
+    check(&mut parser, b"[1, 2, 3] ", None);
+    check(
+        &mut parser,
+        b"[1, 2, 3]  ",
+        Some(ErrorType::DocumentSizeLimit),
+    );
+    check(
+        &mut parser,
+        b"\"0123456789\"",
+        Some(ErrorType::DocumentSizeLimit),
+    );
+    // large inputs are rejected before they are read in full
+    let huge = format!("[{}1]", "1,".repeat(1 << 20));
+    check(
+        &mut parser,
+        huge.as_bytes(),
+        Some(ErrorType::DocumentSizeLimit),
+    );
+}
+
+#[test]
+fn document_size_many() {
+    let mut parser = Parser::new().limits(Limits {
+        max_document_size: 8,
+        ..Limits::default()
+    });
+    let mut d = b"[1, 2]\n[1, 2, 3, 4]\n[3]\n".to_vec();
+    let docs: Vec<_> = parser
+        .parse_many_owned(&mut d)
+        .map(|r| {
+            r.err()
+                .map(|e| e.error_type() == &ErrorType::DocumentSizeLimit)
+        })
+        .collect();
+    assert_eq!(docs, vec![None, Some(true), None]);
+
+    // the limit is per document, not for the whole stream
+    let input = "[1, 2] ".repeat(10_000);
+    let docs: Vec<_> = parser.parse_many_owned_reader(input.as_bytes()).collect();
+    assert_eq!(docs.len(), 10_000);
+    assert!(docs.iter().all(|d| d.is_ok()));
+
+    // a stream can't go on after a document that is too large as we
+    // don't know where the next one starts
+    let input = format!("[1] [{}1] [2]", "1,".repeat(1 << 17));
+    let docs: Vec<_> = parser
+        .parse_many_owned_reader(input.as_bytes())
+        .map(|r| {
+            r.err()
+                .map(|e| e.error_type() == &ErrorType::DocumentSizeLimit)
+        })
+        .collect();
+    assert_eq!(docs, vec![None, Some(true)]);
+}
+
+#[test]
+fn string_length() {
+    let mut parser = Parser::new().limits(Limits {
+        max_string_length: 4,
+        ..Limits::default()
+    });
+    for ok in &[
+        &br#""abcd""#[..],
+        br#"["abcd",  "abcd"   ]"#,
+        br#"{"abcd": "abcd"}"#,
+        br#"{"a": "abcd"     ,     "abcd": 1}"#,
+        br#"["\n\t"]"#,
+        br#"["a\"b"          ]"#,
+        "[\"äö\"]".as_bytes(),
+    ] {
+        check(&mut parser, ok, None);
+    }
+    for too_long in &[
+        &br#""abcde""#[..],
+        br#"["abcde"]"#,
+        br#"[1, "abcd", "abcde"]"#,
+        br#"{"abcde": 1}"#,
+        br#"{"a": {"b": "abcde"}}"#,
+        br#"["\n\t\r"]"#,
+        "[\"äöü\"]".as_bytes(),
+    ] {
+        check(&mut parser, too_long, Some(ErrorType::StringLengthLimit));
+    }
+    let mut d = br#"{"a": [1, {"b": "abcde"}]}"#.to_vec();
+    let e = parser.parse_owned(&mut d).unwrap_err();
+    assert_eq!(e.offset(), Some(16));
+    assert_eq!(e.pointer(), Some("/a/1/b"));
+}
+
+#[test]
+fn element_count() {
+    let mut parser = Parser::new().limits(Limits {
+        max_elements: 3,
+        ..Limits::default()
+    });
+    for ok in &[
+        &b"[]"[..],
+        b"[1, 2, 3]",
+        b"[[1, 2, 3], [1, 2, 3], [1, 2, 3]]",
+        br#"{"a": 1, "b": 2, "c": 3, "d": 4}"#,
+    ] {
+        check(&mut parser, ok, None);
+    }
+    for too_many in &[
+        &b"[1, 2, 3, 4]"[..],
+        b"[[1, 2, 3, 4]]",
+        b"[[1, 2, 3], [1, 2, 3], [1, 2, 3], []]",
+        br#"{"a": [{}, {}, {}, {}]}"#,
+    ] {
+        check(&mut parser, too_many, Some(ErrorType::ElementCountLimit));
+    }
+    let mut d = b"[1, 2, 3, [], 5]".to_vec();
+    let e = parser.parse_owned(&mut d).unwrap_err();
+    assert_eq!(e.offset(), Some(10));
+    assert_eq!(e.pointer(), Some("/3"));
+
+    let mut parser = Parser::new().limits(Limits {
+        max_elements: 0,
+        ..Limits::default()
+    });
+    check(&mut parser, b"[[]]", Some(ErrorType::ElementCountLimit));
+    check(&mut parser, b"{\"a\": []}", None);
+}
+
+#[test]
+fn key_count() {
+    let mut parser = Parser::new().limits(Limits {
+        max_keys: 2,
+        ..Limits::default()
+    });
+    for ok in &[
+        &b"{}"[..],
+        br#"{"a": 1, "b": 2}"#,
+        br#"{"a": {"a": 1, "b": 2}, "b": {"a": 1, "b": 2}}"#,
+        b"[1, 2, 3, 4]",
+    ] {
+        check(&mut parser, ok, None);
+    }
+    for too_many in &[
+        &br#"{"a": 1, "b": 2, "c": 3}"#[..],
+        br#"[{"a": 1, "b": 2, "c": 3}]"#,
+        br#"{"a": {"a": 1, "b": 2, "c": 3}}"#,
+        // duplicate keys count as well
+        br#"{"a": 1, "a": 2, "a": 3}"#,
+    ] {
+        check(&mut parser, too_many, Some(ErrorType::KeyCountLimit));
+    }
+    let mut d = br#"{"a": 1, "b": 2, "c": 3}"#.to_vec();
+    let e = parser.parse_owned(&mut d).unwrap_err();
+    assert_eq!(e.offset(), Some(17));
+
+    let mut parser = Parser::new().limits(Limits {
+        max_keys: 0,
+        ..Limits::default()
+    });
+    check(&mut parser, b"[{}]", None);
+    check(&mut parser, br#"{"a": 1}"#, Some(ErrorType::KeyCountLimit));
+}
+
+// Hostile payloads are rejected before any value is built for them.
+#[test]
+fn hostile() {
+    let mut parser = Parser::new().limits(Limits {
+        max_document_size: 1 << 20,
+        max_string_length: 1 << 10,
+        max_elements: 1 << 10,
+        max_keys: 1 << 10,
+        ..Limits::default()
+    });
+    let elements = format!("[{}1]", "1,".repeat(1_000_000));
+    let keys = format!("{{{}\"a\":1}}", "\"a\":1,".repeat(100_000));
+    let string = format!("[\"{}\"]", "\\u0041".repeat(1 << 10));
+    let nested = format!("{}1{}", "[".repeat(2_000), "]".repeat(2_000));
+    check(
+        &mut parser,
+        elements.as_bytes(),
+        Some(ErrorType::DocumentSizeLimit),
+    );
+    check(
+        &mut parser,
+        &elements.as_bytes()[..500_000],
+        Some(ErrorType::ElementCountLimit),
+    );
+    check(&mut parser, keys.as_bytes(), Some(ErrorType::KeyCountLimit));
+    check(
+        &mut parser,
+        string.as_bytes(),
+        Some(ErrorType::StringLengthLimit),
+    );
+    check(&mut parser, nested.as_bytes(), Some(ErrorType::DepthLimit));
+}
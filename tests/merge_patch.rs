@@ -0,0 +1,77 @@
+use simd_json::{merge_patch, OwnedValue, ValueTrait};
+use std::fs;
+
+fn owned(s: &str) -> OwnedValue {
+    simd_json::to_owned_value(&mut s.as_bytes().to_vec()).unwrap()
+}
+
+// The examples of RFC 7396 appendix A: document, patch and result.
+const RFC: [(&str, &str, &str); 15] = [
+    (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+    (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+    (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+    (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+    (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+    (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+    (
+        r#"{"a":{"b":"c"}}"#,
+        r#"{"a":{"b":"d","c":null}}"#,
+        r#"{"a":{"b":"d"}}"#,
+    ),
+    (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+    (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+    (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+    (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+    (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+    (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+    (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+    (
+        r#"{}"#,
+        r#"{"a":{"bb":{"ccc":null}}}"#,
+        r#"{"a":{"bb":{}}}"#,
+    ),
+];
+
+#[test]
+fn rfc() {
+    for (doc, patch, expected) in RFC.iter() {
+        let expected = owned(expected);
+
+        let mut res = owned(doc);
+        merge_patch::apply(&mut res, &owned(patch));
+        assert_eq!(res, expected, "{} + {}", doc, patch);
+
+        let mut res = owned(doc);
+        let mut p = patch.as_bytes().to_vec();
+        let p = simd_json::to_borrowed_value(&mut p).unwrap();
+        merge_patch::apply_borrowed(&mut res, &p);
+        assert_eq!(res, expected, "{} + {}", doc, patch);
+
+        let mut res = owned(doc);
+        merge_patch::apply(&mut res, &merge_patch::diff(&owned(doc), &expected));
+        assert_eq!(res, expected, "{} + {}", doc, patch);
+    }
+}
+
+#[test]
+fn diff_edits() {
+    let mut d = fs::read("data/citm_catalog.json").unwrap();
+    let from = simd_json::to_owned_value(&mut d).unwrap();
+    let mut to = from.clone();
+    to.pointer_set("/events/138586341/name", "renamed").unwrap();
+    to.pointer_remove("/areaNames/205705993");
+    to.pointer_set("/topicSubTopics/107888604/-", 1).unwrap();
+    to.pointer_set("/venueNames/new", "venue").unwrap();
+
+    let p = merge_patch::diff(&from, &to);
+    assert_eq!(p.len(), Some(4));
+    assert!(p.pointer("/areaNames/205705993").unwrap().is_null());
+    assert_eq!(
+        p.pointer("/topicSubTopics/107888604").unwrap().len(),
+        Some(3)
+    );
+    let mut doc = from.clone();
+    merge_patch::apply(&mut doc, &p);
+    assert_eq!(doc, to);
+    assert_eq!(merge_patch::diff(&to, &to).len(), Some(0));
+}
@@ -0,0 +1,23 @@
+use simd_json::ondemand::Document;
+
+mod common;
+
+// Validating a whole on-demand document succeeds for valid documents and
+// turning it into a Value gives the same as parsing it directly.
+#[test]
+fn pass() {
+    for (path, input) in common::documents("data/pass") {
+        let v = simd_json::to_owned_value(&mut input.clone()).unwrap();
+        let doc = Document::from_slice(&input).unwrap();
+        assert_eq!(doc.validate_all(), Ok(()), "{}", path.display());
+        assert_eq!(doc.root().to_owned_value(), Ok(v), "{}", path.display());
+    }
+}
+
+#[test]
+fn fail() {
+    for (path, input) in common::documents("data/fail") {
+        let r = Document::from_slice(&input).and_then(|d| d.validate_all());
+        assert!(r.is_err(), "{}", path.display());
+    }
+}
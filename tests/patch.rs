@@ -0,0 +1,76 @@
+use simd_json::{patch, OwnedValue, ValueTrait};
+use std::fs;
+
+fn load(path: &str) -> OwnedValue {
+    let mut d = fs::read(path).unwrap();
+    simd_json::to_owned_value(&mut d).unwrap()
+}
+
+// Applies the diff and checks it gets us from one document to the other.
+fn roundtrip(from: &OwnedValue, to: &OwnedValue) -> usize {
+    let p = patch::diff(from, to);
+    let mut doc = from.clone();
+    patch::apply(&mut doc, &p).unwrap();
+    assert_eq!(&doc, to, "{}", p);
+    p.len().unwrap()
+}
+
+// The cases follow the layout of the json-patch test suite
+// (https://github.com/json-patch/json-patch-tests) and start with the
+// examples of RFC 6902 appendix A.
+#[test]
+fn tests() {
+    let tests = load("data/patch/tests.json");
+    for t in tests.as_array().unwrap() {
+        let comment = t["comment"].as_str().unwrap();
+        let mut doc = t["doc"].clone();
+        let res = patch::apply(&mut doc, &t["patch"]);
+        if let Some(expected) = t.get("expected") {
+            assert!(res.is_ok(), "{}: {:?}", comment, res);
+            assert_eq!(&doc, expected, "{}", comment);
+            roundtrip(&t["doc"], expected);
+        } else {
+            assert!(res.is_err(), "{}", comment);
+            // a failing patch leaves the document alone
+            assert_eq!(doc, t["doc"], "{}", comment);
+        }
+    }
+}
+
+#[test]
+fn diff_files() {
+    let files: Vec<OwnedValue> = (1..=17)
+        .filter_map(|i| {
+            let mut d = fs::read(format!("data/pass/pass{:02}.json", i)).ok()?;
+            simd_json::to_owned_value(&mut d).ok()
+        })
+        .collect();
+    for from in &files {
+        assert_eq!(roundtrip(from, from), 0);
+        for to in &files {
+            roundtrip(from, to);
+        }
+    }
+}
+
+#[test]
+fn diff_edits() {
+    let from = load("data/citm_catalog.json");
+    let mut to = from.clone();
+    to.pointer_set("/events/138586341/name", "renamed").unwrap();
+    to.pointer_remove("/areaNames/205705993");
+    to.pointer_set("/topicSubTopics/324846100/-", 1).unwrap();
+    to.pointer_remove("/performances/3");
+    to.pointer_set("/performances/10/prices/0/amount", 1)
+        .unwrap();
+    assert_eq!(roundtrip(&from, &to), 5);
+
+    let from = load("data/twitter.json");
+    let mut to = from.clone();
+    to.pointer_remove("/statuses/0");
+    let mut status = OwnedValue::object();
+    status.insert("id".to_string(), 1).unwrap();
+    to.pointer_set("/statuses/-", status).unwrap();
+    to.pointer_set("/search_metadata/count", 200).unwrap();
+    assert_eq!(roundtrip(&from, &to), 3);
+}
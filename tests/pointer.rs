@@ -0,0 +1,111 @@
+use simd_json::{BorrowedValue, ErrorType, OwnedValue, ValueTrait};
+
+// The example document of RFC 6901 section 5
+const RFC: &str = r#"{
+    "foo": ["bar", "baz"],
+    "": 0,
+    "a/b": 1,
+    "c%d": 2,
+    "e^f": 3,
+    "g|h": 4,
+    "i\\j": 5,
+    "k\"l": 6,
+    " ": 7,
+    "m~n": 8
+}"#;
+
+fn lookup<V: ValueTrait>(v: &V) {
+    assert_eq!(v.pointer("").and_then(|v| v.len()), Some(10));
+    assert_eq!(v.pointer("/foo").and_then(|v| v.len()), Some(2));
+    assert_eq!(v.pointer("/foo/0").and_then(|v| v.as_str()), Some("bar"));
+    let numbers = [
+        ("/", 0),
+        ("/a~1b", 1),
+        ("/c%d", 2),
+        ("/e^f", 3),
+        ("/g|h", 4),
+        ("/i\\j", 5),
+        ("/k\"l", 6),
+        ("/ ", 7),
+        ("/m~0n", 8),
+    ];
+    for (p, n) in numbers.iter() {
+        assert_eq!(v.pointer(p).and_then(|v| v.as_i64()), Some(*n), "{}", p);
+    }
+    for p in [
+        "foo", "/foo/2", "/foo/-", "/foo/01", "/foo/a", "/foo/0/x", "/a/b", "/m~2n", "/bar",
+    ]
+    .iter()
+    {
+        assert!(v.pointer(p).is_none(), "{}", p);
+    }
+}
+
+#[test]
+fn rfc_example() {
+    let mut d = RFC.as_bytes().to_vec();
+    lookup(&simd_json::to_owned_value(&mut d.clone()).unwrap());
+    lookup(&simd_json::to_borrowed_value(&mut d).unwrap());
+}
+
+fn mutate<V: ValueTrait + PartialEq + std::fmt::Debug>(mut v: V) -> V {
+    *v.pointer_mut("/a/0").unwrap() = V::from(10);
+    assert!(v.pointer_mut("/a/9").is_none());
+
+    assert_eq!(v.pointer_set("/a/1", 20).unwrap(), Some(V::from(2)));
+    assert_eq!(v.pointer_set("/a/-", 40).unwrap(), None);
+    assert_eq!(v.pointer_set("/a/4", 50).unwrap(), None);
+    assert_eq!(v.pointer_set("/b/c/d", true).unwrap(), None);
+    assert_eq!(v.pointer_set("/b/c/d", false).unwrap(), Some(V::from(true)));
+    assert_eq!(v.pointer_set("/e/-/f", ()).unwrap(), None);
+    assert_eq!(v.pointer_set("/x~1y", "z".to_string()).unwrap(), None);
+
+    let err = |r: simd_json::Result<Option<V>>| r.map(|_| ()).unwrap_err();
+    assert_eq!(
+        err(v.pointer_set("a", 1)).error_type(),
+        &ErrorType::InvalidPointer
+    );
+    assert_eq!(
+        err(v.pointer_set("/a/01", 1)).error_type(),
+        &ErrorType::InvalidPointer
+    );
+    assert_eq!(
+        err(v.pointer_set("/a/9", 1)).error_type(),
+        &ErrorType::IndexOutOfBounds
+    );
+    assert_eq!(
+        err(v.pointer_set("/a/0/b", 1)).error_type(),
+        &ErrorType::ExpectedMap
+    );
+
+    assert_eq!(v.pointer_remove("/a/1"), Some(V::from(20)));
+    assert_eq!(v.pointer_remove("/a/9"), None);
+    assert_eq!(v.pointer_remove("/a/-"), None);
+    assert_eq!(v.pointer_remove("/b/c/d"), Some(V::from(false)));
+    assert_eq!(v.pointer_remove("/b/c/d"), None);
+    assert_eq!(v.pointer_remove(""), None);
+    assert_eq!(v.pointer_remove("/x~1y"), Some(V::from("z".to_string())));
+    v
+}
+
+#[test]
+fn mutation() {
+    let mut d = br#"{"a": [10, 3, 40, 50], "b": {"c": {}}, "e": [{"f": null}]}"#.to_vec();
+    let expected = simd_json::to_owned_value(&mut d).unwrap();
+    let mut d = br#"{"a": [1, 2, 3], "e": []}"#.to_vec();
+    let v = mutate(simd_json::to_owned_value(&mut d.clone()).unwrap());
+    assert_eq!(v, expected);
+
+    let v = mutate(simd_json::to_borrowed_value(&mut d).unwrap());
+    assert_eq!(OwnedValue::from(v), expected);
+}
+
+#[test]
+fn root() {
+    let mut v = BorrowedValue::from(1);
+    assert_eq!(v.pointer_set("", "x".to_string()).unwrap(), Some(1.into()));
+    assert_eq!(v.as_str(), Some("x"));
+    assert_eq!(v.pointer("/0"), None);
+    let e = v.pointer_set("/0", 1).unwrap_err();
+    assert_eq!(e.error_type(), &ErrorType::ExpectedMap);
+}
@@ -0,0 +1,112 @@
+//! Run with `cargo test --features preserve_order`.
+#![cfg(feature = "preserve_order")]
+use simd_json::{json, DuplicateKeys, OwnedValue, Parser, ValueTrait};
+
+const INPUT: &str = r#"{"z": 1, "a": {"y": [], "b": null, "x": {"c": 3, "a": [{"q": 1, "p": 2}]}}, "m": "n", "0": true}"#;
+const COMPACT: &str =
+    r#"{"z":1,"a":{"y":[],"b":null,"x":{"c":3,"a":[{"q":1,"p":2}]}},"m":"n","0":true}"#;
+
+fn keys(v: &OwnedValue) -> Vec<&str> {
+    v.as_object().unwrap().keys().map(String::as_str).collect()
+}
+
+#[test]
+fn parsing() {
+    let mut parser = Parser::new();
+    let owned = parser.parse_owned(&mut INPUT.as_bytes().to_vec()).unwrap();
+    assert_eq!(owned.to_string(), COMPACT);
+    assert_eq!(keys(&owned), vec!["z", "a", "m", "0"]);
+    let mut d = INPUT.as_bytes().to_vec();
+    assert_eq!(parser.parse_borrowed(&mut d).unwrap().to_string(), COMPACT);
+    let v: OwnedValue = parser.deserialize(&mut INPUT.as_bytes().to_vec()).unwrap();
+    assert_eq!(v.to_string(), COMPACT);
+    let v = parser.parse_owned_reader(INPUT.as_bytes()).unwrap();
+    assert_eq!(v.to_string(), COMPACT);
+    let tape = parser.parse_tape(&mut INPUT.as_bytes().to_vec()).unwrap();
+    assert_eq!(OwnedValue::from(tape.root()).to_string(), COMPACT);
+
+    let json5 = "{z: 1, a: {y: [], b: null, x: {c: 3, a: [{q: 1, p: 2,}]}}, m: 'n', '0': true}";
+    let mut d = json5.as_bytes().to_vec();
+    let v = Parser::new().json5(true).parse_owned(&mut d).unwrap();
+    assert_eq!(v.to_string(), COMPACT);
+}
+
+#[test]
+fn conversions() {
+    let mut d = INPUT.as_bytes().to_vec();
+    let borrowed = simd_json::to_borrowed_value(&mut d).unwrap();
+    let owned = OwnedValue::from(borrowed.clone());
+    assert_eq!(owned.to_string(), COMPACT);
+    assert_eq!(simd_json::serde::to_string(&owned).unwrap(), COMPACT);
+    assert_eq!(simd_json::serde::to_string(&borrowed).unwrap(), COMPACT);
+
+    // through serde's data model
+    let v = simd_json::serde::to_owned_value(&borrowed).unwrap();
+    assert_eq!(v.to_string(), COMPACT);
+    let v: OwnedValue = simd_json::serde::from_owned_value(owned.clone()).unwrap();
+    assert_eq!(v.to_string(), COMPACT);
+    let v: OwnedValue = simd_json::serde::from_borrowed_value(borrowed).unwrap();
+    assert_eq!(v.to_string(), COMPACT);
+
+    assert_eq!(
+        owned.to_string_pp(),
+        r#"{
+  "z": 1,
+  "a": {
+    "y": [],
+    "b": null,
+    "x": {
+      "c": 3,
+      "a": [
+        {
+          "q": 1,
+          "p": 2
+        }
+      ]
+    }
+  },
+  "m": "n",
+  "0": true
+}"#
+    );
+}
+
+#[test]
+fn json_macro() {
+    let v = json!({"b": 1, "a": {"d": 2, "c": 3}});
+    assert_eq!(v.to_string(), r#"{"b":1,"a":{"d":2,"c":3}}"#);
+}
+
+#[test]
+fn duplicate_keys() {
+    // the key stays where it was first seen
+    let input = r#"{"a": 1, "b": 2, "a": 3}"#;
+    let mut d = input.as_bytes().to_vec();
+    let v = Parser::new().parse_owned(&mut d).unwrap();
+    assert_eq!(v.to_string(), r#"{"a":3,"b":2}"#);
+    let mut d = input.as_bytes().to_vec();
+    let v = Parser::new()
+        .duplicate_keys(DuplicateKeys::KeepFirst)
+        .parse_owned(&mut d)
+        .unwrap();
+    assert_eq!(v.to_string(), r#"{"a":1,"b":2}"#);
+}
+
+#[test]
+fn remove() {
+    let mut v = json!({"a": 1, "b": 2, "c": 3, "d": 4});
+    assert_eq!(v.remove("b").unwrap(), Some(OwnedValue::from(2)));
+    assert_eq!(v.to_string(), r#"{"a":1,"c":3,"d":4}"#);
+    let o = v.as_object_mut().unwrap();
+    simd_json::ObjectTrait::remove(o, "a");
+    assert_eq!(v.to_string(), r#"{"c":3,"d":4}"#);
+}
+
+#[test]
+fn merge_patch() {
+    // members keep their place, new ones go to the end
+    let mut v = json!({"d": 1, "c": {"b": 2, "a": 3}, "b": 4, "a": 5});
+    let p = json!({"c": {"b": null, "z": 6}, "b": null, "e": 7});
+    simd_json::merge_patch::apply(&mut v, &p);
+    assert_eq!(v.to_string(), r#"{"d":1,"c":{"a":3,"z":6},"a":5,"e":7}"#);
+}
@@ -0,0 +1,17 @@
+use simd_json::Parser;
+
+mod common;
+
+// Documents parsed with raw numbers write out to text that parses to the
+// same value as the original.
+#[test]
+fn roundtrip() {
+    let mut parser = Parser::new().raw_numbers(true);
+    for (path, input) in common::documents("data/pass") {
+        let v = simd_json::to_owned_value(&mut input.clone()).unwrap();
+        let raw = parser.parse_owned(&mut input.clone()).unwrap();
+        let mut d = raw.to_string().into_bytes();
+        let r = simd_json::to_owned_value(&mut d).unwrap();
+        assert_eq!(r, v, "{}", path.display());
+    }
+}
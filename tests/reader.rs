@@ -0,0 +1,22 @@
+use std::fs::File;
+
+mod common;
+
+// Reading a document from a file gives the same value as parsing it from
+// memory.
+#[test]
+fn pass() {
+    for (path, mut input) in common::documents("data/pass") {
+        let v = simd_json::to_owned_value(&mut input).unwrap();
+        let r = simd_json::to_owned_value_from_reader(File::open(&path).unwrap());
+        assert_eq!(r.unwrap(), v, "{}", path.display());
+    }
+}
+
+#[test]
+fn fail() {
+    for (path, _) in common::documents("data/fail") {
+        let r = simd_json::to_owned_value_from_reader(File::open(&path).unwrap());
+        assert!(r.is_err(), "{}", path.display());
+    }
+}
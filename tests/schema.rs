@@ -0,0 +1,142 @@
+#![cfg(feature = "schema")]
+use simd_json::schema::Violation;
+use simd_json::{BorrowedValue, OwnedValue, Schema, ValueTrait};
+use std::fs;
+
+// The cases follow the layout of the JSON Schema test suite
+// (https://github.com/json-schema-org/JSON-Schema-Test-Suite): every file
+// has groups of tests sharing a schema, every test has the data and if it
+// is valid.
+#[test]
+fn suite() {
+    let mut files: Vec<_> = fs::read_dir("data/schema")
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+    for file in files {
+        let mut d = fs::read(&file).unwrap();
+        let groups = simd_json::to_owned_value(&mut d).unwrap();
+        for group in groups.as_array().unwrap() {
+            let description = group["description"].as_str().unwrap();
+            let schema = Schema::compile(&group["schema"]).unwrap();
+            for test in group["tests"].as_array().unwrap() {
+                let name = format!(
+                    "{}: {}: {}",
+                    file.display(),
+                    description,
+                    test["description"].as_str().unwrap()
+                );
+                let valid = test["valid"].as_bool().unwrap();
+                let owned = &test["data"];
+                let mut d = owned.to_string().into_bytes();
+                let borrowed = simd_json::to_borrowed_value(&mut d).unwrap();
+                assert_eq!(schema.is_valid(owned), valid, "{}", name);
+                assert_eq!(schema.is_valid(&borrowed), valid, "{}", name);
+                let violations = schema.validate(owned);
+                assert_eq!(violations.is_empty(), valid, "{}: {:?}", name, violations);
+                assert_eq!(
+                    schema.validate(&borrowed).len(),
+                    violations.len(),
+                    "{}",
+                    name
+                );
+            }
+        }
+    }
+}
+
+fn value(input: &str) -> OwnedValue {
+    simd_json::to_owned_value(&mut input.as_bytes().to_vec()).unwrap()
+}
+
+fn locations(violations: &[Violation]) -> Vec<(&str, &str)> {
+    let mut res: Vec<_> = violations
+        .iter()
+        .map(|v| (v.instance.as_str(), v.schema.as_str()))
+        .collect();
+    res.sort();
+    res
+}
+
+#[test]
+fn violations() {
+    let schema = Schema::compile(&value(r##"{
+        "definitions": {"id": {"type": "integer", "minimum": 1}},
+        "type": "object",
+        "properties": {
+            "id": {"$ref": "#/definitions/id"},
+            "tags": {"type": "array", "items": {"type": "string", "pattern": "^[a-z]+$"}, "uniqueItems": true},
+            "owner": {
+                "type": "object",
+                "properties": {"id": {"$ref": "#/definitions/id"}, "name/full": {"maxLength": 3}},
+                "additionalProperties": false
+            },
+            "kind": {"oneOf": [{"const": "a"}, {"enum": ["a", "b"]}]}
+        },
+        "required": ["id", "name"]
+    }"##))
+    .unwrap();
+
+    let mut d = br#"{
+        "id": 0.5,
+        "tags": ["ok", "Not ok", 3, "ok"],
+        "owner": {"id": 2, "name/full": "long", "extra": null},
+        "kind": "a"
+    }"#
+    .to_vec();
+    let v = simd_json::to_borrowed_value(&mut d).unwrap();
+    assert!(!schema.is_valid(&v));
+    let violations = schema.validate(&v);
+    assert_eq!(
+        locations(&violations),
+        vec![
+            ("", "/required"),
+            ("/id", "/definitions/id/minimum"),
+            ("/id", "/definitions/id/type"),
+            ("/kind", "/properties/kind/oneOf"),
+            ("/owner/extra", "/properties/owner/additionalProperties"),
+            (
+                "/owner/name~1full",
+                "/properties/owner/properties/name~1full/maxLength"
+            ),
+            ("/tags", "/properties/tags/uniqueItems"),
+            ("/tags/1", "/properties/tags/items/pattern"),
+            ("/tags/2", "/properties/tags/items/type"),
+        ]
+    );
+    let required = violations.iter().find(|v| v.schema == "/required").unwrap();
+    assert_eq!(required.message, "missing the member \"name\"");
+    assert_eq!(required.to_string(), "'': missing the member \"name\"");
+    let kind = violations.iter().find(|v| v.instance == "/kind").unwrap();
+    assert_eq!(
+        kind.message,
+        "expected to match exactly one of the schemas, matched 2"
+    );
+
+    let valid: OwnedValue =
+        value(r#"{"id": 1, "name": "x", "tags": ["a"], "owner": {"id": 3}, "kind": "b"}"#);
+    assert!(schema.is_valid(&valid));
+    assert!(schema.validate(&valid).is_empty());
+}
+
+#[test]
+fn parsed_directly() {
+    let mut d = br##"{"type": "array", "items": {"$ref": "#/definitions/event"},
+        "definitions": {"event": {"type": "object", "required": ["name", "price"],
+        "properties": {"name": {"type": "string"}, "price": {"type": "number", "minimum": 0}}}}}"##
+        .to_vec();
+    let schema = Schema::compile(&simd_json::to_owned_value(&mut d).unwrap()).unwrap();
+    let mut d =
+        br#"[{"name": "a", "price": 1}, {"name": "b", "price": -1}, {"name": "c"}]"#.to_vec();
+    let v: BorrowedValue = simd_json::to_borrowed_value(&mut d).unwrap();
+    let violations: Vec<String> = schema.validate(&v).iter().map(|v| v.to_string()).collect();
+    assert_eq!(
+        violations,
+        vec![
+            "'/1/price': expected at least 0",
+            "'/2': missing the member \"price\""
+        ]
+    );
+}
@@ -0,0 +1,26 @@
+#![cfg(feature = "serde_impl")]
+
+mod common;
+
+// The serde serializer writes Value doms the same way their own
+// generators do.
+#[test]
+fn values() {
+    for (path, input) in common::documents("data/pass") {
+        let owned = simd_json::to_owned_value(&mut input.clone()).unwrap();
+        let mut d = input;
+        let borrowed = simd_json::to_borrowed_value(&mut d).unwrap();
+        assert_eq!(
+            simd_json::serde::to_string(&owned).unwrap(),
+            owned.to_string(),
+            "{}",
+            path.display()
+        );
+        assert_eq!(
+            simd_json::serde::to_string_pretty(&borrowed).unwrap(),
+            owned.to_string_pp(),
+            "{}",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use simd_json::{BorrowedValue, OwnedValue};
+
+mod common;
+
+// Parsing an immutable slice gives the same values as parsing in place
+// and leaves the input as it was.
+#[test]
+fn pass() {
+    for (path, input) in common::documents("data/pass") {
+        let orig = input.clone();
+        let owned = simd_json::to_owned_value(&mut input.clone()).unwrap();
+        let mut d = input.clone();
+        let borrowed = simd_json::to_borrowed_value(&mut d).unwrap();
+        let v: BorrowedValue = simd_json::to_borrowed_value_ref(&input).unwrap();
+        assert_eq!(v, borrowed, "{}", path.display());
+        let v: OwnedValue = simd_json::to_owned_value_ref(&input).unwrap();
+        assert_eq!(v, owned, "{}", path.display());
+        assert_eq!(orig, input, "{}", path.display());
+    }
+}
+
+#[test]
+fn fail() {
+    for (path, input) in common::documents("data/fail") {
+        assert!(
+            simd_json::to_borrowed_value_ref(&input).is_err(),
+            "{}",
+            path.display()
+        );
+        assert!(
+            simd_json::to_owned_value_ref(&input).is_err(),
+            "{}",
+            path.display()
+        );
+    }
+}
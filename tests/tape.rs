@@ -0,0 +1,25 @@
+use simd_json::OwnedValue;
+
+mod common;
+
+// The tape holds the same document as the Value doms.
+#[test]
+fn pass() {
+    for (path, input) in common::documents("data/pass") {
+        let v = simd_json::to_owned_value(&mut input.clone()).unwrap();
+        let mut d = input;
+        let tape = simd_json::to_tape(&mut d).unwrap();
+        assert_eq!(OwnedValue::from(tape.root()), v, "{}", path.display());
+    }
+}
+
+#[test]
+fn fail() {
+    for (path, mut input) in common::documents("data/fail") {
+        assert!(
+            simd_json::to_tape(&mut input).is_err(),
+            "{}",
+            path.display()
+        );
+    }
+}